@@ -3,4 +3,8 @@ import type { AddNumbersGameStatus } from "./AddNumbersGameStatus";
 import type { BitVMXProgramProperties } from "./BitVMXProgramProperties";
 import type { PlayerRole } from "./PlayerRole";
 
-export type AddNumbersGame = { program_id: string, role: PlayerRole, number1: number | null, number2: number | null, guess: number | null, status: AddNumbersGameStatus, created_at: bigint, updated_at: bigint, bitvmx_program_properties: BitVMXProgramProperties, };
+export type AddNumbersGame = { program_id: string, role: PlayerRole, number1: number | null, number2: number | null, guess: number | null, status: AddNumbersGameStatus, created_at: bigint, updated_at: bigint, bitvmx_program_properties: BitVMXProgramProperties, 
+/**
+ * Transaction that paid the won bet to the operator, set once the game is finished
+ */
+payout_txid: string | null, };
@@ -2,4 +2,7 @@
 import type { GameOutcome } from "./GameOutcome";
 import type { GameReason } from "./GameReason";
 
-export type AddNumbersGameStatus = "SetupParticipants" | "PlaceBet" | "SetupFunding" | "SetupGame" | "StartGame" | "SubmitGameData" | { "GameComplete": { outcome: GameOutcome, reason: GameReason, } } | "Finished";
+/**
+ * Status of an add numbers game
+ */
+export type AddNumbersGameStatus = "SetupParticipants" | "PlaceBet" | "SetupFunding" | "SetupGame" | "StartGame" | "SubmitGameData" | { "GameComplete": { outcome: GameOutcome, reason: GameReason, } } | "TransferBetFunds" | "Finished";
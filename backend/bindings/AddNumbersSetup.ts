@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Numbers both players set up, player 2 has to answer with their sum
+ */
+export type AddNumbersSetup = { number1: number, number2: number, };
@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * How a failed job is retried, the delay doubles after each failed attempt
+ */
+export type BackoffPolicy = { 
+/**
+ * Attempts before the job moves to the dead letter list
+ */
+max_attempts: number, 
+/**
+ * Delay before the first retry in milliseconds
+ */
+initial_delay_ms: bigint, 
+/**
+ * Longest delay between two attempts in milliseconds
+ */
+max_delay_ms: bigint, };
@@ -0,0 +1,16 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BitvmxConnectionState } from "./BitvmxConnectionState";
+
+export type BitvmxConnection = { state: BitvmxConnectionState, 
+/**
+ * Failures since the last successful exchange with BitVMX
+ */
+consecutive_failures: number, last_error: string | null, 
+/**
+ * Messages rejected because their sender is not in the allow list
+ */
+rejected_messages: bigint, 
+/**
+ * Messages that could not be decoded or matched, acked and dropped
+ */
+unhandled_messages: bigint, };
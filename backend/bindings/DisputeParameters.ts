@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Dispute parameters of a game, both players must set up the program with the same values
+ */
+export type DisputeParameters = { 
+/**
+ * Blocks a player has to answer in the dispute before the other one can claim a timeout
+ */
+timelock_blocks: number, 
+/**
+ * Program definition file, relative to the bitvmx-client
+ */
+program_definition: string, 
+/**
+ * Index of the participant leading the BitVMX program setup
+ */
+leader_idx: number, };
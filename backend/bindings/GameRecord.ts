@@ -0,0 +1,30 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BitVMXProgramProperties } from "./BitVMXProgramProperties";
+import type { PlayerRole } from "./PlayerRole";
+
+/**
+ * Game persisted by the shared game framework.
+ * The game specific data, e.g. the numbers to sum, is flattened into the record
+ * so an add numbers record serializes like [`crate::models::AddNumbersGame`].
+ */
+export type GameRecord = { program_id: string, 
+/**
+ * Game implementation, e.g. "add-numbers"
+ */
+game_type: string, 
+/**
+ * BitVMX operator the game runs on
+ */
+operator: string, role: PlayerRole, 
+/**
+ * Status of the game, its type depends on the game type, see [`Self::status_as`]
+ */
+status: unknown, created_at: bigint, updated_at: bigint, bitvmx_program_properties: BitVMXProgramProperties, 
+/**
+ * Transaction that paid the won bet to the operator, set once the game is finished
+ */
+payout_txid: string | null, 
+/**
+ * Recorded state that contradicts the chain, found by the reconciliation of the game
+ */
+contradictions: Array<string>, };
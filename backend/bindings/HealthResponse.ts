@@ -1,3 +1,8 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BitvmxConnection } from "./BitvmxConnection";
 
-export type HealthResponse = { status: string, timestamp: bigint, };
+export type HealthResponse = { 
+/**
+ * healthy, or degraded while BitVMX is not connected or an operator is unhealthy
+ */
+status: string, timestamp: bigint, bitvmx: BitvmxConnection, };
@@ -0,0 +1,36 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BackoffPolicy } from "./BackoffPolicy";
+import type { JobStatus } from "./JobStatus";
+
+/**
+ * Persisted state of a background job, the job itself is rebuilt from its kind and payload
+ */
+export type JobRecord = { id: string, 
+/**
+ * Kind of the job, e.g. `wait_start_game`
+ */
+kind: string, 
+/**
+ * Payload of the job, the program it works on
+ */
+program_id: string, 
+/**
+ * Game the job belongs to, see `Job::game_type`
+ */
+game_type: string | null, 
+/**
+ * Jobs with the same key are not queued or run twice, see `Job::dedupe_key`
+ */
+dedupe_key: string | null, status: JobStatus, 
+/**
+ * Failed attempts so far
+ */
+attempts: number, backoff: BackoffPolicy, last_error: string | null, 
+/**
+ * Unix timestamp in milliseconds from when the job can run
+ */
+next_run_at_ms: bigint, 
+/**
+ * Unix timestamp in milliseconds of the start of the last attempt
+ */
+started_at_ms: bigint | null, created_at_ms: bigint, updated_at_ms: bigint, };
@@ -0,0 +1,16 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BitvmxConnection } from "./BitvmxConnection";
+
+export type OperatorInfo = { 
+/**
+ * Name of the operator, `default` for the `bitvmx` section of the config
+ */
+name: string, 
+/**
+ * Used when a request does not select an operator
+ */
+default: boolean, connection: BitvmxConnection, 
+/**
+ * Why the operator stopped working, e.g. its setup failed. None while it is healthy
+ */
+unhealthy: string | null, };
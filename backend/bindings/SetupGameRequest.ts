@@ -1,3 +1,22 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DisputeParameters } from "./DisputeParameters";
 
-export type SetupGameRequest = { program_id: string, number1: number, number2: number, };
+export type SetupGameRequest = { program_id: string, number1: number, number2: number, 
+/**
+ * Blocks a player has to answer in the dispute, defaults to the game config
+ */
+timelock_blocks?: number, 
+/**
+ * Program definition file relative to the bitvmx-client, one of the allowed ones of the
+ * game config, defaults to the game config one
+ */
+program_definition?: string, 
+/**
+ * Index of the participant leading the program setup, defaults to the game config
+ */
+leader_idx?: number, 
+/**
+ * Parameters returned to player 1 when it set up the game, required from player 2.
+ * They must match or the program is not set up.
+ */
+peer_parameters?: DisputeParameters, };
@@ -1,3 +1,8 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DisputeParameters } from "./DisputeParameters";
 
-export type SetupGameResponse = { program_id: string, };
+export type SetupGameResponse = { program_id: string, 
+/**
+ * Parameters used to set up the program, share them with the other player
+ */
+dispute_parameters: DisputeParameters, };
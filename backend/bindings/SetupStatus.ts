@@ -0,0 +1,31 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type SetupStatus = { 
+/**
+ * The P2P address was obtained from BitVMX
+ */
+p2p_address: boolean, 
+/**
+ * The wallet address was obtained from BitVMX
+ */
+wallet_address: boolean, 
+/**
+ * The wallet has enough funds to play
+ */
+wallet_funded: boolean, 
+/**
+ * The operator key was created
+ */
+operator_key: boolean, 
+/**
+ * The funding key was created
+ */
+funding_key: boolean, 
+/**
+ * The funding UTXO was sent and registered in BitVMX
+ */
+funding_utxo: boolean, 
+/**
+ * All the setup steps finished in the current run
+ */
+completed: boolean, };
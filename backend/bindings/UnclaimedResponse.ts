@@ -0,0 +1,16 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { JsonValue } from "./serde_json/JsonValue";
+
+export type UnclaimedResponse = { 
+/**
+ * The correlation ID nobody was waiting for
+ */
+correlation_id: string, 
+/**
+ * Seconds since the response was received
+ */
+age_secs: bigint, 
+/**
+ * The BitVMX message
+ */
+message: JsonValue, };
@@ -0,0 +1,23 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type UnclaimedResponsesMetrics = { 
+/**
+ * Responses kept because nobody was waiting for them
+ */
+retained: bigint, 
+/**
+ * Responses handed to a waiter registered after they arrived
+ */
+claimed: bigint, 
+/**
+ * Responses dropped because nobody claimed them in time
+ */
+expired: bigint, 
+/**
+ * Responses dropped to make room for newer ones
+ */
+evicted: bigint, 
+/**
+ * Responses waiting to be claimed
+ */
+unclaimed: bigint, };
@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { UnclaimedResponse } from "./UnclaimedResponse";
+import type { UnclaimedResponsesMetrics } from "./UnclaimedResponsesMetrics";
+
+export type UnclaimedResponsesResponse = { metrics: UnclaimedResponsesMetrics, 
+/**
+ * Responses waiting to be claimed, oldest first
+ */
+responses: Array<UnclaimedResponse>, };
@@ -24,30 +24,56 @@ use crate::state::AppState;
         routes::add_numbers::setup_game,
         routes::add_numbers::start_game,
         routes::add_numbers::submit_sum,
+        routes::add_numbers::claim_bet,
+        // Games
+        routes::games::get_game,
+        routes::games::get_current_game,
+        routes::games::setup_participants,
+        routes::games::place_bet,
+        routes::games::setup_funding_utxo,
+        routes::games::setup_game,
+        routes::games::start_game,
+        routes::games::submit,
+        routes::games::claim_bet,
         //BitVMX
         routes::bitvmx::comm_info,
         routes::bitvmx::operator_keys,
+        routes::bitvmx::setup_status,
         routes::bitvmx::get_aggregated_key,
         routes::bitvmx::wallet_balance,
         routes::bitvmx::get_transaction,
         routes::bitvmx::get_protocol_visualization,
+        routes::bitvmx::unclaimed_responses,
+        routes::bitvmx::operators,
+        // Jobs
+        routes::jobs::list_jobs,
+        routes::jobs::get_job,
+        routes::jobs::cancel_job,
+        routes::jobs::retry_job,
     ),
     components(
         schemas(
             crate::models::ErrorResponse,
             crate::models::HealthResponse,
+            crate::models::BitvmxConnection,
+            crate::models::BitvmxConnectionState,
             crate::models::AddNumbersGame,
             crate::models::AddNumbersGameStatus,
             crate::models::GameOutcome,
             crate::models::GameReason,
             crate::models::P2PAddress,
             crate::models::OperatorKeys,
+            crate::models::OperatorInfo,
+            crate::models::SetupStatus,
             crate::models::SetupParticipantsRequest,
             crate::models::SetupParticipantsResponse,
             crate::models::AggregatedKeyResponse,
             crate::models::WalletBalance,
             crate::models::Utxo,
             crate::models::TransactionResponse,
+            crate::models::UnclaimedResponse,
+            crate::models::UnclaimedResponsesMetrics,
+            crate::models::UnclaimedResponsesResponse,
             crate::models::PlayerRole,
             crate::models::BitVMXProgramProperties,
             crate::models::AddNumbersGame,
@@ -55,18 +81,28 @@ use crate::state::AppState;
             crate::models::StartGameRequest,
             crate::models::StartGameResponse,
             crate::models::SetupGameRequest,
+            crate::models::DisputeParameters,
             crate::models::SubmitSumRequest,
             crate::models::SubmitSumResponse,
             crate::models::PlaceBetRequest,
             crate::models::PlaceBetResponse,
             crate::models::FundingUtxoRequest,
             crate::models::FundingUtxosResponse,
+            crate::models::GameRecord,
+            crate::models::GameResponse,
+            crate::models::AddNumbersSetup,
+            crate::models::AddNumbersSubmit,
+            crate::models::JobRecord,
+            crate::models::JobStatus,
+            crate::models::BackoffPolicy,
         )
     ),
     tags(
         (name = "Health", description = "Health check endpoints"),
         (name = "AddNumbers", description = "Add numbers game management endpoints"),
-        (name = "BitVMX", description = "BitVMX communication endpoints")
+        (name = "Games", description = "Endpoints shared by every game type"),
+        (name = "BitVMX", description = "BitVMX communication endpoints"),
+        (name = "Jobs", description = "Background jobs inspection and control endpoints")
     ),
     info(
         title = "BitVMX API",
@@ -105,11 +141,18 @@ pub async fn app(app_state: AppState) -> Router {
         )
     });
 
-    // Build our application with routes and middleware
-    Router::new()
+    // Every registered game is served under its game type
+    let mut router = Router::new()
         .nest("/api/health", routes::health::router())
-        .nest("/api/add-numbers", routes::add_numbers::router())
+        .nest("/api/add-numbers", routes::add_numbers::router());
+    for game in app_state.games.all() {
+        router = router.nest(&format!("/api/games/{}", game.game_type()), game.router());
+    }
+
+    // Build our application with routes and middleware
+    router
         .nest("/api/bitvmx", routes::bitvmx::router())
+        .nest("/api/jobs", routes::jobs::router())
         .merge(SwaggerUi::new("/").url("/api-docs/openapi.json", ApiDoc::openapi()))
         .layer(LoggingLayer::new(1024)) // Limit the body log to 1024 bytes
         .layer(trace_layer)
@@ -0,0 +1,102 @@
+//! Replay a BitVMX journal against the BitVMX of a config, e.g. a fake one with the
+//! memory transport, to reproduce an incident locally.
+//!
+//! Usage: `CONFIG_FILE=player_1 replay_journal [--allow-real] <journal.jsonl> [timeout seconds]`
+//!
+//! A config with a real BitVMX, any transport but memory, is refused unless `--allow-real`
+//! is given, then only the requests that do not change its state are sent.
+
+use bitvmx_hackathon_backend::config::BitvmxTransportKind;
+use bitvmx_hackathon_backend::{config, rpc::journal, rpc::rpc_client::RpcClient};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+const DEFAULT_TIMEOUT: u64 = 30; // 30 seconds
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::new(
+            std::env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string()),
+        ))
+        .init();
+
+    let (flags, mut args): (Vec<_>, Vec<_>) = std::env::args()
+        .skip(1)
+        .partition(|arg| arg.starts_with("--"));
+    let allow_real = match flags.as_slice() {
+        [] => false,
+        [flag] if flag == "--allow-real" => true,
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Unknown options {flags:?}, only --allow-real"
+            ))
+        }
+    };
+    args.reverse();
+    let journal_path = args.pop().ok_or(anyhow::anyhow!(
+        "Usage: replay_journal [--allow-real] <journal.jsonl> [timeout seconds]"
+    ))?;
+    let timeout = match args.pop() {
+        Some(seconds) => seconds
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Invalid timeout {seconds}: {e:?}"))?,
+        None => DEFAULT_TIMEOUT,
+    };
+
+    let config_file = std::env::var("CONFIG_FILE").unwrap_or_else(|_| "player_1".to_string());
+    let mut config = config::Config::load(&config_file).unwrap_or_default();
+    // Do not record the replay in the journal being replayed
+    if config.bitvmx.journal_path.as_deref() == Some(journal_path.as_str()) {
+        config.bitvmx.journal_path = None;
+    }
+
+    // The fake BitVMX replays everything, a real one only the requests that read its state
+    let state_changes = config.bitvmx.transport == BitvmxTransportKind::Memory;
+    if !state_changes && !allow_real {
+        return Err(anyhow::anyhow!(
+            "Refusing to replay against the {:?} transport of {config_file}, use the memory \
+             transport or --allow-real to replay the requests that do not change its state",
+            config.bitvmx.transport
+        ));
+    }
+
+    let entries = journal::Journal::read(&journal_path)?;
+    println!(
+        "🔄 Replaying {} journal entries from {journal_path}",
+        entries.len()
+    );
+
+    let (shutdown_tx, _) = broadcast::channel::<()>(1);
+    let (rpc_client, _listener_task) = RpcClient::connect(&config.bitvmx, &shutdown_tx)?;
+    rpc_client.wait_for_ready(shutdown_tx.subscribe()).await;
+
+    let options = journal::ReplayOptions {
+        timeout: Duration::from_secs(timeout),
+        state_changes,
+    };
+    let report = journal::replay(&rpc_client, &entries, options).await;
+    let _ = shutdown_tx.send(());
+    let report = report?;
+
+    println!(
+        "✅ Sent {} messages, {} responses matched the journal",
+        report.sent, report.matched
+    );
+    if report.skipped > 0 {
+        println!(
+            "⏭️ Skipped {} state changing messages, their responses are missing",
+            report.skipped
+        );
+    }
+    for correlation_id in &report.differed {
+        println!("⚠️ Response differs: {correlation_id}");
+    }
+    for correlation_id in &report.missing {
+        println!("❌ Response missing: {correlation_id}");
+    }
+    if !report.missing.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -1,5 +1,11 @@
+use crate::jobs::job::LONG_LIVED_KINDS;
+use crate::models::{DisputeParameters, InvalidDisputeParameters};
+use crate::stores::JobRetention;
 use serde::{Deserialize, Serialize};
-use std::net::SocketAddr;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -8,6 +14,24 @@ pub struct Config {
     pub cors: CorsConfig,
     pub bitvmx: BitVMXClientConfig,
     pub bitcoin: BitcoinConfig,
+    pub storage: StorageConfig,
+    pub game: GameConfig,
+    /// BitVMX nodes managed besides the `bitvmx` one, which is the default operator
+    #[serde(default)]
+    pub operators: Vec<OperatorConfig>,
+    #[serde(default)]
+    pub jobs: JobsConfig,
+}
+
+/// Name of the operator of the `bitvmx` section
+pub const DEFAULT_OPERATOR: &str = "default";
+
+/// BitVMX node of an operator, selected by its name
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperatorConfig {
+    pub name: String,
+    #[serde(flatten)]
+    pub bitvmx: BitVMXClientConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,6 +54,66 @@ pub struct CorsConfig {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BitVMXClientConfig {
     pub broker_port: u16,
+    /// IP or host name of the broker, localhost if unset
+    #[serde(default)]
+    pub broker_host: Option<String>,
+    /// Key of the BitVMX node, its identity receives our messages
+    #[serde(default = "default_bitvmx_key_path")]
+    pub bitvmx_key_path: String,
+    /// Key of this backend, its identity receives the BitVMX messages
+    #[serde(default = "default_l2_key_path")]
+    pub l2_key_path: String,
+    /// Public key hashes allowed to send us messages besides the BitVMX key,
+    /// messages from anyone else are rejected
+    #[serde(default)]
+    pub allow_list: Vec<String>,
+    /// Channel used to talk to BitVMX
+    #[serde(default)]
+    pub transport: BitvmxTransportKind,
+    /// Socket of the unix socket transport
+    #[serde(default)]
+    pub socket_path: Option<String>,
+    /// JSON lines file where every message exchanged with BitVMX is recorded, disabled if unset
+    #[serde(default)]
+    pub journal_path: Option<String>,
+}
+
+fn default_bitvmx_key_path() -> String {
+    "config/keys/bitvmx.key".to_string()
+}
+
+fn default_l2_key_path() -> String {
+    "config/keys/l2.key".to_string()
+}
+
+impl BitVMXClientConfig {
+    /// IP of the broker, the host name is resolved if it is not an IP
+    pub fn broker_ip(&self) -> Result<Option<IpAddr>, anyhow::Error> {
+        let Some(host) = &self.broker_host else {
+            return Ok(None);
+        };
+        if let Ok(ip) = host.parse() {
+            return Ok(Some(ip));
+        }
+        let addr = (host.as_str(), self.broker_port)
+            .to_socket_addrs()
+            .map_err(|e| anyhow::anyhow!("Failed to resolve the broker host {host}: {e:?}"))?
+            .next()
+            .ok_or(anyhow::anyhow!("The broker host {host} has no address"))?;
+        Ok(Some(addr.ip()))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BitvmxTransportKind {
+    /// BitVMX broker listening on `broker_port`
+    #[default]
+    Broker,
+    /// In process channel pair served by a fake BitVMX node, needs the `test-support` feature
+    Memory,
+    /// JSON lines over the unix socket at `socket_path`
+    UnixSocket,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,6 +125,184 @@ pub struct BitcoinConfig {
     pub wallet: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageConfig {
+    /// Where games are persisted
+    pub backend: StorageBackend,
+    /// Database directory, only used by the sled backend
+    pub path: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// Keep everything in memory, games are lost on restart
+    Memory,
+    /// Embedded on-disk key value store
+    Sled,
+}
+
+/// Limits of the background jobs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobsConfig {
+    /// Jobs of the same kind running at once, the others stay queued.
+    /// Only applies to the short jobs, the kinds of `unbounded` are not limited
+    #[serde(default = "default_max_concurrency")]
+    pub max_concurrency: usize,
+    /// Limit by job kind, e.g. `watch_timeout`, instead of `max_concurrency`, also applies to `unbounded` kinds
+    #[serde(default)]
+    pub concurrency: HashMap<String, usize>,
+    /// Kinds not limited by `max_concurrency`, the long-lived game jobs by default.
+    /// They run for the whole game, so a limit would leave the games beyond it unwatched
+    #[serde(default = "default_unbounded")]
+    pub unbounded: Vec<String>,
+    /// How long the running jobs have to finish on shutdown before they are stopped and queued again
+    #[serde(default = "default_shutdown_grace_secs")]
+    pub shutdown_grace_secs: u64,
+    /// How long the succeeded and cancelled jobs are kept after they finished
+    #[serde(default = "default_finished_retention_secs")]
+    pub finished_retention_secs: u64,
+    /// Succeeded and cancelled jobs kept at most, the oldest are removed first
+    #[serde(default = "default_max_finished_jobs")]
+    pub max_finished_jobs: usize,
+}
+
+fn default_max_concurrency() -> usize {
+    16
+}
+
+fn default_unbounded() -> Vec<String> {
+    LONG_LIVED_KINDS
+        .iter()
+        .map(|kind| kind.to_string())
+        .collect()
+}
+
+fn default_shutdown_grace_secs() -> u64 {
+    30
+}
+
+fn default_finished_retention_secs() -> u64 {
+    7 * 24 * 60 * 60 // 7 days
+}
+
+fn default_max_finished_jobs() -> usize {
+    1000
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrency: default_max_concurrency(),
+            concurrency: HashMap::new(),
+            unbounded: default_unbounded(),
+            shutdown_grace_secs: default_shutdown_grace_secs(),
+            finished_retention_secs: default_finished_retention_secs(),
+            max_finished_jobs: default_max_finished_jobs(),
+        }
+    }
+}
+
+impl JobsConfig {
+    /// Jobs of `kind` that can run at once, at least one, `None` when they are not limited
+    pub fn concurrency(&self, kind: &str) -> Option<usize> {
+        match self.concurrency.get(kind) {
+            Some(limit) => Some((*limit).max(1)),
+            None if self.unbounded.iter().any(|unbounded| unbounded == kind) => None,
+            None => Some(self.max_concurrency.max(1)),
+        }
+    }
+
+    /// How long the running jobs have to finish on shutdown
+    pub fn shutdown_grace_period(&self) -> Duration {
+        Duration::from_secs(self.shutdown_grace_secs)
+    }
+
+    /// How long the succeeded and cancelled jobs are kept
+    pub fn retention(&self) -> JobRetention {
+        JobRetention {
+            ttl: Duration::from_secs(self.finished_retention_secs),
+            max_finished: self.max_finished_jobs,
+        }
+    }
+}
+
+/// Default dispute parameters of new games and the bounds accepted from requests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameConfig {
+    /// Blocks a player has to answer in the dispute before the other one can claim a timeout
+    pub timelock_blocks: u32,
+    pub min_timelock_blocks: u32,
+    pub max_timelock_blocks: u32,
+    /// Program definition file, relative to the bitvmx-client
+    pub program_definition: String,
+    /// Other program definition files a setup request may choose, relative to the bitvmx-client.
+    /// The default one is always allowed, nothing else is ever read.
+    #[serde(default)]
+    pub allowed_program_definitions: Vec<String>,
+    /// Index of the participant leading the BitVMX program setup
+    pub leader_idx: u16,
+    /// Directory of the bitvmx-client, the program definitions are relative to it
+    pub bitvmx_client_path: String,
+    /// Seconds between two reconciliations of the stored games with the chain
+    #[serde(default = "default_reconcile_interval_secs")]
+    pub reconcile_interval_secs: u64,
+    /// Seconds between two checks of the block height while a game waits for the other player
+    #[serde(default = "default_timeout_check_interval_secs")]
+    pub timeout_check_interval_secs: u64,
+}
+
+fn default_reconcile_interval_secs() -> u64 {
+    60
+}
+
+fn default_timeout_check_interval_secs() -> u64 {
+    10
+}
+
+impl GameConfig {
+    /// Fill the missing parameters with the defaults and check the bounds
+    pub fn dispute_parameters(
+        &self,
+        timelock_blocks: Option<u32>,
+        program_definition: Option<String>,
+        leader_idx: Option<u16>,
+    ) -> Result<DisputeParameters, InvalidDisputeParameters> {
+        let parameters = DisputeParameters {
+            timelock_blocks: timelock_blocks.unwrap_or(self.timelock_blocks),
+            program_definition: program_definition.unwrap_or(self.program_definition.clone()),
+            leader_idx: leader_idx.unwrap_or(self.leader_idx),
+        };
+
+        if !(self.min_timelock_blocks..=self.max_timelock_blocks)
+            .contains(&parameters.timelock_blocks)
+        {
+            return Err(InvalidDisputeParameters(format!(
+                "Timelock blocks must be between {} and {}",
+                self.min_timelock_blocks, self.max_timelock_blocks
+            )));
+        }
+        self.program_definition_path(&parameters.program_definition)?;
+        Ok(parameters)
+    }
+
+    /// Path of an allowed program definition file as seen from the backend
+    pub fn program_definition_path(
+        &self,
+        program_definition: &str,
+    ) -> Result<PathBuf, InvalidDisputeParameters> {
+        let allowed = std::iter::once(&self.program_definition)
+            .chain(&self.allowed_program_definitions)
+            .any(|allowed| allowed == program_definition);
+        if !allowed {
+            return Err(InvalidDisputeParameters(format!(
+                "Program definition {program_definition:?} is not allowed"
+            )));
+        }
+        Ok(Path::new(&self.bitvmx_client_path).join(program_definition))
+    }
+}
+
 impl Config {
     pub fn load(name: &str) -> Result<Self, anyhow::Error> {
         let config = config::Config::builder()
@@ -52,6 +314,27 @@ impl Config {
         Ok(config)
     }
 
+    /// BitVMX nodes of every operator by name, the default operator first
+    pub fn operator_configs(&self) -> Result<Vec<OperatorConfig>, anyhow::Error> {
+        let mut operators = vec![OperatorConfig {
+            name: DEFAULT_OPERATOR.to_string(),
+            bitvmx: self.bitvmx.clone(),
+        }];
+        for operator in &self.operators {
+            if operator.name.trim().is_empty() {
+                return Err(anyhow::anyhow!("Operator name cannot be empty"));
+            }
+            if operators.iter().any(|other| other.name == operator.name) {
+                return Err(anyhow::anyhow!(
+                    "Operator {} is configured more than once",
+                    operator.name
+                ));
+            }
+            operators.push(operator.clone());
+        }
+        Ok(operators)
+    }
+
     pub fn server_addr(&self) -> Result<SocketAddr, anyhow::Error> {
         let addr = format!("{}:{}", self.server.host, self.server.port);
         let socket_addr = addr.parse()?;
@@ -73,7 +356,16 @@ impl Default for Config {
                 allowed_origins: vec!["*".to_string()],
                 allowed_headers: vec!["*".to_string()],
             },
-            bitvmx: BitVMXClientConfig { broker_port: 22222 },
+            bitvmx: BitVMXClientConfig {
+                broker_port: 22222,
+                broker_host: None,
+                bitvmx_key_path: default_bitvmx_key_path(),
+                l2_key_path: default_l2_key_path(),
+                allow_list: vec![],
+                transport: BitvmxTransportKind::Broker,
+                socket_path: None,
+                journal_path: None,
+            },
             bitcoin: BitcoinConfig {
                 network: "regtest".to_string(),
                 url: "http://127.0.0.1:18443".to_string(),
@@ -81,6 +373,147 @@ impl Default for Config {
                 password: "rpcpassword".to_string(),
                 wallet: "test_wallet".to_string(),
             },
+            storage: StorageConfig {
+                backend: StorageBackend::Memory,
+                path: "data/default".to_string(),
+            },
+            game: GameConfig {
+                timelock_blocks: 5,
+                min_timelock_blocks: 2,
+                max_timelock_blocks: 144,
+                program_definition: "./verifiers/add-test-with-const-pre.yaml".to_string(),
+                allowed_program_definitions: vec![],
+                leader_idx: 1,
+                bitvmx_client_path: "../deps/rust-bitvmx-client".to_string(),
+                reconcile_interval_secs: default_reconcile_interval_secs(),
+                timeout_check_interval_secs: default_timeout_check_interval_secs(),
+            },
+            operators: vec![],
+            jobs: JobsConfig::default(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jobs_concurrency() {
+        let jobs = JobsConfig {
+            max_concurrency: 4,
+            concurrency: HashMap::from([
+                ("watch_timeout".to_string(), 8),
+                ("wait_payout".to_string(), 0),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(jobs.concurrency("watch_timeout"), Some(8));
+        assert_eq!(jobs.concurrency("wait_payout"), Some(1));
+        assert_eq!(jobs.concurrency("wait_start_game"), None);
+        assert_eq!(jobs.concurrency("reconcile_games"), Some(4));
+    }
+
+    #[test]
+    fn test_dispute_parameters_defaults() {
+        let mut game = Config::default().game;
+        game.allowed_program_definitions = vec!["./other.yaml".to_string()];
+        let parameters = game.dispute_parameters(None, None, None).unwrap();
+        assert_eq!(parameters.timelock_blocks, game.timelock_blocks);
+        assert_eq!(parameters.program_definition, game.program_definition);
+        assert_eq!(parameters.leader_idx, game.leader_idx);
+
+        let parameters = game
+            .dispute_parameters(Some(10), Some("./other.yaml".to_string()), Some(0))
+            .unwrap();
+        assert_eq!(
+            parameters,
+            DisputeParameters {
+                timelock_blocks: 10,
+                program_definition: "./other.yaml".to_string(),
+                leader_idx: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_dispute_parameters_bounds() {
+        let game = Config::default().game;
+        assert!(game
+            .dispute_parameters(Some(game.min_timelock_blocks), None, None)
+            .is_ok());
+        assert!(game
+            .dispute_parameters(Some(game.max_timelock_blocks), None, None)
+            .is_ok());
+        assert!(game
+            .dispute_parameters(Some(game.min_timelock_blocks - 1), None, None)
+            .is_err());
+        assert!(game
+            .dispute_parameters(Some(game.max_timelock_blocks + 1), None, None)
+            .is_err());
+        assert!(game
+            .dispute_parameters(None, Some(" ".to_string()), None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_program_definition_allow_list() {
+        let mut game = Config::default().game;
+        game.allowed_program_definitions = vec!["./verifiers/other.yaml".to_string()];
+        assert_eq!(
+            game.program_definition_path(&game.program_definition)
+                .unwrap(),
+            Path::new(&game.bitvmx_client_path).join(&game.program_definition)
+        );
+        assert!(game
+            .program_definition_path("./verifiers/other.yaml")
+            .is_ok());
+        for program_definition in [
+            "/etc/passwd",
+            "../../etc/passwd",
+            "./verifiers/unknown.yaml",
+        ] {
+            assert!(game.program_definition_path(program_definition).is_err());
+            assert!(game
+                .dispute_parameters(None, Some(program_definition.to_string()), None)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn test_broker_ip() {
+        let mut bitvmx = Config::default().bitvmx;
+        assert_eq!(bitvmx.broker_ip().unwrap(), None);
+        bitvmx.broker_host = Some("10.0.0.7".to_string());
+        assert_eq!(
+            bitvmx.broker_ip().unwrap(),
+            Some("10.0.0.7".parse().unwrap())
+        );
+        bitvmx.broker_host = Some("localhost".to_string());
+        assert!(bitvmx.broker_ip().unwrap().unwrap().is_loopback());
+    }
+
+    #[test]
+    fn test_operator_configs() {
+        let mut config = Config::default();
+        let mut operator = OperatorConfig {
+            name: "operator_2".to_string(),
+            bitvmx: config.bitvmx.clone(),
+        };
+        operator.bitvmx.broker_port = 33333;
+        config.operators.push(operator.clone());
+
+        let operators = config.operator_configs().unwrap();
+        let names: Vec<_> = operators
+            .iter()
+            .map(|operator| operator.name.as_str())
+            .collect();
+        assert_eq!(names, vec![DEFAULT_OPERATOR, "operator_2"]);
+        assert_eq!(operators[1].bitvmx.broker_port, 33333);
+
+        config.operators.push(operator);
+        assert!(config.operator_configs().is_err());
+        config.operators[1].name = DEFAULT_OPERATOR.to_string();
+        assert!(config.operator_configs().is_err());
+    }
+}
@@ -0,0 +1,136 @@
+use crate::config::GameConfig;
+use crate::games::program::InputValue;
+use crate::games::{Game, GamePhase, GameStatus, GameStep};
+use crate::models::{
+    AddNumbersGameEvent, AddNumbersGameStatus, AddNumbersSetup, AddNumbersSubmit, PlayerRole,
+};
+
+/// Both players set up two numbers, player 2 answers the challenge with their sum
+/// and player 1 disputes it when it is wrong
+#[derive(Debug, Clone)]
+pub struct AddNumbers {
+    program_definition: String,
+}
+
+impl AddNumbers {
+    pub fn new(game_config: &GameConfig) -> Self {
+        Self {
+            program_definition: game_config.program_definition.clone(),
+        }
+    }
+}
+
+impl Game for AddNumbers {
+    const GAME_TYPE: &'static str = "add-numbers";
+
+    type SetupData = AddNumbersSetup;
+    type SubmitData = AddNumbersSubmit;
+    type Status = AddNumbersGameStatus;
+    type Event = AddNumbersGameEvent;
+
+    fn program_definition(&self) -> String {
+        self.program_definition.clone()
+    }
+
+    fn setup_inputs(
+        &self,
+        _role: &PlayerRole,
+        data: &AddNumbersSetup,
+    ) -> Vec<(u32, Vec<InputValue>)> {
+        // Input 0 is the two numbers to sum concatenated
+        vec![(
+            0,
+            vec![InputValue::U32(data.number1), InputValue::U32(data.number2)],
+        )]
+    }
+
+    fn submit_input_index(&self) -> u32 {
+        // The first input is the numbers to sum
+        1
+    }
+
+    fn submit_input(&self, data: &AddNumbersSubmit) -> Vec<InputValue> {
+        vec![InputValue::U32(data.guess)]
+    }
+}
+
+impl GameStatus for AddNumbersGameStatus {
+    type Event = AddNumbersGameEvent;
+
+    fn initial() -> Self {
+        AddNumbersGameStatus::PlaceBet
+    }
+
+    fn next(&self, role: &PlayerRole, event: &AddNumbersGameEvent) -> Option<Self> {
+        AddNumbersGameStatus::next(self, role, event)
+    }
+
+    fn phase(&self) -> GamePhase {
+        match self {
+            AddNumbersGameStatus::SetupParticipants
+            | AddNumbersGameStatus::PlaceBet
+            | AddNumbersGameStatus::SetupFunding
+            | AddNumbersGameStatus::SetupGame => GamePhase::Setup,
+            AddNumbersGameStatus::StartGame => GamePhase::StartGame,
+            AddNumbersGameStatus::SubmitGameData => GamePhase::SubmitGameData,
+            AddNumbersGameStatus::GameComplete { outcome, reason } => GamePhase::GameComplete {
+                outcome: outcome.clone(),
+                reason: reason.clone(),
+            },
+            AddNumbersGameStatus::TransferBetFunds => GamePhase::TransferBetFunds,
+            AddNumbersGameStatus::Finished => GamePhase::Finished,
+        }
+    }
+}
+
+impl From<GameStep> for AddNumbersGameEvent {
+    fn from(step: GameStep) -> Self {
+        match step {
+            GameStep::PlaceBet => AddNumbersGameEvent::PlaceBet,
+            GameStep::SaveFundingUtxos => AddNumbersGameEvent::SaveFundingUtxos,
+            GameStep::SetupGame => AddNumbersGameEvent::SetupGame,
+            GameStep::StartGame => AddNumbersGameEvent::StartGame,
+            GameStep::SubmitGameData => AddNumbersGameEvent::SubmitGameData,
+            GameStep::CompleteGame { outcome, reason } => {
+                AddNumbersGameEvent::CompleteGame { outcome, reason }
+            }
+            GameStep::ClaimBet => AddNumbersGameEvent::ClaimBet,
+            GameStep::Finish => AddNumbersGameEvent::Finish,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::games::program::{InputOwner, ProgramDefinition, ADD_NUMBERS_YAML};
+
+    #[test]
+    fn test_add_numbers_inputs_fit_the_program() {
+        let game = AddNumbers::new(&Config::default().game);
+        let definition = ProgramDefinition::from_yaml(ADD_NUMBERS_YAML).unwrap();
+        let setup = AddNumbersSetup {
+            number1: 1,
+            number2: 258,
+        };
+
+        for role in [PlayerRole::Player1, PlayerRole::Player2] {
+            let inputs = game.setup_inputs(&role, &setup);
+            assert_eq!(inputs.len(), 1);
+            let (index, values) = &inputs[0];
+            assert_eq!(
+                definition.encode_input(*index, &[InputOwner::Const], values),
+                Ok(vec![0, 0, 0, 1, 0, 0, 1, 2])
+            );
+        }
+        assert_eq!(
+            definition.encode_input(
+                game.submit_input_index(),
+                &[InputOwner::Prover],
+                &game.submit_input(&AddNumbersSubmit { guess: 259 })
+            ),
+            Ok(vec![0, 0, 1, 3])
+        );
+    }
+}
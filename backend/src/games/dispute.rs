@@ -0,0 +1,157 @@
+//! Dispute resolution program steps shared by the games
+
+use crate::models::{GameReason, PlayerRole};
+use bitvmx_client::program::participant::ParticipantRole;
+use bitvmx_client::program::protocols::dispute;
+
+/// Dispute steps where a player waits for the other one to answer
+/// (transaction seen, transaction expected, role expected to dispatch it)
+pub fn timeout_steps(submit_input_index: u32) -> Vec<(String, String, ParticipantRole)> {
+    vec![
+        (
+            dispute::START_CH.to_string(),
+            dispute::input_tx_name(submit_input_index),
+            ParticipantRole::Prover,
+        ),
+        (
+            "NARY_PROVER_1".to_string(),
+            "NARY_VERIFIER_1".to_string(),
+            ParticipantRole::Verifier,
+        ),
+        (
+            "NARY_VERIFIER_1".to_string(),
+            "NARY_PROVER_2".to_string(),
+            ParticipantRole::Prover,
+        ),
+        (
+            "NARY_PROVER_2".to_string(),
+            "NARY_VERIFIER_2".to_string(),
+            ParticipantRole::Verifier,
+        ),
+    ]
+}
+
+/// Timeout transaction dispatched when `expected_tx` is missing
+pub fn timeout_tx_name(expected_tx: &str) -> String {
+    format!("{expected_tx}_TO")
+}
+
+/// Every timeout transaction of the dispute, one per timeout step and the challenge one
+pub fn timeout_tx_names(submit_input_index: u32) -> Vec<String> {
+    timeout_steps(submit_input_index)
+        .iter()
+        .map(|(_, expected, _)| expected.as_str())
+        .chain([dispute::CHALLENGE])
+        .map(timeout_tx_name)
+        .collect()
+}
+
+/// Decide the winner of the dispute from the names of the transactions seen on chain.
+/// The winner action transaction is the terminal one, the game was won by timeout when
+/// a timeout transaction was needed to get there. Returns None while there is no winner.
+pub fn dispute_outcome(
+    seen_txs: &[String],
+    submit_input_index: u32,
+) -> Option<(ParticipantRole, GameReason)> {
+    let seen = |name: &str| seen_txs.iter().any(|tx_name| tx_name == name);
+    let winner = [ParticipantRole::Prover, ParticipantRole::Verifier]
+        .into_iter()
+        .find(|role| seen(&dispute::action_wins(role, 1)))?;
+
+    let timed_out = timeout_tx_names(submit_input_index)
+        .iter()
+        .any(|tx_name| seen(tx_name));
+    let reason = if timed_out {
+        GameReason::Timeout
+    } else {
+        GameReason::Challenge
+    };
+    Some((winner, reason))
+}
+
+/// Program transaction that spends the output of the winner action transaction to the winner operator.
+/// BitVMX builds and signs it at setup, so dispatching it again yields the same transaction
+pub fn payout_tx_name(winner: &ParticipantRole) -> String {
+    format!("{}_PAYOUT", dispute::action_wins(winner, 1))
+}
+
+/// Role of the player in the BitVMX dispute, player 1 challenges the answer sent by player 2
+pub fn participant_role(role: &PlayerRole) -> ParticipantRole {
+    match role {
+        PlayerRole::Player1 => ParticipantRole::Verifier,
+        PlayerRole::Player2 => ParticipantRole::Prover,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dispute_outcome() {
+        let prover_wins = dispute::action_wins(&ParticipantRole::Prover, 1);
+        let verifier_wins = dispute::action_wins(&ParticipantRole::Verifier, 1);
+        let challenge_timeout = timeout_tx_name(dispute::CHALLENGE);
+        let dispute_outcome = |seen_txs: &[String]| dispute_outcome(seen_txs, 1);
+
+        // No terminal transaction yet
+        assert_eq!(dispute_outcome(&[]), None);
+        assert_eq!(
+            dispute_outcome(&[dispute::COMMITMENT.to_string(), challenge_timeout.clone()]),
+            None
+        );
+
+        // Only the timeout transactions of the protocol count
+        assert_eq!(
+            dispute_outcome(&["UNKNOWN_TO".to_string(), prover_wins.clone()]),
+            Some((ParticipantRole::Prover, GameReason::Challenge))
+        );
+        assert_eq!(
+            dispute_outcome(&[timeout_tx_name("NARY_VERIFIER_1"), prover_wins.clone()]),
+            Some((ParticipantRole::Prover, GameReason::Timeout))
+        );
+
+        assert_eq!(
+            dispute_outcome(&[dispute::EXECUTE.to_string(), prover_wins.clone()]),
+            Some((ParticipantRole::Prover, GameReason::Challenge))
+        );
+        assert_eq!(
+            dispute_outcome(&[challenge_timeout.clone(), prover_wins]),
+            Some((ParticipantRole::Prover, GameReason::Timeout))
+        );
+        assert_eq!(
+            dispute_outcome(&[dispute::EXECUTE.to_string(), verifier_wins.clone()]),
+            Some((ParticipantRole::Verifier, GameReason::Challenge))
+        );
+        assert_eq!(
+            dispute_outcome(&[challenge_timeout, verifier_wins]),
+            Some((ParticipantRole::Verifier, GameReason::Timeout))
+        );
+    }
+
+    #[test]
+    fn test_timeout_tx_names() {
+        assert_eq!(
+            timeout_tx_names(1),
+            vec![
+                format!("{}_TO", dispute::input_tx_name(1)),
+                "NARY_VERIFIER_1_TO".to_string(),
+                "NARY_PROVER_2_TO".to_string(),
+                "NARY_VERIFIER_2_TO".to_string(),
+                format!("{}_TO", dispute::CHALLENGE),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_participant_role() {
+        assert_eq!(
+            participant_role(&PlayerRole::Player1),
+            ParticipantRole::Verifier
+        );
+        assert_eq!(
+            participant_role(&PlayerRole::Player2),
+            ParticipantRole::Prover
+        );
+    }
+}
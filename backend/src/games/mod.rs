@@ -0,0 +1,144 @@
+//! Games played over a BitVMX dispute resolution program.
+//!
+//! A game only defines what is specific to it: the program inputs of each role, the
+//! mapping from dispute transactions to the winner and its request payloads. Setup,
+//! funding, challenge, waiting, persistence and routing are shared by
+//! [`crate::services::GameService`] and the `/api/games/{game_type}` routes.
+
+pub mod add_numbers;
+pub mod dispute;
+pub mod program;
+
+pub use add_numbers::AddNumbers;
+
+use crate::config::GameConfig;
+use crate::games::program::InputValue;
+use crate::models::{GameOutcome, GameReason, InvalidTransition, PlayerRole};
+use crate::services::GameRegistry;
+use bitvmx_client::program::participant::ParticipantRole;
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt::Debug;
+
+/// Register the games played by the backend, a new game is only added here
+pub fn register_games(
+    registry: &mut GameRegistry,
+    game_config: &GameConfig,
+) -> Result<(), anyhow::Error> {
+    registry.register(AddNumbers::new(game_config))
+}
+
+/// Payload sent by a player to a game endpoint
+pub trait GameData: Serialize + DeserializeOwned + Debug + Clone + Send + Sync + 'static {}
+
+impl<T> GameData for T where T: Serialize + DeserializeOwned + Debug + Clone + Send + Sync + 'static {}
+
+/// Steps of the shared game flow, fired on the status of every game type
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameStep {
+    /// Player 2 accepted the bet and waits for player 1 funding UTXOs
+    PlaceBet,
+    /// The funding UTXOs of the game were saved
+    SaveFundingUtxos,
+    /// The setup data was sent to BitVMX
+    SetupGame,
+    /// The challenge transaction was seen on chain
+    StartGame,
+    /// Player 2 submitted its answer
+    SubmitGameData,
+    /// The dispute finished with a known outcome
+    CompleteGame {
+        outcome: GameOutcome,
+        reason: GameReason,
+    },
+    /// The winner claimed the bet
+    ClaimBet,
+    /// The payout transaction was confirmed
+    Finish,
+}
+
+/// Where a game status is in the shared game flow
+#[derive(Debug, Clone, PartialEq)]
+pub enum GamePhase {
+    /// Participants, bet, funding and program setup
+    Setup,
+    /// Waiting for the challenge transaction
+    StartGame,
+    /// Waiting for the answer of player 2 and the dispute outcome
+    SubmitGameData,
+    GameComplete {
+        outcome: GameOutcome,
+        reason: GameReason,
+    },
+    /// The winner claimed the bet, waiting for the payout transaction
+    TransferBetFunds,
+    Finished,
+}
+
+/// Event of a game, the shared game flow fires its [`GameStep`]s
+pub trait GameEvent: From<GameStep> + Debug + Clone + Send + Sync + 'static {}
+
+impl<T> GameEvent for T where T: From<GameStep> + Debug + Clone + Send + Sync + 'static {}
+
+/// Status of a game, persisted in the game records
+pub trait GameStatus: GameData + PartialEq {
+    type Event: GameEvent;
+
+    /// Status of a game once its participants are set up
+    fn initial() -> Self;
+
+    /// Next status when `role` may fire `event` from the current status
+    fn next(&self, role: &PlayerRole, event: &Self::Event) -> Option<Self>;
+
+    /// Where the status is in the shared game flow
+    fn phase(&self) -> GamePhase;
+
+    /// Same as [`Self::next`] but returns a typed error for illegal transitions
+    fn transition(&self, role: &PlayerRole, event: Self::Event) -> Result<Self, InvalidTransition> {
+        self.next(role, &event).ok_or_else(|| InvalidTransition {
+            status: format!("{self:?}"),
+            role: role.clone(),
+            event: format!("{event:?}"),
+        })
+    }
+}
+
+/// What a game must define to be played with the shared game service
+pub trait Game: Debug + Send + Sync + 'static {
+    /// Name of the game in the API path and in the stored games, e.g. "add-numbers"
+    const GAME_TYPE: &'static str;
+
+    /// Data both players send to set up the program
+    type SetupData: GameData;
+
+    /// Data player 2 submits to answer the challenge
+    type SubmitData: GameData;
+
+    /// Status of the game records
+    type Status: GameStatus<Event = Self::Event>;
+
+    /// Events moving the game from one status to the next
+    type Event: GameEvent;
+
+    /// Program definition file used when the setup request does not override it
+    fn program_definition(&self) -> String;
+
+    /// Program inputs set by `role` when the game is set up, as (input index, values).
+    /// They are checked against the `inputs` of the program definition before they are set.
+    fn setup_inputs(
+        &self,
+        role: &PlayerRole,
+        data: &Self::SetupData,
+    ) -> Vec<(u32, Vec<InputValue>)>;
+
+    /// Program input player 2 answers the challenge with
+    fn submit_input_index(&self) -> u32;
+
+    /// Values of the submit input for the player 2 answer
+    fn submit_input(&self, data: &Self::SubmitData) -> Vec<InputValue>;
+
+    /// Decide the winner from the names of the dispute transactions seen on chain,
+    /// None while there is no winner
+    fn dispute_outcome(&self, seen_txs: &[String]) -> Option<(ParticipantRole, GameReason)> {
+        dispute::dispute_outcome(seen_txs, self.submit_input_index())
+    }
+}
@@ -0,0 +1,278 @@
+//! Program definition YAML passed to BitVMX, used to check the program inputs before they are set
+
+use bitvmx_client::program::participant::ParticipantRole;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Participant that sets a program input
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputOwner {
+    /// Known by every participant when the program is set up
+    Const,
+    Prover,
+    Verifier,
+}
+
+impl InputOwner {
+    /// Owner of the inputs set by `role`
+    pub fn of(role: &ParticipantRole) -> Self {
+        match role {
+            ParticipantRole::Prover => Self::Prover,
+            ParticipantRole::Verifier => Self::Verifier,
+        }
+    }
+}
+
+/// Byte order of the numbers written to an input
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Endianness {
+    /// Encoding used by the inputs of the existing programs
+    #[default]
+    Big,
+    Little,
+}
+
+/// Input declared in the `inputs` section of the program definition
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProgramInput {
+    /// Size in bytes
+    pub size: usize,
+    pub owner: InputOwner,
+    #[serde(default)]
+    pub endianness: Endianness,
+}
+
+/// Program definition YAML, e.g. `add-test-with-const-pre.yaml`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProgramDefinition {
+    pub elf: String,
+    pub nary_search: u32,
+    pub max_steps: u64,
+    pub input_section_name: String,
+    /// Inputs in program input index order
+    pub inputs: Vec<ProgramInput>,
+}
+
+/// Typed value written to a program input, numbers use the input endianness
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputValue {
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    Bytes(Vec<u8>),
+}
+
+impl InputValue {
+    fn encode(&self, endianness: &Endianness) -> Vec<u8> {
+        match (self, endianness) {
+            (Self::U8(value), _) => vec![*value],
+            (Self::U16(value), Endianness::Big) => value.to_be_bytes().to_vec(),
+            (Self::U16(value), Endianness::Little) => value.to_le_bytes().to_vec(),
+            (Self::U32(value), Endianness::Big) => value.to_be_bytes().to_vec(),
+            (Self::U32(value), Endianness::Little) => value.to_le_bytes().to_vec(),
+            (Self::U64(value), Endianness::Big) => value.to_be_bytes().to_vec(),
+            (Self::U64(value), Endianness::Little) => value.to_le_bytes().to_vec(),
+            (Self::Bytes(bytes), _) => bytes.clone(),
+        }
+    }
+}
+
+/// Error returned when input values do not match the program definition
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidProgramInput(pub String);
+
+impl std::fmt::Display for InvalidProgramInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid program input: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidProgramInput {}
+
+impl ProgramDefinition {
+    /// Parse a program definition YAML
+    pub fn from_yaml(yaml: &str) -> Result<Self, anyhow::Error> {
+        config::Config::builder()
+            .add_source(config::File::from_str(yaml, config::FileFormat::Yaml))
+            .build()
+            .and_then(|definition| definition.try_deserialize())
+            .map_err(|e| anyhow::anyhow!("Failed to parse program definition: {e:?}"))
+    }
+
+    /// Read and parse a program definition file
+    pub fn load(path: &Path) -> Result<Self, anyhow::Error> {
+        let yaml = std::fs::read_to_string(path).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to read program definition {}: {e:?}",
+                path.display()
+            )
+        })?;
+        Self::from_yaml(&yaml)
+    }
+
+    /// Check the values of input `index` against its declared size and owner,
+    /// `owners` are the owners the caller may set, and encode them as the input bytes
+    pub fn encode_input(
+        &self,
+        index: u32,
+        owners: &[InputOwner],
+        values: &[InputValue],
+    ) -> Result<Vec<u8>, InvalidProgramInput> {
+        let input = usize::try_from(index)
+            .ok()
+            .and_then(|index| self.inputs.get(index))
+            .ok_or_else(|| {
+                InvalidProgramInput(format!(
+                    "Input {index} is not declared, the program has {} inputs",
+                    self.inputs.len()
+                ))
+            })?;
+
+        if !owners.contains(&input.owner) {
+            return Err(InvalidProgramInput(format!(
+                "Input {index} is owned by {:?}, expected one of {owners:?}",
+                input.owner
+            )));
+        }
+
+        let bytes: Vec<u8> = values
+            .iter()
+            .flat_map(|value| value.encode(&input.endianness))
+            .collect();
+        if bytes.len() != input.size {
+            return Err(InvalidProgramInput(format!(
+                "Input {index} is {} bytes, got {} bytes",
+                input.size,
+                bytes.len()
+            )));
+        }
+
+        Ok(bytes)
+    }
+}
+
+/// `add-test-with-const-pre.yaml` from the bitvmx-client verifiers, the tests check it matches
+/// the file of the `deps/rust-bitvmx-client` submodule
+#[cfg(any(test, feature = "test-support"))]
+pub const ADD_NUMBERS_YAML: &str = "
+elf: add-test.elf
+nary_search: 8
+max_steps: 50
+input_section_name: .input
+inputs:
+- size: 8
+  owner: const
+- size: 4
+  owner: prover
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_add_numbers_yaml_is_the_bitvmx_client_one() {
+        let game_config = Config::default().game;
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join(&game_config.bitvmx_client_path)
+            .join(&game_config.program_definition);
+        let Ok(yaml) = std::fs::read_to_string(&path) else {
+            // CI checks out the submodules, a local checkout may not have them
+            assert!(
+                std::env::var_os("CI").is_none(),
+                "{} is missing",
+                path.display()
+            );
+            return;
+        };
+        assert_eq!(
+            ProgramDefinition::from_yaml(&yaml).unwrap(),
+            ProgramDefinition::from_yaml(ADD_NUMBERS_YAML).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_program_definition() {
+        let definition = ProgramDefinition::from_yaml(ADD_NUMBERS_YAML).unwrap();
+        assert_eq!(definition.elf, "add-test.elf");
+        assert_eq!(definition.nary_search, 8);
+        assert_eq!(
+            definition.inputs,
+            vec![
+                ProgramInput {
+                    size: 8,
+                    owner: InputOwner::Const,
+                    endianness: Endianness::Big,
+                },
+                ProgramInput {
+                    size: 4,
+                    owner: InputOwner::Prover,
+                    endianness: Endianness::Big,
+                },
+            ]
+        );
+
+        assert!(ProgramDefinition::from_yaml("elf: add-test.elf").is_err());
+    }
+
+    #[test]
+    fn test_encode_input() {
+        let definition = ProgramDefinition::from_yaml(ADD_NUMBERS_YAML).unwrap();
+        let setup = [InputOwner::Const, InputOwner::Verifier];
+
+        assert_eq!(
+            definition.encode_input(0, &setup, &[InputValue::U32(1), InputValue::U32(258)]),
+            Ok(vec![0, 0, 0, 1, 0, 0, 1, 2])
+        );
+        assert_eq!(
+            definition.encode_input(1, &[InputOwner::Prover], &[InputValue::U32(259)]),
+            Ok(vec![0, 0, 1, 3])
+        );
+
+        // Wrong size
+        assert!(definition
+            .encode_input(0, &setup, &[InputValue::U32(1)])
+            .is_err());
+        assert!(definition
+            .encode_input(1, &[InputOwner::Prover], &[InputValue::U64(259)])
+            .is_err());
+        // The verifier cannot set the prover input
+        assert!(definition
+            .encode_input(1, &setup, &[InputValue::U32(259)])
+            .is_err());
+        // Not declared
+        assert!(definition
+            .encode_input(2, &[InputOwner::Prover], &[InputValue::U32(259)])
+            .is_err());
+    }
+
+    #[test]
+    fn test_encode_little_endian_input() {
+        let definition = ProgramDefinition::from_yaml(
+            "
+elf: test.elf
+nary_search: 4
+max_steps: 10
+input_section_name: .input
+inputs:
+- size: 6
+  owner: verifier
+  endianness: little
+",
+        )
+        .unwrap();
+
+        assert_eq!(
+            definition.encode_input(
+                0,
+                &[InputOwner::Verifier],
+                &[InputValue::U16(258), InputValue::Bytes(vec![9, 8, 7, 6])]
+            ),
+            Ok(vec![2, 1, 9, 8, 7, 6])
+        );
+    }
+}
@@ -1,4 +1,6 @@
-use crate::services::AddNumbersService;
+use crate::games::Game;
+use crate::models::{BackoffPolicy, JobRecord, LEGACY_GAME_TYPE};
+use crate::services::GameService;
 use async_trait::async_trait;
 use std::fmt::Debug;
 use std::sync::Arc;
@@ -7,36 +9,286 @@ use uuid::Uuid;
 // ---------- Definition of a generic Job ----------
 #[async_trait]
 pub trait Job: Send + Sync + Debug + 'static {
-    async fn run(self: Box<Self>) -> Result<(), anyhow::Error>;
+    /// Kind of the job, a [`JobFactory`] rebuilds the job from it after a restart
+    fn kind(&self) -> &'static str;
+
+    /// Program the job works on, persisted as the job payload
+    fn program_id(&self) -> Uuid;
+
+    /// Game the job belongs to, only the factory of that game rebuilds it
+    fn game_type(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Jobs with the same key are merged while one is queued or running, `None` never merges
+    fn dedupe_key(&self) -> Option<String> {
+        None
+    }
+
+    /// How the job is retried when it fails
+    fn backoff(&self) -> BackoffPolicy {
+        BackoffPolicy::default()
+    }
+
+    /// Run one attempt of the job
+    async fn run(&self) -> Result<(), anyhow::Error>;
+
+    /// Called when the job is cancelled while running, once its attempt was stopped
+    async fn cancel(&self) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+}
+
+/// Rebuilds the jobs persisted by the [`JobWorker`](crate::jobs::JobWorker) from their kind and payload
+pub trait JobFactory: Send + Sync + Debug {
+    /// The job of a record, `None` for jobs built by other factories
+    fn build(&self, record: &JobRecord) -> Option<Box<dyn Job>>;
+}
+
+/// Dedupe key of a job that runs once at a time for a program
+pub fn program_dedupe_key(kind: &str, program_id: Uuid) -> String {
+    format!("{kind}:{program_id}")
 }
 
+// Kinds of the game jobs
+pub const WAIT_START_GAME: &str = "wait_start_game";
+pub const WAIT_PLAYER2_WINS_GAME_OUTCOME: &str = "wait_player2_wins_game_outcome";
+pub const WAIT_SUBMIT_OUTCOME: &str = "wait_submit_outcome";
+pub const WAIT_PAYOUT: &str = "wait_payout";
+pub const WATCH_TIMEOUT: &str = "watch_timeout";
+pub const RECONCILE_GAMES: &str = "reconcile_games";
+
+/// Kinds of the game jobs that wait for the whole game, one per game is running at any time,
+/// so they are not limited by `jobs.max_concurrency` by default
+pub const LONG_LIVED_KINDS: &[&str] = &[
+    WAIT_START_GAME,
+    WAIT_PLAYER2_WINS_GAME_OUTCOME,
+    WAIT_SUBMIT_OUTCOME,
+    WAIT_PAYOUT,
+    WATCH_TIMEOUT,
+];
+
 // Implementation of Jobs
 #[derive(Debug)]
-pub struct WaitStartGameJob {
+pub struct WaitStartGameJob<G: Game> {
     pub program_id: Uuid,
-    pub add_numbers_service: Arc<AddNumbersService>,
+    pub game_service: Arc<GameService<G>>,
 }
 
 #[async_trait]
-impl Job for WaitStartGameJob {
-    async fn run(self: Box<Self>) -> Result<(), anyhow::Error> {
-        self.add_numbers_service
-            .wait_start_game_tx(self.program_id)
-            .await
+impl<G: Game> Job for WaitStartGameJob<G> {
+    fn kind(&self) -> &'static str {
+        WAIT_START_GAME
+    }
+
+    fn program_id(&self) -> Uuid {
+        self.program_id
+    }
+
+    fn game_type(&self) -> Option<&'static str> {
+        Some(G::GAME_TYPE)
+    }
+
+    fn dedupe_key(&self) -> Option<String> {
+        Some(program_dedupe_key(self.kind(), self.program_id))
+    }
+
+    async fn run(&self) -> Result<(), anyhow::Error> {
+        self.game_service.wait_start_game_tx(self.program_id).await
     }
 }
 
 #[derive(Debug)]
-pub struct WaitPlayer2WinsGameOutcomeJob {
+pub struct WaitPlayer2WinsGameOutcomeJob<G: Game> {
     pub program_id: Uuid,
-    pub add_numbers_service: Arc<AddNumbersService>,
+    pub game_service: Arc<GameService<G>>,
 }
 
 #[async_trait]
-impl Job for WaitPlayer2WinsGameOutcomeJob {
-    async fn run(self: Box<Self>) -> Result<(), anyhow::Error> {
-        self.add_numbers_service
+impl<G: Game> Job for WaitPlayer2WinsGameOutcomeJob<G> {
+    fn kind(&self) -> &'static str {
+        WAIT_PLAYER2_WINS_GAME_OUTCOME
+    }
+
+    fn program_id(&self) -> Uuid {
+        self.program_id
+    }
+
+    fn game_type(&self) -> Option<&'static str> {
+        Some(G::GAME_TYPE)
+    }
+
+    fn dedupe_key(&self) -> Option<String> {
+        Some(program_dedupe_key(self.kind(), self.program_id))
+    }
+
+    async fn run(&self) -> Result<(), anyhow::Error> {
+        self.game_service
             .wait_player2_wins_game_outcome_tx(self.program_id)
             .await
     }
 }
+
+#[derive(Debug)]
+pub struct WaitSubmitOutcomeJob<G: Game> {
+    pub program_id: Uuid,
+    pub game_service: Arc<GameService<G>>,
+}
+
+#[async_trait]
+impl<G: Game> Job for WaitSubmitOutcomeJob<G> {
+    fn kind(&self) -> &'static str {
+        WAIT_SUBMIT_OUTCOME
+    }
+
+    fn program_id(&self) -> Uuid {
+        self.program_id
+    }
+
+    fn game_type(&self) -> Option<&'static str> {
+        Some(G::GAME_TYPE)
+    }
+
+    fn dedupe_key(&self) -> Option<String> {
+        Some(program_dedupe_key(self.kind(), self.program_id))
+    }
+
+    async fn run(&self) -> Result<(), anyhow::Error> {
+        self.game_service
+            .wait_submit_outcome_tx(self.program_id)
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct WaitPayoutJob<G: Game> {
+    pub program_id: Uuid,
+    pub game_service: Arc<GameService<G>>,
+}
+
+#[async_trait]
+impl<G: Game> Job for WaitPayoutJob<G> {
+    fn kind(&self) -> &'static str {
+        WAIT_PAYOUT
+    }
+
+    fn program_id(&self) -> Uuid {
+        self.program_id
+    }
+
+    fn game_type(&self) -> Option<&'static str> {
+        Some(G::GAME_TYPE)
+    }
+
+    fn dedupe_key(&self) -> Option<String> {
+        Some(program_dedupe_key(self.kind(), self.program_id))
+    }
+
+    async fn run(&self) -> Result<(), anyhow::Error> {
+        self.game_service.wait_payout_tx(self.program_id).await?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct WatchTimeoutJob<G: Game> {
+    pub program_id: Uuid,
+    pub game_service: Arc<GameService<G>>,
+}
+
+#[async_trait]
+impl<G: Game> Job for WatchTimeoutJob<G> {
+    fn kind(&self) -> &'static str {
+        WATCH_TIMEOUT
+    }
+
+    fn program_id(&self) -> Uuid {
+        self.program_id
+    }
+
+    fn game_type(&self) -> Option<&'static str> {
+        Some(G::GAME_TYPE)
+    }
+
+    fn dedupe_key(&self) -> Option<String> {
+        Some(program_dedupe_key(self.kind(), self.program_id))
+    }
+
+    async fn run(&self) -> Result<(), anyhow::Error> {
+        self.game_service.watch_timeout(self.program_id).await
+    }
+}
+
+/// Reconciles the stored games with the chain once, enqueued on every reconciliation tick.
+/// It is not bound to a program, its payload is the nil program id.
+#[derive(Debug)]
+pub struct ReconcileGamesJob<G: Game> {
+    pub game_service: Arc<GameService<G>>,
+}
+
+#[async_trait]
+impl<G: Game> Job for ReconcileGamesJob<G> {
+    fn kind(&self) -> &'static str {
+        RECONCILE_GAMES
+    }
+
+    fn program_id(&self) -> Uuid {
+        Uuid::nil()
+    }
+
+    fn game_type(&self) -> Option<&'static str> {
+        Some(G::GAME_TYPE)
+    }
+
+    fn dedupe_key(&self) -> Option<String> {
+        Some(format!("{}:{}", self.kind(), G::GAME_TYPE))
+    }
+
+    async fn run(&self) -> Result<(), anyhow::Error> {
+        self.game_service.reconcile_games().await
+    }
+}
+
+/// Rebuilds the jobs of the games played by a [`GameService`]
+#[derive(Debug)]
+pub struct GameJobFactory<G: Game> {
+    pub game_service: Arc<GameService<G>>,
+}
+
+impl<G: Game> JobFactory for GameJobFactory<G> {
+    fn build(&self, record: &JobRecord) -> Option<Box<dyn Job>> {
+        // Jobs persisted before games were pluggable have no game type
+        let game_type = record.game_type.as_deref().unwrap_or(LEGACY_GAME_TYPE);
+        if game_type != G::GAME_TYPE {
+            return None;
+        }
+        let program_id = record.program_id;
+        let game_service = self.game_service.clone();
+        let job: Box<dyn Job> = match record.kind.as_str() {
+            WAIT_START_GAME => Box::new(WaitStartGameJob {
+                program_id,
+                game_service,
+            }),
+            WAIT_PLAYER2_WINS_GAME_OUTCOME => Box::new(WaitPlayer2WinsGameOutcomeJob {
+                program_id,
+                game_service,
+            }),
+            WAIT_SUBMIT_OUTCOME => Box::new(WaitSubmitOutcomeJob {
+                program_id,
+                game_service,
+            }),
+            WAIT_PAYOUT => Box::new(WaitPayoutJob {
+                program_id,
+                game_service,
+            }),
+            WATCH_TIMEOUT => Box::new(WatchTimeoutJob {
+                program_id,
+                game_service,
+            }),
+            RECONCILE_GAMES => Box::new(ReconcileGamesJob { game_service }),
+            _ => return None,
+        };
+        Some(job)
+    }
+}
@@ -1,87 +1,503 @@
-use std::sync::Arc;
-// use std::time::Duration;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 use tokio::sync::broadcast::Sender;
-use tokio::sync::mpsc;
+use tokio::sync::{Notify, Semaphore};
 use tokio::task::JoinHandle;
-// use tokio::time::sleep;
-use tracing::{debug, info, warn, Instrument};
+use tokio::time::{sleep, timeout};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn, Instrument};
+use uuid::Uuid;
 
-use crate::jobs::Job;
+use crate::config::JobsConfig;
+use crate::jobs::{Job, JobFactory};
+use crate::models::{InvalidJobAction, JobNotFound, JobRecord, JobStatus};
+use crate::stores::job_store::now_ms;
+use crate::stores::JobStore;
 
-//const CHECK_SHUTDOWN_INTERVAL: u64 = 100; // 100 milliseconds
+const IDLE_CHECK_INTERVAL: u64 = 60_000; // 60 seconds, how long the worker sleeps without due jobs
+const PRUNE_INTERVAL: u64 = 60_000; // 60 seconds, how often the finished jobs past the retention are removed
 
-#[derive(Clone, Debug)]
+/// Handle of a running job
+#[derive(Debug)]
+struct JobHandle {
+    /// Stops the running attempt of the job
+    cancellation: CancellationToken,
+    /// Task running the attempt, awaited when the worker is drained
+    task: JoinHandle<()>,
+}
+
+/// Runs the persisted jobs of the [`JobStore`] in background, retrying the failed ones
+#[derive(Debug)]
 pub struct JobWorker {
-    job_queue_sender: mpsc::UnboundedSender<Box<dyn Job>>,
+    store: Arc<dyn JobStore>,
+    config: JobsConfig,
+    /// Limits the running jobs of each kind
+    semaphores: Mutex<HashMap<String, Arc<Semaphore>>>,
+    /// Jobs of the queued and running records, rebuilt by the factories after a restart
+    jobs: Mutex<HashMap<Uuid, Arc<dyn Job>>>,
+    /// Tasks of the running jobs
+    handles: Mutex<HashMap<Uuid, JobHandle>>,
+    factories: RwLock<Vec<Arc<dyn JobFactory>>>,
+    /// Wakes up the worker when a job is enqueued or can be built
+    wake_up: Notify,
+    /// Stops every running attempt once the shutdown grace period is over
+    interrupted: CancellationToken,
 }
 
 impl JobWorker {
     /// Start the job worker
-    /// It will create a new job queue and spawn the worker in background
-    pub fn start(shutdown_tx: &Sender<()>) -> (Arc<Self>, JoinHandle<Result<(), anyhow::Error>>) {
-        let (tx, rx) = mpsc::unbounded_channel::<Box<dyn Job>>();
-
+    /// It will run the jobs of the store in background, the jobs left running by a previous run are queued again
+    pub fn start(
+        store: Arc<dyn JobStore>,
+        config: JobsConfig,
+        shutdown_tx: &Sender<()>,
+    ) -> (Arc<Self>, JoinHandle<Result<(), anyhow::Error>>) {
         let job_worker = Arc::new(Self {
-            job_queue_sender: tx,
+            store,
+            config,
+            semaphores: Mutex::new(HashMap::new()),
+            jobs: Mutex::new(HashMap::new()),
+            handles: Mutex::new(HashMap::new()),
+            factories: RwLock::new(Vec::new()),
+            wake_up: Notify::new(),
+            interrupted: CancellationToken::new(),
         });
-        let worker_task = Self::spawn_worker(rx, shutdown_tx);
+        let worker_task = Self::spawn_worker(job_worker.clone(), shutdown_tx);
         (job_worker, worker_task)
     }
 
-    /// Enqueue a job
-    pub fn enqueue<J: Job>(&self, job: J) -> Result<(), anyhow::Error> {
-        debug!("Enqueuing job: {}", std::any::type_name::<J>());
-        self.job_queue_sender
-            .send(Box::new(job))
-            .map_err(|_| anyhow::anyhow!("Failed to enqueue job - channel closed"))?;
-        Ok(())
+    /// Register a factory to rebuild the persisted jobs of its kinds
+    pub fn register_factory(&self, factory: Arc<dyn JobFactory>) {
+        self.factories.write().unwrap().push(factory);
+        self.wake_up.notify_one();
+    }
+
+    /// Enqueue a job, it is persisted before returning.
+    /// A job with the dedupe key of a queued or running job is merged into it.
+    /// Returns the ID of the job
+    pub async fn enqueue<J: Job>(&self, job: J) -> Result<Uuid, anyhow::Error> {
+        debug!("Enqueuing job: {}", job.kind());
+        let mut record = JobRecord::new(job.kind(), job.program_id(), job.backoff(), now_ms());
+        record.game_type = job.game_type().map(str::to_string);
+        record.dedupe_key = job.dedupe_key();
+        self.jobs.lock().unwrap().insert(record.id, Arc::new(job));
+        let saved = match self.store.save_unless_pending(&record).await {
+            Ok(saved) => saved,
+            Err(e) => {
+                self.jobs.lock().unwrap().remove(&record.id);
+                return Err(anyhow::anyhow!("Failed to enqueue job: {e:?}"));
+            }
+        };
+        if saved.id != record.id {
+            self.jobs.lock().unwrap().remove(&record.id);
+            debug!(
+                "Job {} {} is already pending, not enqueuing it again",
+                saved.kind, saved.id
+            );
+            return Ok(saved.id);
+        }
+        self.wake_up.notify_one();
+        Ok(record.id)
+    }
+
+    /// Whether a job of `kind` for the program is queued or running
+    pub async fn is_pending(&self, kind: &str, program_id: Uuid) -> bool {
+        self.store
+            .jobs()
+            .await
+            .iter()
+            .any(|job| job.kind == kind && job.program_id == program_id && job.status.is_pending())
+    }
+
+    /// Jobs with the given status, or every job including the dead letters, oldest first
+    pub async fn list(&self, status: Option<JobStatus>) -> Vec<JobRecord> {
+        let mut jobs = self.store.jobs().await;
+        jobs.extend(self.store.dead_letters().await);
+        jobs.retain(|job| status.is_none_or(|status| job.status == status));
+        jobs.sort_by_key(|job| job.created_at_ms);
+        jobs
+    }
+
+    /// Get a job, including the dead letters
+    pub async fn get(&self, id: Uuid) -> Result<JobRecord, anyhow::Error> {
+        match self.store.get(id).await {
+            Some(job) => Ok(job),
+            None => self
+                .store
+                .get_dead_letter(id)
+                .await
+                .ok_or(anyhow::Error::new(JobNotFound(id))),
+        }
+    }
+
+    /// Cancel a queued or running job, a running attempt is stopped
+    pub async fn cancel(&self, id: Uuid) -> Result<JobRecord, anyhow::Error> {
+        let job = self.get(id).await?;
+        if !job.status.is_pending() {
+            return Err(anyhow::Error::new(InvalidJobAction {
+                id,
+                status: job.status,
+                action: "cancel",
+            }));
+        }
+
+        let job = self
+            .store
+            .update(id, Box::new(|job| job.status = JobStatus::Cancelled))
+            .await?;
+        if let Some(handle) = self.handles.lock().unwrap().remove(&id) {
+            handle.cancellation.cancel();
+        }
+        self.jobs.lock().unwrap().remove(&id);
+        info!("Job {} {} cancelled", job.kind, id);
+        Ok(job)
+    }
+
+    /// Queue again a job of the dead letter list, with all its attempts.
+    /// Returns the queued or running job with the same dedupe key instead when there is one
+    pub async fn retry(&self, id: Uuid) -> Result<JobRecord, anyhow::Error> {
+        let job = self.get(id).await?;
+        if job.status != JobStatus::Failed {
+            return Err(anyhow::Error::new(InvalidJobAction {
+                id,
+                status: job.status,
+                action: "retry",
+            }));
+        }
+
+        let now = now_ms();
+        let job = self
+            .store
+            .restore(
+                id,
+                Box::new(move |job| {
+                    job.status = JobStatus::Queued;
+                    job.attempts = 0;
+                    job.next_run_at_ms = now;
+                }),
+            )
+            .await?;
+        if job.id != id {
+            info!(
+                "Job {} {} not retried, job {} is already {:?}",
+                job.kind, id, job.id, job.status
+            );
+            return Ok(job);
+        }
+        info!("Job {} {} queued again", job.kind, id);
+        self.wake_up.notify_one();
+        Ok(job)
+    }
+
+    /// Wait up to `grace_period` for the running jobs to finish, the ones still running are then
+    /// stopped and queued again so they resume after a restart. Their cancel hook is not called.
+    /// Call it once the worker task stopped, so no new job starts meanwhile.
+    /// Returns the jobs queued again
+    pub async fn drain(&self, grace_period: Duration) -> Result<Vec<JobRecord>, anyhow::Error> {
+        let mut tasks: Vec<_> = self
+            .handles
+            .lock()
+            .unwrap()
+            .drain()
+            .map(|(_, handle)| handle.task)
+            .collect();
+        info!(
+            "Waiting up to {} ms for {} running jobs",
+            grace_period.as_millis(),
+            tasks.len()
+        );
+        if timeout(grace_period, Self::join_tasks(&mut tasks))
+            .await
+            .is_err()
+        {
+            self.interrupted.cancel();
+            Self::join_tasks(&mut tasks).await;
+        }
+
+        let mut checkpointed = Vec::new();
+        for record in self.store.jobs().await {
+            if record.status != JobStatus::Running {
+                continue;
+            }
+            warn!(
+                "Job {} {} did not finish in the grace period, queuing it again",
+                record.kind, record.id
+            );
+            let record = self
+                .store
+                .update(record.id, Box::new(|job| job.status = JobStatus::Queued))
+                .await?;
+            checkpointed.push(record);
+        }
+        Ok(checkpointed)
+    }
+
+    /// Wait for the tasks of the running jobs, a task is removed once it finished
+    async fn join_tasks(tasks: &mut Vec<JoinHandle<()>>) {
+        while let Some(task) = tasks.last_mut() {
+            if let Err(e) = task.await {
+                error!("Job task failed: {e}");
+            }
+            tasks.pop();
+        }
+    }
+
+    /// Semaphore limiting the running jobs of `kind`, `None` when they are not limited
+    fn semaphore(&self, kind: &str) -> Option<Arc<Semaphore>> {
+        let limit = self.config.concurrency(kind)?;
+        Some(
+            self.semaphores
+                .lock()
+                .unwrap()
+                .entry(kind.to_string())
+                .or_insert_with(|| Arc::new(Semaphore::new(limit)))
+                .clone(),
+        )
+    }
+
+    /// The job of a record, built by the registered factories when it is not in memory
+    fn job(&self, record: &JobRecord) -> Option<Arc<dyn Job>> {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(job) = jobs.get(&record.id) {
+            return Some(job.clone());
+        }
+        let job: Arc<dyn Job> = self
+            .factories
+            .read()
+            .unwrap()
+            .iter()
+            .find_map(|factory| factory.build(record))?
+            .into();
+        jobs.insert(record.id, job.clone());
+        Some(job)
     }
 
     /// Spawn the worker in background
-    /// It will listen for jobs and spawn them in a separate task for parallel processing
+    /// It will run each due job in a separate task for parallel processing
     fn spawn_worker(
-        mut queue_receiver: mpsc::UnboundedReceiver<Box<dyn Job>>,
+        job_worker: Arc<Self>,
         shutdown_tx: &Sender<()>,
     ) -> JoinHandle<Result<(), anyhow::Error>> {
         let mut shutdown_rx = shutdown_tx.subscribe();
         tokio::spawn(async move {
             info!("Job worker started");
+            job_worker.requeue_interrupted().await?;
+            let mut pruned_at = 0;
             loop {
+                if now_ms() >= pruned_at + PRUNE_INTERVAL {
+                    job_worker.prune_finished().await;
+                    pruned_at = now_ms();
+                }
+                let next_run_in = job_worker.run_due_jobs().await?;
                 tokio::select! {
                     _ = shutdown_rx.recv() => {
                         info!("Job worker received shutdown signal");
                         break;
                     }
-                    job_result = queue_receiver.recv() => {
-                        match job_result {
-                            Some(job) => {
-                                debug!("Received job, spawning execution");
-                                // Spawn job execution in a separate task for parallel processing
-                                tokio::spawn(async move {
-                                    job.run().await.map_err(|e| anyhow::anyhow!("Job run failed: {}", e))
-                                }.instrument(tracing::info_span!("job_worker")));
+                    _ = job_worker.wake_up.notified() => {}
+                    _ = sleep(next_run_in) => {}
+                }
+            }
+            info!("Job worker shutdown complete");
+            Ok::<_, anyhow::Error>(())
+        })
+    }
+
+    /// Remove the succeeded and cancelled jobs past the retention of the config
+    async fn prune_finished(&self) {
+        match self
+            .store
+            .prune_finished(&self.config.retention(), now_ms())
+            .await
+        {
+            Ok(0) => {}
+            Ok(pruned) => debug!("Removed {pruned} finished jobs past the retention"),
+            Err(e) => warn!("Failed to remove the finished jobs past the retention: {e:?}"),
+        }
+    }
+
+    /// Queue again the jobs that were running when the previous run stopped
+    async fn requeue_interrupted(&self) -> Result<(), anyhow::Error> {
+        for record in self.store.jobs().await {
+            if record.status == JobStatus::Running {
+                info!(
+                    "Queuing again interrupted job {} {}",
+                    record.kind, record.id
+                );
+                self.store
+                    .update(record.id, Box::new(|job| job.status = JobStatus::Queued))
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Start the queued jobs that are due, returns how long to wait for the next one
+    async fn run_due_jobs(self: &Arc<Self>) -> Result<Duration, anyhow::Error> {
+        let now = now_ms();
+        let mut next_run_at = now + IDLE_CHECK_INTERVAL;
+        for record in self.store.jobs().await {
+            if record.status != JobStatus::Queued {
+                continue;
+            }
+            if record.next_run_at_ms > now {
+                next_run_at = next_run_at.min(record.next_run_at_ms);
+                continue;
+            }
+            let Some(job) = self.job(&record) else {
+                debug!("No factory for job {} {} yet", record.kind, record.id);
+                continue;
+            };
+            let permit = match self
+                .semaphore(&record.kind)
+                .map(Semaphore::try_acquire_owned)
+            {
+                Some(Ok(permit)) => Some(permit),
+                Some(Err(_)) => {
+                    debug!(
+                        "Job {} {} waits for a running {} job to finish",
+                        record.kind, record.id, record.kind
+                    );
+                    continue;
+                }
+                None => None,
+            };
+
+            self.store
+                .update(
+                    record.id,
+                    Box::new(move |job| {
+                        job.status = JobStatus::Running;
+                        job.started_at_ms = Some(now);
+                    }),
+                )
+                .await?;
+            debug!("Running job {} {}", record.kind, record.id);
+            // Spawn job execution in a separate task for parallel processing,
+            // the handles are locked until the job is tracked
+            let mut handles = self.handles.lock().unwrap();
+            let cancellation = self.interrupted.child_token();
+            let job_worker = self.clone();
+            let job_cancellation = cancellation.clone();
+            let task = tokio::spawn(
+                async move {
+                    let result = tokio::select! {
+                        result = job.run() => Some(result),
+                        _ = job_cancellation.cancelled() => None,
+                    };
+                    match result {
+                        Some(result) => {
+                            if let Err(e) = job_worker.finish(record.id, result).await {
+                                error!("Failed to record the result of job {}: {e:?}", record.id);
                             }
-                            None => {
-                                warn!("Job receiver channel closed");
-                                break;
+                        }
+                        // Interrupted by a shutdown, the job is queued again by `drain`
+                        None if job_worker.interrupted.is_cancelled() => {}
+                        None => {
+                            if let Err(e) = job.cancel().await {
+                                warn!("Failed to cancel job {}: {e:?}", record.id);
                             }
                         }
                     }
-                    // _ = sleep(Duration::from_millis(CHECK_SHUTDOWN_INTERVAL)) => {
-                    //     // Periodic check for shutdown signal when no jobs are queued
-                    //     // This ensures we can respond to shutdown even when the queue is empty
-                    // }
+                    job_worker.handles.lock().unwrap().remove(&record.id);
+                    // Let the queued jobs of the kind take the permit
+                    drop(permit);
+                    job_worker.wake_up.notify_one();
+                }
+                .instrument(tracing::info_span!("job", kind = %record.kind, id = %record.id)),
+            );
+            handles.insert(record.id, JobHandle { cancellation, task });
+        }
+        Ok(Duration::from_millis(next_run_at.saturating_sub(now)))
+    }
+
+    /// Record the result of an attempt, failed jobs are retried after their backoff delay
+    /// until they run out of attempts and move to the dead letter list.
+    /// The result of a job cancelled meanwhile is ignored.
+    async fn finish(
+        &self,
+        id: Uuid,
+        result: Result<(), anyhow::Error>,
+    ) -> Result<(), anyhow::Error> {
+        let record = match result {
+            Ok(()) => {
+                let record = self
+                    .store
+                    .update(
+                        id,
+                        Box::new(|job| {
+                            if job.status == JobStatus::Running {
+                                job.status = JobStatus::Succeeded;
+                            }
+                        }),
+                    )
+                    .await?;
+                if record.status == JobStatus::Succeeded {
+                    debug!("Job {id} succeeded");
+                    self.jobs.lock().unwrap().remove(&id);
                 }
+                return Ok(());
             }
-            info!("Job worker shutdown complete");
-            Ok::<_, anyhow::Error>(())
-        })
+            Err(e) => {
+                let now = now_ms();
+                let error = format!("{e:#}");
+                self.store
+                    .update(
+                        id,
+                        Box::new(move |job| {
+                            if job.status != JobStatus::Running {
+                                return;
+                            }
+                            job.attempts += 1;
+                            job.last_error = Some(error);
+                            if job.attempts >= job.backoff.max_attempts {
+                                job.status = JobStatus::Failed;
+                            } else {
+                                job.status = JobStatus::Queued;
+                                job.next_run_at_ms = now + job.backoff.delay_ms(job.attempts);
+                            }
+                        }),
+                    )
+                    .await?
+            }
+        };
+
+        if record.status == JobStatus::Cancelled {
+            return Ok(());
+        }
+        if record.status == JobStatus::Failed {
+            error!(
+                "Job {} {} failed {} times, moving it to the dead letter list: {}",
+                record.kind,
+                id,
+                record.attempts,
+                record.last_error.as_deref().unwrap_or_default()
+            );
+            self.jobs.lock().unwrap().remove(&id);
+            self.store.dead_letter(&record).await?;
+        } else {
+            warn!(
+                "Job {} {} failed attempt {}, retrying in {} ms: {}",
+                record.kind,
+                id,
+                record.attempts,
+                record.next_run_at_ms.saturating_sub(now_ms()),
+                record.last_error.as_deref().unwrap_or_default()
+            );
+            self.wake_up.notify_one();
+        }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::jobs::job::WATCH_TIMEOUT;
+    use crate::models::BackoffPolicy;
+    use crate::stores::{MemoryJobStore, SledJobStore};
+    use crate::test_support::wait_until;
     use async_trait::async_trait;
     use std::sync::atomic::{AtomicU32, Ordering};
     use std::sync::Arc;
@@ -92,21 +508,147 @@ mod tests {
     struct CounterJob {
         counter: Arc<AtomicU32>,
         id: u32,
+        /// Attempts that fail before the job succeeds
+        failures: u32,
     }
 
     #[async_trait]
     impl Job for CounterJob {
-        async fn run(self: Box<Self>) -> Result<(), anyhow::Error> {
+        fn kind(&self) -> &'static str {
+            "counter"
+        }
+
+        fn program_id(&self) -> Uuid {
+            Uuid::nil()
+        }
+
+        fn backoff(&self) -> BackoffPolicy {
+            BackoffPolicy {
+                max_attempts: 3,
+                initial_delay_ms: 10,
+                max_delay_ms: 10,
+            }
+        }
+
+        async fn run(&self) -> Result<(), anyhow::Error> {
             let current = self.counter.fetch_add(1, Ordering::SeqCst);
             println!("Job {} executed, counter: {}", self.id, current + 1);
+            if current < self.failures {
+                return Err(anyhow::anyhow!("Attempt {} failed", current + 1));
+            }
+            Ok(())
+        }
+    }
+
+    /// Job that runs until it is cancelled
+    #[derive(Debug)]
+    struct BlockingJob {
+        cancelled: Arc<AtomicU32>,
+        dedupe_key: Option<&'static str>,
+    }
+
+    #[async_trait]
+    impl Job for BlockingJob {
+        fn kind(&self) -> &'static str {
+            "blocking"
+        }
+
+        fn program_id(&self) -> Uuid {
+            Uuid::nil()
+        }
+
+        fn dedupe_key(&self) -> Option<String> {
+            self.dedupe_key.map(str::to_string)
+        }
+
+        async fn run(&self) -> Result<(), anyhow::Error> {
+            std::future::pending().await
+        }
+
+        async fn cancel(&self) -> Result<(), anyhow::Error> {
+            self.cancelled.fetch_add(1, Ordering::SeqCst);
             Ok(())
         }
     }
 
+    /// Long-lived job watching a game until it is cancelled
+    #[derive(Debug)]
+    struct WatchJob {
+        program_id: Uuid,
+    }
+
+    #[async_trait]
+    impl Job for WatchJob {
+        fn kind(&self) -> &'static str {
+            WATCH_TIMEOUT
+        }
+
+        fn program_id(&self) -> Uuid {
+            self.program_id
+        }
+
+        async fn run(&self) -> Result<(), anyhow::Error> {
+            std::future::pending().await
+        }
+    }
+
+    /// Job that takes a while to succeed
+    #[derive(Debug)]
+    struct SlowJob {
+        done: Arc<AtomicU32>,
+    }
+
+    #[async_trait]
+    impl Job for SlowJob {
+        fn kind(&self) -> &'static str {
+            "slow"
+        }
+
+        fn program_id(&self) -> Uuid {
+            Uuid::nil()
+        }
+
+        async fn run(&self) -> Result<(), anyhow::Error> {
+            sleep(Duration::from_millis(100)).await;
+            self.done.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    /// Builds counter jobs, to resume them after a restart
+    #[derive(Debug)]
+    struct CounterJobFactory {
+        counter: Arc<AtomicU32>,
+    }
+
+    impl JobFactory for CounterJobFactory {
+        fn build(&self, record: &JobRecord) -> Option<Box<dyn Job>> {
+            (record.kind == "counter").then(|| {
+                Box::new(CounterJob {
+                    counter: self.counter.clone(),
+                    id: 0,
+                    failures: 0,
+                }) as Box<dyn Job>
+            })
+        }
+    }
+
+    fn counter_job(counter: &Arc<AtomicU32>, failures: u32) -> CounterJob {
+        CounterJob {
+            counter: counter.clone(),
+            id: 0,
+            failures,
+        }
+    }
+
     #[tokio::test]
     async fn test_job_worker_thread_safety() {
         let (shutdown_tx, _) = broadcast::channel(1);
-        let (worker, worker_task) = JobWorker::start(&shutdown_tx);
+        let (worker, worker_task) = JobWorker::start(
+            Arc::new(MemoryJobStore::new()),
+            JobsConfig::default(),
+            &shutdown_tx,
+        );
 
         let counter = Arc::new(AtomicU32::new(0));
         let num_jobs = 100;
@@ -121,8 +663,9 @@ mod tests {
                 let job = CounterJob {
                     counter: counter_clone,
                     id: i,
+                    failures: 0,
                 };
-                worker_clone.enqueue(job).unwrap();
+                worker_clone.enqueue(job).await.unwrap();
             });
             handles.push(handle);
         }
@@ -146,14 +689,17 @@ mod tests {
     #[tokio::test]
     async fn test_job_worker_shutdown() {
         let (shutdown_tx, _) = broadcast::channel(1);
-        let (worker, worker_task) = JobWorker::start(&shutdown_tx);
+        let (worker, worker_task) = JobWorker::start(
+            Arc::new(MemoryJobStore::new()),
+            JobsConfig::default(),
+            &shutdown_tx,
+        );
 
         // Enqueue a job
-        let job = CounterJob {
-            counter: Arc::new(AtomicU32::new(0)),
-            id: 0,
-        };
-        worker.enqueue(job).unwrap();
+        worker
+            .enqueue(counter_job(&Arc::new(AtomicU32::new(0)), 0))
+            .await
+            .unwrap();
 
         // Shutdown immediately
         let _ = shutdown_tx.send(());
@@ -162,4 +708,347 @@ mod tests {
         let result = worker_task.await;
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_failed_job_is_retried() {
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let store = Arc::new(MemoryJobStore::new());
+        let (worker, _worker_task) =
+            JobWorker::start(store.clone(), JobsConfig::default(), &shutdown_tx);
+        let counter = Arc::new(AtomicU32::new(0));
+
+        let id = worker.enqueue(counter_job(&counter, 2)).await.unwrap();
+
+        wait_until("the job succeeds", || async {
+            store.get(id).await.unwrap().status == JobStatus::Succeeded
+        })
+        .await;
+        let job = store.get(id).await.unwrap();
+        assert_eq!(job.attempts, 2);
+        assert_eq!(job.last_error.as_deref(), Some("Attempt 2 failed"));
+        assert_eq!(counter.load(Ordering::SeqCst), 3);
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[tokio::test]
+    async fn test_job_failing_every_attempt_moves_to_the_dead_letter_list() {
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let store = Arc::new(MemoryJobStore::new());
+        let (worker, _worker_task) =
+            JobWorker::start(store.clone(), JobsConfig::default(), &shutdown_tx);
+        let counter = Arc::new(AtomicU32::new(0));
+
+        let id = worker.enqueue(counter_job(&counter, 5)).await.unwrap();
+
+        wait_until("the job moves to the dead letter list", || async {
+            !store.dead_letters().await.is_empty()
+        })
+        .await;
+        let dead_letters = store.dead_letters().await;
+        assert_eq!(dead_letters[0].id, id);
+        assert_eq!(dead_letters[0].status, JobStatus::Failed);
+        assert_eq!(dead_letters[0].attempts, 3);
+        assert_eq!(counter.load(Ordering::SeqCst), 3);
+        assert!(store.get(id).await.is_none());
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_a_running_job() {
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let (worker, _worker_task) = JobWorker::start(
+            Arc::new(MemoryJobStore::new()),
+            JobsConfig::default(),
+            &shutdown_tx,
+        );
+        let cancelled = Arc::new(AtomicU32::new(0));
+
+        let id = worker
+            .enqueue(BlockingJob {
+                cancelled: cancelled.clone(),
+                dedupe_key: None,
+            })
+            .await
+            .unwrap();
+        wait_until("the job runs", || async {
+            worker.get(id).await.unwrap().status == JobStatus::Running
+        })
+        .await;
+        assert!(worker.get(id).await.unwrap().started_at_ms.is_some());
+
+        let job = worker.cancel(id).await.unwrap();
+        assert_eq!(job.status, JobStatus::Cancelled);
+        wait_until("the job cancel hook runs", || async {
+            cancelled.load(Ordering::SeqCst) == 1
+        })
+        .await;
+        assert_eq!(worker.list(Some(JobStatus::Cancelled)).await, vec![job]);
+        assert!(worker.list(Some(JobStatus::Running)).await.is_empty());
+
+        // A cancelled job cannot be cancelled again
+        let error = worker.cancel(id).await.unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<InvalidJobAction>().unwrap().status,
+            JobStatus::Cancelled
+        );
+        let error = worker.cancel(Uuid::new_v4()).await.unwrap_err();
+        assert!(error.is::<JobNotFound>());
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[tokio::test]
+    async fn test_retry_a_failed_job() {
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let store = Arc::new(MemoryJobStore::new());
+        let (worker, _worker_task) =
+            JobWorker::start(store.clone(), JobsConfig::default(), &shutdown_tx);
+        let counter = Arc::new(AtomicU32::new(0));
+
+        // Fails its 3 attempts, then succeeds on the first attempt of the retry
+        let id = worker.enqueue(counter_job(&counter, 3)).await.unwrap();
+        wait_until("the job fails", || async {
+            worker.get(id).await.unwrap().status == JobStatus::Failed
+        })
+        .await;
+        assert_eq!(worker.list(Some(JobStatus::Failed)).await.len(), 1);
+
+        // The failed job is no longer in memory, the factory builds it again
+        worker.register_factory(Arc::new(CounterJobFactory {
+            counter: counter.clone(),
+        }));
+        let job = worker.retry(id).await.unwrap();
+        assert_eq!(job.status, JobStatus::Queued);
+        assert_eq!(job.attempts, 0);
+        assert!(store.dead_letters().await.is_empty());
+
+        wait_until("the retried job succeeds", || async {
+            worker.get(id).await.unwrap().status == JobStatus::Succeeded
+        })
+        .await;
+        assert_eq!(counter.load(Ordering::SeqCst), 4);
+
+        // Only failed jobs can be retried
+        let error = worker.retry(id).await.unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<InvalidJobAction>().unwrap().action,
+            "retry"
+        );
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[tokio::test]
+    async fn test_retry_a_failed_job_with_a_pending_duplicate() {
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let store = Arc::new(MemoryJobStore::new());
+        let mut failed = JobRecord::new("blocking", Uuid::nil(), BackoffPolicy::default(), 0);
+        failed.dedupe_key = Some("blocking:1".to_string());
+        failed.status = JobStatus::Failed;
+        store.save(&failed).await.unwrap();
+        store.dead_letter(&failed).await.unwrap();
+        let (worker, _worker_task) =
+            JobWorker::start(store.clone(), JobsConfig::default(), &shutdown_tx);
+
+        // A new job with the same key was queued meanwhile
+        let pending = worker
+            .enqueue(BlockingJob {
+                cancelled: Arc::new(AtomicU32::new(0)),
+                dedupe_key: Some("blocking:1"),
+            })
+            .await
+            .unwrap();
+        wait_until("the new job runs", || async {
+            worker.get(pending).await.unwrap().status == JobStatus::Running
+        })
+        .await;
+
+        // The retry returns it instead of queuing a second one
+        assert_eq!(worker.retry(failed.id).await.unwrap().id, pending);
+        assert_eq!(store.jobs().await.len(), 1);
+        assert_eq!(store.dead_letters().await, vec![failed]);
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_jobs_are_merged() {
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let (worker, _worker_task) = JobWorker::start(
+            Arc::new(MemoryJobStore::new()),
+            JobsConfig::default(),
+            &shutdown_tx,
+        );
+        let cancelled = Arc::new(AtomicU32::new(0));
+        let job = || BlockingJob {
+            cancelled: cancelled.clone(),
+            dedupe_key: Some("blocking:1"),
+        };
+
+        let id = worker.enqueue(job()).await.unwrap();
+        assert_eq!(worker.enqueue(job()).await.unwrap(), id);
+        wait_until("the job runs", || async {
+            worker.get(id).await.unwrap().status == JobStatus::Running
+        })
+        .await;
+        assert_eq!(worker.enqueue(job()).await.unwrap(), id);
+        assert_eq!(worker.list(None).await.len(), 1);
+
+        // Once cancelled the job can be queued again
+        worker.cancel(id).await.unwrap();
+        assert_ne!(worker.enqueue(job()).await.unwrap(), id);
+        assert_eq!(worker.list(None).await.len(), 2);
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[tokio::test]
+    async fn test_running_jobs_are_limited_by_kind() {
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let config = JobsConfig {
+            max_concurrency: 10,
+            concurrency: HashMap::from([("blocking".to_string(), 1)]),
+            ..Default::default()
+        };
+        let (worker, _worker_task) =
+            JobWorker::start(Arc::new(MemoryJobStore::new()), config, &shutdown_tx);
+        let cancelled = Arc::new(AtomicU32::new(0));
+        let job = || BlockingJob {
+            cancelled: cancelled.clone(),
+            dedupe_key: None,
+        };
+
+        let first = worker.enqueue(job()).await.unwrap();
+        wait_until("the first job runs", || async {
+            worker.get(first).await.unwrap().status == JobStatus::Running
+        })
+        .await;
+        let second = worker.enqueue(job()).await.unwrap();
+        // Other kinds are not limited by the blocking jobs
+        let counter = Arc::new(AtomicU32::new(0));
+        let counter_id = worker.enqueue(counter_job(&counter, 0)).await.unwrap();
+        wait_until("the counter job succeeds", || async {
+            worker.get(counter_id).await.unwrap().status == JobStatus::Succeeded
+        })
+        .await;
+        assert_eq!(worker.get(second).await.unwrap().status, JobStatus::Queued);
+
+        // The second job runs once the first one stops
+        worker.cancel(first).await.unwrap();
+        wait_until("the second job runs", || async {
+            worker.get(second).await.unwrap().status == JobStatus::Running
+        })
+        .await;
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[tokio::test]
+    async fn test_long_lived_jobs_are_not_limited() {
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let config = JobsConfig {
+            max_concurrency: 2,
+            ..Default::default()
+        };
+        let (worker, _worker_task) =
+            JobWorker::start(Arc::new(MemoryJobStore::new()), config, &shutdown_tx);
+
+        // Every game is watched even with more games than `max_concurrency`
+        let mut ids = Vec::new();
+        for _ in 0..5 {
+            let job = WatchJob {
+                program_id: Uuid::new_v4(),
+            };
+            ids.push(worker.enqueue(job).await.unwrap());
+        }
+        for id in ids {
+            wait_until("the watch job runs", || async {
+                worker.get(id).await.unwrap().status == JobStatus::Running
+            })
+            .await;
+        }
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[tokio::test]
+    async fn test_queued_jobs_survive_a_restart() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let counter = Arc::new(AtomicU32::new(0));
+
+        // The job is persisted but the worker stops before running it
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let (worker, worker_task) = JobWorker::start(
+            Arc::new(SledJobStore::open(&db).unwrap()),
+            JobsConfig::default(),
+            &shutdown_tx,
+        );
+        let _ = shutdown_tx.send(());
+        worker_task.await.unwrap().unwrap();
+        let id = worker.enqueue(counter_job(&counter, 0)).await.unwrap();
+        drop(worker);
+
+        // The new worker runs it once the factory of its kind is registered
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let store = Arc::new(SledJobStore::open(&db).unwrap());
+        let (worker, _worker_task) =
+            JobWorker::start(store.clone(), JobsConfig::default(), &shutdown_tx);
+        assert_eq!(store.get(id).await.unwrap().status, JobStatus::Queued);
+        worker.register_factory(Arc::new(CounterJobFactory {
+            counter: counter.clone(),
+        }));
+
+        wait_until("the resumed job succeeds", || async {
+            store.get(id).await.unwrap().status == JobStatus::Succeeded
+        })
+        .await;
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[tokio::test]
+    async fn test_drain_waits_for_running_jobs_and_queues_the_others_again() {
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let (worker, worker_task) = JobWorker::start(
+            Arc::new(MemoryJobStore::new()),
+            JobsConfig::default(),
+            &shutdown_tx,
+        );
+        let done = Arc::new(AtomicU32::new(0));
+        let cancelled = Arc::new(AtomicU32::new(0));
+
+        let slow_id = worker
+            .enqueue(SlowJob { done: done.clone() })
+            .await
+            .unwrap();
+        let blocking_id = worker
+            .enqueue(BlockingJob {
+                cancelled: cancelled.clone(),
+                dedupe_key: None,
+            })
+            .await
+            .unwrap();
+        wait_until("both jobs run", || async {
+            worker.list(Some(JobStatus::Running)).await.len() == 2
+        })
+        .await;
+        let _ = shutdown_tx.send(());
+        worker_task.await.unwrap().unwrap();
+
+        let checkpointed = worker.drain(Duration::from_millis(500)).await.unwrap();
+        assert_eq!(done.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            worker.get(slow_id).await.unwrap().status,
+            JobStatus::Succeeded
+        );
+        let blocking = worker.get(blocking_id).await.unwrap();
+        assert_eq!(blocking.status, JobStatus::Queued);
+        assert_eq!(blocking.attempts, 0);
+        assert_eq!(checkpointed, vec![blocking]);
+        // Interrupted jobs are not cancelled, they resume after a restart
+        assert_eq!(cancelled.load(Ordering::SeqCst), 0);
+    }
 }
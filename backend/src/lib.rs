@@ -1,11 +1,15 @@
 pub mod api;
 pub mod config;
+pub mod games;
 pub mod jobs;
 pub mod middleware;
 pub mod models;
 pub mod routes;
 pub mod rpc;
 pub mod services;
+pub mod shutdown;
 pub mod state;
 pub mod stores;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
 pub mod utils;
@@ -1,6 +1,13 @@
 use bitvmx_hackathon_backend::{
-    api, config, jobs::worker::JobWorker, rpc::rpc_client::RpcClient, state::AppState,
+    api, config,
+    jobs::worker::JobWorker,
+    rpc::rpc_client::RpcClient,
+    services::{operators, BitcoindService},
+    shutdown::Shutdown,
+    state::AppState,
+    stores,
 };
+use std::sync::Arc;
 use tokio::{signal, sync::broadcast, task::JoinError};
 use tracing::{error, info, trace, warn, Instrument};
 use tracing_appender::rolling;
@@ -64,45 +71,108 @@ async fn main() -> anyhow::Result<()> {
     // Create a span for the main application
     let _main_span = tracing::info_span!("", config = %config_file).entered();
 
-    // 3. Create shutdown signals
+    // 3. Create shutdown signals, the BitVMX connections are closed last so jobs can finish
     let (shutdown_tx, _) = broadcast::channel::<()>(1);
-
-    // 4. Connect to BitVMX RPC, spawn listener task
-    let (rpc_client, rpc_listener_task) =
-        RpcClient::connect(config.bitvmx.broker_port, None, &shutdown_tx).map_err(|e| {
-            error!("❌ Failed to connect to BitVMX RPC: {e:?}");
-            e
-        })?;
+    let (rpc_shutdown_tx, _) = broadcast::channel::<()>(1);
+
+    // 4. Connect to the BitVMX RPC of every operator, spawn their listener tasks
+    let operator_configs = config.operator_configs().map_err(|e| {
+        error!("❌ Invalid operators config: {e:?}");
+        e
+    })?;
+    let mut rpc_clients = Vec::new();
+    let mut rpc_listener_tasks = Vec::new();
+    for operator in operator_configs {
+        let (rpc_client, rpc_listener_task) =
+            RpcClient::connect(&operator.bitvmx, &rpc_shutdown_tx).map_err(|e| {
+                error!(
+                    "❌ Failed to connect to BitVMX RPC of {}: {e:?}",
+                    operator.name
+                );
+                e
+            })?;
+        rpc_clients.push((operator.name.clone(), rpc_client));
+        rpc_listener_tasks.push((operator.name, rpc_listener_task));
+    }
 
     // 5. Start job worker
-    let (job_worker, job_worker_task) = JobWorker::start(&shutdown_tx);
+    let db = stores::open_database(&config.storage).map_err(|e| {
+        error!("❌ Failed to open the storage: {e:?}");
+        e
+    })?;
+    let job_store = stores::new_job_store(db.as_ref())?;
+    let (job_worker, mut job_worker_task) =
+        JobWorker::start(job_store, config.jobs.clone(), &shutdown_tx);
+    let grace_period = config.jobs.shutdown_grace_period();
 
     // 6. Initialize app state
-    let app_state = AppState::new(config.clone(), rpc_client.clone(), job_worker.clone());
+    let bitcoin_service = Arc::new(BitcoindService::new(config.bitcoin.clone()));
+    let app_state = AppState::new(
+        config.clone(),
+        db.as_ref(),
+        rpc_clients,
+        bitcoin_service,
+        job_worker.clone(),
+    )
+    .map_err(|e| {
+        error!("❌ Failed to initialize app state: {e:?}");
+        e
+    })?;
+    // An operator whose listener fails is marked unhealthy, the others keep serving
+    let mut rpc_listener_task =
+        operators::join_listeners(app_state.operators.clone(), rpc_listener_tasks);
 
     // 7. Spawn setup task that waits for RPC to be ready
     let app_state_setup = app_state.clone();
-    let shutdown_tx_setup = shutdown_tx.clone();
     let shutdown_rx_setup = shutdown_tx.subscribe();
     // Setup task happens only once at the beginning
-    let _ = tokio::task::spawn(
+    let _setup_task = tokio::task::spawn(
         async move {
-            // Wait for the RPC client to be ready
-            app_state_setup
-                .rpc_client
-                .wait_for_ready(shutdown_rx_setup)
-                .await;
-
-            // Setup does multiple things so we should not lock the service,
-            // but since this is just a one time task at the beginning, we can do it here
-            let result = app_state_setup.bitvmx_service.initial_setup().await;
-            if let Err(e) = result {
-                error!("❌ setup: Error: {e}");
-                // Send shutdown signal to all tasks and exit
-                let _ = shutdown_tx_setup.send(());
-                return Err(e);
-            } else {
+            // Setup does multiple things so we should not lock the services,
+            // but since this is just a one time task at the beginning, we can do it here.
+            // Each operator waits for its own RPC client to be ready, an operator whose setup
+            // fails is marked unhealthy and the others keep serving.
+            let setups = app_state_setup
+                .operators
+                .list()
+                .iter()
+                .map(|operator| {
+                    let name = operator.name.clone();
+                    let operator = operator.clone();
+                    let shutdown_rx = shutdown_rx_setup.resubscribe();
+                    let setup = tokio::spawn(async move {
+                        operator.rpc_client.wait_for_ready(shutdown_rx).await;
+                        operator.bitvmx_service.initial_setup().await
+                    });
+                    (name, setup)
+                })
+                .collect();
+            let failed =
+                operators::join_operator_tasks(&app_state_setup.operators, setups, "setup").await;
+            if failed == 0 {
                 info!("✅ setup: BitVMX setup completed successfully");
+            } else {
+                warn!("⚠️ setup: BitVMX setup failed for {failed} operators, they are unhealthy");
+            }
+
+            // Resume the background waits of the games that were in flight before a restart
+            for game in app_state_setup.games.all() {
+                let game_type = game.game_type();
+                match game.resume_pending_jobs().await {
+                    Ok(resumed) => {
+                        info!("✅ setup: Resumed {resumed} pending {game_type} game jobs")
+                    }
+                    Err(e) => {
+                        error!("❌ setup: Failed to resume pending {game_type} game jobs: {e}")
+                    }
+                }
+                // Ticker outside the job worker, it enqueues a reconcile_games job per tick
+                let game = game.clone();
+                let shutdown_rx = shutdown_rx_setup.resubscribe();
+                tokio::spawn(
+                    async move { game.handle_reconciliation(shutdown_rx).await }
+                        .instrument(tracing::info_span!("reconciliation", game_type)),
+                );
             }
 
             Ok::<_, anyhow::Error>(()) // coercion to Result
@@ -113,7 +183,7 @@ async fn main() -> anyhow::Result<()> {
     // 8. Spawn Axum server task
     let app_state_axum = app_state.clone();
     let mut shutdown_rx_axum = shutdown_tx.subscribe();
-    let axum_task = tokio::task::spawn(
+    let mut axum_task = tokio::task::spawn(
         async move {
             // Create the application
             let app = api::app(app_state_axum).await;
@@ -138,17 +208,29 @@ async fn main() -> anyhow::Result<()> {
 
     // 9. Run tasks in parallel with tokio::select!
     tokio::select! {
-        res = rpc_listener_task => task_result(res, "rpc_listener", &shutdown_tx),
-        res = job_worker_task => task_result(res, "job_worker", &shutdown_tx),
-        res = axum_task => task_result(res, "axum_server", &shutdown_tx),
-        _ = signal::ctrl_c() => {
-            info!("Ctrl-C received, shutting down...");
-            // Send shutdown signal to all tasks
-            let _ = shutdown_tx.send(());
-        },
+        res = &mut rpc_listener_task => task_result(res, "rpc_listener", &shutdown_tx),
+        res = &mut job_worker_task => task_result(res, "job_worker", &shutdown_tx),
+        res = &mut axum_task => task_result(res, "axum_server", &shutdown_tx),
+        _ = signal::ctrl_c() => info!("Ctrl-C received, shutting down..."),
     }
 
-    Ok(())
+    // 10. Stop everything in order, the running jobs get the grace period to finish
+    Shutdown {
+        shutdown_tx,
+        http_task: axum_task,
+        job_worker,
+        job_worker_task,
+        grace_period,
+        db,
+        rpc_shutdown_tx,
+        rpc_listener_task,
+    }
+    .run()
+    .await
+    .map_err(|e| {
+        error!("❌ Shutdown failed: {e:?}");
+        e
+    })
 }
 
 fn task_result(
@@ -6,8 +6,9 @@ use ts_rs::TS;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
-use crate::models::{P2PAddress, Utxo};
+use crate::models::{GameSetupRequest, P2PAddress, Utxo};
 
+/// Status of an add numbers game
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS, ToSchema)]
 #[ts(export)]
 pub enum AddNumbersGameStatus {
@@ -21,10 +22,74 @@ pub enum AddNumbersGameStatus {
         outcome: GameOutcome,
         reason: GameReason,
     },
-    // TransferBetFunds,
+    TransferBetFunds, // The winner claimed the bet, waiting for the payout transaction to be confirmed
     Finished,
 }
 
+/// Events that move an add numbers game from one status to the next
+#[derive(Debug, Clone, PartialEq)]
+pub enum AddNumbersGameEvent {
+    /// Player 2 accepted the bet and waits for player 1 funding UTXOs
+    PlaceBet,
+    /// The funding UTXOs of the game were saved
+    SaveFundingUtxos,
+    /// The numbers to sum were sent to BitVMX
+    SetupGame,
+    /// The challenge transaction was seen on chain
+    StartGame,
+    /// Player 2 submitted its answer, e.g. the sum
+    SubmitGameData,
+    /// The dispute finished with a known outcome
+    CompleteGame {
+        outcome: GameOutcome,
+        reason: GameReason,
+    },
+    /// The winner claimed the bet
+    ClaimBet,
+    /// The payout transaction was confirmed, nothing else can happen
+    Finish,
+}
+
+impl AddNumbersGameStatus {
+    /// Transition table of the game.
+    /// Returns the next status when `role` may fire `event` from the current status.
+    pub fn next(&self, role: &PlayerRole, event: &AddNumbersGameEvent) -> Option<Self> {
+        use AddNumbersGameEvent as Event;
+        use AddNumbersGameStatus as Status;
+
+        match (self, role, event) {
+            (Status::PlaceBet, PlayerRole::Player2, Event::PlaceBet) => Some(Status::SetupFunding),
+            (Status::PlaceBet, PlayerRole::Player1, Event::SaveFundingUtxos) => {
+                Some(Status::SetupGame)
+            }
+            (Status::SetupFunding, PlayerRole::Player2, Event::SaveFundingUtxos) => {
+                Some(Status::SetupGame)
+            }
+            (Status::SetupGame, _, Event::SetupGame) => Some(Status::StartGame),
+            (Status::StartGame, _, Event::StartGame) => Some(Status::SubmitGameData),
+            (Status::SubmitGameData, PlayerRole::Player2, Event::SubmitGameData) => {
+                Some(Status::SubmitGameData)
+            }
+            (Status::SubmitGameData, _, Event::CompleteGame { outcome, reason }) => {
+                Some(Status::GameComplete {
+                    outcome: outcome.clone(),
+                    reason: reason.clone(),
+                })
+            }
+            (
+                Status::GameComplete {
+                    outcome: GameOutcome::Win,
+                    ..
+                },
+                _,
+                Event::ClaimBet,
+            ) => Some(Status::TransferBetFunds),
+            (Status::TransferBetFunds, _, Event::Finish) => Some(Status::Finished),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, TS, ToSchema, PartialEq)]
 #[ts(export)]
 pub enum GameOutcome {
@@ -53,6 +118,9 @@ pub struct AddNumbersGame {
     pub created_at: u64,
     pub updated_at: u64,
     pub bitvmx_program_properties: BitVMXProgramProperties,
+    /// Transaction that paid the won bet to the operator, set once the game is finished
+    #[serde(default)]
+    pub payout_txid: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS, ToSchema)]
@@ -83,8 +151,54 @@ pub struct BitVMXProgramProperties {
     pub funding_protocol_utxo: Option<Utxo>,
     pub funding_bet_utxo: Option<Utxo>,
     pub txs: HashMap<String, serde_json::Value>,
+    /// Dispute parameters used to set up the program, known once the game is set up
+    #[serde(default)]
+    pub dispute_parameters: Option<DisputeParameters>,
+}
+
+/// Dispute parameters of a game, both players must set up the program with the same values
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS, ToSchema)]
+#[ts(export)]
+pub struct DisputeParameters {
+    /// Blocks a player has to answer in the dispute before the other one can claim a timeout
+    pub timelock_blocks: u32,
+    /// Program definition file, relative to the bitvmx-client
+    pub program_definition: String,
+    /// Index of the participant leading the BitVMX program setup
+    pub leader_idx: u16,
+}
+
+/// Error returned when the requested dispute parameters are not valid
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidDisputeParameters(pub String);
+
+impl std::fmt::Display for InvalidDisputeParameters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid dispute parameters: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidDisputeParameters {}
+
+/// Error returned when the other player set up the game with different dispute parameters
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisputeParametersMismatch {
+    pub local: DisputeParameters,
+    pub peer: DisputeParameters,
+}
+
+impl std::fmt::Display for DisputeParametersMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Dispute parameters do not match the other player: {:?} != {:?}",
+            self.local, self.peer
+        )
+    }
 }
 
+impl std::error::Error for DisputeParametersMismatch {}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS, ToSchema)]
 #[ts(export)]
 pub struct AddNumbersResponse {
@@ -174,6 +288,55 @@ pub struct SetupGameRequest {
     pub number1: u32,
     #[schema(example = 58)]
     pub number2: u32,
+    /// Blocks a player has to answer in the dispute, defaults to the game config
+    #[ts(optional)]
+    #[schema(example = 5)]
+    pub timelock_blocks: Option<u32>,
+    /// Program definition file relative to the bitvmx-client, one of the allowed ones of the
+    /// game config, defaults to the game config one
+    #[ts(optional)]
+    pub program_definition: Option<String>,
+    /// Index of the participant leading the program setup, defaults to the game config
+    #[ts(optional)]
+    pub leader_idx: Option<u16>,
+    /// Parameters returned to player 1 when it set up the game, required from player 2.
+    /// They must match or the program is not set up.
+    #[ts(optional)]
+    pub peer_parameters: Option<DisputeParameters>,
+}
+
+impl From<SetupGameRequest> for GameSetupRequest<AddNumbersSetup> {
+    fn from(request: SetupGameRequest) -> Self {
+        Self {
+            program_id: request.program_id,
+            data: AddNumbersSetup {
+                number1: request.number1,
+                number2: request.number2,
+            },
+            timelock_blocks: request.timelock_blocks,
+            program_definition: request.program_definition,
+            leader_idx: request.leader_idx,
+            peer_parameters: request.peer_parameters,
+        }
+    }
+}
+
+/// Numbers both players set up, player 2 has to answer with their sum
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS, ToSchema)]
+#[ts(export)]
+pub struct AddNumbersSetup {
+    #[schema(example = 42)]
+    pub number1: u32,
+    #[schema(example = 58)]
+    pub number2: u32,
+}
+
+/// Sum submitted by player 2
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS, ToSchema)]
+#[ts(export)]
+pub struct AddNumbersSubmit {
+    #[schema(example = 100)]
+    pub guess: u32,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS, ToSchema)]
@@ -182,6 +345,8 @@ pub struct SetupGameResponse {
     #[ts(type = "string")]
     #[schema(value_type = String, example = "123e4567-e89b-12d3-a456-426614174000")]
     pub program_id: Uuid,
+    /// Parameters used to set up the program, share them with the other player
+    pub dispute_parameters: DisputeParameters,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS, ToSchema)]
@@ -200,3 +365,208 @@ pub struct StartGameResponse {
     pub program_id: Uuid,
     pub challenge_tx: serde_json::Value,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::GameStatus;
+    use crate::models::InvalidTransition;
+
+    fn complete_event() -> AddNumbersGameEvent {
+        AddNumbersGameEvent::CompleteGame {
+            outcome: GameOutcome::Win,
+            reason: GameReason::Challenge,
+        }
+    }
+
+    fn complete_status() -> AddNumbersGameStatus {
+        AddNumbersGameStatus::GameComplete {
+            outcome: GameOutcome::Win,
+            reason: GameReason::Challenge,
+        }
+    }
+
+    /// Every allowed edge of the transition table
+    fn edges() -> Vec<(
+        AddNumbersGameStatus,
+        PlayerRole,
+        AddNumbersGameEvent,
+        AddNumbersGameStatus,
+    )> {
+        use AddNumbersGameEvent as Event;
+        use AddNumbersGameStatus as Status;
+        use PlayerRole::*;
+
+        vec![
+            (
+                Status::PlaceBet,
+                Player2,
+                Event::PlaceBet,
+                Status::SetupFunding,
+            ),
+            (
+                Status::PlaceBet,
+                Player1,
+                Event::SaveFundingUtxos,
+                Status::SetupGame,
+            ),
+            (
+                Status::SetupFunding,
+                Player2,
+                Event::SaveFundingUtxos,
+                Status::SetupGame,
+            ),
+            (
+                Status::SetupGame,
+                Player1,
+                Event::SetupGame,
+                Status::StartGame,
+            ),
+            (
+                Status::SetupGame,
+                Player2,
+                Event::SetupGame,
+                Status::StartGame,
+            ),
+            (
+                Status::StartGame,
+                Player1,
+                Event::StartGame,
+                Status::SubmitGameData,
+            ),
+            (
+                Status::StartGame,
+                Player2,
+                Event::StartGame,
+                Status::SubmitGameData,
+            ),
+            (
+                Status::SubmitGameData,
+                Player2,
+                Event::SubmitGameData,
+                Status::SubmitGameData,
+            ),
+            (
+                Status::SubmitGameData,
+                Player1,
+                complete_event(),
+                complete_status(),
+            ),
+            (
+                Status::SubmitGameData,
+                Player2,
+                complete_event(),
+                complete_status(),
+            ),
+            (
+                complete_status(),
+                Player1,
+                Event::ClaimBet,
+                Status::TransferBetFunds,
+            ),
+            (
+                complete_status(),
+                Player2,
+                Event::ClaimBet,
+                Status::TransferBetFunds,
+            ),
+            (
+                Status::TransferBetFunds,
+                Player1,
+                Event::Finish,
+                Status::Finished,
+            ),
+            (
+                Status::TransferBetFunds,
+                Player2,
+                Event::Finish,
+                Status::Finished,
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_allowed_transitions() {
+        for (status, role, event, next) in edges() {
+            assert_eq!(
+                status.transition(&role, event.clone()),
+                Ok(next),
+                "{role:?} firing {event:?} from {status:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_illegal_transitions() {
+        use AddNumbersGameEvent as Event;
+        use AddNumbersGameStatus as Status;
+
+        let statuses = [
+            Status::SetupParticipants,
+            Status::PlaceBet,
+            Status::SetupFunding,
+            Status::SetupGame,
+            Status::StartGame,
+            Status::SubmitGameData,
+            complete_status(),
+            Status::GameComplete {
+                outcome: GameOutcome::Lose,
+                reason: GameReason::Challenge,
+            },
+            Status::TransferBetFunds,
+            Status::Finished,
+        ];
+        let events = [
+            Event::PlaceBet,
+            Event::SaveFundingUtxos,
+            Event::SetupGame,
+            Event::StartGame,
+            Event::SubmitGameData,
+            complete_event(),
+            Event::ClaimBet,
+            Event::Finish,
+        ];
+        let edges = edges();
+
+        for status in &statuses {
+            for role in [PlayerRole::Player1, PlayerRole::Player2] {
+                for event in &events {
+                    let allowed = edges
+                        .iter()
+                        .any(|(from, r, e, _)| from == status && *r == role && e == event);
+                    if allowed {
+                        continue;
+                    }
+                    assert_eq!(
+                        status.transition(&role, event.clone()),
+                        Err(InvalidTransition {
+                            status: format!("{status:?}"),
+                            role: role.clone(),
+                            event: format!("{event:?}"),
+                        })
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_complete_game_keeps_outcome() {
+        let next = AddNumbersGameStatus::SubmitGameData
+            .transition(
+                &PlayerRole::Player1,
+                AddNumbersGameEvent::CompleteGame {
+                    outcome: GameOutcome::Lose,
+                    reason: GameReason::Timeout,
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            next,
+            AddNumbersGameStatus::GameComplete {
+                outcome: GameOutcome::Lose,
+                reason: GameReason::Timeout,
+            }
+        );
+    }
+}
@@ -4,7 +4,29 @@ use bitvmx_client::program::variables::PartialUtxo;
 use serde::{Deserialize, Serialize};
 use std::{net::SocketAddr, str::FromStr};
 use ts_rs::TS;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::models::BitvmxConnection;
+
+/// Selects the BitVMX operator of a request
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OperatorQuery {
+    /// Name of a configured operator, the default operator when missing
+    pub operator: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS, ToSchema)]
+#[ts(export)]
+pub struct OperatorInfo {
+    /// Name of the operator, `default` for the `bitvmx` section of the config
+    pub name: String,
+    /// Used when a request does not select an operator
+    pub default: bool,
+    pub connection: BitvmxConnection,
+    /// Why the operator stopped working, e.g. its setup failed. None while it is healthy
+    pub unhealthy: Option<String>,
+}
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS, ToSchema)]
 #[ts(export)]
@@ -42,6 +64,25 @@ pub struct OperatorKeys {
     pub funding_key: String,
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS, ToSchema)]
+#[ts(export)]
+pub struct SetupStatus {
+    /// The P2P address was obtained from BitVMX
+    pub p2p_address: bool,
+    /// The wallet address was obtained from BitVMX
+    pub wallet_address: bool,
+    /// The wallet has enough funds to play
+    pub wallet_funded: bool,
+    /// The operator key was created
+    pub operator_key: bool,
+    /// The funding key was created
+    pub funding_key: bool,
+    /// The funding UTXO was sent and registered in BitVMX
+    pub funding_utxo: bool,
+    /// All the setup steps finished in the current run
+    pub completed: bool,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS, ToSchema)]
 #[ts(export)]
 pub struct AggregatedKeyResponse {
@@ -123,3 +164,37 @@ pub struct ProtocolVisualizationResponse {
     /// The program ID
     pub visualization: String,
 }
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS, ToSchema)]
+#[ts(export)]
+pub struct UnclaimedResponse {
+    /// The correlation ID nobody was waiting for
+    pub correlation_id: String,
+    /// Seconds since the response was received
+    pub age_secs: u64,
+    /// The BitVMX message
+    pub message: serde_json::Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS, ToSchema)]
+#[ts(export)]
+pub struct UnclaimedResponsesMetrics {
+    /// Responses kept because nobody was waiting for them
+    pub retained: u64,
+    /// Responses handed to a waiter registered after they arrived
+    pub claimed: u64,
+    /// Responses dropped because nobody claimed them in time
+    pub expired: u64,
+    /// Responses dropped to make room for newer ones
+    pub evicted: u64,
+    /// Responses waiting to be claimed
+    pub unclaimed: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS, ToSchema)]
+#[ts(export)]
+pub struct UnclaimedResponsesResponse {
+    pub metrics: UnclaimedResponsesMetrics,
+    /// Responses waiting to be claimed, oldest first
+    pub responses: Vec<UnclaimedResponse>,
+}
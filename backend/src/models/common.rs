@@ -1,3 +1,4 @@
+use crate::rpc::{ConnectionState, ConnectionStatus, RpcClient};
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 use utoipa::ToSchema;
@@ -12,6 +13,58 @@ pub struct ErrorResponse {
 #[derive(Debug, Serialize, Deserialize, TS, ToSchema)]
 #[ts(export)]
 pub struct HealthResponse {
+    /// healthy, or degraded while BitVMX is not connected or an operator is unhealthy
     pub status: String,
     pub timestamp: u64,
+    pub bitvmx: BitvmxConnection,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, TS, ToSchema)]
+#[ts(export)]
+pub enum BitvmxConnectionState {
+    Connecting,
+    Connected,
+    /// Reconnecting, requests to BitVMX fail right away
+    Disconnected,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS, ToSchema)]
+#[ts(export)]
+pub struct BitvmxConnection {
+    pub state: BitvmxConnectionState,
+    /// Failures since the last successful exchange with BitVMX
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+    /// Messages rejected because their sender is not in the allow list
+    pub rejected_messages: u64,
+    /// Messages that could not be decoded or matched, acked and dropped
+    pub unhandled_messages: u64,
+}
+
+impl From<ConnectionStatus> for BitvmxConnection {
+    fn from(status: ConnectionStatus) -> Self {
+        let state = match status.state {
+            ConnectionState::Connecting => BitvmxConnectionState::Connecting,
+            ConnectionState::Connected => BitvmxConnectionState::Connected,
+            ConnectionState::Disconnected => BitvmxConnectionState::Disconnected,
+        };
+        Self {
+            state,
+            consecutive_failures: status.consecutive_failures,
+            last_error: status.last_error,
+            rejected_messages: 0,
+            unhandled_messages: 0,
+        }
+    }
+}
+
+impl BitvmxConnection {
+    /// Connection state of an RPC client with its rejected and unhandled messages
+    pub fn of(rpc_client: &RpcClient) -> Self {
+        Self {
+            rejected_messages: rpc_client.rejected_messages(),
+            unhandled_messages: rpc_client.unhandled_messages(),
+            ..Self::from(rpc_client.connection_status())
+        }
+    }
 }
@@ -0,0 +1,201 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use ts_rs::TS;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::config::DEFAULT_OPERATOR;
+use crate::models::{BitVMXProgramProperties, DisputeParameters, PlayerRole};
+
+/// Game type of the records and jobs stored before games were pluggable
+pub const LEGACY_GAME_TYPE: &str = "add-numbers";
+
+fn default_game_type() -> String {
+    LEGACY_GAME_TYPE.to_string()
+}
+
+/// Operator of the records stored before the backend managed several operators
+fn default_operator() -> String {
+    DEFAULT_OPERATOR.to_string()
+}
+
+/// Game persisted by the shared game framework.
+/// The game specific data, e.g. the numbers to sum, is flattened into the record
+/// so an add numbers record serializes like [`crate::models::AddNumbersGame`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS, ToSchema)]
+#[ts(export)]
+pub struct GameRecord {
+    #[ts(type = "string")]
+    #[schema(value_type = String, example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub program_id: Uuid,
+    /// Game implementation, e.g. "add-numbers"
+    #[serde(default = "default_game_type")]
+    pub game_type: String,
+    /// BitVMX operator the game runs on
+    #[serde(default = "default_operator")]
+    pub operator: String,
+    pub role: PlayerRole,
+    /// Status of the game, its type depends on the game type, see [`Self::status_as`]
+    #[ts(type = "unknown")]
+    pub status: serde_json::Value,
+    pub created_at: u64,
+    pub updated_at: u64,
+    pub bitvmx_program_properties: BitVMXProgramProperties,
+    /// Transaction that paid the won bet to the operator, set once the game is finished
+    #[serde(default)]
+    pub payout_txid: Option<String>,
+    /// Recorded state that contradicts the chain, found by the reconciliation of the game
+    #[serde(default)]
+    pub contradictions: Vec<String>,
+    /// Setup and submitted data of the game
+    #[serde(flatten)]
+    #[ts(skip)]
+    #[schema(value_type = Object)]
+    pub data: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Error returned when an event is not allowed for the game status and role
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidTransition {
+    pub status: String,
+    pub role: PlayerRole,
+    pub event: String,
+}
+
+impl std::fmt::Display for InvalidTransition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Invalid transition: {:?} cannot fire {} in {} state",
+            self.role, self.event, self.status
+        )
+    }
+}
+
+impl std::error::Error for InvalidTransition {}
+
+impl GameRecord {
+    /// Read the status as the status type of the game
+    pub fn status_as<S: DeserializeOwned>(&self) -> Result<S, anyhow::Error> {
+        serde_json::from_value(self.status.clone())
+            .map_err(|e| anyhow::anyhow!("Failed to read game status {}: {e:?}", self.status))
+    }
+
+    /// Replace the status
+    pub fn set_status(&mut self, status: &impl Serialize) -> Result<(), anyhow::Error> {
+        self.status = serde_json::to_value(status)
+            .map_err(|e| anyhow::anyhow!("Failed to serialize game status: {e:?}"))?;
+        Ok(())
+    }
+
+    /// Merge the fields of a game payload into the record data
+    pub fn set_data(&mut self, data: &impl Serialize) -> Result<(), anyhow::Error> {
+        match serde_json::to_value(data)
+            .map_err(|e| anyhow::anyhow!("Failed to serialize game data: {e:?}"))?
+        {
+            serde_json::Value::Object(fields) => {
+                self.data.extend(fields);
+                Ok(())
+            }
+            value => Err(anyhow::anyhow!("Game data must be an object: {value}")),
+        }
+    }
+
+    /// Read the record as a game specific view
+    pub fn to_view<T: DeserializeOwned>(&self) -> Result<T, anyhow::Error> {
+        let value = serde_json::to_value(self)
+            .map_err(|e| anyhow::anyhow!("Failed to serialize game: {e:?}"))?;
+        serde_json::from_value(value).map_err(|e| anyhow::anyhow!("Failed to read game: {e:?}"))
+    }
+}
+
+/// Setup request of any game, the game specific payload is flattened into it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameSetupRequest<D> {
+    pub program_id: Uuid,
+    /// Game specific setup payload
+    #[serde(flatten)]
+    pub data: D,
+    /// Blocks a player has to answer in the dispute, defaults to the game config
+    #[serde(default)]
+    pub timelock_blocks: Option<u32>,
+    /// Program definition file relative to the bitvmx-client, one of the allowed ones of the
+    /// game config, defaults to the game
+    #[serde(default)]
+    pub program_definition: Option<String>,
+    /// Index of the participant leading the program setup, defaults to the game config
+    #[serde(default)]
+    pub leader_idx: Option<u16>,
+    /// Parameters returned to player 1 when it set up the game, required from player 2.
+    /// They must match or the program is not set up.
+    #[serde(default)]
+    pub peer_parameters: Option<DisputeParameters>,
+}
+
+/// Request of player 2 answering the challenge, the game specific payload is flattened into it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameSubmitRequest<D> {
+    pub program_id: Uuid,
+    /// Game specific answer
+    #[serde(flatten)]
+    pub data: D,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS, ToSchema)]
+#[ts(export)]
+pub struct GameResponse {
+    pub game: GameRecord,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{AddNumbersGame, AddNumbersGameStatus, AddNumbersSubmit};
+    use serde_json::json;
+
+    /// Add numbers game as it was stored before games were pluggable
+    fn stored_add_numbers_game() -> serde_json::Value {
+        json!({
+            "program_id": "123e4567-e89b-12d3-a456-426614174000",
+            "role": "Player2",
+            "number1": 1,
+            "number2": 2,
+            "guess": null,
+            "status": "SubmitGameData",
+            "created_at": 1,
+            "updated_at": 2,
+            "bitvmx_program_properties": {
+                "aggregated_key": "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+                "aggregated_id": "123e4567-e89b-12d3-a456-426614174001",
+                "protocol_address": "",
+                "participants_addresses": [],
+                "participants_keys": [],
+                "funding_protocol_utxo": null,
+                "funding_bet_utxo": null,
+                "txs": {}
+            }
+        })
+    }
+
+    #[test]
+    fn test_stored_add_numbers_game_is_a_record() {
+        let mut record: GameRecord = serde_json::from_value(stored_add_numbers_game()).unwrap();
+        assert_eq!(record.game_type, "add-numbers");
+        assert_eq!(record.operator, DEFAULT_OPERATOR);
+        assert_eq!(record.data["number1"], json!(1));
+
+        record.set_data(&AddNumbersSubmit { guess: 3 }).unwrap();
+        let game: AddNumbersGame = record.to_view().unwrap();
+        assert_eq!(game.program_id, record.program_id);
+        assert_eq!(
+            (game.number1, game.number2, game.guess),
+            (Some(1), Some(2), Some(3))
+        );
+        assert_eq!(game.status, AddNumbersGameStatus::SubmitGameData);
+    }
+
+    #[test]
+    fn test_game_data_must_be_an_object() {
+        let mut record: GameRecord = serde_json::from_value(stored_add_numbers_game()).unwrap();
+        assert!(record.set_data(&3).is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS, ToSchema)]
+#[ts(export)]
+pub enum JobStatus {
+    /// Waiting for its next run
+    Queued,
+    Running,
+    Succeeded,
+    /// Failed every attempt, the job is in the dead letter list
+    Failed,
+    /// Cancelled through the jobs API, it does not run again
+    Cancelled,
+}
+
+/// Error returned when a job does not exist, in the jobs or in the dead letter list
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobNotFound(pub Uuid);
+
+impl std::fmt::Display for JobNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Job {} not found", self.0)
+    }
+}
+
+impl std::error::Error for JobNotFound {}
+
+/// Error returned when a job cannot be cancelled or retried in its status
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidJobAction {
+    pub id: Uuid,
+    pub status: JobStatus,
+    /// The action that was refused, e.g. `cancel`
+    pub action: &'static str,
+}
+
+impl std::fmt::Display for InvalidJobAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Cannot {} job {} in {:?} status",
+            self.action, self.id, self.status
+        )
+    }
+}
+
+impl std::error::Error for InvalidJobAction {}
+
+/// How a failed job is retried, the delay doubles after each failed attempt
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS, ToSchema)]
+#[ts(export)]
+pub struct BackoffPolicy {
+    /// Attempts before the job moves to the dead letter list
+    pub max_attempts: u32,
+    /// Delay before the first retry in milliseconds
+    pub initial_delay_ms: u64,
+    /// Longest delay between two attempts in milliseconds
+    pub max_delay_ms: u64,
+}
+
+impl Default for BackoffPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_delay_ms: 1_000,
+            max_delay_ms: 60_000,
+        }
+    }
+}
+
+impl BackoffPolicy {
+    /// Delay before the next attempt once `attempts` attempts failed
+    pub fn delay_ms(&self, attempts: u32) -> u64 {
+        let doublings = attempts.saturating_sub(1).min(32);
+        self.initial_delay_ms
+            .saturating_mul(1 << doublings)
+            .min(self.max_delay_ms)
+    }
+}
+
+/// Persisted state of a background job, the job itself is rebuilt from its kind and payload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS, ToSchema)]
+#[ts(export)]
+pub struct JobRecord {
+    #[ts(type = "string")]
+    #[schema(value_type = String, example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub id: Uuid,
+    /// Kind of the job, e.g. `wait_start_game`
+    pub kind: String,
+    /// Payload of the job, the program it works on
+    #[ts(type = "string")]
+    #[schema(value_type = String, example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub program_id: Uuid,
+    /// Game the job belongs to, see `Job::game_type`
+    #[serde(default)]
+    pub game_type: Option<String>,
+    /// Jobs with the same key are not queued or run twice, see `Job::dedupe_key`
+    #[serde(default)]
+    pub dedupe_key: Option<String>,
+    pub status: JobStatus,
+    /// Failed attempts so far
+    pub attempts: u32,
+    pub backoff: BackoffPolicy,
+    pub last_error: Option<String>,
+    /// Unix timestamp in milliseconds from when the job can run
+    pub next_run_at_ms: u64,
+    /// Unix timestamp in milliseconds of the start of the last attempt
+    #[serde(default)]
+    pub started_at_ms: Option<u64>,
+    pub created_at_ms: u64,
+    pub updated_at_ms: u64,
+}
+
+impl JobStatus {
+    /// Whether the job is waiting to run or running
+    pub fn is_pending(&self) -> bool {
+        matches!(self, JobStatus::Queued | JobStatus::Running)
+    }
+}
+
+impl JobRecord {
+    /// New job queued to run right away
+    pub fn new(kind: &str, program_id: Uuid, backoff: BackoffPolicy, now_ms: u64) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            kind: kind.to_string(),
+            program_id,
+            game_type: None,
+            dedupe_key: None,
+            status: JobStatus::Queued,
+            attempts: 0,
+            backoff,
+            last_error: None,
+            next_run_at_ms: now_ms,
+            started_at_ms: None,
+            created_at_ms: now_ms,
+            updated_at_ms: now_ms,
+        }
+    }
+}
+
+/// Filters the jobs listed by the jobs API
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct JobsQuery {
+    /// Only the jobs in this status, every job when missing
+    pub status: Option<JobStatus>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_delay() {
+        let backoff = BackoffPolicy {
+            max_attempts: 10,
+            initial_delay_ms: 100,
+            max_delay_ms: 1_000,
+        };
+        assert_eq!(backoff.delay_ms(1), 100);
+        assert_eq!(backoff.delay_ms(2), 200);
+        assert_eq!(backoff.delay_ms(4), 800);
+        assert_eq!(backoff.delay_ms(5), 1_000);
+        assert_eq!(backoff.delay_ms(100), 1_000);
+    }
+}
@@ -1,8 +1,12 @@
 pub mod add_numbers;
 pub mod bitvmx;
 pub mod common;
+pub mod game;
+pub mod job;
 
 // Re-export commonly used types for convenience
 pub use add_numbers::*;
 pub use bitvmx::*;
 pub use common::*;
+pub use game::*;
+pub use job::*;
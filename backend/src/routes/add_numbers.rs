@@ -1,24 +1,31 @@
+use crate::games::AddNumbers;
 use crate::models::{
-    AddNumbersGame, ErrorResponse, FundingUtxoRequest, FundingUtxosResponse, PlaceBetRequest,
+    AddNumbersGame, AddNumbersResponse, AddNumbersSubmit, ErrorResponse, FundingUtxoRequest,
+    FundingUtxosResponse, GameRecord, GameSubmitRequest, OperatorQuery, PlaceBetRequest,
     PlaceBetResponse, SetupGameRequest, SetupGameResponse, SetupParticipantsRequest,
     SetupParticipantsResponse, StartGameRequest, StartGameResponse, SubmitSumRequest,
     SubmitSumResponse,
 };
+use crate::routes::games;
+use crate::services::{AddNumbersService, WorkerService};
 use crate::state::AppState;
 use crate::utils::http_errors;
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     routing::{get, post},
-    Json, Router,
+    Extension, Json, Router,
 };
-use tracing::{debug, info};
+use std::sync::Arc;
 use uuid::Uuid;
 
+/// Add numbers routes kept for the existing clients, they use the shared game routes
+/// of /api/games/add-numbers/ and return the add numbers view of the games
 pub fn router() -> Router<AppState> {
     // Base path is /api/add-numbers/
     Router::new()
         .route("/{id}", get(get_game))
+        .route("/{id}/claim", post(claim_bet)) // for the winner (spend the action output to the operator)
         .route("/current-game", get(get_current_game))
         .route("/setup-participants", post(setup_participants))
         .route("/place-bet", post(place_bet))
@@ -28,6 +35,33 @@ pub fn router() -> Router<AppState> {
         .route("/submit-sum", post(submit_sum)) // Player 2 will send the sum to answer the challenge once he see the challenge transaction.
 }
 
+/// Add numbers game service of the game registry
+fn add_numbers_service(
+    app_state: &AppState,
+) -> Result<Arc<AddNumbersService>, (StatusCode, Json<ErrorResponse>)> {
+    app_state
+        .games
+        .game_service::<AddNumbers>()
+        .ok_or_else(|| http_errors::internal_server_error("Add numbers game is not registered"))
+}
+
+/// Add numbers worker service of the game registry
+fn add_numbers_worker(
+    app_state: &AppState,
+) -> Result<Arc<WorkerService<AddNumbers>>, (StatusCode, Json<ErrorResponse>)> {
+    app_state
+        .games
+        .worker_service::<AddNumbers>()
+        .ok_or_else(|| http_errors::internal_server_error("Add numbers game is not registered"))
+}
+
+/// Read a stored game as an add numbers game
+fn add_numbers_game(game: GameRecord) -> Result<AddNumbersGame, (StatusCode, Json<ErrorResponse>)> {
+    game.to_view().map_err(|e| {
+        http_errors::internal_server_error(&format!("Failed to read add numbers game: {e:?}"))
+    })
+}
+
 /// Get the current game
 #[utoipa::path( get,
     path = "/api/add-numbers/current-game",
@@ -40,21 +74,16 @@ pub fn router() -> Router<AppState> {
 pub async fn get_current_game(
     State(app_state): State<AppState>,
 ) -> Result<Json<Option<AddNumbersGame>>, (StatusCode, Json<ErrorResponse>)> {
-    let game = app_state
-        .add_numbers_service
-        .get_current_game()
-        .await
-        .map_err(|e| {
-            http_errors::internal_server_error(&format!("Failed to get current game: {e:?}"))
-        })?;
+    let Json(game) = games::get_current_game(Extension(add_numbers_service(&app_state)?)).await?;
 
-    Ok(Json(game))
+    Ok(Json(game.map(add_numbers_game).transpose()?))
 }
 
 /// Create a new add numbers game
 #[utoipa::path(
     post,
     path = "/api/add-numbers/setup-participants",
+    params(OperatorQuery),
     request_body = SetupParticipantsRequest,
     responses(
         (status = 201, description = "Game created successfully", body = SetupParticipantsResponse),
@@ -62,56 +91,17 @@ pub async fn get_current_game(
         (status = 400, description = "Aggregated ID cannot be empty", body = ErrorResponse),
         (status = 400, description = "At least one participant address is required", body = ErrorResponse),
         (status = 400, description = "At least one participant key is required", body = ErrorResponse),
+        (status = 404, description = "Unknown operator", body = ErrorResponse),
         (status = 500, description = "Failed to create aggregated key", body = ErrorResponse),
     ),
     tag = "AddNumbers"
 )]
 pub async fn setup_participants(
     State(app_state): State<AppState>,
-    Json(request): Json<SetupParticipantsRequest>,
+    query: Query<OperatorQuery>,
+    request: Json<SetupParticipantsRequest>,
 ) -> Result<Json<SetupParticipantsResponse>, (StatusCode, Json<ErrorResponse>)> {
-    // Validate the aggregated ID
-    if request.aggregated_id == Uuid::default() {
-        return Err(http_errors::bad_request("Aggregated ID cannot be empty"));
-    }
-    let aggregated_id = request.aggregated_id;
-
-    let leader_idx = request.leader_idx;
-
-    // Validate the participants addresses
-    if request.participants_addresses.is_empty() {
-        return Err(http_errors::bad_request(
-            "At least one participant address is required",
-        ));
-    }
-
-    // Validate the participants keys
-    if request.participants_keys.is_empty() {
-        return Err(http_errors::bad_request(
-            "At least one participant key is required",
-        ));
-    }
-
-    // Create the aggregated key
-    let (program_id, aggregated_key) = app_state
-        .add_numbers_service
-        .setup_participants(
-            aggregated_id,
-            leader_idx,
-            request.participants_addresses,
-            request.participants_keys,
-            request.role,
-        )
-        .await
-        .map_err(|e| {
-            http_errors::internal_server_error(&format!("Failed to create aggregated key: {e:?}"))
-        })?;
-    debug!("Aggregated key created: {:?}", aggregated_key);
-
-    Ok(Json(SetupParticipantsResponse {
-        program_id,
-        aggregated_key,
-    }))
+    games::setup_participants(Extension(add_numbers_service(&app_state)?), query, request).await
 }
 
 /// Get a specific add numbers game by ID
@@ -130,16 +120,11 @@ pub async fn setup_participants(
 )]
 pub async fn get_game(
     State(app_state): State<AppState>,
-    Path(id): Path<Uuid>,
+    id: Path<Uuid>,
 ) -> Result<Json<AddNumbersGame>, (StatusCode, Json<ErrorResponse>)> {
-    let game = app_state
-        .add_numbers_service
-        .get_game(id)
-        .await
-        .map_err(|e| http_errors::internal_server_error(&format!("Failed to get game: {e:?}")))?
-        .ok_or(http_errors::not_found("Game not found"))?;
+    let Json(game) = games::get_game(Extension(add_numbers_service(&app_state)?), id).await?;
 
-    Ok(Json(game.clone()))
+    Ok(Json(add_numbers_game(game)?))
 }
 
 /// Place a bet for the add numbers game
@@ -152,6 +137,7 @@ pub async fn get_game(
         (status = 400, description = "Invalid program ID", body = ErrorResponse),
         (status = 400, description = "Amount cannot be 0", body = ErrorResponse),
         (status = 404, description = "Game not found", body = ErrorResponse),
+        (status = 409, description = "Game is not in place bet state", body = ErrorResponse),
         (status = 500, description = "Failed to send protocol funds", body = ErrorResponse),
         (status = 500, description = "Failed to send bet funds", body = ErrorResponse),
         (status = 500, description = "Failed to obtain protocol destination from aggregated key", body = ErrorResponse),
@@ -162,28 +148,14 @@ pub async fn get_game(
 )]
 pub async fn place_bet(
     State(app_state): State<AppState>,
-    Json(request): Json<PlaceBetRequest>,
+    request: Json<PlaceBetRequest>,
 ) -> Result<Json<PlaceBetResponse>, (StatusCode, Json<ErrorResponse>)> {
-    // Validate the program ID
-    if request.program_id == Uuid::default() {
-        return Err(http_errors::bad_request("Program ID cannot be empty"));
-    }
-    let program_id = request.program_id;
-
-    // Validate the amount
-    if request.amount == 0 {
-        return Err(http_errors::bad_request("Amount cannot be 0"));
-    }
+    let Json(response) =
+        games::place_bet(Extension(add_numbers_service(&app_state)?), request).await?;
 
-    let game = app_state
-        .add_numbers_service
-        .place_bet(program_id, request.amount)
-        .await
-        .map_err(|e| http_errors::internal_server_error(&format!("Failed to place bet: {e:?}")))?;
-
-    info!("Place bet successfully for program id: {:?}", program_id);
-
-    Ok(Json(PlaceBetResponse { game }))
+    Ok(Json(PlaceBetResponse {
+        game: add_numbers_game(response.game)?,
+    }))
 }
 
 /// Setup the game for the add numbers game
@@ -195,48 +167,16 @@ pub async fn place_bet(
         (status = 200, description = "Funding UTXO setup successfully", body = FundingUtxosResponse),
         (status = 400, description = "Invalid UTXO", body = ErrorResponse),
         (status = 404, description = "Game not found", body = ErrorResponse),
+        (status = 409, description = "Game is not waiting for funding UTXOs", body = ErrorResponse),
         (status = 500, description = "Failed to add funding UTXO", body = ErrorResponse)
     ),
     tag = "AddNumbers"
 )]
 pub async fn setup_funding_utxo(
     State(app_state): State<AppState>,
-    Json(request): Json<FundingUtxoRequest>,
+    request: Json<FundingUtxoRequest>,
 ) -> Result<Json<FundingUtxosResponse>, (StatusCode, Json<ErrorResponse>)> {
-    // Validate the program ID
-    if request.program_id == Uuid::default() {
-        return Err(http_errors::bad_request("Invalid program ID"));
-    }
-
-    // Validate the protocol fees UTXO
-    if request.funding_protocol_utxo.txid.is_empty() || request.funding_protocol_utxo.amount == 0 {
-        return Err(http_errors::bad_request("Invalid UTXO"));
-    }
-    let funding_protocol_utxo = request.funding_protocol_utxo;
-
-    // Validate the bet UTXO
-    if request.funding_bet_utxo.txid.is_empty() || request.funding_bet_utxo.amount == 0 {
-        return Err(http_errors::bad_request("Invalid UTXO"));
-    }
-    let funding_bet_utxo = request.funding_bet_utxo;
-
-    // Save the funding UTXOs
-    app_state
-        .add_numbers_service
-        .setup_funding_utxo(
-            request.program_id,
-            funding_protocol_utxo.clone(),
-            funding_bet_utxo.clone(),
-        )
-        .await
-        .map_err(|e| {
-            http_errors::internal_server_error(&format!("Failed to add funding UTXO: {e:?}"))
-        })?;
-
-    Ok(Json(FundingUtxosResponse {
-        funding_protocol_utxo: Some(funding_protocol_utxo),
-        funding_bet_utxo: Some(funding_bet_utxo),
-    }))
+    games::setup_funding_utxo(Extension(add_numbers_service(&app_state)?), request).await
 }
 
 #[utoipa::path(
@@ -246,6 +186,7 @@ pub async fn setup_funding_utxo(
     responses(
         (status = 200, description = "Game started successfully", body = StartGameResponse),
         (status = 400, description = "Invalid program ID", body = ErrorResponse),
+        (status = 409, description = "Game is not in start game state", body = ErrorResponse),
         (status = 500, description = "Failed to start game", body = ErrorResponse),
         (status = 500, description = "Failed to convert challenge transaction to JSON", body = ErrorResponse),
     ),
@@ -253,38 +194,26 @@ pub async fn setup_funding_utxo(
 )]
 pub async fn start_game(
     State(app_state): State<AppState>,
-    Json(request): Json<StartGameRequest>,
+    request: Json<StartGameRequest>,
 ) -> Result<Json<StartGameResponse>, (StatusCode, Json<ErrorResponse>)> {
-    // Validate the program ID
-    if request.program_id == Uuid::default() {
-        return Err(http_errors::bad_request("Invalid program ID"));
-    }
-    let program_id = request.program_id;
-
-    // Player 1 send the challenge transaction to start the game.
-    let (_challenge_tx_name, challenge_tx) = app_state
-        .add_numbers_service
-        .start_game(program_id, app_state.worker_service.clone())
-        .await
-        .map_err(|e| http_errors::internal_server_error(&format!("Failed to start game: {e:?}")))?;
-
-    Ok(Json(StartGameResponse {
-        program_id,
-        challenge_tx: serde_json::to_value(challenge_tx).map_err(|e| {
-            http_errors::internal_server_error(&format!(
-                "Failed to convert challenge transaction to JSON: {e:?}"
-            ))
-        })?,
-    }))
+    games::start_game(
+        Extension(add_numbers_service(&app_state)?),
+        Extension(add_numbers_worker(&app_state)?),
+        request,
+    )
+    .await
 }
 
 #[utoipa::path(
     post,
     path = "/api/add-numbers/setup-game",
-    request_body = StartGameRequest,
+    request_body = SetupGameRequest,
     responses(
         (status = 200, description = "Game setup successfully", body = SetupGameResponse),
         (status = 400, description = "Invalid program ID", body = ErrorResponse),
+        (status = 400, description = "Invalid dispute parameters", body = ErrorResponse),
+        (status = 409, description = "Game is not in setup game state", body = ErrorResponse),
+        (status = 409, description = "Dispute parameters do not match the other player", body = ErrorResponse),
         (status = 500, description = "Failed to setup game", body = ErrorResponse)
     ),
     tag = "AddNumbers"
@@ -293,25 +222,12 @@ pub async fn setup_game(
     State(app_state): State<AppState>,
     Json(request): Json<SetupGameRequest>,
 ) -> Result<Json<SetupGameResponse>, (StatusCode, Json<ErrorResponse>)> {
-    // Validate the program ID
-    if request.program_id == Uuid::default() {
-        return Err(http_errors::bad_request("Invalid program ID"));
-    }
-    let program_id = request.program_id;
-
-    app_state
-        .add_numbers_service
-        .setup_game(
-            program_id,
-            request.number1,
-            request.number2,
-            app_state.worker_service.clone(),
-        )
-        .await
-        .map_err(|e| http_errors::internal_server_error(&format!("Failed to setup game: {e:?}")))?;
-
-    // Return the program ID
-    Ok(Json(SetupGameResponse { program_id }))
+    games::setup_game(
+        Extension(add_numbers_service(&app_state)?),
+        Extension(add_numbers_worker(&app_state)?),
+        Json(request.into()),
+    )
+    .await
 }
 
 /// Submit the sum for the add numbers game
@@ -322,6 +238,7 @@ pub async fn setup_game(
     responses(
         (status = 200, description = "Sum submitted successfully", body = SubmitSumResponse),
         (status = 400, description = "Invalid program ID", body = ErrorResponse),
+        (status = 409, description = "Game is not waiting for the sum", body = ErrorResponse),
         (status = 500, description = "Failed to submit sum", body = ErrorResponse),
         (status = 500, description = "Failed to convert challenge input transaction to JSON", body = ErrorResponse),
         (status = 500, description = "Failed to convert challenge result transaction to JSON", body = ErrorResponse),
@@ -332,18 +249,53 @@ pub async fn submit_sum(
     State(app_state): State<AppState>,
     Json(request): Json<SubmitSumRequest>,
 ) -> Result<Json<SubmitSumResponse>, (StatusCode, Json<ErrorResponse>)> {
-    // Validate the program ID
-    if request.id == Uuid::default() {
-        return Err(http_errors::bad_request("Invalid program ID"));
-    }
     let program_id = request.id;
+    let request = GameSubmitRequest {
+        program_id,
+        data: AddNumbersSubmit {
+            guess: request.guess,
+        },
+    };
+
+    let Json(response) = games::submit(
+        Extension(add_numbers_service(&app_state)?),
+        Extension(add_numbers_worker(&app_state)?),
+        Json(request),
+    )
+    .await?;
 
-    // TOOD: PEDRO Wait until you know the answer
-    let game = app_state
-        .add_numbers_service
-        .submit_sum(program_id, request.guess)
-        .await
-        .map_err(|e| http_errors::internal_server_error(&format!("Failed to submit sum: {e:?}")))?;
+    Ok(Json(SubmitSumResponse {
+        program_id,
+        game: add_numbers_game(response.game)?,
+    }))
+}
 
-    Ok(Json(SubmitSumResponse { program_id, game }))
+/// Claim the bet of a won game
+#[utoipa::path(
+    post,
+    path = "/api/add-numbers/{id}/claim",
+    params(
+        ("id" = String, Path, description = "Game ID", example = "123e4567-e89b-12d3-a456-426614174000")
+    ),
+    responses(
+        (status = 200, description = "Bet claimed, waiting for the payout", body = AddNumbersResponse),
+        (status = 409, description = "Game is not won", body = ErrorResponse),
+        (status = 500, description = "Failed to claim bet", body = ErrorResponse)
+    ),
+    tag = "AddNumbers"
+)]
+pub async fn claim_bet(
+    State(app_state): State<AppState>,
+    id: Path<Uuid>,
+) -> Result<Json<AddNumbersResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let Json(response) = games::claim_bet(
+        Extension(add_numbers_service(&app_state)?),
+        Extension(add_numbers_worker(&app_state)?),
+        id,
+    )
+    .await?;
+
+    Ok(Json(AddNumbersResponse {
+        game: add_numbers_game(response.game)?,
+    }))
 }
@@ -1,11 +1,18 @@
 use crate::models::{
-    AggregatedKeyResponse, ErrorResponse, OperatorKeys, P2PAddress, ProtocolVisualizationResponse,
-    TransactionResponse, WalletBalance,
+    AggregatedKeyResponse, BitvmxConnection, ErrorResponse, OperatorInfo, OperatorKeys,
+    OperatorQuery, P2PAddress, ProtocolVisualizationResponse, SetupStatus, TransactionResponse,
+    UnclaimedResponse, UnclaimedResponsesMetrics, UnclaimedResponsesResponse, WalletBalance,
 };
+use crate::services::Operator;
 use crate::state::AppState;
 use crate::utils::http_errors;
-use axum::{extract::Path, extract::State, routing::get, Json, Router};
+use axum::{
+    extract::{Path, Query, State},
+    routing::get,
+    Json, Router,
+};
 use http::StatusCode;
+use std::sync::Arc;
 use tracing::info;
 use uuid::Uuid;
 
@@ -14,6 +21,7 @@ pub fn router() -> Router<AppState> {
     Router::new()
         .route("/comm-info", get(comm_info))
         .route("/operator-keys", get(operator_keys))
+        .route("/setup-status", get(setup_status))
         .route("/aggregated-key/{uuid}", get(get_aggregated_key))
         .route("/wallet-balance", get(wallet_balance))
         .route("/transaction/{txid}", get(get_transaction))
@@ -21,14 +29,34 @@ pub fn router() -> Router<AppState> {
             "/protocol/visualization/{uuid}",
             get(get_protocol_visualization),
         )
+        .route("/unclaimed-responses", get(unclaimed_responses))
+        .route("/operators", get(operators))
+}
+
+/// Operator selected by the `operator` query parameter, unknown operators are not found
+/// and unhealthy ones unavailable
+fn operator(
+    app_state: &AppState,
+    query: &OperatorQuery,
+) -> Result<Arc<Operator>, (StatusCode, Json<ErrorResponse>)> {
+    let operator = app_state
+        .operators
+        .select(query.operator.as_deref())
+        .map_err(|e| http_errors::not_found(&e.to_string()))?;
+    operator
+        .check_healthy()
+        .map_err(|e| http_errors::service_unavailable(&e.to_string()))?;
+    Ok(operator)
 }
 
 /// Get BitVMX P2P address information
 #[utoipa::path(
     get,
     path = "/api/bitvmx/comm-info",
+    params(OperatorQuery),
     responses(
         (status = 200, description = "BitVMX P2P address information", body = P2PAddress),
+        (status = 503, description = "Operator unhealthy", body = ErrorResponse),
         (status = 404, description = "P2P address not found", body = ErrorResponse),
         (status = 500, description = "Failed to get p2p address", body = ErrorResponse),
     ),
@@ -36,8 +64,10 @@ pub fn router() -> Router<AppState> {
 )]
 pub async fn comm_info(
     State(app_state): State<AppState>,
+    Query(query): Query<OperatorQuery>,
 ) -> Result<Json<P2PAddress>, (StatusCode, Json<ErrorResponse>)> {
-    let p2p_address = app_state
+    let operator = operator(&app_state, &query)?;
+    let p2p_address = operator
         .bitvmx_service
         .get_p2p_address()
         .await
@@ -52,8 +82,10 @@ pub async fn comm_info(
 #[utoipa::path(
     get,
     path = "/api/bitvmx/operator-keys",
+    params(OperatorQuery),
     responses(
         (status = 200, description = "BitVMX Operator key", body = OperatorKeys),
+        (status = 503, description = "Operator unhealthy", body = ErrorResponse),
         (status = 404, description = "Operator key not found", body = ErrorResponse),
         (status = 404, description = "Operator funding key not found", body = ErrorResponse),
         (status = 500, description = "Failed to get pub key", body = ErrorResponse),
@@ -63,14 +95,16 @@ pub async fn comm_info(
 )]
 pub async fn operator_keys(
     State(app_state): State<AppState>,
+    Query(query): Query<OperatorQuery>,
 ) -> Result<Json<OperatorKeys>, (StatusCode, Json<ErrorResponse>)> {
-    let pub_key = app_state
+    let operator = operator(&app_state, &query)?;
+    let pub_key = operator
         .bitvmx_service
         .get_pub_key()
         .await
         .map_err(|e| http_errors::internal_server_error(&format!("Failed to get pub key: {e:?}")))?
         .ok_or(http_errors::not_found("Operator pub key not found"))?;
-    let funding_key = app_state
+    let funding_key = operator
         .bitvmx_service
         .get_funding_key()
         .await
@@ -84,15 +118,37 @@ pub async fn operator_keys(
     }))
 }
 
+/// Get the status of the BitVMX initial setup steps
+#[utoipa::path(
+    get,
+    path = "/api/bitvmx/setup-status",
+    params(OperatorQuery),
+    responses(
+        (status = 200, description = "BitVMX setup status", body = SetupStatus),
+        (status = 503, description = "Operator unhealthy", body = ErrorResponse),
+        (status = 404, description = "Unknown operator", body = ErrorResponse),
+    ),
+    tag = "BitVMX"
+)]
+pub async fn setup_status(
+    State(app_state): State<AppState>,
+    Query(query): Query<OperatorQuery>,
+) -> Result<Json<SetupStatus>, (StatusCode, Json<ErrorResponse>)> {
+    let operator = operator(&app_state, &query)?;
+    Ok(Json(operator.bitvmx_service.setup_status().await))
+}
+
 /// Get BitVMX aggregated key
 #[utoipa::path(
     get,
     path = "/api/bitvmx/aggregated-key/{uuid}",
     params(
-        ("uuid" = String, Path, description = "Aggregated key UUID")
+        ("uuid" = String, Path, description = "Aggregated key UUID"),
+        OperatorQuery
     ),
     responses(
         (status = 200, description = "Aggregated key", body = AggregatedKeyResponse),
+        (status = 503, description = "Operator unhealthy", body = ErrorResponse),
         (status = 404, description = "Aggregated key not found", body = ErrorResponse)
     ),
     tag = "BitVMX"
@@ -100,8 +156,10 @@ pub async fn operator_keys(
 pub async fn get_aggregated_key(
     State(app_state): State<AppState>,
     Path(uuid): Path<Uuid>,
+    Query(query): Query<OperatorQuery>,
 ) -> Result<Json<AggregatedKeyResponse>, (StatusCode, Json<ErrorResponse>)> {
-    let aggregated_key = app_state
+    let operator = operator(&app_state, &query)?;
+    let aggregated_key = operator
         .bitvmx_service
         .aggregated_key(uuid)
         .await
@@ -118,16 +176,20 @@ pub async fn get_aggregated_key(
 #[utoipa::path(
     get,
     path = "/api/bitvmx/wallet-balance",
+    params(OperatorQuery),
     responses(
         (status = 200, description = "BitVMX Wallet balance", body = WalletBalance),
+        (status = 503, description = "Operator unhealthy", body = ErrorResponse),
         (status = 404, description = "Wallet balance not found", body = ErrorResponse)
     ),
     tag = "BitVMX"
 )]
 pub async fn wallet_balance(
     State(app_state): State<AppState>,
+    Query(query): Query<OperatorQuery>,
 ) -> Result<Json<WalletBalance>, (StatusCode, Json<ErrorResponse>)> {
-    let wallet_balance = app_state
+    let operator = operator(&app_state, &query)?;
+    let wallet_balance = operator
         .bitvmx_service
         .wallet_balance()
         .await
@@ -141,8 +203,10 @@ pub async fn wallet_balance(
 #[utoipa::path(
     get,
     path = "/api/bitvmx/transaction/{txid}",
+    params(OperatorQuery),
     responses(
         (status = 200, description = "Transaction", body = TransactionResponse),
+        (status = 503, description = "Operator unhealthy", body = ErrorResponse),
         (status = 404, description = "Transaction not found", body = ErrorResponse)
     ),
     tag = "BitVMX"
@@ -150,8 +214,10 @@ pub async fn wallet_balance(
 pub async fn get_transaction(
     State(app_state): State<AppState>,
     Path(txid): Path<String>,
+    Query(query): Query<OperatorQuery>,
 ) -> Result<Json<TransactionResponse>, (StatusCode, Json<ErrorResponse>)> {
-    let transaction = app_state
+    let operator = operator(&app_state, &query)?;
+    let transaction = operator
         .bitvmx_service
         .get_transaction(txid)
         .await
@@ -179,10 +245,12 @@ pub async fn get_transaction(
     get,
     path = "/api/bitvmx/protocol/visualization/{uuid}",
     params(
-        ("uuid" = String, Path, description = "Aggregated key UUID")
+        ("uuid" = String, Path, description = "Aggregated key UUID"),
+        OperatorQuery
     ),
     responses(
         (status = 200, description = "Protocol visualization", body = String),
+        (status = 503, description = "Operator unhealthy", body = ErrorResponse),
         (status = 404, description = "Protocol visualization not found", body = ErrorResponse),
         (status = 500, description = "Failed to get protocol visualization", body = ErrorResponse)
     ),
@@ -191,8 +259,10 @@ pub async fn get_transaction(
 pub async fn get_protocol_visualization(
     State(app_state): State<AppState>,
     Path(program_id): Path<Uuid>,
+    Query(query): Query<OperatorQuery>,
 ) -> Result<Json<ProtocolVisualizationResponse>, (StatusCode, Json<ErrorResponse>)> {
-    let visualization = app_state
+    let operator = operator(&app_state, &query)?;
+    let visualization = operator
         .bitvmx_service
         .get_protocol_visualization(program_id)
         .await
@@ -209,3 +279,78 @@ pub async fn get_protocol_visualization(
     info!("HOLAAAAAAAAAAAAAA: {:?}", response);
     Ok(Json(response))
 }
+
+/// List the BitVMX responses received while nobody was waiting for them
+#[utoipa::path(
+    get,
+    path = "/api/bitvmx/unclaimed-responses",
+    params(OperatorQuery),
+    responses(
+        (status = 200, description = "Unclaimed responses", body = UnclaimedResponsesResponse),
+        (status = 500, description = "Failed to serialize an unclaimed response", body = ErrorResponse)
+    ),
+    tag = "BitVMX"
+)]
+pub async fn unclaimed_responses(
+    State(app_state): State<AppState>,
+    Query(query): Query<OperatorQuery>,
+) -> Result<Json<UnclaimedResponsesResponse>, (StatusCode, Json<ErrorResponse>)> {
+    // Unhealthy operators too, to see what they received
+    let operator = app_state
+        .operators
+        .select(query.operator.as_deref())
+        .map_err(|e| http_errors::not_found(&e.to_string()))?;
+    let (metrics, unclaimed) = operator.rpc_client.unclaimed_responses().await;
+    let responses = unclaimed
+        .into_iter()
+        .map(|unclaimed| {
+            Ok(UnclaimedResponse {
+                message: serde_json::to_value(&unclaimed.response)?,
+                age_secs: unclaimed.received_at.elapsed().as_secs(),
+                correlation_id: unclaimed.correlation_id,
+            })
+        })
+        .collect::<Result<Vec<_>, serde_json::Error>>()
+        .map_err(|e| {
+            http_errors::internal_server_error(&format!(
+                "Failed to serialize unclaimed response: {e:?}"
+            ))
+        })?;
+
+    Ok(Json(UnclaimedResponsesResponse {
+        metrics: UnclaimedResponsesMetrics {
+            retained: metrics.retained,
+            claimed: metrics.claimed,
+            expired: metrics.expired,
+            evicted: metrics.evicted,
+            unclaimed: responses.len() as u64,
+        },
+        responses,
+    }))
+}
+
+/// List the BitVMX operators and their connection state
+#[utoipa::path(
+    get,
+    path = "/api/bitvmx/operators",
+    responses(
+        (status = 200, description = "Operators, the default one first", body = Vec<OperatorInfo>),
+    ),
+    tag = "BitVMX"
+)]
+pub async fn operators(State(app_state): State<AppState>) -> Json<Vec<OperatorInfo>> {
+    let default_operator = &app_state.operators.default_operator().name;
+    Json(
+        app_state
+            .operators
+            .list()
+            .iter()
+            .map(|operator| OperatorInfo {
+                name: operator.name.clone(),
+                default: &operator.name == default_operator,
+                connection: BitvmxConnection::of(&operator.rpc_client),
+                unhealthy: operator.unhealthy_reason(),
+            })
+            .collect(),
+    )
+}
@@ -0,0 +1,402 @@
+use crate::games::Game;
+use crate::models::{
+    ErrorResponse, FundingUtxoRequest, FundingUtxosResponse, GameRecord, GameResponse,
+    GameSetupRequest, GameSubmitRequest, OperatorQuery, PlaceBetRequest, SetupGameResponse,
+    SetupParticipantsRequest, SetupParticipantsResponse, StartGameRequest, StartGameResponse,
+};
+use crate::services::{GameService, WorkerService};
+use crate::state::AppState;
+use crate::utils::http_errors;
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use std::sync::Arc;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+/// Routes shared by every game, nested under /api/games/{game_type}/
+pub fn router<G: Game>(
+    game_service: Arc<GameService<G>>,
+    worker_service: Arc<WorkerService<G>>,
+) -> Router<AppState> {
+    Router::new()
+        .route("/{id}", get(get_game::<G>))
+        .route("/{id}/claim", post(claim_bet::<G>)) // for the winner (spend the action output to the operator)
+        .route("/current-game", get(get_current_game::<G>))
+        .route("/setup-participants", post(setup_participants::<G>))
+        .route("/place-bet", post(place_bet::<G>))
+        .route("/setup-funding-utxo", post(setup_funding_utxo::<G>)) // for player 2
+        .route("/setup-game", post(setup_game::<G>)) // for player 1 and player 2 (send the game setup data)
+        .route("/start-game", post(start_game::<G>)) // for player 1 (send the challenge transaction to start the game)
+        .route("/submit", post(submit::<G>)) // for player 2 (answer the challenge)
+        .layer(Extension(game_service))
+        .layer(Extension(worker_service))
+}
+
+/// Get the current game
+#[utoipa::path(
+    get,
+    path = "/api/games/{game_type}/current-game",
+    params(
+        ("game_type" = String, Path, description = "Game type", example = "add-numbers")
+    ),
+    responses(
+        (status = 200, description = "Current game", body = Option<GameRecord>),
+        (status = 500, description = "Failed to get current game", body = ErrorResponse)
+    ),
+    tag = "Games"
+)]
+pub async fn get_current_game<G: Game>(
+    Extension(game_service): Extension<Arc<GameService<G>>>,
+) -> Result<Json<Option<GameRecord>>, (StatusCode, Json<ErrorResponse>)> {
+    let game = game_service.get_current_game().await.map_err(|e| {
+        http_errors::internal_server_error(&format!("Failed to get current game: {e:?}"))
+    })?;
+
+    Ok(Json(game))
+}
+
+/// Create a new game
+#[utoipa::path(
+    post,
+    path = "/api/games/{game_type}/setup-participants",
+    params(
+        ("game_type" = String, Path, description = "Game type", example = "add-numbers"),
+        OperatorQuery
+    ),
+    request_body = SetupParticipantsRequest,
+    responses(
+        (status = 201, description = "Game created successfully", body = SetupParticipantsResponse),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "Unknown operator", body = ErrorResponse),
+        (status = 500, description = "Failed to create aggregated key", body = ErrorResponse),
+    ),
+    tag = "Games"
+)]
+pub async fn setup_participants<G: Game>(
+    Extension(game_service): Extension<Arc<GameService<G>>>,
+    Query(query): Query<OperatorQuery>,
+    Json(request): Json<SetupParticipantsRequest>,
+) -> Result<Json<SetupParticipantsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate the aggregated ID
+    if request.aggregated_id == Uuid::default() {
+        return Err(http_errors::bad_request("Aggregated ID cannot be empty"));
+    }
+    let aggregated_id = request.aggregated_id;
+
+    let leader_idx = request.leader_idx;
+
+    // Validate the participants addresses
+    if request.participants_addresses.is_empty() {
+        return Err(http_errors::bad_request(
+            "At least one participant address is required",
+        ));
+    }
+
+    // Validate the participants keys
+    if request.participants_keys.is_empty() {
+        return Err(http_errors::bad_request(
+            "At least one participant key is required",
+        ));
+    }
+
+    // Create the aggregated key
+    let (program_id, aggregated_key) = game_service
+        .setup_participants(
+            aggregated_id,
+            leader_idx,
+            request.participants_addresses,
+            request.participants_keys,
+            request.role,
+            query.operator.as_deref(),
+        )
+        .await
+        .map_err(|e| http_errors::game_error("Failed to create aggregated key", &e))?;
+    debug!("Aggregated key created: {:?}", aggregated_key);
+
+    Ok(Json(SetupParticipantsResponse {
+        program_id,
+        aggregated_key,
+    }))
+}
+
+/// Get a specific game by ID
+#[utoipa::path(
+    get,
+    path = "/api/games/{game_type}/{id}",
+    params(
+        ("game_type" = String, Path, description = "Game type", example = "add-numbers"),
+        ("id" = String, Path, description = "Game ID", example = "123e4567-e89b-12d3-a456-426614174000")
+    ),
+    responses(
+        (status = 200, description = "Game found", body = GameRecord),
+        (status = 404, description = "Game not found", body = ErrorResponse),
+        (status = 500, description = "Failed to get game", body = ErrorResponse)
+    ),
+    tag = "Games"
+)]
+pub async fn get_game<G: Game>(
+    Extension(game_service): Extension<Arc<GameService<G>>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<GameRecord>, (StatusCode, Json<ErrorResponse>)> {
+    let game = game_service
+        .get_game(id)
+        .await
+        .map_err(|e| http_errors::internal_server_error(&format!("Failed to get game: {e:?}")))?
+        .ok_or(http_errors::not_found("Game not found"))?;
+
+    Ok(Json(game))
+}
+
+/// Place a bet for the game
+#[utoipa::path(
+    post,
+    path = "/api/games/{game_type}/place-bet",
+    params(
+        ("game_type" = String, Path, description = "Game type", example = "add-numbers")
+    ),
+    request_body = PlaceBetRequest,
+    responses(
+        (status = 200, description = "Place bet successfully", body = GameResponse),
+        (status = 400, description = "Invalid program ID", body = ErrorResponse),
+        (status = 400, description = "Amount cannot be 0", body = ErrorResponse),
+        (status = 409, description = "Game is not in place bet state", body = ErrorResponse),
+        (status = 500, description = "Failed to place bet", body = ErrorResponse)
+    ),
+    tag = "Games"
+)]
+pub async fn place_bet<G: Game>(
+    Extension(game_service): Extension<Arc<GameService<G>>>,
+    Json(request): Json<PlaceBetRequest>,
+) -> Result<Json<GameResponse>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate the program ID
+    if request.program_id == Uuid::default() {
+        return Err(http_errors::bad_request("Program ID cannot be empty"));
+    }
+    let program_id = request.program_id;
+
+    // Validate the amount
+    if request.amount == 0 {
+        return Err(http_errors::bad_request("Amount cannot be 0"));
+    }
+
+    let game = game_service
+        .place_bet(program_id, request.amount)
+        .await
+        .map_err(|e| http_errors::game_error("Failed to place bet", &e))?;
+
+    info!("Place bet successfully for program id: {:?}", program_id);
+
+    Ok(Json(GameResponse { game }))
+}
+
+/// Save the funding UTXOs sent by the other player
+#[utoipa::path(
+    post,
+    path = "/api/games/{game_type}/setup-funding-utxo",
+    params(
+        ("game_type" = String, Path, description = "Game type", example = "add-numbers")
+    ),
+    request_body = FundingUtxoRequest,
+    responses(
+        (status = 200, description = "Funding UTXO setup successfully", body = FundingUtxosResponse),
+        (status = 400, description = "Invalid UTXO", body = ErrorResponse),
+        (status = 409, description = "Game is not waiting for funding UTXOs", body = ErrorResponse),
+        (status = 500, description = "Failed to add funding UTXO", body = ErrorResponse)
+    ),
+    tag = "Games"
+)]
+pub async fn setup_funding_utxo<G: Game>(
+    Extension(game_service): Extension<Arc<GameService<G>>>,
+    Json(request): Json<FundingUtxoRequest>,
+) -> Result<Json<FundingUtxosResponse>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate the program ID
+    if request.program_id == Uuid::default() {
+        return Err(http_errors::bad_request("Invalid program ID"));
+    }
+
+    // Validate the protocol fees UTXO
+    if request.funding_protocol_utxo.txid.is_empty() || request.funding_protocol_utxo.amount == 0 {
+        return Err(http_errors::bad_request("Invalid UTXO"));
+    }
+    let funding_protocol_utxo = request.funding_protocol_utxo;
+
+    // Validate the bet UTXO
+    if request.funding_bet_utxo.txid.is_empty() || request.funding_bet_utxo.amount == 0 {
+        return Err(http_errors::bad_request("Invalid UTXO"));
+    }
+    let funding_bet_utxo = request.funding_bet_utxo;
+
+    // Save the funding UTXOs
+    game_service
+        .setup_funding_utxo(
+            request.program_id,
+            funding_protocol_utxo.clone(),
+            funding_bet_utxo.clone(),
+        )
+        .await
+        .map_err(|e| http_errors::game_error("Failed to add funding UTXO", &e))?;
+
+    Ok(Json(FundingUtxosResponse {
+        funding_protocol_utxo: Some(funding_protocol_utxo),
+        funding_bet_utxo: Some(funding_bet_utxo),
+    }))
+}
+
+/// Start the game, player 1 sends the challenge transaction
+#[utoipa::path(
+    post,
+    path = "/api/games/{game_type}/start-game",
+    params(
+        ("game_type" = String, Path, description = "Game type", example = "add-numbers")
+    ),
+    request_body = StartGameRequest,
+    responses(
+        (status = 200, description = "Game started successfully", body = StartGameResponse),
+        (status = 400, description = "Invalid program ID", body = ErrorResponse),
+        (status = 409, description = "Game is not in start game state", body = ErrorResponse),
+        (status = 500, description = "Failed to start game", body = ErrorResponse),
+    ),
+    tag = "Games"
+)]
+pub async fn start_game<G: Game>(
+    Extension(game_service): Extension<Arc<GameService<G>>>,
+    Extension(worker_service): Extension<Arc<WorkerService<G>>>,
+    Json(request): Json<StartGameRequest>,
+) -> Result<Json<StartGameResponse>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate the program ID
+    if request.program_id == Uuid::default() {
+        return Err(http_errors::bad_request("Invalid program ID"));
+    }
+    let program_id = request.program_id;
+
+    // Player 1 send the challenge transaction to start the game.
+    let (_challenge_tx_name, challenge_tx) = game_service
+        .start_game(program_id, worker_service)
+        .await
+        .map_err(|e| http_errors::game_error("Failed to start game", &e))?;
+
+    Ok(Json(StartGameResponse {
+        program_id,
+        challenge_tx: serde_json::to_value(challenge_tx).map_err(|e| {
+            http_errors::internal_server_error(&format!(
+                "Failed to convert challenge transaction to JSON: {e:?}"
+            ))
+        })?,
+    }))
+}
+
+/// Set up the program with the game setup data, e.g. the numbers to sum
+#[utoipa::path(
+    post,
+    path = "/api/games/{game_type}/setup-game",
+    params(
+        ("game_type" = String, Path, description = "Game type", example = "add-numbers")
+    ),
+    request_body(
+        content = Object,
+        description = "Program ID, the game setup data and the optional dispute parameters overrides"
+    ),
+    responses(
+        (status = 200, description = "Game setup successfully", body = SetupGameResponse),
+        (status = 400, description = "Invalid program ID", body = ErrorResponse),
+        (status = 400, description = "Invalid dispute parameters", body = ErrorResponse),
+        (status = 409, description = "Game is not in setup game state", body = ErrorResponse),
+        (status = 409, description = "Dispute parameters do not match the other player", body = ErrorResponse),
+        (status = 500, description = "Failed to setup game", body = ErrorResponse)
+    ),
+    tag = "Games"
+)]
+pub async fn setup_game<G: Game>(
+    Extension(game_service): Extension<Arc<GameService<G>>>,
+    Extension(worker_service): Extension<Arc<WorkerService<G>>>,
+    Json(request): Json<GameSetupRequest<G::SetupData>>,
+) -> Result<Json<SetupGameResponse>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate the program ID
+    if request.program_id == Uuid::default() {
+        return Err(http_errors::bad_request("Invalid program ID"));
+    }
+    let program_id = request.program_id;
+
+    let dispute_parameters = game_service
+        .setup_game(request, worker_service)
+        .await
+        .map_err(|e| http_errors::game_error("Failed to setup game", &e))?;
+
+    // Return the program ID and the parameters for the other player
+    Ok(Json(SetupGameResponse {
+        program_id,
+        dispute_parameters,
+    }))
+}
+
+/// Submit the answer of player 2, e.g. the sum
+#[utoipa::path(
+    post,
+    path = "/api/games/{game_type}/submit",
+    params(
+        ("game_type" = String, Path, description = "Game type", example = "add-numbers")
+    ),
+    request_body(
+        content = Object,
+        description = "Program ID and the game answer"
+    ),
+    responses(
+        (status = 200, description = "Answer submitted successfully", body = GameResponse),
+        (status = 400, description = "Invalid program ID", body = ErrorResponse),
+        (status = 409, description = "Game is not waiting for the answer", body = ErrorResponse),
+        (status = 500, description = "Failed to submit answer", body = ErrorResponse),
+    ),
+    tag = "Games"
+)]
+pub async fn submit<G: Game>(
+    Extension(game_service): Extension<Arc<GameService<G>>>,
+    Extension(worker_service): Extension<Arc<WorkerService<G>>>,
+    Json(request): Json<GameSubmitRequest<G::SubmitData>>,
+) -> Result<Json<GameResponse>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate the program ID
+    if request.program_id == Uuid::default() {
+        return Err(http_errors::bad_request("Invalid program ID"));
+    }
+
+    // The outcome is set in background, the game status changes once the dispute is decided
+    let game = game_service
+        .submit(request.program_id, request.data, worker_service)
+        .await
+        .map_err(|e| http_errors::game_error("Failed to submit answer", &e))?;
+
+    Ok(Json(GameResponse { game }))
+}
+
+/// Claim the bet of a won game
+#[utoipa::path(
+    post,
+    path = "/api/games/{game_type}/{id}/claim",
+    params(
+        ("game_type" = String, Path, description = "Game type", example = "add-numbers"),
+        ("id" = String, Path, description = "Game ID", example = "123e4567-e89b-12d3-a456-426614174000")
+    ),
+    responses(
+        (status = 200, description = "Bet claimed, waiting for the payout", body = GameResponse),
+        (status = 409, description = "Game is not won", body = ErrorResponse),
+        (status = 500, description = "Failed to claim bet", body = ErrorResponse)
+    ),
+    tag = "Games"
+)]
+pub async fn claim_bet<G: Game>(
+    Extension(game_service): Extension<Arc<GameService<G>>>,
+    Extension(worker_service): Extension<Arc<WorkerService<G>>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<GameResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let game = game_service
+        .claim_bet(id, worker_service)
+        .await
+        .map_err(|e| http_errors::game_error("Failed to claim bet", &e))?;
+
+    info!("Bet claimed for program id: {:?}", id);
+
+    Ok(Json(GameResponse { game }))
+}
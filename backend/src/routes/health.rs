@@ -1,4 +1,4 @@
-use crate::models::HealthResponse;
+use crate::models::{BitvmxConnection, BitvmxConnectionState, HealthResponse};
 use crate::state::AppState;
 use axum::{extract::State, routing::get, Json, Router};
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -14,19 +14,32 @@ pub fn router() -> Router<AppState> {
     get,
     path = "/api/health",
     responses(
-        (status = 200, description = "Service is healthy", body = HealthResponse)
+        (status = 200, description = "Service is healthy, or degraded while BitVMX is not connected or an operator is unhealthy", body = HealthResponse)
     ),
     tag = "Health"
 )]
-#[instrument(skip(_app_state))]
-pub async fn health_check(State(_app_state): State<AppState>) -> Json<HealthResponse> {
+#[instrument(skip(app_state))]
+pub async fn health_check(State(app_state): State<AppState>) -> Json<HealthResponse> {
     let timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
         .as_secs();
 
+    let bitvmx = BitvmxConnection::of(&app_state.rpc_client);
+    let unhealthy_operator = app_state
+        .operators
+        .list()
+        .iter()
+        .any(|operator| operator.unhealthy_reason().is_some());
+    let status = if bitvmx.state == BitvmxConnectionState::Connected && !unhealthy_operator {
+        "healthy"
+    } else {
+        "degraded"
+    };
+
     Json(HealthResponse {
-        status: "healthy".to_string(),
+        status: status.to_string(),
         timestamp,
+        bitvmx,
     })
 }
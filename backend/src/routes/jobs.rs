@@ -0,0 +1,118 @@
+use crate::models::{ErrorResponse, JobRecord, JobsQuery};
+use crate::state::AppState;
+use crate::utils::http_errors;
+use axum::{
+    extract::{Path, Query, State},
+    routing::{get, post},
+    Json, Router,
+};
+use http::StatusCode;
+use tracing::info;
+use uuid::Uuid;
+
+pub fn router() -> Router<AppState> {
+    // Base path is /api/jobs/
+    Router::new()
+        .route("/", get(list_jobs))
+        .route("/{id}", get(get_job))
+        .route("/{id}/cancel", post(cancel_job))
+        .route("/{id}/retry", post(retry_job)) // for the jobs in the dead letter list
+}
+
+/// List the background jobs, including the dead letters
+#[utoipa::path(
+    get,
+    path = "/api/jobs",
+    params(JobsQuery),
+    responses(
+        (status = 200, description = "Background jobs, oldest first", body = Vec<JobRecord>),
+    ),
+    tag = "Jobs"
+)]
+pub async fn list_jobs(
+    State(app_state): State<AppState>,
+    Query(query): Query<JobsQuery>,
+) -> Json<Vec<JobRecord>> {
+    Json(app_state.job_worker.list(query.status).await)
+}
+
+/// Get a background job
+#[utoipa::path(
+    get,
+    path = "/api/jobs/{id}",
+    params(
+        ("id" = String, Path, description = "Job ID", example = "123e4567-e89b-12d3-a456-426614174000")
+    ),
+    responses(
+        (status = 200, description = "Background job", body = JobRecord),
+        (status = 404, description = "Job not found", body = ErrorResponse),
+    ),
+    tag = "Jobs"
+)]
+pub async fn get_job(
+    State(app_state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<JobRecord>, (StatusCode, Json<ErrorResponse>)> {
+    let job = app_state
+        .job_worker
+        .get(id)
+        .await
+        .map_err(|e| http_errors::job_error("Failed to get job", &e))?;
+    Ok(Json(job))
+}
+
+/// Cancel a queued or running job
+#[utoipa::path(
+    post,
+    path = "/api/jobs/{id}/cancel",
+    params(
+        ("id" = String, Path, description = "Job ID", example = "123e4567-e89b-12d3-a456-426614174000")
+    ),
+    responses(
+        (status = 200, description = "Job cancelled", body = JobRecord),
+        (status = 404, description = "Job not found", body = ErrorResponse),
+        (status = 409, description = "Job already finished", body = ErrorResponse),
+        (status = 500, description = "Failed to cancel job", body = ErrorResponse),
+    ),
+    tag = "Jobs"
+)]
+pub async fn cancel_job(
+    State(app_state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<JobRecord>, (StatusCode, Json<ErrorResponse>)> {
+    info!("Cancel job {id}");
+    let job = app_state
+        .job_worker
+        .cancel(id)
+        .await
+        .map_err(|e| http_errors::job_error("Failed to cancel job", &e))?;
+    Ok(Json(job))
+}
+
+/// Queue again a job that failed every attempt
+#[utoipa::path(
+    post,
+    path = "/api/jobs/{id}/retry",
+    params(
+        ("id" = String, Path, description = "Job ID", example = "123e4567-e89b-12d3-a456-426614174000")
+    ),
+    responses(
+        (status = 200, description = "Job queued again", body = JobRecord),
+        (status = 404, description = "Job not found", body = ErrorResponse),
+        (status = 409, description = "Job did not fail", body = ErrorResponse),
+        (status = 500, description = "Failed to retry job", body = ErrorResponse),
+    ),
+    tag = "Jobs"
+)]
+pub async fn retry_job(
+    State(app_state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<JobRecord>, (StatusCode, Json<ErrorResponse>)> {
+    info!("Retry job {id}");
+    let job = app_state
+        .job_worker
+        .retry(id)
+        .await
+        .map_err(|e| http_errors::job_error("Failed to retry job", &e))?;
+    Ok(Json(job))
+}
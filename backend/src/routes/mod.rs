@@ -1,3 +1,5 @@
 pub mod add_numbers;
 pub mod bitvmx;
+pub mod games;
 pub mod health;
+pub mod jobs;
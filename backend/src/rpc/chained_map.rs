@@ -25,6 +25,11 @@ where
         self.map.entry(key).or_default().push(value);
     }
 
+    /// Check if there are values for a given key
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
     /// Remove and return all values for a given key
     pub fn drain_all_for_key(&mut self, key: &K) -> Result<Vec<V>> {
         if let Some(values) = self.map.remove(key) {
@@ -33,6 +38,21 @@ where
             Ok(Vec::new())
         }
     }
+
+    /// Keep only the values matching the predicate, returns how many were removed
+    pub fn retain<F>(&mut self, mut keep: F) -> usize
+    where
+        F: FnMut(&V) -> bool,
+    {
+        let mut removed = 0;
+        self.map.retain(|_key, values| {
+            let len = values.len();
+            values.retain(&mut keep);
+            removed += len - values.len();
+            !values.is_empty()
+        });
+        removed
+    }
 }
 
 #[cfg(test)]
@@ -85,4 +105,16 @@ mod tests {
         assert!(bag.drain_all_for_key(&"job1")?.is_empty());
         Ok(())
     }
+
+    #[test]
+    fn test_retain() {
+        let mut bag: ChainedMap<&str, u32> = ChainedMap::new();
+        bag.insert("job1", 1);
+        bag.insert("job1", 2);
+        bag.insert("job2", 3);
+
+        assert_eq!(bag.retain(|value| value % 2 == 0), 2);
+        assert!(!bag.contains_key(&"job2"));
+        assert_eq!(bag.drain_all_for_key(&"job1").unwrap(), vec![2]);
+    }
 }
@@ -0,0 +1,488 @@
+//! Journal of the messages exchanged with BitVMX, one JSON line per message,
+//! and the replay of a journal to reproduce an incident against a fake or real BitVMX.
+
+use crate::rpc::correlation::{request_to_correlation_id, response_to_correlation_id};
+use crate::rpc::request_options::RequestOptions;
+use crate::rpc::rpc_client::RpcClient;
+use bitvmx_client::types::{IncomingBitVMXApiMessages, OutgoingBitVMXApiMessages};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+/// Whether the message was sent to BitVMX or received from it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalDirection {
+    Sent,
+    Received,
+}
+
+/// Line of the journal
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Unix timestamp in milliseconds
+    pub timestamp_ms: u64,
+    pub direction: JournalDirection,
+    /// None when the message cannot be correlated, e.g. it is not a BitVMX message
+    pub correlation_id: Option<String>,
+    /// The message as exchanged, a JSON string if it is not valid JSON
+    pub message: serde_json::Value,
+}
+
+impl JournalEntry {
+    /// Entry of a serialized message sent or received now
+    pub fn new(direction: JournalDirection, msg: &str) -> Self {
+        let message = serde_json::from_str(msg)
+            .unwrap_or_else(|_| serde_json::Value::String(msg.to_string()));
+        let correlation_id = match direction {
+            JournalDirection::Sent => {
+                decode(&message).map(|request| request_to_correlation_id(&request))
+            }
+            JournalDirection::Received => {
+                decode(&message).map(|response| response_to_correlation_id(&response))
+            }
+        };
+        Self {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64,
+            direction,
+            correlation_id,
+            message,
+        }
+    }
+}
+
+/// Decode a journaled message.
+/// It goes through the JSON text because `serde_json::from_value` rejects the tuple variants
+/// without fields, e.g. `GetCommInfo()`.
+fn decode<T: DeserializeOwned>(message: &serde_json::Value) -> Option<T> {
+    serde_json::from_str(&message.to_string()).ok()
+}
+
+/// Append only JSON lines file of the messages exchanged with BitVMX
+#[derive(Debug)]
+pub struct Journal {
+    file: Mutex<File>,
+}
+
+impl Journal {
+    /// Open the journal, appending to it if it exists
+    pub fn open(path: &str) -> Result<Self, anyhow::Error> {
+        if let Some(dir) = Path::new(path)
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(dir).map_err(|e| {
+                anyhow::anyhow!("Failed to create the journal directory {dir:?}: {e:?}")
+            })?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| anyhow::anyhow!("Failed to open the journal {path}: {e:?}"))?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Append the message, a failure is logged so the journal never breaks the exchange
+    pub fn record(&self, direction: JournalDirection, msg: &str) {
+        let entry = JournalEntry::new(direction, msg);
+        let result = serde_json::to_string(&entry)
+            .map_err(anyhow::Error::from)
+            .and_then(|line| {
+                // One write per line so lines are not interleaved
+                Ok(self
+                    .file
+                    .lock()
+                    .unwrap()
+                    .write_all(format!("{line}\n").as_bytes())?)
+            });
+        if let Err(e) = result {
+            warn!("Failed to write the BitVMX journal: {e:?}");
+        }
+    }
+
+    /// Read every entry of a journal, in the order they were written
+    pub fn read(path: &str) -> Result<Vec<JournalEntry>, anyhow::Error> {
+        let file = File::open(path)
+            .map_err(|e| anyhow::anyhow!("Failed to open the journal {path}: {e:?}"))?;
+        BufReader::new(file)
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.as_ref().is_ok_and(|line| line.trim().is_empty()))
+            .map(|(index, line)| {
+                let line = line.map_err(|e| anyhow::anyhow!("Failed to read {path}: {e:?}"))?;
+                serde_json::from_str(&line).map_err(|e| {
+                    anyhow::anyhow!("Invalid journal entry at {path}:{}: {e:?}", index + 1)
+                })
+            })
+            .collect()
+    }
+}
+
+/// Step of a journal replay
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplayStep {
+    /// Send a request whose response arrived before the next message was sent,
+    /// and wait for it
+    Request {
+        message: IncomingBitVMXApiMessages,
+        expected: OutgoingBitVMXApiMessages,
+    },
+    /// Send a message without waiting, e.g. a transaction dispatch answered once mined
+    Send(IncomingBitVMXApiMessages),
+    /// Wait for a message received later, e.g. a transaction seen on chain
+    Receive {
+        correlation_id: String,
+        expected: OutgoingBitVMXApiMessages,
+    },
+}
+
+/// How to replay a journal
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplayOptions {
+    /// How long to wait for each response
+    pub timeout: Duration,
+    /// Send the requests that change the state of BitVMX or the chain, e.g. `SendFunds`.
+    /// Only set it against a fake BitVMX, they are skipped otherwise.
+    pub state_changes: bool,
+}
+
+/// Outcome of a journal replay
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReplayReport {
+    /// Messages sent
+    pub sent: usize,
+    /// State changing messages not sent, see [`ReplayOptions::state_changes`]
+    pub skipped: usize,
+    /// Responses of the same variant and correlation id as the journal
+    pub matched: usize,
+    /// Correlation ids whose response differs from the journal
+    pub differed: Vec<String>,
+    /// Correlation ids without a response in time
+    pub missing: Vec<String>,
+}
+
+/// Steps to replay the journal, messages that cannot be decoded are skipped
+pub fn replay_plan(entries: &[JournalEntry]) -> Vec<ReplayStep> {
+    let mut steps = Vec::new();
+    let mut answered = HashSet::new();
+    for (index, entry) in entries.iter().enumerate() {
+        let Some(correlation_id) = entry.correlation_id.clone() else {
+            continue;
+        };
+        match entry.direction {
+            JournalDirection::Sent => {
+                let Some(message) = decode(&entry.message) else {
+                    warn!("Skipping a sent message that cannot be decoded: {correlation_id}");
+                    continue;
+                };
+                // The response arrived before anything else was sent
+                let response = entries[index + 1..]
+                    .iter()
+                    .take_while(|next| next.direction == JournalDirection::Received)
+                    .find(|next| next.correlation_id.as_ref() == Some(&correlation_id))
+                    .and_then(|next| decode(&next.message));
+                match response {
+                    Some(expected) => {
+                        answered.insert(correlation_id);
+                        steps.push(ReplayStep::Request { message, expected })
+                    }
+                    None => steps.push(ReplayStep::Send(message)),
+                }
+            }
+            JournalDirection::Received => {
+                if answered.remove(&correlation_id) {
+                    continue;
+                }
+                match decode(&entry.message) {
+                    Some(expected) => steps.push(ReplayStep::Receive {
+                        correlation_id,
+                        expected,
+                    }),
+                    None => {
+                        warn!(
+                            "Skipping a received message that cannot be decoded: {correlation_id}"
+                        )
+                    }
+                }
+            }
+        }
+    }
+    steps
+}
+
+/// Whether sending the message again changes the state of BitVMX or the chain
+pub fn is_state_changing(message: &IncomingBitVMXApiMessages) -> bool {
+    match message {
+        IncomingBitVMXApiMessages::SetVar(..)
+        | IncomingBitVMXApiMessages::SetWitness(..)
+        | IncomingBitVMXApiMessages::SetFundingUtxo(..)
+        | IncomingBitVMXApiMessages::Setup(..)
+        | IncomingBitVMXApiMessages::SetupKey(..)
+        | IncomingBitVMXApiMessages::SubscribeToTransaction(..)
+        | IncomingBitVMXApiMessages::SubscribeUTXO()
+        | IncomingBitVMXApiMessages::SubscribeToRskPegin()
+        | IncomingBitVMXApiMessages::DispatchTransaction(..)
+        | IncomingBitVMXApiMessages::DispatchTransactionName(..)
+        | IncomingBitVMXApiMessages::GetKeyPair(..)
+        | IncomingBitVMXApiMessages::GenerateZKP(..)
+        | IncomingBitVMXApiMessages::ProofReady(..)
+        | IncomingBitVMXApiMessages::SendFunds(..) => true,
+        // A new key is generated
+        IncomingBitVMXApiMessages::GetPubKey(_uuid, new) => *new,
+        IncomingBitVMXApiMessages::Ping()
+        | IncomingBitVMXApiMessages::GetVar(..)
+        | IncomingBitVMXApiMessages::GetWitness(..)
+        | IncomingBitVMXApiMessages::GetCommInfo()
+        | IncomingBitVMXApiMessages::GetTransaction(..)
+        | IncomingBitVMXApiMessages::GetTransactionInfoByName(..)
+        | IncomingBitVMXApiMessages::GetHashedMessage(..)
+        | IncomingBitVMXApiMessages::GetSPVProof(..)
+        | IncomingBitVMXApiMessages::GetAggregatedPubkey(..)
+        | IncomingBitVMXApiMessages::GetProtocolVisualization(..)
+        | IncomingBitVMXApiMessages::SignMessage(..)
+        | IncomingBitVMXApiMessages::GetZKPExecutionResult(..)
+        | IncomingBitVMXApiMessages::Encrypt(..)
+        | IncomingBitVMXApiMessages::Decrypt(..)
+        | IncomingBitVMXApiMessages::GetFundingBalance(..)
+        | IncomingBitVMXApiMessages::GetFundingAddress(..) => false,
+    }
+}
+
+/// Whether the response matches the journal, the payloads differ between runs, e.g. txids
+fn same_response(
+    expected: &OutgoingBitVMXApiMessages,
+    response: &OutgoingBitVMXApiMessages,
+) -> bool {
+    std::mem::discriminant(expected) == std::mem::discriminant(response)
+        && response_to_correlation_id(expected) == response_to_correlation_id(response)
+}
+
+/// Replay the journal, sending its messages in order and comparing the responses
+pub async fn replay(
+    rpc_client: &RpcClient,
+    entries: &[JournalEntry],
+    options: ReplayOptions,
+) -> Result<ReplayReport, anyhow::Error> {
+    let timeout = options.timeout;
+    let mut report = ReplayReport::default();
+    for step in replay_plan(entries) {
+        let message = match &step {
+            ReplayStep::Request { message, .. } | ReplayStep::Send(message) => Some(message),
+            ReplayStep::Receive { .. } => None,
+        };
+        if message.is_some_and(|message| !options.state_changes && is_state_changing(message)) {
+            info!("Replay skips the state changing message {message:?}");
+            report.skipped += 1;
+            continue;
+        }
+        let (correlation_id, expected, response) = match step {
+            ReplayStep::Request { message, expected } => {
+                report.sent += 1;
+                let correlation_id = request_to_correlation_id(&message);
+                let response = rpc_client
+                    .send_request_with(message, RequestOptions::timeout(timeout))
+                    .await;
+                (correlation_id, expected, response)
+            }
+            ReplayStep::Send(message) => {
+                report.sent += 1;
+                rpc_client.send_fire_and_forget(&message).await?;
+                continue;
+            }
+            ReplayStep::Receive {
+                correlation_id,
+                expected,
+            } => {
+                let response = rpc_client
+                    .wait_for_response_with(
+                        correlation_id.clone(),
+                        RequestOptions::timeout(timeout),
+                    )
+                    .await;
+                (correlation_id, expected, response)
+            }
+        };
+
+        match response {
+            Ok(response) if same_response(&expected, &response) => report.matched += 1,
+            Ok(response) => {
+                info!("Replay response differs for {correlation_id}: expected {expected:?}, got {response:?}");
+                report.differed.push(correlation_id);
+            }
+            Err(e) => {
+                info!("Replay response missing for {correlation_id}: {e}");
+                report.missing.push(correlation_id);
+            }
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::transport::JournalTransport;
+    use crate::rpc::tx_name_to_correlation_id;
+    use crate::test_support::fake_bitvmx::{FakeBitvmx, FakeChain};
+    use tokio::sync::broadcast;
+    use uuid::Uuid;
+
+    fn journal_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("{name}-{}.jsonl", Uuid::new_v4()));
+        path.to_string_lossy().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_record_the_messages_exchanged() {
+        let path = journal_path("journal");
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let bitvmx = FakeBitvmx::new(&FakeChain::new());
+        let transport = JournalTransport::new(bitvmx, Journal::open(&path).unwrap());
+        let (rpc_client, _listener_task) =
+            RpcClient::with_transport(std::sync::Arc::new(transport), &shutdown_tx);
+
+        rpc_client
+            .send_request(IncomingBitVMXApiMessages::GetCommInfo())
+            .await
+            .unwrap();
+        let _ = shutdown_tx.send(());
+
+        let entries = Journal::read(&path).unwrap();
+        let directions: Vec<_> = entries.iter().map(|entry| entry.direction).collect();
+        assert_eq!(
+            directions,
+            vec![JournalDirection::Sent, JournalDirection::Received]
+        );
+        for entry in &entries {
+            assert_eq!(entry.correlation_id.as_deref(), Some("get_comm_info"));
+            assert!(entry.timestamp_ms > 0);
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_responses_are_compared_by_variant_and_correlation_id() {
+        let chain = FakeChain::new();
+        let program_id = Uuid::new_v4();
+        let transaction = |tx_name: &str| {
+            OutgoingBitVMXApiMessages::Transaction(
+                program_id,
+                chain.new_transaction(),
+                Some(tx_name.to_string()),
+            )
+        };
+        let expected = transaction("START_CHALLENGE");
+
+        // Another run sees another transaction
+        assert!(same_response(&expected, &transaction("START_CHALLENGE")));
+        assert!(!same_response(&expected, &transaction("NARY_PROVER_1")));
+        assert!(!same_response(
+            &expected,
+            &OutgoingBitVMXApiMessages::NotFound(program_id, "START_CHALLENGE".to_string())
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_replay_against_the_fake_bitvmx() {
+        let path = journal_path("replay");
+        let journal = Journal::open(&path).unwrap();
+        let chain = FakeChain::new();
+        let bitvmx = FakeBitvmx::new(&chain);
+        let program_id = Uuid::new_v4();
+        let event = OutgoingBitVMXApiMessages::Transaction(
+            program_id,
+            chain.new_transaction(),
+            Some("START_CHALLENGE".to_string()),
+        );
+        let sent = |message: IncomingBitVMXApiMessages| {
+            (
+                JournalDirection::Sent,
+                serde_json::to_string(&message).unwrap(),
+            )
+        };
+        let received = |message: &OutgoingBitVMXApiMessages| {
+            (
+                JournalDirection::Received,
+                serde_json::to_string(message).unwrap(),
+            )
+        };
+        for (direction, message) in [
+            sent(IncomingBitVMXApiMessages::Ping()),
+            received(&OutgoingBitVMXApiMessages::Pong()),
+            sent(IncomingBitVMXApiMessages::DispatchTransactionName(
+                program_id,
+                "START_CHALLENGE".to_string(),
+            )),
+            sent(IncomingBitVMXApiMessages::GetCommInfo()),
+            received(&OutgoingBitVMXApiMessages::CommInfo(bitvmx.comm_address())),
+            received(&event),
+            (JournalDirection::Received, "not json".to_string()),
+        ] {
+            journal.record(direction, &message);
+        }
+        let entries = Journal::read(&path).unwrap();
+        assert!(matches!(
+            replay_plan(&entries)[..],
+            [
+                ReplayStep::Request { .. },
+                ReplayStep::Send(_),
+                ReplayStep::Request { .. },
+                ReplayStep::Receive { .. },
+            ]
+        ));
+
+        // The fake node sees the same transaction on chain once dispatched
+        bitvmx.on(move |message| match message {
+            IncomingBitVMXApiMessages::DispatchTransactionName(..) => Some(vec![event.clone()]),
+            _ => None,
+        });
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let (rpc_client, _listener_task) = RpcClient::with_transport(bitvmx.clone(), &shutdown_tx);
+        let options = ReplayOptions {
+            timeout: Duration::from_secs(5),
+            state_changes: true,
+        };
+        let report = replay(&rpc_client, &entries, options).await.unwrap();
+        assert_eq!(
+            report,
+            ReplayReport {
+                sent: 3,
+                matched: 3,
+                ..Default::default()
+            }
+        );
+        assert_eq!(bitvmx.received().len(), 3);
+
+        // By default the dispatch is not sent again, so the transaction is not seen
+        let options = ReplayOptions {
+            timeout: Duration::from_millis(200),
+            state_changes: false,
+        };
+        let report = replay(&rpc_client, &entries, options).await.unwrap();
+        assert_eq!(
+            report,
+            ReplayReport {
+                sent: 2,
+                skipped: 1,
+                matched: 2,
+                missing: vec![tx_name_to_correlation_id(&program_id, "START_CHALLENGE")],
+                ..Default::default()
+            }
+        );
+        assert_eq!(bitvmx.received().len(), 5);
+        let _ = shutdown_tx.send(());
+        std::fs::remove_file(path).unwrap();
+    }
+}
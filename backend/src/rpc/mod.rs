@@ -1,7 +1,19 @@
 pub mod chained_map;
 pub mod correlation;
+pub mod journal;
+pub mod request_options;
+pub mod requests;
 pub mod rpc_client;
+pub mod subscription;
+pub mod transport;
+pub mod unclaimed;
 
 pub use chained_map::*;
 pub use correlation::*;
+pub use journal::*;
+pub use request_options::*;
+pub use requests::{BitvmxError, BitvmxRequest};
 pub use rpc_client::*;
+pub use subscription::*;
+pub use transport::*;
+pub use unclaimed::*;
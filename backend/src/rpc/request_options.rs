@@ -0,0 +1,109 @@
+use crate::rpc::rpc_client::{Disconnected, RequestTimeout, REQUEST_TIMEOUT};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Error returned when the request or the wait was cancelled with its token
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestCancelled {
+    pub correlation_id: String,
+}
+
+impl std::fmt::Display for RequestCancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Request cancelled for correlation_id: {}",
+            self.correlation_id
+        )
+    }
+}
+
+impl std::error::Error for RequestCancelled {}
+
+/// How many times a request is tried again after it timed out or BitVMX was disconnected
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts after the first one
+    pub retries: u32,
+    /// Wait before each retry
+    pub delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Try only once, e.g. requests that are not safe to send twice like `SendFunds`
+    pub fn none() -> Self {
+        Self {
+            retries: 0,
+            delay: Duration::ZERO,
+        }
+    }
+
+    pub fn new(retries: u32, delay: Duration) -> Self {
+        Self { retries, delay }
+    }
+
+    /// Whether the error of the attempt number `attempt` (0 for the first one) is retried
+    pub fn should_retry(&self, attempt: u32, error: &anyhow::Error) -> bool {
+        attempt < self.retries && (error.is::<RequestTimeout>() || error.is::<Disconnected>())
+    }
+}
+
+/// Options of a request to BitVMX or of a wait for a response
+#[derive(Debug, Clone)]
+pub struct RequestOptions {
+    /// How long each attempt waits for the response
+    pub timeout: Duration,
+    /// Cancel the request, the waiter is removed right away
+    pub cancellation: CancellationToken,
+    pub retry: RetryPolicy,
+}
+
+impl Default for RequestOptions {
+    fn default() -> Self {
+        Self::timeout(Duration::from_secs(REQUEST_TIMEOUT))
+    }
+}
+
+impl RequestOptions {
+    /// Options with the timeout, no cancellation and no retries
+    pub fn timeout(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            cancellation: CancellationToken::new(),
+            retry: RetryPolicy::none(),
+        }
+    }
+
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_only_timeouts_and_disconnections() {
+        let retry = RetryPolicy::new(1, Duration::ZERO);
+        let timeout = anyhow::Error::new(RequestTimeout {
+            correlation_id: "ping".to_string(),
+            seconds: 1,
+        });
+        let disconnected = anyhow::Error::new(Disconnected {
+            correlation_id: "ping".to_string(),
+        });
+
+        assert!(retry.should_retry(0, &timeout));
+        assert!(retry.should_retry(0, &disconnected));
+        assert!(!retry.should_retry(1, &timeout));
+        assert!(!retry.should_retry(0, &anyhow::anyhow!("Unexpected response")));
+        assert!(!RetryPolicy::none().should_retry(0, &timeout));
+    }
+}
@@ -0,0 +1,759 @@
+//! Typed BitVMX requests, each one knows the response it expects and decodes its payload.
+//! Use them with [`crate::rpc::RpcClient::send_typed`].
+
+use crate::rpc::correlation::{
+    funding_utxo_correlation_id, protocol_visualization_correlation_id, request_to_correlation_id,
+    tx_name_to_correlation_id, COMM_INFO_CORRELATION_ID, PING_CORRELATION_ID,
+};
+use bitvmx_client::bitcoin::address::NetworkUnchecked;
+use bitvmx_client::bitcoin::{Address, PublicKey, Transaction, Txid};
+use bitvmx_client::bitcoin_coordinator::TransactionStatus;
+use bitvmx_client::bitvmx_wallet::wallet::Destination;
+use bitvmx_client::program::participant::CommsAddress;
+use bitvmx_client::program::variables::VariableTypes;
+use bitvmx_client::protocol_builder::types::Utxo;
+use bitvmx_client::types::{IncomingBitVMXApiMessages, OutgoingBitVMXApiMessages};
+use uuid::Uuid;
+
+/// Error answered by BitVMX instead of the expected response
+#[derive(Debug, Clone, PartialEq)]
+pub enum BitvmxError {
+    /// The wallet is not ready yet, e.g. still syncing
+    WalletNotReady(Uuid),
+    WalletError(Uuid, String),
+    /// BitVMX does not know the key asked for
+    NotFound(Uuid, String),
+    /// The aggregated key is not ready, the other participants did not answer yet
+    AggregatedPubkeyNotReady(Uuid),
+    /// The response is not one the request expects
+    UnexpectedResponse {
+        expected: &'static str,
+        response: String,
+    },
+}
+
+impl BitvmxError {
+    /// Error of a response that is not the expected one
+    pub fn from_response(expected: &'static str, response: OutgoingBitVMXApiMessages) -> Self {
+        match response {
+            OutgoingBitVMXApiMessages::WalletNotReady(uuid) => Self::WalletNotReady(uuid),
+            OutgoingBitVMXApiMessages::WalletError(uuid, error) => Self::WalletError(uuid, error),
+            OutgoingBitVMXApiMessages::NotFound(uuid, key) => Self::NotFound(uuid, key),
+            OutgoingBitVMXApiMessages::AggregatedPubkeyNotReady(uuid) => {
+                Self::AggregatedPubkeyNotReady(uuid)
+            }
+            response => Self::UnexpectedResponse {
+                expected,
+                response: format!("{response:?}"),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for BitvmxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::WalletNotReady(uuid) => write!(f, "Wallet not ready, correlation id: {uuid}"),
+            Self::WalletError(uuid, error) => {
+                write!(f, "Wallet error, correlation id: {uuid}, error: {error}")
+            }
+            Self::NotFound(uuid, key) => write!(f, "Not found {key}, correlation id: {uuid}"),
+            Self::AggregatedPubkeyNotReady(uuid) => write!(f, "Aggregated key not ready: {uuid}"),
+            Self::UnexpectedResponse { expected, response } => {
+                write!(f, "Expected {expected} response, got: {response}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BitvmxError {}
+
+/// Request to BitVMX with the response it expects
+pub trait BitvmxRequest {
+    /// Payload of the expected response
+    type Response;
+
+    /// Message sent to BitVMX
+    fn message(&self) -> IncomingBitVMXApiMessages;
+
+    /// Correlation ID of the response, the one of [`crate::rpc::response_to_correlation_id`]
+    fn correlation_id(&self) -> String;
+
+    /// Decode the payload of the expected response, error variants become a [`BitvmxError`]
+    fn decode(&self, response: OutgoingBitVMXApiMessages) -> Result<Self::Response, BitvmxError>;
+}
+
+/// Any message to BitVMX, its response is not decoded
+impl BitvmxRequest for IncomingBitVMXApiMessages {
+    type Response = OutgoingBitVMXApiMessages;
+
+    fn message(&self) -> IncomingBitVMXApiMessages {
+        self.clone()
+    }
+
+    fn correlation_id(&self) -> String {
+        request_to_correlation_id(self)
+    }
+
+    fn decode(
+        &self,
+        response: OutgoingBitVMXApiMessages,
+    ) -> Result<OutgoingBitVMXApiMessages, BitvmxError> {
+        Ok(response)
+    }
+}
+
+/// Error of a response to a request BitVMX does not answer
+fn unanswered(response: OutgoingBitVMXApiMessages) -> BitvmxError {
+    BitvmxError::from_response("no", response)
+}
+
+/// Decode a transaction response, checking its name when one is expected
+pub fn decode_transaction(
+    response: OutgoingBitVMXApiMessages,
+    tx_name: Option<&str>,
+) -> Result<TransactionStatus, BitvmxError> {
+    match response {
+        OutgoingBitVMXApiMessages::Transaction(_uuid, tx_status, name) => match tx_name {
+            Some(tx_name) if name.as_deref() != Some(tx_name) => {
+                Err(BitvmxError::UnexpectedResponse {
+                    expected: "Transaction",
+                    response: format!("Transaction with name {name:?} instead of {tx_name:?}"),
+                })
+            }
+            _ => Ok(tx_status),
+        },
+        response => Err(BitvmxError::from_response("Transaction", response)),
+    }
+}
+
+/// Check BitVMX answers
+#[derive(Debug, Clone)]
+pub struct Ping;
+
+impl BitvmxRequest for Ping {
+    type Response = ();
+
+    fn message(&self) -> IncomingBitVMXApiMessages {
+        IncomingBitVMXApiMessages::Ping()
+    }
+
+    fn correlation_id(&self) -> String {
+        PING_CORRELATION_ID.to_string()
+    }
+
+    fn decode(&self, response: OutgoingBitVMXApiMessages) -> Result<(), BitvmxError> {
+        match response {
+            OutgoingBitVMXApiMessages::Pong() => Ok(()),
+            response => Err(BitvmxError::from_response("Pong", response)),
+        }
+    }
+}
+
+/// P2P address of the BitVMX node
+#[derive(Debug, Clone)]
+pub struct GetCommInfo;
+
+impl BitvmxRequest for GetCommInfo {
+    type Response = CommsAddress;
+
+    fn message(&self) -> IncomingBitVMXApiMessages {
+        IncomingBitVMXApiMessages::GetCommInfo()
+    }
+
+    fn correlation_id(&self) -> String {
+        COMM_INFO_CORRELATION_ID.to_string()
+    }
+
+    fn decode(&self, response: OutgoingBitVMXApiMessages) -> Result<CommsAddress, BitvmxError> {
+        match response {
+            OutgoingBitVMXApiMessages::CommInfo(comm_info) => Ok(comm_info),
+            response => Err(BitvmxError::from_response("CommInfo", response)),
+        }
+    }
+}
+
+/// Pub key with the id, a new one is generated if `new` is set.
+/// Answers [`BitvmxError::NotFound`] when the id is unknown.
+#[derive(Debug, Clone)]
+pub struct GetPubKey {
+    pub id: Uuid,
+    pub new: bool,
+}
+
+impl BitvmxRequest for GetPubKey {
+    type Response = PublicKey;
+
+    fn message(&self) -> IncomingBitVMXApiMessages {
+        IncomingBitVMXApiMessages::GetPubKey(self.id, self.new)
+    }
+
+    fn correlation_id(&self) -> String {
+        self.id.to_string()
+    }
+
+    fn decode(&self, response: OutgoingBitVMXApiMessages) -> Result<PublicKey, BitvmxError> {
+        match response {
+            OutgoingBitVMXApiMessages::PubKey(_uuid, pub_key) => Ok(pub_key),
+            response => Err(BitvmxError::from_response("PubKey", response)),
+        }
+    }
+}
+
+/// Aggregate the keys of the participants
+#[derive(Debug, Clone)]
+pub struct SetupKey {
+    pub id: Uuid,
+    pub participants: Vec<CommsAddress>,
+    pub participants_keys: Option<Vec<PublicKey>>,
+    pub leader_idx: u16,
+}
+
+impl BitvmxRequest for SetupKey {
+    type Response = PublicKey;
+
+    fn message(&self) -> IncomingBitVMXApiMessages {
+        IncomingBitVMXApiMessages::SetupKey(
+            self.id,
+            self.participants.clone(),
+            self.participants_keys.clone(),
+            self.leader_idx,
+        )
+    }
+
+    fn correlation_id(&self) -> String {
+        self.id.to_string()
+    }
+
+    fn decode(&self, response: OutgoingBitVMXApiMessages) -> Result<PublicKey, BitvmxError> {
+        match response {
+            OutgoingBitVMXApiMessages::AggregatedPubkey(_uuid, aggregated_pubkey) => {
+                Ok(aggregated_pubkey)
+            }
+            response => Err(BitvmxError::from_response("AggregatedPubkey", response)),
+        }
+    }
+}
+
+/// Aggregated key already set up with the id
+#[derive(Debug, Clone)]
+pub struct GetAggregatedPubkey {
+    pub id: Uuid,
+}
+
+impl BitvmxRequest for GetAggregatedPubkey {
+    type Response = PublicKey;
+
+    fn message(&self) -> IncomingBitVMXApiMessages {
+        IncomingBitVMXApiMessages::GetAggregatedPubkey(self.id)
+    }
+
+    fn correlation_id(&self) -> String {
+        self.id.to_string()
+    }
+
+    fn decode(&self, response: OutgoingBitVMXApiMessages) -> Result<PublicKey, BitvmxError> {
+        match response {
+            OutgoingBitVMXApiMessages::AggregatedPubkey(_uuid, aggregated_pubkey) => {
+                Ok(aggregated_pubkey)
+            }
+            response => Err(BitvmxError::from_response("AggregatedPubkey", response)),
+        }
+    }
+}
+
+/// Set up a program with the participants
+#[derive(Debug, Clone)]
+pub struct Setup {
+    pub program_id: Uuid,
+    pub program_type: String,
+    pub participants: Vec<CommsAddress>,
+    pub leader_idx: u16,
+}
+
+impl BitvmxRequest for Setup {
+    type Response = ();
+
+    fn message(&self) -> IncomingBitVMXApiMessages {
+        IncomingBitVMXApiMessages::Setup(
+            self.program_id,
+            self.program_type.clone(),
+            self.participants.clone(),
+            self.leader_idx,
+        )
+    }
+
+    fn correlation_id(&self) -> String {
+        self.program_id.to_string()
+    }
+
+    fn decode(&self, response: OutgoingBitVMXApiMessages) -> Result<(), BitvmxError> {
+        match response {
+            OutgoingBitVMXApiMessages::SetupCompleted(uuid) if uuid == self.program_id => Ok(()),
+            response => Err(BitvmxError::from_response("SetupCompleted", response)),
+        }
+    }
+}
+
+/// Graph of the program transactions
+#[derive(Debug, Clone)]
+pub struct GetProtocolVisualization {
+    pub program_id: Uuid,
+}
+
+impl BitvmxRequest for GetProtocolVisualization {
+    type Response = String;
+
+    fn message(&self) -> IncomingBitVMXApiMessages {
+        IncomingBitVMXApiMessages::GetProtocolVisualization(self.program_id)
+    }
+
+    fn correlation_id(&self) -> String {
+        protocol_visualization_correlation_id(&self.program_id)
+    }
+
+    fn decode(&self, response: OutgoingBitVMXApiMessages) -> Result<String, BitvmxError> {
+        match response {
+            OutgoingBitVMXApiMessages::ProtocolVisualization(_uuid, visualization) => {
+                Ok(visualization)
+            }
+            response => Err(BitvmxError::from_response(
+                "ProtocolVisualization",
+                response,
+            )),
+        }
+    }
+}
+
+/// Set a program variable, BitVMX does not answer it.
+/// Send it with [`crate::rpc::RpcClient::send_fire_and_forget`].
+#[derive(Debug, Clone)]
+pub struct SetVar {
+    pub program_id: Uuid,
+    pub key: String,
+    pub value: VariableTypes,
+}
+
+impl BitvmxRequest for SetVar {
+    type Response = ();
+
+    fn message(&self) -> IncomingBitVMXApiMessages {
+        IncomingBitVMXApiMessages::SetVar(self.program_id, self.key.clone(), self.value.clone())
+    }
+
+    fn correlation_id(&self) -> String {
+        self.program_id.to_string()
+    }
+
+    fn decode(&self, response: OutgoingBitVMXApiMessages) -> Result<(), BitvmxError> {
+        Err(unanswered(response))
+    }
+}
+
+/// Program variable set with `SetVar`.
+/// Answers [`BitvmxError::NotFound`] when the variable is not set.
+#[derive(Debug, Clone)]
+pub struct GetVar {
+    pub program_id: Uuid,
+    pub key: String,
+}
+
+impl BitvmxRequest for GetVar {
+    type Response = VariableTypes;
+
+    fn message(&self) -> IncomingBitVMXApiMessages {
+        IncomingBitVMXApiMessages::GetVar(self.program_id, self.key.clone())
+    }
+
+    fn correlation_id(&self) -> String {
+        self.program_id.to_string()
+    }
+
+    fn decode(&self, response: OutgoingBitVMXApiMessages) -> Result<VariableTypes, BitvmxError> {
+        match response {
+            OutgoingBitVMXApiMessages::Variable(_uuid, key, value) if key == self.key => Ok(value),
+            response => Err(BitvmxError::from_response("Variable", response)),
+        }
+    }
+}
+
+/// Address of the BitVMX wallet
+#[derive(Debug, Clone)]
+pub struct GetFundingAddress {
+    pub id: Uuid,
+}
+
+impl BitvmxRequest for GetFundingAddress {
+    type Response = Address<NetworkUnchecked>;
+
+    fn message(&self) -> IncomingBitVMXApiMessages {
+        IncomingBitVMXApiMessages::GetFundingAddress(self.id)
+    }
+
+    fn correlation_id(&self) -> String {
+        self.id.to_string()
+    }
+
+    fn decode(
+        &self,
+        response: OutgoingBitVMXApiMessages,
+    ) -> Result<Address<NetworkUnchecked>, BitvmxError> {
+        match response {
+            OutgoingBitVMXApiMessages::FundingAddress(_uuid, address) => Ok(address),
+            response => Err(BitvmxError::from_response("FundingAddress", response)),
+        }
+    }
+}
+
+/// Balance of the BitVMX wallet in satoshis
+#[derive(Debug, Clone)]
+pub struct GetFundingBalance {
+    pub id: Uuid,
+}
+
+impl BitvmxRequest for GetFundingBalance {
+    type Response = u64;
+
+    fn message(&self) -> IncomingBitVMXApiMessages {
+        IncomingBitVMXApiMessages::GetFundingBalance(self.id)
+    }
+
+    fn correlation_id(&self) -> String {
+        self.id.to_string()
+    }
+
+    fn decode(&self, response: OutgoingBitVMXApiMessages) -> Result<u64, BitvmxError> {
+        match response {
+            OutgoingBitVMXApiMessages::FundingBalance(_uuid, balance) => Ok(balance),
+            response => Err(BitvmxError::from_response("FundingBalance", response)),
+        }
+    }
+}
+
+/// UTXO funding the protocol transactions, BitVMX does not answer it.
+/// Send it with [`crate::rpc::RpcClient::send_fire_and_forget`].
+#[derive(Debug, Clone)]
+pub struct SetFundingUtxo {
+    pub utxo: Utxo,
+}
+
+impl BitvmxRequest for SetFundingUtxo {
+    type Response = ();
+
+    fn message(&self) -> IncomingBitVMXApiMessages {
+        IncomingBitVMXApiMessages::SetFundingUtxo(self.utxo.clone())
+    }
+
+    fn correlation_id(&self) -> String {
+        funding_utxo_correlation_id(&self.utxo)
+    }
+
+    fn decode(&self, response: OutgoingBitVMXApiMessages) -> Result<(), BitvmxError> {
+        Err(unanswered(response))
+    }
+}
+
+/// Send funds from the BitVMX wallet, answers the id of the request and the txid
+#[derive(Debug, Clone)]
+pub struct SendFunds {
+    pub id: Uuid,
+    pub destination: Destination,
+}
+
+impl BitvmxRequest for SendFunds {
+    type Response = (Uuid, Txid);
+
+    fn message(&self) -> IncomingBitVMXApiMessages {
+        // Fee rate not needed for regtest
+        IncomingBitVMXApiMessages::SendFunds(self.id, self.destination.clone(), None)
+    }
+
+    fn correlation_id(&self) -> String {
+        self.id.to_string()
+    }
+
+    fn decode(&self, response: OutgoingBitVMXApiMessages) -> Result<(Uuid, Txid), BitvmxError> {
+        match response {
+            OutgoingBitVMXApiMessages::FundsSent(uuid, txid) => Ok((uuid, txid)),
+            response => Err(BitvmxError::from_response("FundsSent", response)),
+        }
+    }
+}
+
+/// Status of a transaction followed by BitVMX
+#[derive(Debug, Clone)]
+pub struct GetTransaction {
+    pub id: Uuid,
+    pub txid: Txid,
+}
+
+impl BitvmxRequest for GetTransaction {
+    type Response = TransactionStatus;
+
+    fn message(&self) -> IncomingBitVMXApiMessages {
+        IncomingBitVMXApiMessages::GetTransaction(self.id, self.txid)
+    }
+
+    fn correlation_id(&self) -> String {
+        self.id.to_string()
+    }
+
+    fn decode(
+        &self,
+        response: OutgoingBitVMXApiMessages,
+    ) -> Result<TransactionStatus, BitvmxError> {
+        decode_transaction(response, None)
+    }
+}
+
+/// Program transaction by name, whether it was sent or not
+#[derive(Debug, Clone)]
+pub struct GetTransactionInfoByName {
+    pub program_id: Uuid,
+    pub tx_name: String,
+}
+
+impl BitvmxRequest for GetTransactionInfoByName {
+    type Response = Transaction;
+
+    fn message(&self) -> IncomingBitVMXApiMessages {
+        IncomingBitVMXApiMessages::GetTransactionInfoByName(self.program_id, self.tx_name.clone())
+    }
+
+    fn correlation_id(&self) -> String {
+        self.program_id.to_string()
+    }
+
+    fn decode(&self, response: OutgoingBitVMXApiMessages) -> Result<Transaction, BitvmxError> {
+        match response {
+            OutgoingBitVMXApiMessages::TransactionInfo(_uuid, name, transaction)
+                if name == self.tx_name =>
+            {
+                Ok(transaction)
+            }
+            response => Err(BitvmxError::from_response("TransactionInfo", response)),
+        }
+    }
+}
+
+/// Dispatch a program transaction by name
+#[derive(Debug, Clone)]
+pub struct DispatchTransactionName {
+    pub program_id: Uuid,
+    pub tx_name: String,
+}
+
+impl BitvmxRequest for DispatchTransactionName {
+    type Response = TransactionStatus;
+
+    fn message(&self) -> IncomingBitVMXApiMessages {
+        IncomingBitVMXApiMessages::DispatchTransactionName(self.program_id, self.tx_name.clone())
+    }
+
+    fn correlation_id(&self) -> String {
+        tx_name_to_correlation_id(&self.program_id, &self.tx_name)
+    }
+
+    fn decode(
+        &self,
+        response: OutgoingBitVMXApiMessages,
+    ) -> Result<TransactionStatus, BitvmxError> {
+        decode_transaction(response, Some(&self.tx_name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::{response_to_correlation_id, BitvmxTransport};
+    use crate::test_support::fake_bitvmx::FAKE_PUB_KEY;
+    use crate::test_support::{FakeBitvmx, FakeChain};
+    use std::str::FromStr;
+
+    /// Send the request to a fake node and check its reply correlates with the request
+    async fn round_trip<R: BitvmxRequest>(
+        bitvmx: &FakeBitvmx,
+        request: &R,
+    ) -> Result<R::Response, BitvmxError> {
+        let message = request.message();
+        let correlation_id = request.correlation_id();
+        assert_eq!(correlation_id, request_to_correlation_id(&message));
+        bitvmx
+            .send(serde_json::to_string(&message).unwrap())
+            .await
+            .unwrap();
+        let reply = loop {
+            if let Some(reply) = bitvmx.receive().await.unwrap() {
+                break reply.msg;
+            }
+        };
+
+        let response = serde_json::from_str(&reply).unwrap();
+        assert_eq!(
+            response_to_correlation_id(&response),
+            correlation_id,
+            "Response {response:?} does not correlate with {message:?}"
+        );
+        request.decode(response)
+    }
+
+    #[tokio::test]
+    async fn test_requests_round_trip() {
+        let chain = FakeChain::new();
+        let bitvmx = FakeBitvmx::new(&chain);
+        let pub_key = PublicKey::from_str(FAKE_PUB_KEY).unwrap();
+        let program_id = Uuid::new_v4();
+
+        round_trip(&bitvmx, &Ping).await.unwrap();
+        let comm_info = round_trip(&bitvmx, &GetCommInfo).await.unwrap();
+        assert_eq!(comm_info.pubkey_hash, bitvmx.comm_address().pubkey_hash);
+        let request = GetPubKey {
+            id: Uuid::new_v4(),
+            new: true,
+        };
+        assert_eq!(round_trip(&bitvmx, &request).await.unwrap(), pub_key);
+        let request = SetupKey {
+            id: Uuid::new_v4(),
+            participants: vec![comm_info.clone()],
+            participants_keys: None,
+            leader_idx: 0,
+        };
+        let aggregated_key = round_trip(&bitvmx, &request).await.unwrap();
+        let request = GetAggregatedPubkey { id: request.id };
+        assert_eq!(round_trip(&bitvmx, &request).await.unwrap(), aggregated_key);
+        let request = Setup {
+            program_id,
+            program_type: "dispute".to_string(),
+            participants: vec![comm_info],
+            leader_idx: 0,
+        };
+        round_trip(&bitvmx, &request).await.unwrap();
+        let request = SetVar {
+            program_id,
+            key: "timelock_blocks".to_string(),
+            value: VariableTypes::Number(10),
+        };
+        assert_eq!(
+            request.correlation_id(),
+            request_to_correlation_id(&request.message())
+        );
+        bitvmx
+            .send(serde_json::to_string(&request.message()).unwrap())
+            .await
+            .unwrap();
+        let request = GetVar {
+            program_id,
+            key: request.key,
+        };
+        assert_eq!(
+            round_trip(&bitvmx, &request).await.unwrap(),
+            VariableTypes::Number(10)
+        );
+        let request = GetProtocolVisualization { program_id };
+        assert!(!round_trip(&bitvmx, &request).await.unwrap().is_empty());
+        let request = GetFundingAddress { id: Uuid::new_v4() };
+        round_trip(&bitvmx, &request).await.unwrap();
+        let request = GetFundingBalance { id: Uuid::new_v4() };
+        assert!(round_trip(&bitvmx, &request).await.unwrap() > 0);
+        let request = SendFunds {
+            id: Uuid::new_v4(),
+            destination: Destination::P2WPKH(pub_key, 1_000),
+        };
+        let (id, txid) = round_trip(&bitvmx, &request).await.unwrap();
+        assert_eq!(id, request.id);
+        let request = GetTransaction {
+            id: Uuid::new_v4(),
+            txid,
+        };
+        assert_eq!(round_trip(&bitvmx, &request).await.unwrap().tx_id, txid);
+        let request = DispatchTransactionName {
+            program_id,
+            tx_name: "START_CHALLENGE".to_string(),
+        };
+        let tx_status = round_trip(&bitvmx, &request).await.unwrap();
+        let request = GetTransactionInfoByName {
+            program_id,
+            tx_name: "START_CHALLENGE".to_string(),
+        };
+        assert_eq!(
+            round_trip(&bitvmx, &request).await.unwrap().compute_txid(),
+            tx_status.tx_id
+        );
+        let request = GetTransactionInfoByName {
+            program_id,
+            tx_name: "NARY_PROVER_1".to_string(),
+        };
+        assert!(matches!(
+            round_trip(&bitvmx, &request).await,
+            Err(BitvmxError::NotFound(..))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_error_responses_round_trip() {
+        let bitvmx = FakeBitvmx::new(&FakeChain::new());
+        bitvmx.on(|message| match message {
+            IncomingBitVMXApiMessages::GetFundingBalance(uuid) => {
+                Some(vec![OutgoingBitVMXApiMessages::WalletNotReady(*uuid)])
+            }
+            IncomingBitVMXApiMessages::GetFundingAddress(uuid) => {
+                Some(vec![OutgoingBitVMXApiMessages::WalletError(
+                    *uuid,
+                    "Wallet locked".to_string(),
+                )])
+            }
+            IncomingBitVMXApiMessages::GetPubKey(uuid, false) => {
+                Some(vec![OutgoingBitVMXApiMessages::NotFound(
+                    *uuid,
+                    uuid.to_string(),
+                )])
+            }
+            IncomingBitVMXApiMessages::GetAggregatedPubkey(uuid) => {
+                Some(vec![OutgoingBitVMXApiMessages::AggregatedPubkeyNotReady(
+                    *uuid,
+                )])
+            }
+            _ => None,
+        });
+
+        let id = Uuid::new_v4();
+        let request = GetFundingBalance { id };
+        assert_eq!(
+            round_trip(&bitvmx, &request).await,
+            Err(BitvmxError::WalletNotReady(id))
+        );
+        let request = GetFundingAddress { id };
+        assert_eq!(
+            round_trip(&bitvmx, &request).await,
+            Err(BitvmxError::WalletError(id, "Wallet locked".to_string()))
+        );
+        let request = GetPubKey { id, new: false };
+        assert_eq!(
+            round_trip(&bitvmx, &request).await,
+            Err(BitvmxError::NotFound(id, id.to_string()))
+        );
+        let request = GetAggregatedPubkey { id };
+        assert_eq!(
+            round_trip(&bitvmx, &request).await,
+            Err(BitvmxError::AggregatedPubkeyNotReady(id))
+        );
+    }
+
+    #[test]
+    fn test_dispatch_checks_the_transaction_name() {
+        let tx_status = FakeChain::new().new_transaction();
+        let request = DispatchTransactionName {
+            program_id: Uuid::new_v4(),
+            tx_name: "START_CHALLENGE".to_string(),
+        };
+        let response = OutgoingBitVMXApiMessages::Transaction(
+            request.program_id,
+            tx_status,
+            Some("OTHER".to_string()),
+        );
+        assert!(matches!(
+            request.decode(response),
+            Err(BitvmxError::UnexpectedResponse { .. })
+        ));
+    }
+}
@@ -1,88 +1,163 @@
+use crate::config::BitVMXClientConfig;
 use crate::rpc::chained_map::ChainedMap;
-use crate::rpc::correlation::{request_to_correlation_id, response_to_correlation_id};
-use bitvmx_broker::identification::allow_list::AllowList;
-use bitvmx_broker::identification::identifier::Identifier;
-use bitvmx_broker::rpc::client::Client;
-use bitvmx_broker::rpc::tls_helper::Cert;
-use bitvmx_broker::rpc::BrokerConfig;
+use crate::rpc::correlation::response_to_correlation_id;
+use crate::rpc::request_options::{RequestCancelled, RequestOptions};
+use crate::rpc::requests::BitvmxRequest;
+use crate::rpc::subscription::{program_event_id, ProgramSubscription};
+use crate::rpc::transport::{self, BitvmxTransport};
+use crate::rpc::unclaimed::{UnclaimedMetrics, UnclaimedResponse, UnclaimedResponses};
 use bitvmx_client::types::{IncomingBitVMXApiMessages, OutgoingBitVMXApiMessages};
-use std::net::IpAddr;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::broadcast::{Receiver, Sender};
-use tokio::sync::{oneshot, Mutex};
+use tokio::sync::{mpsc, oneshot, Mutex};
 use tokio::task::JoinHandle;
 use tokio::time::sleep;
 use tracing::{debug, info, trace, warn, Instrument};
+use uuid::Uuid;
 
-const REQUEST_TIMEOUT: u64 = 240; // 240 seconds = 4 minutes
+/// Default timeout of the requests, see [`RequestOptions`]
+pub const REQUEST_TIMEOUT: u64 = 240; // 240 seconds = 4 minutes
 const SLEEP_INTERVAL: u64 = 10; // 10 milliseconds
 const CHECK_SHUTDOWN_INTERVAL: u64 = 100; // 100 milliseconds
+const UNCLAIMED_TTL: u64 = 600; // 600 seconds = 10 minutes
+const UNCLAIMED_CAPACITY: usize = 1000;
+const RECONNECT_MIN_DELAY: u64 = 1; // 1 second
+const RECONNECT_MAX_DELAY: u64 = 60; // 60 seconds
+
+/// Background task running the broker message listener
+pub type ListenerTask = JoinHandle<Result<(), anyhow::Error>>;
+
+/// Error returned when no response arrives for a correlation id in time
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestTimeout {
+    pub correlation_id: String,
+    pub seconds: u64,
+}
+
+impl std::fmt::Display for RequestTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Request timed out after {} seconds for correlation_id: {}",
+            self.seconds, self.correlation_id
+        )
+    }
+}
+
+impl std::error::Error for RequestTimeout {}
+
+/// Error returned when BitVMX is disconnected, the request may not have been delivered
+#[derive(Debug, Clone, PartialEq)]
+pub struct Disconnected {
+    pub correlation_id: String,
+}
+
+impl std::fmt::Display for Disconnected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "BitVMX disconnected, request failed for correlation_id: {}",
+            self.correlation_id
+        )
+    }
+}
+
+impl std::error::Error for Disconnected {}
+
+/// State of the connection to BitVMX, it works as the circuit breaker of the requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// Waiting for the first message exchange, at start or after reconnecting
+    Connecting,
+    /// Messages are flowing
+    Connected,
+    /// The transport failed and is reconnecting, requests fail right away
+    Disconnected,
+}
+
+/// Connection state with the failures that led to it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectionStatus {
+    pub state: ConnectionState,
+    /// Failures since the last successful exchange
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+}
+
+/// Handler waiting for a response
+#[derive(Debug)]
+struct PendingResponse {
+    tx: oneshot::Sender<OutgoingBitVMXApiMessages>,
+    /// Waiting for the response of a request sent, it fails when disconnected because the
+    /// request may be lost. Otherwise it waits for an event, e.g. a transaction on chain.
+    request: bool,
+}
 
 /// BitVMX RPC Client with direct message sending
 #[derive(Debug, Clone)]
 pub struct RpcClient {
-    /// Internal Broker RPC client
-    client: Client,
-    /// My ID for sending messages
-    my_id: u8,
-    /// Target identifier for sending messages
-    to_identifier: Identifier,
+    /// Channel to BitVMX, e.g. the broker
+    transport: Arc<dyn BitvmxTransport>,
     /// Pending responses waiting to be matched with correlation IDs
-    pending_responses: Arc<Mutex<ChainedMap<String, oneshot::Sender<OutgoingBitVMXApiMessages>>>>,
-    /// Ready flag
-    ready: Arc<AtomicBool>,
+    pending_responses: Arc<Mutex<ChainedMap<String, PendingResponse>>>,
+    /// Subscribers to the on chain events of each program, locked after pending_responses
+    subscriptions: Arc<Mutex<HashMap<Uuid, Vec<mpsc::UnboundedSender<OutgoingBitVMXApiMessages>>>>>,
+    /// Responses that arrived before anyone waited for them, locked after pending_responses
+    /// and subscriptions
+    unclaimed_responses: Arc<Mutex<UnclaimedResponses>>,
+    /// Connection state, ready when connected
+    connection: Arc<std::sync::Mutex<ConnectionStatus>>,
+    /// Messages acked without being handled because they could not be decoded or matched
+    unhandled_messages: Arc<AtomicU64>,
 }
 
 impl RpcClient {
     /// Start a new RPC service
-    /// Initialize the Broker RPC client with the specified port
+    /// Initialize the transport selected in the config, the broker by default
     pub fn connect(
-        broker_port: u16,
-        broker_ip: Option<IpAddr>,
+        config: &BitVMXClientConfig,
         shutdown_tx: &Sender<()>,
-    ) -> Result<(Arc<Self>, JoinHandle<Result<(), anyhow::Error>>), anyhow::Error> {
-        let bitvmx_key_file = "config/keys/bitvmx.key";
-        let bitvmx_cert = Cert::from_key_file(bitvmx_key_file).map_err(|e| {
-            anyhow::anyhow!("Failed to create certificate from file {bitvmx_key_file} err: {e:?}")
-        })?;
-        let bitvmx_identifier = Identifier::new(bitvmx_cert.get_pubk_hash()?, 0);
-        debug!("BitVMX identifier: {:?}", bitvmx_identifier);
-
-        let l2_key_file = "config/keys/l2.key";
-        let l2_cert = Cert::from_key_file(l2_key_file).map_err(|e| {
-            anyhow::anyhow!("Failed to create certificate from file {l2_key_file} err: {e:?}")
-        })?;
-        let l2_identifier = Identifier::new(l2_cert.get_pubk_hash()?, 0);
-        debug!("L2 identifier: {:?}", l2_identifier);
-
-        // Create allow list
-        let allow_list = AllowList::new();
-        allow_list.lock().unwrap().allow_all();
-
-        // Create broker client
-        let config = BrokerConfig::new(broker_port, broker_ip, bitvmx_cert.get_pubk_hash()?);
-        let client = Client::new(&config, l2_cert.clone(), allow_list);
-
-        // Create RPC client
+    ) -> Result<(Arc<Self>, ListenerTask), anyhow::Error> {
+        let transport = transport::connect(config)?;
+        Ok(Self::with_transport(transport, shutdown_tx))
+    }
+
+    /// Start a new RPC service over the given transport
+    pub fn with_transport(
+        transport: Arc<dyn BitvmxTransport>,
+        shutdown_tx: &Sender<()>,
+    ) -> (Arc<Self>, ListenerTask) {
         let rpc_client = Arc::new(Self {
-            client,
-            my_id: 0,
-            to_identifier: bitvmx_identifier.clone(),
+            transport,
             pending_responses: Arc::new(Mutex::new(ChainedMap::new())),
-            ready: Arc::new(AtomicBool::new(false)),
+            subscriptions: Arc::new(Mutex::new(HashMap::new())),
+            unclaimed_responses: Arc::new(Mutex::new(UnclaimedResponses::new(
+                Duration::from_secs(UNCLAIMED_TTL),
+                UNCLAIMED_CAPACITY,
+            ))),
+            connection: Arc::new(std::sync::Mutex::new(ConnectionStatus {
+                state: ConnectionState::Connecting,
+                consecutive_failures: 0,
+                last_error: None,
+            })),
+            unhandled_messages: Arc::new(AtomicU64::new(0)),
         });
 
-        let listener_task =
-            Self::spawn_listener(rpc_client.clone(), l2_identifier.clone(), shutdown_tx);
+        let listener_task = Self::spawn_listener(rpc_client.clone(), shutdown_tx);
 
-        Ok((rpc_client, listener_task))
+        (rpc_client, listener_task)
     }
 
+    /// Register a handler for the correlation id.
+    /// A request handler expects a new response, otherwise a response that arrived before
+    /// is delivered right away.
     async fn add_response_handler(
         &self,
         correlation_id: &str,
+        request: bool,
     ) -> Result<oneshot::Receiver<OutgoingBitVMXApiMessages>, anyhow::Error> {
         trace!(
             "Adding response handler to queue for correlation id: {:?}",
@@ -91,46 +166,94 @@ impl RpcClient {
         let (tx, rx) = oneshot::channel();
         {
             let mut pending_responses = self.pending_responses.lock().await;
-            pending_responses.insert(correlation_id.to_string(), tx);
+            Self::prune_dropped_handlers(&mut pending_responses);
+            if !request {
+                let unclaimed = self.unclaimed_responses.lock().await.claim(correlation_id);
+                if let Some(response) = unclaimed {
+                    debug!(
+                        "Claimed response received before waiting for correlation id: {:?}",
+                        correlation_id
+                    );
+                    let _ = tx.send(response);
+                    return Ok(rx);
+                }
+            }
+            pending_responses.insert(correlation_id.to_string(), PendingResponse { tx, request });
         }
         Ok(rx)
     }
 
+    /// Wait for the response until the timeout or the cancellation of the options
     async fn get_response(
         &self,
         correlation_id: &str,
         rx: oneshot::Receiver<OutgoingBitVMXApiMessages>,
+        options: &RequestOptions,
     ) -> Result<OutgoingBitVMXApiMessages, anyhow::Error> {
-        // Wait for response with timeout
-        let response = tokio::time::timeout(Duration::from_secs(REQUEST_TIMEOUT), rx)
-            .await
-            .map_err(|_| {
-                anyhow::anyhow!(
-                    "Request timed out after {} seconds for correlation_id: {}",
-                    REQUEST_TIMEOUT,
-                    correlation_id
-                )
-            })?
-            .map_err(|_| {
-                anyhow::anyhow!(
-                    "Channel closed while waiting for response for correlation_id: {}",
-                    correlation_id
-                )
-            })?;
+        let result = tokio::select! {
+            _ = options.cancellation.cancelled() => Err(anyhow::Error::new(RequestCancelled {
+                correlation_id: correlation_id.to_string(),
+            })),
+            response = tokio::time::timeout(options.timeout, rx) => match response {
+                Ok(Ok(response)) => Ok(response),
+                Err(_timeout) => Err(anyhow::Error::new(RequestTimeout {
+                    correlation_id: correlation_id.to_string(),
+                    seconds: options.timeout.as_secs(),
+                })),
+                // The handler is dropped when a request fails because BitVMX disconnected
+                Ok(Err(_closed)) => Err(anyhow::Error::new(Disconnected {
+                    correlation_id: correlation_id.to_string(),
+                })),
+            },
+        };
 
-        Ok(response)
+        if result.is_err() {
+            // The receiver is gone, do not keep its handler
+            Self::prune_dropped_handlers(&mut *self.pending_responses.lock().await);
+        }
+        result
+    }
+
+    /// Remove the handlers whose receiver was dropped, e.g. after a timeout
+    fn prune_dropped_handlers(pending_responses: &mut ChainedMap<String, PendingResponse>) {
+        let pruned = pending_responses.retain(|pending| !pending.tx.is_closed());
+        if pruned > 0 {
+            trace!("Pruned {pruned} dropped response handlers");
+        }
     }
 
+    /// Wait for a response with the default options
     pub async fn wait_for_response(
         &self,
         correlation_id: String,
+    ) -> Result<OutgoingBitVMXApiMessages, anyhow::Error> {
+        self.wait_for_response_with(correlation_id, RequestOptions::default())
+            .await
+    }
+
+    /// Wait for a response, retrying the wait when it times out as the retry policy says
+    pub async fn wait_for_response_with(
+        &self,
+        correlation_id: String,
+        options: RequestOptions,
     ) -> Result<OutgoingBitVMXApiMessages, anyhow::Error> {
         debug!(
             "Waiting for BitVMX response for correlation id: {:?}",
             correlation_id
         );
-        let rx = self.add_response_handler(&correlation_id).await?;
-        let response = self.get_response(&correlation_id, rx).await?;
+        let mut attempt = 0;
+        let response = loop {
+            let rx = self.add_response_handler(&correlation_id, false).await?;
+            match self.get_response(&correlation_id, rx, &options).await {
+                Ok(response) => break response,
+                Err(e) if options.retry.should_retry(attempt, &e) => {
+                    attempt += 1;
+                    self.wait_retry(&correlation_id, attempt, &options, &e)
+                        .await?;
+                }
+                Err(e) => return Err(e),
+            }
+        };
         debug!(
             "Received from BitVMX response: {:?} message: {}",
             correlation_id,
@@ -139,26 +262,134 @@ impl RpcClient {
         Ok(response)
     }
 
+    /// Wait the retry delay, unless the request is cancelled
+    async fn wait_retry(
+        &self,
+        correlation_id: &str,
+        attempt: u32,
+        options: &RequestOptions,
+        error: &anyhow::Error,
+    ) -> Result<(), RequestCancelled> {
+        debug!(
+            "Retrying {correlation_id} in {:?}, attempt {attempt} of {}: {error}",
+            options.retry.delay, options.retry.retries
+        );
+        tokio::select! {
+            _ = options.cancellation.cancelled() => Err(RequestCancelled {
+                correlation_id: correlation_id.to_string(),
+            }),
+            _ = sleep(options.retry.delay) => Ok(()),
+        }
+    }
+
+    /// Check if a response handler is waiting for the correlation id
+    pub async fn is_waiting_for(&self, correlation_id: &str) -> bool {
+        let mut pending_responses = self.pending_responses.lock().await;
+        Self::prune_dropped_handlers(&mut pending_responses);
+        pending_responses.contains_key(&correlation_id.to_string())
+    }
+
+    /// Subscribe to every on chain event of the program, e.g. `Transaction` and
+    /// `SpendingUTXOTransactionFound`, whatever its name is.
+    /// Events received before subscribing that are still unclaimed are claimed and delivered first.
+    /// An event delivered to a subscriber is not kept unclaimed, a later `wait_for_response`
+    /// for it only gets it if BitVMX sends it again.
+    pub async fn subscribe(&self, program_id: Uuid) -> ProgramSubscription {
+        debug!("Subscribing to the events of program: {program_id}");
+        let (tx, rx) = mpsc::unbounded_channel();
+        // Lock the handlers so no event is handled between the replay and the subscription
+        let _pending_responses = self.pending_responses.lock().await;
+        let mut subscriptions = self.subscriptions.lock().await;
+        let replayed = self
+            .unclaimed_responses
+            .lock()
+            .await
+            .claim_where(|response| program_event_id(response) == Some(program_id));
+        for response in replayed {
+            let _ = tx.send(response);
+        }
+        subscriptions.entry(program_id).or_default().push(tx);
+        ProgramSubscription::new(program_id, rx)
+    }
+
+    /// Check if someone is subscribed to the events of the program
+    pub async fn is_subscribed(&self, program_id: Uuid) -> bool {
+        self.subscriptions
+            .lock()
+            .await
+            .get(&program_id)
+            .is_some_and(|subscribers| subscribers.iter().any(|tx| !tx.is_closed()))
+    }
+
+    /// Responses nobody waited for yet, oldest first, and the unclaimed metrics
+    pub async fn unclaimed_responses(&self) -> (UnclaimedMetrics, Vec<UnclaimedResponse>) {
+        let mut unclaimed_responses = self.unclaimed_responses.lock().await;
+        let responses = unclaimed_responses.list();
+        (unclaimed_responses.metrics(), responses)
+    }
+
+    /// Send a request and wait for its response with the default options
     pub async fn send_request(
         &self,
         message: IncomingBitVMXApiMessages,
     ) -> Result<OutgoingBitVMXApiMessages, anyhow::Error> {
-        let correlation_id = request_to_correlation_id(&message)?;
+        self.send_request_with(message, RequestOptions::default())
+            .await
+    }
+
+    /// Send a request and wait for its response, the request is sent again when it times out
+    /// or BitVMX disconnects as the retry policy says
+    pub async fn send_request_with(
+        &self,
+        message: IncomingBitVMXApiMessages,
+        options: RequestOptions,
+    ) -> Result<OutgoingBitVMXApiMessages, anyhow::Error> {
+        self.send_typed(&message, options).await
+    }
+
+    /// Send a typed request and decode the payload of its response.
+    /// Error responses like `WalletNotReady` fail with a [`BitvmxError`].
+    pub async fn send_typed<R: BitvmxRequest>(
+        &self,
+        request: &R,
+        options: RequestOptions,
+    ) -> Result<R::Response, anyhow::Error> {
+        let correlation_id = request.correlation_id();
+        let message = request.message();
         debug!(
             "Sending to BitVMX and waiting for response, request correlation_id: {:?} message: {:?}",
             correlation_id, message
         );
-        let rx = self.add_response_handler(&correlation_id).await?;
-
-        self.send_message(message).await?;
-
-        let response = self.get_response(&correlation_id, rx).await?;
+        let mut attempt = 0;
+        let response = loop {
+            match self.request_once(&correlation_id, &message, &options).await {
+                Ok(response) => break response,
+                Err(e) if options.retry.should_retry(attempt, &e) => {
+                    attempt += 1;
+                    self.wait_retry(&correlation_id, attempt, &options, &e)
+                        .await?;
+                }
+                Err(e) => return Err(e),
+            }
+        };
         debug!(
             "Received from BitVMX response: {:?} message: {}",
             correlation_id,
             self.format_message(&response)
         );
-        Ok(response)
+        Ok(request.decode(response)?)
+    }
+
+    async fn request_once(
+        &self,
+        correlation_id: &str,
+        message: &IncomingBitVMXApiMessages,
+        options: &RequestOptions,
+    ) -> Result<OutgoingBitVMXApiMessages, anyhow::Error> {
+        self.check_connected(correlation_id)?;
+        let rx = self.add_response_handler(correlation_id, true).await?;
+        self.send_message(message).await?;
+        self.get_response(correlation_id, rx, options).await
     }
 
     fn format_message(&self, message: &OutgoingBitVMXApiMessages) -> String {
@@ -181,32 +412,33 @@ impl RpcClient {
         }
     }
 
-    async fn send_message(&self, message: IncomingBitVMXApiMessages) -> Result<(), anyhow::Error> {
+    async fn send_message(&self, message: &IncomingBitVMXApiMessages) -> Result<(), anyhow::Error> {
         // Serialize the message
-        let serialized_msg = serde_json::to_string(&message)?;
+        let serialized_msg = serde_json::to_string(message)?;
 
         trace!("Sending message to BitVMX: {:?}", serialized_msg);
 
         // Send the message directly to BitVMX
-        self.client
-            .async_send_msg(self.my_id, self.to_identifier.clone(), serialized_msg)
-            .await
-            .map_err(|e| anyhow::anyhow!("Send message to BitVMX failed: {e}"))?;
+        self.transport.send(serialized_msg).await?;
 
         trace!("Sent message to BitVMX: {:?}", message);
         Ok(())
     }
 
-    pub async fn send_fire_and_forget(
+    /// Send a request without waiting for a response, e.g. `SetVar` that BitVMX does not answer.
+    /// Returns the correlation ID of the request.
+    pub async fn send_fire_and_forget<R: BitvmxRequest>(
         &self,
-        message: IncomingBitVMXApiMessages,
+        request: &R,
     ) -> Result<String, anyhow::Error> {
-        let correlation_id = request_to_correlation_id(&message)?;
+        let correlation_id = request.correlation_id();
+        let message = request.message();
         debug!(
             "Sending fire-and-forget to BitVMX request: {:?} message: {:?}",
             correlation_id, message
         );
-        self.send_message(message).await?;
+        self.check_connected(&correlation_id)?;
+        self.send_message(&message).await?;
 
         Ok(correlation_id)
     }
@@ -215,7 +447,7 @@ impl RpcClient {
         // Deserialize the response
         let response = serde_json::from_str(&resp)?;
 
-        let correlation_id = response_to_correlation_id(&response)?;
+        let correlation_id = response_to_correlation_id(&response);
         trace!(
             "Received response: {:?} message: {:?}",
             correlation_id,
@@ -224,20 +456,26 @@ impl RpcClient {
 
         let waiting_for_response = {
             let mut queue = self.pending_responses.lock().await;
-            queue.drain_all_for_key(&correlation_id)?
+            let waiting_for_response = queue.drain_all_for_key(&correlation_id)?;
+            let subscribed = self.notify_subscribers(&response).await;
+            if waiting_for_response.is_empty() && !subscribed {
+                // Keep it while the queue is locked so a waiter registering now can claim it
+                info!(
+                    "No response handler for correlation ID: {}, keeping it unclaimed, type: {:?}",
+                    correlation_id, response
+                );
+                self.unclaimed_responses
+                    .lock()
+                    .await
+                    .retain(correlation_id, response);
+                return Ok(());
+            }
+            waiting_for_response
         };
 
-        if waiting_for_response.is_empty() {
-            info!(
-                "No response handler for correlation ID: {}, type: {:?}",
-                correlation_id, response
-            );
-            return Ok(());
-        }
-
         // Send the response to all pending handlers for this correlation ID
-        for tx in waiting_for_response {
-            if let Err(e) = tx.send(response.clone()) {
+        for pending in waiting_for_response {
+            if let Err(e) = pending.tx.send(response.clone()) {
                 warn!(
                     "Failed to send response to handler for correlation ID {}: {:?}",
                     correlation_id, e
@@ -248,16 +486,29 @@ impl RpcClient {
         Ok(())
     }
 
-    fn spawn_listener(
-        service: Arc<RpcClient>,
-        my_identifier: Identifier,
-        shutdown_tx: &Sender<()>,
-    ) -> JoinHandle<Result<(), anyhow::Error>> {
+    /// Send the event to the subscribers of its program, true if any received it
+    async fn notify_subscribers(&self, response: &OutgoingBitVMXApiMessages) -> bool {
+        let Some(program_id) = program_event_id(response) else {
+            return false;
+        };
+        let mut subscriptions = self.subscriptions.lock().await;
+        let Some(subscribers) = subscriptions.get_mut(&program_id) else {
+            return false;
+        };
+        // Drop the subscriptions that were dropped
+        subscribers.retain(|tx| tx.send(response.clone()).is_ok());
+        if subscribers.is_empty() {
+            subscriptions.remove(&program_id);
+            return false;
+        }
+        true
+    }
+
+    fn spawn_listener(service: Arc<RpcClient>, shutdown_tx: &Sender<()>) -> ListenerTask {
         let mut shutdown_rx = shutdown_tx.subscribe();
         tokio::spawn(
             async move {
                 info!("Start rpc listener");
-                let mut first_time = true;
                 loop {
                     tokio::select! {
                         _ = shutdown_rx.recv() => {
@@ -266,29 +517,32 @@ impl RpcClient {
                         }
                         result = tokio::time::timeout(
                             Duration::from_millis(CHECK_SHUTDOWN_INTERVAL),
-                            service.client.async_get_msg(my_identifier.clone())
+                            service.transport.receive()
                         ) => {
                             match result {
-                                Ok(msg_result) => {
-                                    match msg_result {
-                                        Ok(Some(msg)) => {
-                                            trace!("Received message from BitVMX: {:?}", msg);
-                                            service.handle_response(msg.msg).await?;
-                                            service.client.async_ack(my_identifier.clone(), msg.uid).await?;
-                                        }
-                                        Ok(None) => {
-                                            // No message received, continue loop
-                                        }
+                                Ok(Ok(Some(msg))) => {
+                                    trace!("Received message from BitVMX: {:?}", msg);
+                                    if let Err(e) = service.handle_response(msg.msg).await {
+                                        // Ack it anyway, it would fail the same way if delivered again
+                                        warn!("Dropping unhandled message {} from BitVMX: {e:?}", msg.uid);
+                                        service.unhandled_messages.fetch_add(1, Ordering::Relaxed);
+                                    }
+                                    match service.transport.ack(msg.uid).await {
+                                        Ok(()) => service.set_connected(),
                                         Err(e) => {
-                                            return Err(anyhow::anyhow!(
-                                                "Get message from BitVMX failed: {e}"
-                                            ));
+                                            if !service.reconnect(e, &mut shutdown_rx).await {
+                                                break;
+                                            }
                                         }
                                     }
-
-                                    if first_time {
-                                        first_time = false;
-                                        service.set_ready();
+                                }
+                                Ok(Ok(None)) => {
+                                    // No message received, continue loop
+                                    service.set_connected();
+                                }
+                                Ok(Err(e)) => {
+                                    if !service.reconnect(e, &mut shutdown_rx).await {
+                                        break;
                                     }
                                 }
                                 Err(_timeout) => {
@@ -304,14 +558,91 @@ impl RpcClient {
         )
     }
 
-    /// Set the RPC client as ready
-    fn set_ready(&self) {
-        self.ready.store(true, Ordering::Release);
+    /// Open the circuit after the transport failed, fail the pending requests and
+    /// reconnect with exponential backoff. Returns false if shutdown while reconnecting.
+    async fn reconnect(&self, error: anyhow::Error, shutdown_rx: &mut Receiver<()>) -> bool {
+        warn!("BitVMX transport failed: {error:?}");
+        self.set_disconnected(&error);
+        let failed = self
+            .pending_responses
+            .lock()
+            .await
+            .retain(|pending| !pending.request);
+        if failed > 0 {
+            warn!("Failed {failed} pending requests, BitVMX disconnected");
+        }
+
+        let mut delay = RECONNECT_MIN_DELAY;
+        loop {
+            tokio::select! {
+                _ = shutdown_rx.recv() => {
+                    warn!("Shutting down rpc listener while reconnecting...");
+                    return false;
+                }
+                _ = sleep(Duration::from_secs(delay)) => {}
+            }
+
+            match self.transport.reconnect().await {
+                Ok(()) => {
+                    info!("Reconnected to BitVMX");
+                    self.connection.lock().unwrap().state = ConnectionState::Connecting;
+                    return true;
+                }
+                Err(e) => {
+                    warn!("Failed to reconnect to BitVMX, retry in {delay} seconds: {e:?}");
+                    self.set_disconnected(&e);
+                    delay = (delay * 2).min(RECONNECT_MAX_DELAY);
+                }
+            }
+        }
+    }
+
+    fn set_connected(&self) {
+        let mut connection = self.connection.lock().unwrap();
+        if connection.state != ConnectionState::Connected {
+            *connection = ConnectionStatus {
+                state: ConnectionState::Connected,
+                consecutive_failures: 0,
+                last_error: None,
+            };
+        }
+    }
+
+    fn set_disconnected(&self, error: &anyhow::Error) {
+        let mut connection = self.connection.lock().unwrap();
+        connection.state = ConnectionState::Disconnected;
+        connection.consecutive_failures += 1;
+        connection.last_error = Some(error.to_string());
+    }
+
+    /// Fail right away while disconnected instead of waiting for the request timeout
+    fn check_connected(&self, correlation_id: &str) -> Result<(), Disconnected> {
+        if self.connection_status().state == ConnectionState::Disconnected {
+            return Err(Disconnected {
+                correlation_id: correlation_id.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// State of the connection to BitVMX
+    pub fn connection_status(&self) -> ConnectionStatus {
+        self.connection.lock().unwrap().clone()
+    }
+
+    /// Messages rejected by the transport because their sender is not allowed
+    pub fn rejected_messages(&self) -> u64 {
+        self.transport.rejected_messages()
+    }
+
+    /// Messages received from BitVMX that could not be decoded or matched, acked and dropped
+    pub fn unhandled_messages(&self) -> u64 {
+        self.unhandled_messages.load(Ordering::Relaxed)
     }
 
     /// Check if the RPC client is ready
     pub fn is_ready(&self) -> bool {
-        self.ready.load(Ordering::Acquire)
+        self.connection_status().state == ConnectionState::Connected
     }
 
     /// Wait for the RPC client to be ready
@@ -331,3 +662,333 @@ impl RpcClient {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::transport::{MemoryTransport, TransportMessage};
+    use crate::rpc::tx_name_to_correlation_id;
+    use crate::rpc::{requests, RetryPolicy};
+    use crate::test_support::fake_bitvmx::{FakeBitvmx, FakeChain};
+    use crate::test_support::wait_until;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tokio::sync::broadcast;
+    use tokio_util::sync::CancellationToken;
+
+    #[tokio::test]
+    async fn test_response_received_before_waiting_is_claimed() {
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let chain = FakeChain::new();
+        let bitvmx = FakeBitvmx::new(&chain);
+        let (rpc_client, _listener_task) = RpcClient::with_transport(bitvmx.clone(), &shutdown_tx);
+
+        let program_id = Uuid::new_v4();
+        let tx_name = "START_CHALLENGE";
+        bitvmx.emit(OutgoingBitVMXApiMessages::Transaction(
+            program_id,
+            chain.new_transaction(),
+            Some(tx_name.to_string()),
+        ));
+        wait_until("the transaction is unclaimed", || async {
+            !rpc_client.unclaimed_responses().await.1.is_empty()
+        })
+        .await;
+
+        let (metrics, responses) = rpc_client.unclaimed_responses().await;
+        let correlation_id = tx_name_to_correlation_id(&program_id, tx_name);
+        assert_eq!(responses[0].correlation_id, correlation_id);
+        assert_eq!(metrics.retained, 1);
+
+        let response = rpc_client
+            .wait_for_response(correlation_id.clone())
+            .await
+            .unwrap();
+        assert!(matches!(
+            response,
+            OutgoingBitVMXApiMessages::Transaction(id, _, _) if id == program_id
+        ));
+
+        let (metrics, responses) = rpc_client.unclaimed_responses().await;
+        assert!(responses.is_empty());
+        assert_eq!(metrics.claimed, 1);
+        assert!(!rpc_client.is_waiting_for(&correlation_id).await);
+    }
+
+    #[tokio::test]
+    async fn test_request_does_not_claim_an_old_response() {
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let bitvmx = FakeBitvmx::new(&FakeChain::new());
+        let (rpc_client, _listener_task) = RpcClient::with_transport(bitvmx.clone(), &shutdown_tx);
+
+        bitvmx.emit(OutgoingBitVMXApiMessages::Pong());
+        wait_until("the pong is unclaimed", || async {
+            !rpc_client.unclaimed_responses().await.1.is_empty()
+        })
+        .await;
+
+        let response = rpc_client
+            .send_request(IncomingBitVMXApiMessages::Ping())
+            .await
+            .unwrap();
+        assert!(matches!(response, OutgoingBitVMXApiMessages::Pong()));
+        assert!(bitvmx
+            .received()
+            .iter()
+            .any(|message| matches!(message, IncomingBitVMXApiMessages::Ping())));
+
+        let (metrics, responses) = rpc_client.unclaimed_responses().await;
+        assert_eq!(responses.len(), 1);
+        assert_eq!(metrics.claimed, 0);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_to_program_events() {
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let chain = FakeChain::new();
+        let bitvmx = FakeBitvmx::new(&chain);
+        let (rpc_client, _listener_task) = RpcClient::with_transport(bitvmx.clone(), &shutdown_tx);
+        let (program_id, other_program_id) = (Uuid::new_v4(), Uuid::new_v4());
+
+        // Seen before subscribing
+        chain.mine(program_id, "COMMITMENT");
+        wait_until("the commitment is unclaimed", || async {
+            !rpc_client.unclaimed_responses().await.1.is_empty()
+        })
+        .await;
+
+        let mut subscription = rpc_client.subscribe(program_id).await;
+        assert!(rpc_client.is_subscribed(program_id).await);
+        chain.mine(other_program_id, "COMMITMENT");
+        chain.mine(program_id, "NARY_PROVER_1");
+
+        let mut names = Vec::new();
+        while names.len() < 2 {
+            match subscription.recv().await.unwrap() {
+                OutgoingBitVMXApiMessages::Transaction(id, _, Some(name)) => {
+                    assert_eq!(id, program_id);
+                    names.push(name);
+                }
+                event => panic!("Unexpected event: {event:?}"),
+            }
+        }
+        assert_eq!(names, vec!["COMMITMENT", "NARY_PROVER_1"]);
+
+        // The replayed and delivered events are not kept, only the other program one is
+        let (metrics, unclaimed) = rpc_client.unclaimed_responses().await;
+        assert_eq!(unclaimed.len(), 1);
+        assert_eq!(
+            program_event_id(&unclaimed[0].response),
+            Some(other_program_id)
+        );
+        assert_eq!(metrics.claimed, 1);
+        let options = RequestOptions {
+            timeout: Duration::from_millis(50),
+            ..Default::default()
+        };
+        let commitment_id = tx_name_to_correlation_id(&program_id, "COMMITMENT");
+        assert!(rpc_client
+            .wait_for_response_with(commitment_id, options)
+            .await
+            .is_err());
+
+        drop(subscription);
+        assert!(!rpc_client.is_subscribed(program_id).await);
+    }
+
+    #[tokio::test]
+    async fn test_unhandled_message_is_acked_and_counted() {
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let (client, bitvmx) = MemoryTransport::pair();
+        let (rpc_client, listener_task) = RpcClient::with_transport(Arc::new(client), &shutdown_tx);
+
+        bitvmx
+            .send("not a BitVMX message".to_string())
+            .await
+            .unwrap();
+        let pong = serde_json::to_string(&OutgoingBitVMXApiMessages::Pong()).unwrap();
+        bitvmx.send(pong).await.unwrap();
+
+        // The listener keeps going after the unhandled message
+        wait_until("the pong is unclaimed", || async {
+            !rpc_client.unclaimed_responses().await.1.is_empty()
+        })
+        .await;
+        assert_eq!(rpc_client.unhandled_messages(), 1);
+        assert!(!listener_task.is_finished());
+    }
+
+    /// Memory transport that fails to receive until it reconnects
+    #[derive(Debug)]
+    struct FlakyTransport {
+        inner: MemoryTransport,
+        failing: AtomicBool,
+    }
+
+    #[async_trait]
+    impl BitvmxTransport for FlakyTransport {
+        async fn send(&self, msg: String) -> Result<(), anyhow::Error> {
+            self.inner.send(msg).await
+        }
+
+        async fn receive(&self) -> Result<Option<TransportMessage>, anyhow::Error> {
+            if self.failing.load(Ordering::SeqCst) {
+                return Err(anyhow::anyhow!("Connection reset"));
+            }
+            self.inner.receive().await
+        }
+
+        async fn ack(&self, uid: u64) -> Result<(), anyhow::Error> {
+            self.inner.ack(uid).await
+        }
+
+        async fn reconnect(&self) -> Result<(), anyhow::Error> {
+            self.failing.store(false, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_fails_pending_requests_and_keeps_waiting_for_events() {
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let (client, bitvmx) = MemoryTransport::pair();
+        let transport = Arc::new(FlakyTransport {
+            inner: client,
+            failing: AtomicBool::new(false),
+        });
+        let (rpc_client, listener_task) =
+            RpcClient::with_transport(transport.clone(), &shutdown_tx);
+        wait_until("the client is ready", || async { rpc_client.is_ready() }).await;
+
+        // BitVMX never answers the ping
+        let request = tokio::spawn({
+            let rpc_client = rpc_client.clone();
+            async move {
+                rpc_client
+                    .send_request(IncomingBitVMXApiMessages::Ping())
+                    .await
+            }
+        });
+        let program_id = Uuid::new_v4();
+        let event = tokio::spawn({
+            let rpc_client = rpc_client.clone();
+            async move { rpc_client.wait_for_response(program_id.to_string()).await }
+        });
+        wait_until("the request and the event are pending", || async {
+            rpc_client.is_waiting_for("ping").await
+                && rpc_client.is_waiting_for(&program_id.to_string()).await
+        })
+        .await;
+
+        transport.failing.store(true, Ordering::SeqCst);
+        let error = request.await.unwrap().unwrap_err();
+        assert!(error.is::<Disconnected>());
+        let status = rpc_client.connection_status();
+        assert_eq!(status.state, ConnectionState::Disconnected);
+        assert_eq!(status.consecutive_failures, 1);
+        assert!(!rpc_client.is_ready());
+
+        // Requests fail right away while disconnected
+        let error = rpc_client
+            .send_fire_and_forget(&requests::Ping)
+            .await
+            .unwrap_err();
+        assert!(error.is::<Disconnected>());
+
+        // The listener reconnects instead of finishing
+        wait_until("the client reconnects", || async { rpc_client.is_ready() }).await;
+        assert!(!listener_task.is_finished());
+        assert_eq!(rpc_client.connection_status().consecutive_failures, 0);
+
+        let completed = OutgoingBitVMXApiMessages::SetupCompleted(program_id);
+        bitvmx
+            .send(serde_json::to_string(&completed).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(event.await.unwrap().unwrap(), completed);
+    }
+
+    #[tokio::test]
+    async fn test_wait_with_timeout_prunes_the_handler() {
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let bitvmx = FakeBitvmx::new(&FakeChain::new());
+        let (rpc_client, _listener_task) = RpcClient::with_transport(bitvmx, &shutdown_tx);
+        let correlation_id = Uuid::new_v4().to_string();
+
+        let error = rpc_client
+            .wait_for_response_with(
+                correlation_id.clone(),
+                RequestOptions::timeout(Duration::from_millis(20)),
+            )
+            .await
+            .unwrap_err();
+        assert!(error.is::<RequestTimeout>());
+        assert!(!rpc_client.is_waiting_for(&correlation_id).await);
+
+        // A wait dropped by the caller is pruned too
+        let dropped = tokio::time::timeout(
+            Duration::from_millis(20),
+            rpc_client.wait_for_response(correlation_id.clone()),
+        )
+        .await;
+        assert!(dropped.is_err());
+        assert!(!rpc_client.is_waiting_for(&correlation_id).await);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_a_request() {
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let bitvmx = FakeBitvmx::new(&FakeChain::new());
+        // BitVMX never answers the ping
+        bitvmx.on(|message| matches!(message, IncomingBitVMXApiMessages::Ping()).then(Vec::new));
+        let (rpc_client, _listener_task) = RpcClient::with_transport(bitvmx, &shutdown_tx);
+
+        let cancellation = CancellationToken::new();
+        let request = tokio::spawn({
+            let rpc_client = rpc_client.clone();
+            let options = RequestOptions::default()
+                .with_cancellation(cancellation.clone())
+                .with_retry(RetryPolicy::new(3, Duration::ZERO));
+            async move {
+                rpc_client
+                    .send_request_with(IncomingBitVMXApiMessages::Ping(), options)
+                    .await
+            }
+        });
+        wait_until("the ping is sent", || rpc_client.is_waiting_for("ping")).await;
+
+        cancellation.cancel();
+        let error = request.await.unwrap().unwrap_err();
+        assert!(error.is::<RequestCancelled>());
+        assert!(!rpc_client.is_waiting_for("ping").await);
+    }
+
+    #[tokio::test]
+    async fn test_retry_a_request_that_timed_out() {
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let bitvmx = FakeBitvmx::new(&FakeChain::new());
+        // The first ping is lost, the next ones get the default pong
+        let lost = AtomicBool::new(false);
+        bitvmx.on(move |message| {
+            (matches!(message, IncomingBitVMXApiMessages::Ping())
+                && !lost.swap(true, Ordering::SeqCst))
+            .then(Vec::new)
+        });
+        let (rpc_client, _listener_task) = RpcClient::with_transport(bitvmx.clone(), &shutdown_tx);
+
+        let options = RequestOptions::timeout(Duration::from_millis(200))
+            .with_retry(RetryPolicy::new(1, Duration::from_millis(10)));
+        let response = rpc_client
+            .send_request_with(IncomingBitVMXApiMessages::Ping(), options)
+            .await
+            .unwrap();
+        assert!(matches!(response, OutgoingBitVMXApiMessages::Pong()));
+
+        let pings = bitvmx
+            .received()
+            .iter()
+            .filter(|message| matches!(message, IncomingBitVMXApiMessages::Ping()))
+            .count();
+        assert_eq!(pings, 2);
+    }
+}
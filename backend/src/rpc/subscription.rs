@@ -0,0 +1,43 @@
+use bitvmx_client::types::OutgoingBitVMXApiMessages;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// Program of the on chain events that subscriptions receive, None for any other message
+pub fn program_event_id(message: &OutgoingBitVMXApiMessages) -> Option<Uuid> {
+    match message {
+        OutgoingBitVMXApiMessages::Transaction(uuid, _transaction_status, _name) => Some(*uuid),
+        OutgoingBitVMXApiMessages::SpendingUTXOTransactionFound(
+            uuid,
+            _txid,
+            _vout,
+            _transaction_status,
+        ) => Some(*uuid),
+        _ => None,
+    }
+}
+
+/// Stream of the on chain events of a program, e.g. every dispute transaction seen.
+/// Dropping it unsubscribes.
+#[derive(Debug)]
+pub struct ProgramSubscription {
+    program_id: Uuid,
+    rx: mpsc::UnboundedReceiver<OutgoingBitVMXApiMessages>,
+}
+
+impl ProgramSubscription {
+    pub(crate) fn new(
+        program_id: Uuid,
+        rx: mpsc::UnboundedReceiver<OutgoingBitVMXApiMessages>,
+    ) -> Self {
+        Self { program_id, rx }
+    }
+
+    pub fn program_id(&self) -> Uuid {
+        self.program_id
+    }
+
+    /// Next event of the program, None once the client is gone
+    pub async fn recv(&mut self) -> Option<OutgoingBitVMXApiMessages> {
+        self.rx.recv().await
+    }
+}
@@ -0,0 +1,212 @@
+use crate::config::BitVMXClientConfig;
+use crate::rpc::transport::{BitvmxTransport, TransportMessage};
+use async_trait::async_trait;
+use bitvmx_broker::identification::allow_list::AllowList;
+use bitvmx_broker::identification::identifier::Identifier;
+use bitvmx_broker::rpc::client::Client;
+use bitvmx_broker::rpc::tls_helper::Cert;
+use bitvmx_broker::rpc::BrokerConfig;
+use std::collections::HashSet;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+use tracing::{debug, info, warn};
+
+/// Transport over the BitVMX broker
+#[derive(Debug)]
+pub struct BrokerTransport {
+    /// Broker, keys and allow list, kept to reconnect
+    config: BitVMXClientConfig,
+    broker_ip: Option<IpAddr>,
+    /// Internal Broker RPC client, replaced when reconnecting
+    client: RwLock<Client>,
+    /// My ID for sending messages
+    my_id: u8,
+    /// My identifier to get the messages sent to me
+    my_identifier: Identifier,
+    /// Target identifier for sending messages
+    to_identifier: Identifier,
+    /// Public key hashes allowed to send us messages
+    allowed: HashSet<String>,
+    /// Messages rejected because their sender is not allowed
+    rejected: AtomicU64,
+}
+
+impl BrokerTransport {
+    /// Create the broker client with the BitVMX and L2 keys of the config
+    pub fn connect(config: &BitVMXClientConfig) -> Result<Self, anyhow::Error> {
+        let broker_ip = config.broker_ip()?;
+        let (client, my_identifier, to_identifier) = Self::new_client(config, broker_ip)?;
+        let allowed = allowed_identities(config, &to_identifier)?;
+        info!(
+            "Broker at {:?}:{}, allowed identities: {:?}",
+            broker_ip, config.broker_port, allowed
+        );
+        Ok(Self {
+            config: config.clone(),
+            broker_ip,
+            client: RwLock::new(client),
+            my_id: 0,
+            my_identifier,
+            to_identifier,
+            allowed,
+            rejected: AtomicU64::new(0),
+        })
+    }
+
+    /// Create a broker client, returns it with the L2 and BitVMX identifiers
+    fn new_client(
+        config: &BitVMXClientConfig,
+        broker_ip: Option<IpAddr>,
+    ) -> Result<(Client, Identifier, Identifier), anyhow::Error> {
+        let (bitvmx_cert, bitvmx_identifier) = load_key(&config.bitvmx_key_path)?;
+        debug!("BitVMX identifier: {:?}", bitvmx_identifier);
+
+        let (l2_cert, l2_identifier) = load_key(&config.l2_key_path)?;
+        debug!("L2 identifier: {:?}", l2_identifier);
+
+        if bitvmx_identifier.pubkey_hash == l2_identifier.pubkey_hash {
+            return Err(anyhow::anyhow!(
+                "The BitVMX key {} and the L2 key {} must be different",
+                config.bitvmx_key_path,
+                config.l2_key_path
+            ));
+        }
+
+        // Only the BitVMX broker and the identities of the config are trusted
+        let allow_list = AllowList::new();
+        let allow_ip = broker_ip.unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        for pubkey_hash in allowed_identities(config, &bitvmx_identifier)? {
+            allow_list.lock().unwrap().add(pubkey_hash, allow_ip);
+        }
+
+        // Create broker client
+        let broker_config =
+            BrokerConfig::new(config.broker_port, broker_ip, bitvmx_cert.get_pubk_hash()?);
+        let client = Client::new(&broker_config, l2_cert, allow_list);
+
+        Ok((client, l2_identifier, bitvmx_identifier))
+    }
+
+    /// Current broker client, cloned so the lock is not held across awaits
+    fn client(&self) -> Client {
+        self.client.read().unwrap().clone()
+    }
+}
+
+/// Load a key file, returns its certificate and identity
+fn load_key(key_file: &str) -> Result<(Cert, Identifier), anyhow::Error> {
+    if !Path::new(key_file).is_file() {
+        return Err(anyhow::anyhow!("Key file {key_file} not found"));
+    }
+    let cert = Cert::from_key_file(key_file).map_err(|e| {
+        anyhow::anyhow!("Failed to create certificate from file {key_file} err: {e:?}")
+    })?;
+    let pubkey_hash = cert
+        .get_pubk_hash()
+        .map_err(|e| anyhow::anyhow!("Invalid key in file {key_file} err: {e:?}"))?;
+    Ok((cert, Identifier::new(pubkey_hash, 0)))
+}
+
+/// Public key hashes allowed to send us messages: BitVMX and the allow list of the config
+fn allowed_identities(
+    config: &BitVMXClientConfig,
+    bitvmx_identifier: &Identifier,
+) -> Result<HashSet<String>, anyhow::Error> {
+    if let Some(invalid) = config.allow_list.iter().find(|pubkey_hash| {
+        pubkey_hash.trim().is_empty() || pubkey_hash.contains(char::is_whitespace)
+    }) {
+        return Err(anyhow::anyhow!(
+            "Invalid public key hash in the allow list: {invalid:?}"
+        ));
+    }
+    Ok(config
+        .allow_list
+        .iter()
+        .cloned()
+        .chain([bitvmx_identifier.pubkey_hash.clone()])
+        .collect())
+}
+
+#[async_trait]
+impl BitvmxTransport for BrokerTransport {
+    async fn send(&self, msg: String) -> Result<(), anyhow::Error> {
+        self.client()
+            .async_send_msg(self.my_id, self.to_identifier.clone(), msg)
+            .await
+            .map_err(|e| anyhow::anyhow!("Send message to BitVMX failed: {e}"))
+    }
+
+    async fn receive(&self) -> Result<Option<TransportMessage>, anyhow::Error> {
+        let msg = self
+            .client()
+            .async_get_msg(self.my_identifier.clone())
+            .await
+            .map_err(|e| anyhow::anyhow!("Get message from BitVMX failed: {e}"))?;
+
+        let Some(msg) = msg else {
+            return Ok(None);
+        };
+        if !self.allowed.contains(&msg.from.pubkey_hash) {
+            let rejected = self.rejected.fetch_add(1, Ordering::SeqCst) + 1;
+            warn!(
+                "Rejected message {} from {:?}, it is not in the allow list, {rejected} rejected so far",
+                msg.uid, msg.from
+            );
+            // Acknowledge it so the broker does not deliver it again
+            self.ack(msg.uid).await?;
+            return Ok(None);
+        }
+
+        Ok(Some(TransportMessage {
+            uid: msg.uid,
+            msg: msg.msg,
+        }))
+    }
+
+    async fn ack(&self, uid: u64) -> Result<(), anyhow::Error> {
+        self.client()
+            .async_ack(self.my_identifier.clone(), uid)
+            .await
+            .map_err(|e| anyhow::anyhow!("Ack message from BitVMX failed: {e}"))?;
+        Ok(())
+    }
+
+    fn rejected_messages(&self) -> u64 {
+        self.rejected.load(Ordering::SeqCst)
+    }
+
+    async fn reconnect(&self) -> Result<(), anyhow::Error> {
+        let (client, _, _) = Self::new_client(&self.config, self.broker_ip)?;
+        *self.client.write().unwrap() = client;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_missing_key_file_fails() {
+        let error = load_key("config/keys/missing.key").unwrap_err();
+        assert!(error.to_string().contains("not found"));
+    }
+
+    #[test]
+    fn test_allowed_identities() {
+        let mut config = Config::default().bitvmx;
+        config.allow_list = vec!["operator".to_string()];
+        let bitvmx = Identifier::new("bitvmx".to_string(), 0);
+        let allowed = allowed_identities(&config, &bitvmx).unwrap();
+        assert_eq!(
+            allowed,
+            HashSet::from(["bitvmx".to_string(), "operator".to_string()])
+        );
+
+        config.allow_list.push(" ".to_string());
+        assert!(allowed_identities(&config, &bitvmx).is_err());
+    }
+}
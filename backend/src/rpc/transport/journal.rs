@@ -0,0 +1,46 @@
+use crate::rpc::journal::{Journal, JournalDirection};
+use crate::rpc::transport::{BitvmxTransport, TransportMessage};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// Transport that records every message exchanged by another transport in a [`Journal`]
+#[derive(Debug)]
+pub struct JournalTransport {
+    inner: Arc<dyn BitvmxTransport>,
+    journal: Journal,
+}
+
+impl JournalTransport {
+    pub fn new(inner: Arc<dyn BitvmxTransport>, journal: Journal) -> Self {
+        Self { inner, journal }
+    }
+}
+
+#[async_trait]
+impl BitvmxTransport for JournalTransport {
+    async fn send(&self, msg: String) -> Result<(), anyhow::Error> {
+        self.journal.record(JournalDirection::Sent, &msg);
+        self.inner.send(msg).await
+    }
+
+    async fn receive(&self) -> Result<Option<TransportMessage>, anyhow::Error> {
+        let received = self.inner.receive().await?;
+        if let Some(message) = &received {
+            self.journal
+                .record(JournalDirection::Received, &message.msg);
+        }
+        Ok(received)
+    }
+
+    async fn ack(&self, uid: u64) -> Result<(), anyhow::Error> {
+        self.inner.ack(uid).await
+    }
+
+    fn rejected_messages(&self) -> u64 {
+        self.inner.rejected_messages()
+    }
+
+    async fn reconnect(&self) -> Result<(), anyhow::Error> {
+        self.inner.reconnect().await
+    }
+}
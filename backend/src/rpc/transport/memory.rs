@@ -0,0 +1,92 @@
+use crate::rpc::transport::{BitvmxTransport, TransportMessage};
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex};
+
+/// How long a receive waits for a message before returning None
+const RECEIVE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// End of an in process channel pair, what one end sends the other one receives
+#[derive(Debug)]
+pub struct MemoryTransport {
+    tx: mpsc::UnboundedSender<String>,
+    rx: Mutex<mpsc::UnboundedReceiver<String>>,
+    next_uid: AtomicU64,
+}
+
+impl MemoryTransport {
+    /// Create both ends, one for the client and one for whoever plays BitVMX
+    pub fn pair() -> (Self, Self) {
+        let (client_tx, bitvmx_rx) = mpsc::unbounded_channel();
+        let (bitvmx_tx, client_rx) = mpsc::unbounded_channel();
+        (
+            Self::new(client_tx, client_rx),
+            Self::new(bitvmx_tx, bitvmx_rx),
+        )
+    }
+
+    fn new(tx: mpsc::UnboundedSender<String>, rx: mpsc::UnboundedReceiver<String>) -> Self {
+        Self {
+            tx,
+            rx: Mutex::new(rx),
+            next_uid: AtomicU64::new(0),
+        }
+    }
+}
+
+#[async_trait]
+impl BitvmxTransport for MemoryTransport {
+    async fn send(&self, msg: String) -> Result<(), anyhow::Error> {
+        self.tx
+            .send(msg)
+            .map_err(|_| anyhow::anyhow!("Memory transport closed by the other end"))
+    }
+
+    async fn receive(&self) -> Result<Option<TransportMessage>, anyhow::Error> {
+        let mut rx = self.rx.lock().await;
+        match tokio::time::timeout(RECEIVE_INTERVAL, rx.recv()).await {
+            Ok(Some(msg)) => Ok(Some(TransportMessage {
+                uid: self.next_uid.fetch_add(1, Ordering::SeqCst),
+                msg,
+            })),
+            Ok(None) => Err(anyhow::anyhow!("Memory transport closed by the other end")),
+            Err(_timeout) => Ok(None),
+        }
+    }
+
+    async fn ack(&self, _uid: u64) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_transport_pair() {
+        let (client, bitvmx) = MemoryTransport::pair();
+
+        client.send("request".to_string()).await.unwrap();
+        let received = bitvmx.receive().await.unwrap().unwrap();
+        assert_eq!(received.msg, "request");
+
+        bitvmx.send("response".to_string()).await.unwrap();
+        bitvmx.send("event".to_string()).await.unwrap();
+        let first = client.receive().await.unwrap().unwrap();
+        let second = client.receive().await.unwrap().unwrap();
+        assert_eq!(
+            (first.msg.as_str(), second.msg.as_str()),
+            ("response", "event")
+        );
+        assert_ne!(first.uid, second.uid);
+
+        // Nothing else to read
+        assert_eq!(client.receive().await.unwrap(), None);
+
+        drop(bitvmx);
+        assert!(client.send("request".to_string()).await.is_err());
+        assert!(client.receive().await.is_err());
+    }
+}
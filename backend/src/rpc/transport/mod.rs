@@ -0,0 +1,131 @@
+pub mod broker;
+pub mod journal;
+pub mod memory;
+pub mod unix_socket;
+
+pub use broker::BrokerTransport;
+pub use journal::JournalTransport;
+pub use memory::MemoryTransport;
+pub use unix_socket::UnixSocketTransport;
+
+use crate::config::{BitVMXClientConfig, BitvmxTransportKind};
+use crate::rpc::journal::Journal;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// Serialized message received from BitVMX
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransportMessage {
+    /// Id used to acknowledge the message once it is handled
+    pub uid: u64,
+    pub msg: String,
+}
+
+/// Channel used by the [`crate::rpc::RpcClient`] to exchange serialized messages with BitVMX.
+/// Correlation of the responses is done by the client, a transport only moves messages.
+#[async_trait]
+pub trait BitvmxTransport: std::fmt::Debug + Send + Sync {
+    /// Send a message to BitVMX
+    async fn send(&self, msg: String) -> Result<(), anyhow::Error>;
+
+    /// Get the next message from BitVMX, None when there is no message yet
+    async fn receive(&self) -> Result<Option<TransportMessage>, anyhow::Error>;
+
+    /// Acknowledge a received message
+    async fn ack(&self, uid: u64) -> Result<(), anyhow::Error>;
+
+    /// Messages dropped because their sender is not allowed to talk to us
+    fn rejected_messages(&self) -> u64 {
+        0
+    }
+
+    /// Connect again after the transport failed, e.g. with a new broker client
+    async fn reconnect(&self) -> Result<(), anyhow::Error> {
+        Err(anyhow::anyhow!("The transport cannot reconnect"))
+    }
+}
+
+/// Create the transport selected in the config, journaled if the config has a journal
+pub fn connect(config: &BitVMXClientConfig) -> Result<Arc<dyn BitvmxTransport>, anyhow::Error> {
+    let transport = connect_transport(config)?;
+    match &config.journal_path {
+        Some(journal_path) => Ok(Arc::new(JournalTransport::new(
+            transport,
+            Journal::open(journal_path)?,
+        ))),
+        None => Ok(transport),
+    }
+}
+
+fn connect_transport(
+    config: &BitVMXClientConfig,
+) -> Result<Arc<dyn BitvmxTransport>, anyhow::Error> {
+    match config.transport {
+        BitvmxTransportKind::Broker => Ok(Arc::new(BrokerTransport::connect(config)?)),
+        BitvmxTransportKind::Memory => fake_bitvmx(),
+        BitvmxTransportKind::UnixSocket => {
+            let socket_path = config.socket_path.as_ref().ok_or(anyhow::anyhow!(
+                "The unix socket transport needs a socket_path"
+            ))?;
+            Ok(Arc::new(UnixSocketTransport::connect(socket_path)?))
+        }
+    }
+}
+
+/// Memory transport served by a fake BitVMX node on its own chain
+#[cfg(any(test, feature = "test-support"))]
+fn fake_bitvmx() -> Result<Arc<dyn BitvmxTransport>, anyhow::Error> {
+    use crate::test_support::{FakeBitvmx, FakeChain};
+
+    let (client, bitvmx) = MemoryTransport::pair();
+    tokio::spawn(FakeBitvmx::new(&FakeChain::new()).serve(bitvmx));
+    Ok(Arc::new(client))
+}
+
+#[cfg(not(any(test, feature = "test-support")))]
+fn fake_bitvmx() -> Result<Arc<dyn BitvmxTransport>, anyhow::Error> {
+    Err(anyhow::anyhow!(
+        "The memory transport needs the test-support feature"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::rpc::RpcClient;
+    use bitvmx_client::types::{IncomingBitVMXApiMessages, OutgoingBitVMXApiMessages};
+    use tokio::sync::broadcast;
+
+    #[tokio::test]
+    async fn test_connect_memory_transport_to_fake_bitvmx() {
+        let mut config = Config::default().bitvmx;
+        config.transport = BitvmxTransportKind::Memory;
+        let (shutdown_tx, _) = broadcast::channel(1);
+
+        let (rpc_client, _listener_task) = RpcClient::connect(&config, &shutdown_tx).unwrap();
+        let response = rpc_client
+            .send_request(IncomingBitVMXApiMessages::Ping())
+            .await
+            .unwrap();
+        assert_eq!(response, OutgoingBitVMXApiMessages::Pong());
+        assert!(rpc_client.is_ready());
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[test]
+    fn test_broker_transport_validates_the_keys() {
+        let mut config = Config::default().bitvmx;
+        config.l2_key_path = "config/keys/missing.key".to_string();
+        let error = connect(&config).unwrap_err();
+        assert!(error.to_string().contains("config/keys/missing.key"));
+    }
+
+    #[test]
+    fn test_unix_socket_transport_needs_a_socket_path() {
+        let mut config = Config::default().bitvmx;
+        config.transport = BitvmxTransportKind::UnixSocket;
+        assert!(connect(&config).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+use crate::rpc::transport::{BitvmxTransport, TransportMessage};
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::unix::OwnedWriteHalf;
+use tokio::net::UnixStream;
+use tokio::sync::{mpsc, Mutex};
+use tracing::warn;
+
+/// How long a receive waits for a message before returning None
+const RECEIVE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Transport over a unix socket, every message is one JSON line
+#[derive(Debug)]
+pub struct UnixSocketTransport {
+    socket_path: String,
+    writer: Mutex<OwnedWriteHalf>,
+    /// Lines read from the socket, closed when the socket is closed
+    lines: Mutex<mpsc::UnboundedReceiver<String>>,
+    next_uid: AtomicU64,
+}
+
+impl UnixSocketTransport {
+    /// Connect to the socket and read its lines in background
+    pub fn connect(socket_path: &str) -> Result<Self, anyhow::Error> {
+        let (writer, lines) = Self::open(socket_path)?;
+        Ok(Self {
+            socket_path: socket_path.to_string(),
+            writer: Mutex::new(writer),
+            lines: Mutex::new(lines),
+            next_uid: AtomicU64::new(0),
+        })
+    }
+
+    /// Open the socket, returns its writer and the lines read from it
+    fn open(
+        socket_path: &str,
+    ) -> Result<(OwnedWriteHalf, mpsc::UnboundedReceiver<String>), anyhow::Error> {
+        let stream = std::os::unix::net::UnixStream::connect(socket_path)
+            .and_then(|stream| {
+                stream.set_nonblocking(true)?;
+                Ok(stream)
+            })
+            .map_err(|e| {
+                anyhow::anyhow!("Failed to connect to unix socket {socket_path}: {e:?}")
+            })?;
+        let stream = UnixStream::from_std(stream)
+            .map_err(|e| anyhow::anyhow!("Failed to use unix socket {socket_path}: {e:?}"))?;
+        let (reader, writer) = stream.into_split();
+        let (lines_tx, lines_rx) = mpsc::unbounded_channel();
+
+        // Reading a line is not cancel safe, keep it out of receive
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(reader).lines();
+            loop {
+                match lines.next_line().await {
+                    Ok(Some(line)) if line.trim().is_empty() => {}
+                    Ok(Some(line)) => {
+                        if lines_tx.send(line).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        warn!("Failed to read from unix socket: {e:?}");
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok((writer, lines_rx))
+    }
+}
+
+#[async_trait]
+impl BitvmxTransport for UnixSocketTransport {
+    async fn send(&self, msg: String) -> Result<(), anyhow::Error> {
+        if msg.contains('\n') {
+            return Err(anyhow::anyhow!("Message must fit in one line: {msg}"));
+        }
+        let mut writer = self.writer.lock().await;
+        writer
+            .write_all(format!("{msg}\n").as_bytes())
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to write to unix socket: {e:?}"))
+    }
+
+    async fn receive(&self) -> Result<Option<TransportMessage>, anyhow::Error> {
+        let mut lines = self.lines.lock().await;
+        match tokio::time::timeout(RECEIVE_INTERVAL, lines.recv()).await {
+            Ok(Some(msg)) => Ok(Some(TransportMessage {
+                uid: self.next_uid.fetch_add(1, Ordering::SeqCst),
+                msg,
+            })),
+            Ok(None) => Err(anyhow::anyhow!("Unix socket closed")),
+            Err(_timeout) => Ok(None),
+        }
+    }
+
+    async fn ack(&self, _uid: u64) -> Result<(), anyhow::Error> {
+        // Lines are consumed once read
+        Ok(())
+    }
+
+    async fn reconnect(&self) -> Result<(), anyhow::Error> {
+        let (writer, lines) = Self::open(&self.socket_path)?;
+        *self.writer.lock().await = writer;
+        *self.lines.lock().await = lines;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::UnixListener;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_unix_socket_transport() {
+        let socket_path = std::env::temp_dir().join(format!("bitvmx-{}.sock", Uuid::new_v4()));
+        let listener = UnixListener::bind(&socket_path).unwrap();
+
+        // BitVMX side answers every line with two lines
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            let line = lines.next_line().await.unwrap().unwrap();
+            writer
+                .write_all(format!("{line}-response\n\n{line}-event\n").as_bytes())
+                .await
+                .unwrap();
+        });
+
+        let transport = UnixSocketTransport::connect(socket_path.to_str().unwrap()).unwrap();
+        transport.send("\"request\"".to_string()).await.unwrap();
+        assert!(transport.send("two\nlines".to_string()).await.is_err());
+
+        let mut received = Vec::new();
+        while received.len() < 2 {
+            if let Some(message) = transport.receive().await.unwrap() {
+                received.push(message.msg);
+            }
+        }
+        assert_eq!(received, vec!["\"request\"-response", "\"request\"-event"]);
+
+        server.await.unwrap();
+        let closed = loop {
+            match transport.receive().await {
+                Ok(None) => continue,
+                result => break result,
+            }
+        };
+        assert!(closed.is_err());
+
+        // A new connection to the same socket
+        std::fs::remove_file(&socket_path).unwrap();
+        let listener = UnixListener::bind(&socket_path).unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (_reader, mut writer) = stream.into_split();
+            writer.write_all(b"\"reconnected\"\n").await.unwrap();
+        });
+        transport.reconnect().await.unwrap();
+        let reconnected = loop {
+            if let Some(message) = transport.receive().await.unwrap() {
+                break message.msg;
+            }
+        };
+        assert_eq!(reconnected, "\"reconnected\"");
+        server.await.unwrap();
+
+        let _ = std::fs::remove_file(&socket_path);
+        assert!(UnixSocketTransport::connect(socket_path.to_str().unwrap()).is_err());
+    }
+}
@@ -0,0 +1,198 @@
+use bitvmx_client::types::OutgoingBitVMXApiMessages;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Response received from BitVMX when nobody was waiting for it
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnclaimedResponse {
+    pub correlation_id: String,
+    pub received_at: Instant,
+    pub response: OutgoingBitVMXApiMessages,
+}
+
+/// Counters of the unclaimed responses since the client started
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UnclaimedMetrics {
+    /// Responses kept because nobody was waiting for them
+    pub retained: u64,
+    /// Responses handed to a waiter registered after they arrived
+    pub claimed: u64,
+    /// Responses dropped because nobody claimed them in time
+    pub expired: u64,
+    /// Responses dropped to make room for newer ones
+    pub evicted: u64,
+}
+
+/// Bounded cache of the responses that arrived before their waiter was registered,
+/// e.g. a transaction seen on chain before the game waits for it.
+/// Responses are claimed in the order they arrived and dropped after the TTL.
+#[derive(Debug)]
+pub struct UnclaimedResponses {
+    ttl: Duration,
+    capacity: usize,
+    /// Oldest first
+    responses: VecDeque<UnclaimedResponse>,
+    metrics: UnclaimedMetrics,
+}
+
+impl UnclaimedResponses {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity,
+            responses: VecDeque::new(),
+            metrics: UnclaimedMetrics::default(),
+        }
+    }
+
+    /// Keep a response nobody was waiting for, the oldest one is evicted when full
+    pub fn retain(&mut self, correlation_id: String, response: OutgoingBitVMXApiMessages) {
+        let now = Instant::now();
+        self.prune(now);
+        if self.capacity == 0 {
+            self.metrics.evicted += 1;
+            return;
+        }
+        while self.responses.len() >= self.capacity {
+            self.responses.pop_front();
+            self.metrics.evicted += 1;
+        }
+
+        self.responses.push_back(UnclaimedResponse {
+            correlation_id,
+            received_at: now,
+            response,
+        });
+        self.metrics.retained += 1;
+    }
+
+    /// Take the oldest response kept for the correlation id
+    pub fn claim(&mut self, correlation_id: &str) -> Option<OutgoingBitVMXApiMessages> {
+        self.prune(Instant::now());
+        let index = self
+            .responses
+            .iter()
+            .position(|unclaimed| unclaimed.correlation_id == correlation_id)?;
+        let unclaimed = self.responses.remove(index)?;
+        self.metrics.claimed += 1;
+        Some(unclaimed.response)
+    }
+
+    /// Take every response kept that matches `filter`, oldest first
+    pub fn claim_where(
+        &mut self,
+        filter: impl Fn(&OutgoingBitVMXApiMessages) -> bool,
+    ) -> Vec<OutgoingBitVMXApiMessages> {
+        self.prune(Instant::now());
+        let (claimed, kept) = self
+            .responses
+            .drain(..)
+            .partition(|unclaimed| filter(&unclaimed.response));
+        self.responses = kept;
+        let claimed: Vec<_> = claimed
+            .into_iter()
+            .map(|unclaimed: UnclaimedResponse| unclaimed.response)
+            .collect();
+        self.metrics.claimed += claimed.len() as u64;
+        claimed
+    }
+
+    /// Responses still waiting to be claimed, oldest first
+    pub fn list(&mut self) -> Vec<UnclaimedResponse> {
+        self.prune(Instant::now());
+        self.responses.iter().cloned().collect()
+    }
+
+    pub fn metrics(&self) -> UnclaimedMetrics {
+        self.metrics.clone()
+    }
+
+    /// Drop the responses older than the TTL
+    fn prune(&mut self, now: Instant) {
+        while self
+            .responses
+            .front()
+            .is_some_and(|unclaimed| now.duration_since(unclaimed.received_at) >= self.ttl)
+        {
+            self.responses.pop_front();
+            self.metrics.expired += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn setup_completed() -> OutgoingBitVMXApiMessages {
+        OutgoingBitVMXApiMessages::SetupCompleted(Uuid::new_v4())
+    }
+
+    #[test]
+    fn test_claim_in_arrival_order() {
+        let mut unclaimed = UnclaimedResponses::new(Duration::from_secs(60), 10);
+        let (first, second) = (setup_completed(), setup_completed());
+        unclaimed.retain("a".to_string(), first.clone());
+        unclaimed.retain("b".to_string(), setup_completed());
+        unclaimed.retain("a".to_string(), second.clone());
+
+        assert_eq!(unclaimed.claim("a"), Some(first));
+        assert_eq!(unclaimed.claim("a"), Some(second));
+        assert_eq!(unclaimed.claim("a"), None);
+        assert_eq!(unclaimed.list().len(), 1);
+        assert_eq!(
+            unclaimed.metrics(),
+            UnclaimedMetrics {
+                retained: 3,
+                claimed: 2,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_claim_where() {
+        let mut unclaimed = UnclaimedResponses::new(Duration::from_secs(60), 10);
+        let (first, second) = (setup_completed(), setup_completed());
+        unclaimed.retain("a".to_string(), first.clone());
+        unclaimed.retain("b".to_string(), OutgoingBitVMXApiMessages::Pong());
+        unclaimed.retain("c".to_string(), second.clone());
+
+        let claimed = unclaimed.claim_where(|response| {
+            matches!(response, OutgoingBitVMXApiMessages::SetupCompleted(_))
+        });
+        assert_eq!(claimed, vec![first, second]);
+        assert_eq!(unclaimed.list().len(), 1);
+        assert_eq!(unclaimed.claim("a"), None);
+        assert_eq!(unclaimed.metrics().claimed, 2);
+    }
+
+    #[test]
+    fn test_evict_the_oldest_when_full() {
+        let mut unclaimed = UnclaimedResponses::new(Duration::from_secs(60), 2);
+        for correlation_id in ["a", "b", "c"] {
+            unclaimed.retain(correlation_id.to_string(), setup_completed());
+        }
+
+        let ids: Vec<String> = unclaimed
+            .list()
+            .into_iter()
+            .map(|unclaimed| unclaimed.correlation_id)
+            .collect();
+        assert_eq!(ids, vec!["b", "c"]);
+        assert_eq!(unclaimed.claim("a"), None);
+        assert_eq!(unclaimed.metrics().evicted, 1);
+    }
+
+    #[test]
+    fn test_expire_after_the_ttl() {
+        let mut unclaimed = UnclaimedResponses::new(Duration::from_millis(20), 10);
+        unclaimed.retain("a".to_string(), setup_completed());
+        std::thread::sleep(Duration::from_millis(30));
+
+        assert_eq!(unclaimed.claim("a"), None);
+        assert!(unclaimed.list().is_empty());
+        assert_eq!(unclaimed.metrics().expired, 1);
+    }
+}
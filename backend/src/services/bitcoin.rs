@@ -1,85 +1,163 @@
+use std::sync::{Arc, Mutex};
 use std::thread::sleep;
 use std::time::Duration;
 
 use crate::config::BitcoinConfig;
+use async_trait::async_trait;
 use bitvmx_bitcoin_rpc::bitcoin_client::BitcoinClient;
 use bitvmx_bitcoin_rpc::bitcoin_client::BitcoinClientApi;
-use bitvmx_client::bitcoin::Address;
+use bitvmx_client::bitcoin::{Address, Txid};
 use tracing::instrument;
 use tracing::trace;
 
-#[derive(Debug, Clone)]
-pub struct BitcoinService {
+/// Bitcoin node used to mine blocks on regtest and to follow the block height
+#[async_trait]
+pub trait BitcoinService: std::fmt::Debug + Send + Sync {
+    /// Mine blocks
+    async fn mine_blocks(&self, blocks: u64) -> Result<(), anyhow::Error>;
+
+    /// Mine blocks to address then mine 100 blocks for maturity
+    async fn mine_blocks_to_address(
+        &self,
+        blocks: u64,
+        address: Address,
+    ) -> Result<(), anyhow::Error>;
+
+    /// Get the height of the best block
+    async fn get_block_height(&self) -> Result<u32, anyhow::Error>;
+
+    /// Check an output is on chain and not spent
+    async fn is_unspent(&self, txid: Txid, vout: u32) -> Result<bool, anyhow::Error>;
+}
+
+/// Bitcoin service talking to bitcoind over RPC, with one client for every call
+pub struct BitcoindService {
     pub bitcoin_config: BitcoinConfig,
+    /// Created on the first call, so the backend starts while bitcoind is down
+    client: Arc<Mutex<Option<Arc<BitcoinClient>>>>,
 }
 
-impl BitcoinService {
+impl std::fmt::Debug for BitcoindService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BitcoindService")
+            .field("bitcoin_config", &self.bitcoin_config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl BitcoindService {
     pub fn new(bitcoin_config: BitcoinConfig) -> Self {
         Self {
-            bitcoin_config: bitcoin_config.clone(),
+            bitcoin_config,
+            client: Arc::new(Mutex::new(None)),
         }
     }
 
-    /// Mine blocks
-    #[instrument(skip(self))]
-    pub async fn mine_blocks(&self, blocks: u64) -> Result<(), anyhow::Error> {
-        trace!("Mining {blocks} blocks");
+    /// Run a blocking call on the bitcoin client without blocking the runtime
+    async fn with_client<T, F>(&self, call: F) -> Result<T, anyhow::Error>
+    where
+        F: FnOnce(&BitcoinClient) -> Result<T, anyhow::Error> + Send + 'static,
+        T: Send + 'static,
+    {
         let bitcoin_config = self.bitcoin_config.clone();
-        // run a blocking routine without blocking the runtime
+        let client = self.client.clone();
         tokio::task::spawn_blocking(move || {
-            // create the bitcoin client
-            let bitcoin_client = BitcoinClient::new(
-                &bitcoin_config.url,
-                &bitcoin_config.username,
-                &bitcoin_config.password,
-            )
-            .unwrap();
-
-            // mine blocks
-            bitcoin_client.mine_blocks(blocks).unwrap();
+            let bitcoin_client = {
+                let mut client = client
+                    .lock()
+                    .map_err(|e| anyhow::anyhow!("Bitcoin client lock poisoned: {e:?}"))?;
+                match client.as_ref() {
+                    Some(bitcoin_client) => bitcoin_client.clone(),
+                    None => {
+                        let bitcoin_client = Arc::new(
+                            BitcoinClient::new(
+                                &bitcoin_config.url,
+                                &bitcoin_config.username,
+                                &bitcoin_config.password,
+                            )
+                            .map_err(|e| {
+                                anyhow::anyhow!("Failed to create bitcoin client: {e:?}")
+                            })?,
+                        );
+                        *client = Some(bitcoin_client.clone());
+                        bitcoin_client
+                    }
+                }
+            };
+            call(&bitcoin_client)
         })
         .await
-        .map_err(|e| anyhow::anyhow!("Failed to mine blocks for funding wallet address: {e:?}"))?;
+        .map_err(|e| anyhow::anyhow!("Bitcoin client call panicked: {e:?}"))?
+    }
+}
+
+#[async_trait]
+impl BitcoinService for BitcoindService {
+    #[instrument(skip(self))]
+    async fn mine_blocks(&self, blocks: u64) -> Result<(), anyhow::Error> {
+        trace!("Mining {blocks} blocks");
+        self.with_client(move |bitcoin_client| {
+            bitcoin_client
+                .mine_blocks(blocks)
+                .map_err(|e| anyhow::anyhow!("Failed to mine blocks: {e:?}"))
+        })
+        .await?;
 
         trace!("Mined {blocks} blocks");
         Ok(())
     }
 
-    /// Mine blocks to address then mine 100 blocks for maturity
     #[instrument(skip(self))]
-    pub async fn mine_blocks_to_address(
+    async fn mine_blocks_to_address(
         &self,
         blocks: u64,
         address: Address,
     ) -> Result<(), anyhow::Error> {
         trace!("Mining {blocks} blocks to address: {address}");
-        let bitcoin_config = self.bitcoin_config.clone();
-        // run a blocking routine without blocking the runtime
-        tokio::task::spawn_blocking(move || {
-            // create the bitcoin client
-            let bitcoin_client = BitcoinClient::new(
-                &bitcoin_config.url,
-                &bitcoin_config.username,
-                &bitcoin_config.password,
-            )
-            .unwrap();
-
+        self.with_client(move |bitcoin_client| {
             // each block gives a 50 BTC reward
             bitcoin_client
                 .mine_blocks_to_address(blocks, &address)
-                .unwrap();
+                .map_err(|e| anyhow::anyhow!("Failed to mine blocks to address: {e:?}"))?;
 
             // mine 100 blocks for maturity
             // we split it to make it easier for the client to process
-            bitcoin_client.mine_blocks(50).unwrap();
-            sleep(Duration::from_secs(5));
-            bitcoin_client.mine_blocks(50).unwrap();
-            sleep(Duration::from_secs(5));
+            for _ in 0..2 {
+                bitcoin_client
+                    .mine_blocks(50)
+                    .map_err(|e| anyhow::anyhow!("Failed to mine maturity blocks: {e:?}"))?;
+                sleep(Duration::from_secs(5));
+            }
+            Ok(())
         })
-        .await
-        .map_err(|e| anyhow::anyhow!("Failed to mine blocks for funding wallet address: {e:?}"))?;
+        .await?;
 
         trace!("Mined {blocks} blocks coinbase and 100 blocks maturity");
         Ok(())
     }
+
+    #[instrument(skip(self))]
+    async fn get_block_height(&self) -> Result<u32, anyhow::Error> {
+        let height = self
+            .with_client(|bitcoin_client| {
+                bitcoin_client
+                    .get_best_block()
+                    .map_err(|e| anyhow::anyhow!("Failed to get best block: {e:?}"))
+            })
+            .await?;
+
+        trace!("Block height: {height}");
+        Ok(height)
+    }
+
+    #[instrument(skip(self))]
+    async fn is_unspent(&self, txid: Txid, vout: u32) -> Result<bool, anyhow::Error> {
+        self.with_client(move |bitcoin_client| {
+            let tx_out = bitcoin_client
+                .get_tx_out(&txid, vout)
+                .map_err(|e| anyhow::anyhow!("Failed to get tx out {txid}:{vout}: {e:?}"))?;
+            Ok(tx_out.is_some())
+        })
+        .await
+    }
 }
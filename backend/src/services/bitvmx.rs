@@ -1,38 +1,60 @@
-use crate::models::{P2PAddress, WalletBalance};
-use crate::rpc::{self, rpc_client::RpcClient};
+use crate::models::{P2PAddress, SetupStatus, WalletBalance};
+use crate::rpc::{self, requests, rpc_client::RpcClient, BitvmxError, RequestOptions, RetryPolicy};
 use crate::services::BitcoinService;
-use crate::stores::BitvmxStore;
+use crate::stores::{bitvmx::FundingUtxo, BitvmxStore};
 use bitvmx_client::bitcoin::{Address, PublicKey, Txid};
-use bitvmx_client::bitcoin_coordinator::TransactionStatus;
+use bitvmx_client::bitcoin_coordinator::{TransactionBlockchainStatus, TransactionStatus};
 use bitvmx_client::bitvmx_wallet::wallet::Destination;
 use bitvmx_client::program::participant::CommsAddress as BitVMXP2PAddress;
 use bitvmx_client::program::protocols::dispute;
 use bitvmx_client::program::variables::VariableTypes;
-use bitvmx_client::types::{IncomingBitVMXApiMessages, OutgoingBitVMXApiMessages};
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::time::sleep;
-use tracing::{debug, info, instrument, trace};
+use tracing::{debug, info, instrument, trace, warn};
 use uuid::Uuid;
 
+/// Minimum wallet balance needed to play, 1 BTC
+const WALLET_MIN_BALANCE: u64 = 100_000_000;
+/// Deadline of the queries the BitVMX node answers by itself, e.g. the comm info
+const QUERY_TIMEOUT: Duration = Duration::from_secs(30);
+const QUERY_RETRIES: u32 = 2;
+const QUERY_RETRY_DELAY: Duration = Duration::from_secs(1);
+/// Deadline of the setups that need the other participants, e.g. the aggregated key
+const SETUP_TIMEOUT: Duration = Duration::from_secs(600);
+/// Deadline of the requests that move funds or dispatch a transaction, they are not retried
+const DISPATCH_TIMEOUT: Duration = Duration::from_secs(120);
+/// Deadline of a transaction to be seen on chain, e.g. a N-ary search step takes many blocks
+const TRANSACTION_TIMEOUT: Duration = Duration::from_secs(3600);
+
 #[derive(Debug, Clone)]
 pub struct BitvmxService {
     pub bitvmx_store: Arc<BitvmxStore>,
-    pub bitcoin_service: Arc<BitcoinService>,
+    pub bitcoin_service: Arc<dyn BitcoinService>,
     /// BitVMX RPC client
     pub rpc_client: Arc<RpcClient>,
 }
 
 impl BitvmxService {
-    pub fn new(rpc_client: Arc<RpcClient>, bitcoin_service: Arc<BitcoinService>) -> Self {
+    pub fn new(
+        rpc_client: Arc<RpcClient>,
+        bitcoin_service: Arc<dyn BitcoinService>,
+        bitvmx_store: Arc<BitvmxStore>,
+    ) -> Self {
         Self {
-            bitvmx_store: Arc::new(BitvmxStore::new()),
+            bitvmx_store,
             bitcoin_service: bitcoin_service.clone(),
             rpc_client,
         }
     }
 
+    /// Options of the queries, safe to send again when they time out or BitVMX disconnects
+    fn query_options() -> RequestOptions {
+        RequestOptions::timeout(QUERY_TIMEOUT)
+            .with_retry(RetryPolicy::new(QUERY_RETRIES, QUERY_RETRY_DELAY))
+    }
+
     /// Get pub key
     pub async fn get_pub_key(&self) -> Result<Option<String>, anyhow::Error> {
         let bitvmx_pub_key = self.bitvmx_store.get_pub_key().await?;
@@ -65,48 +87,36 @@ impl BitvmxService {
         leader_idx: u16,
     ) -> Result<PublicKey, anyhow::Error> {
         trace!("Create aggregated key from BitVMX");
-        let message =
-            IncomingBitVMXApiMessages::SetupKey(uuid, participants, participants_keys, leader_idx);
-
-        let response = self.rpc_client.send_request(message).await?;
-
-        if let OutgoingBitVMXApiMessages::AggregatedPubkey(_uuid, aggregated_pubkey) = response {
-            trace!(
-                "Obtained aggregated key: {:?}",
-                aggregated_pubkey.to_string()
-            );
-            Ok(aggregated_pubkey)
-        } else {
-            Err(anyhow::anyhow!(
-                "Expected AggregatedPubkey response, got: {:?}",
-                response
-            ))
-        }
+        let request = requests::SetupKey {
+            id: uuid,
+            participants,
+            participants_keys,
+            leader_idx,
+        };
+        let aggregated_pubkey = self
+            .rpc_client
+            .send_typed(&request, RequestOptions::timeout(SETUP_TIMEOUT))
+            .await?;
+        trace!(
+            "Obtained aggregated key: {:?}",
+            aggregated_pubkey.to_string()
+        );
+        Ok(aggregated_pubkey)
     }
 
     /// Get aggregated key
     pub async fn aggregated_key(&self, aggregated_id: Uuid) -> Result<PublicKey, anyhow::Error> {
         trace!("Get aggregated key from BitVMX");
-        let response = self
+        let request = requests::GetAggregatedPubkey { id: aggregated_id };
+        let aggregated_pubkey = self
             .rpc_client
-            .send_request(IncomingBitVMXApiMessages::GetAggregatedPubkey(
-                aggregated_id,
-            ))
+            .send_typed(&request, Self::query_options())
             .await?;
-        if let OutgoingBitVMXApiMessages::AggregatedPubkey(_uuid, aggregated_pubkey) = response {
-            trace!(
-                "Obtained aggregated key: {:?}",
-                aggregated_pubkey.to_string()
-            );
-            Ok(aggregated_pubkey)
-        } else if let OutgoingBitVMXApiMessages::AggregatedPubkeyNotReady(uuid) = response {
-            Err(anyhow::anyhow!("Aggregated key not ready: {:?}", uuid))
-        } else {
-            Err(anyhow::anyhow!(
-                "Expected AggregatedPubkey response, got: {:?}",
-                response
-            ))
-        }
+        trace!(
+            "Obtained aggregated key: {:?}",
+            aggregated_pubkey.to_string()
+        );
+        Ok(aggregated_pubkey)
     }
 
     pub async fn get_protocol_visualization(
@@ -114,22 +124,13 @@ impl BitvmxService {
         program_id: Uuid,
     ) -> Result<String, anyhow::Error> {
         info!("Get protocol visualization from BitVMX");
-        let response = self
+        let request = requests::GetProtocolVisualization { program_id };
+        let visualization = self
             .rpc_client
-            .send_request(IncomingBitVMXApiMessages::GetProtocolVisualization(
-                program_id,
-            ))
+            .send_typed(&request, Self::query_options())
             .await?;
-        // TODO add back UUID
-        if let OutgoingBitVMXApiMessages::ProtocolVisualization(_uuid, visualization) = response {
-            info!("Obtained protocol visualization: {:?}", visualization);
-            Ok(visualization)
-        } else {
-            Err(anyhow::anyhow!(
-                "Expected ProtocolVisualization response, got: {:?}",
-                response
-            ))
-        }
+        info!("Obtained protocol visualization: {:?}", visualization);
+        Ok(visualization)
     }
 
     #[instrument(skip(self))]
@@ -138,22 +139,11 @@ impl BitvmxService {
             .get_wallet_address()
             .await?
             .ok_or(anyhow::anyhow!("Wallet address not found"))?;
-        let response = self
-            .rpc_client
-            .send_request(IncomingBitVMXApiMessages::GetFundingBalance(Uuid::new_v4()))
-            .await?;
-
-        if let OutgoingBitVMXApiMessages::FundingBalance(_uuid, balance) = response {
-            Ok(WalletBalance {
-                address: address.to_string(),
-                balance,
-            })
-        } else {
-            Err(anyhow::anyhow!(
-                "Expected Funding Address response, got: {:?}",
-                response
-            ))
-        }
+        let balance = self.get_funding_balance().await?;
+        Ok(WalletBalance {
+            address: address.to_string(),
+            balance,
+        })
     }
 
     #[instrument(skip(self))]
@@ -161,24 +151,14 @@ impl BitvmxService {
         &self,
         destination: &Destination,
     ) -> Result<(Uuid, Txid), anyhow::Error> {
-        let response = self
-            .rpc_client
-            .send_request(IncomingBitVMXApiMessages::SendFunds(
-                Uuid::new_v4(),
-                destination.clone(),
-                None, // fee rate not needed for regtest
-            ))
+        let request = requests::SendFunds {
+            id: Uuid::new_v4(),
+            destination: destination.clone(),
+        };
+        self.rpc_client
+            .send_typed(&request, RequestOptions::timeout(DISPATCH_TIMEOUT))
             .await
-            .map_err(|e| anyhow::anyhow!("Failed to send funds: {e:?}"))?;
-
-        if let OutgoingBitVMXApiMessages::FundsSent(uuid, txid) = response {
-            Ok((uuid, txid))
-        } else {
-            Err(anyhow::anyhow!(
-                "Expected Funds Sent response, got: {:?}",
-                response
-            ))
-        }
+            .map_err(|e| anyhow::anyhow!("Failed to send funds: {e:?}"))
     }
 
     pub async fn send_funds_wait_confirmation(
@@ -200,13 +180,11 @@ impl BitvmxService {
         );
         let response = self
             .rpc_client
-            .wait_for_response(correlation_id)
+            .wait_for_response_with(correlation_id, RequestOptions::timeout(TRANSACTION_TIMEOUT))
             .await
             .map_err(|e| anyhow::anyhow!("Failed to wait for transaction response: {e:?}"))?;
 
-        let (transaction_status, _) = Self::transaction_response(response, None)?;
-
-        Ok(transaction_status)
+        Ok(requests::decode_transaction(response, None)?)
     }
 
     pub async fn wait_transaction_by_name_response(
@@ -225,17 +203,94 @@ impl BitvmxService {
         Ok((name.to_string(), tx_status))
     }
 
+    /// Follow every transaction of the program seen on chain, whatever its name
+    pub async fn subscribe_program(&self, program_id: Uuid) -> rpc::ProgramSubscription {
+        self.rpc_client.subscribe(program_id).await
+    }
+
+    /// Wait until the transaction is seen on chain.
+    /// Unlike [`Self::wait_transaction_by_name_response`] it keeps waiting when the RPC request times out.
+    pub async fn watch_transaction_by_name(
+        &self,
+        program_id: Uuid,
+        name: &str,
+    ) -> Result<(String, TransactionStatus), anyhow::Error> {
+        let correlation_id = rpc::tx_name_to_correlation_id(&program_id, name);
+        loop {
+            match self
+                .rpc_client
+                .wait_for_response_with(
+                    correlation_id.clone(),
+                    RequestOptions::timeout(TRANSACTION_TIMEOUT),
+                )
+                .await
+            {
+                Ok(response) => {
+                    let transaction_status = requests::decode_transaction(response, None)?;
+                    return Ok((name.to_string(), transaction_status));
+                }
+                Err(e) if e.is::<rpc::RequestTimeout>() => {
+                    trace!("Transaction {name} not seen yet, keep waiting");
+                }
+                Err(e) => {
+                    return Err(anyhow::anyhow!(
+                        "Failed to wait for transaction {name}: {e:?}"
+                    ))
+                }
+            }
+        }
+    }
+
     pub async fn get_transaction(&self, txid: String) -> Result<TransactionStatus, anyhow::Error> {
-        let response = self
-            .rpc_client
-            .send_request(IncomingBitVMXApiMessages::GetTransaction(
-                Uuid::new_v4(),
-                Txid::from_str(&txid)?,
-            ))
-            .await?;
+        let request = requests::GetTransaction {
+            id: Uuid::new_v4(),
+            txid: Txid::from_str(&txid)?,
+        };
+        self.rpc_client
+            .send_typed(&request, Self::query_options())
+            .await
+    }
 
-        let (transaction_status, _) = Self::transaction_response(response, None)?;
-        Ok(transaction_status)
+    /// Status of a program transaction on chain, None while it is not mined or was orphaned
+    pub async fn find_transaction_by_name(
+        &self,
+        program_id: Uuid,
+        name: &str,
+    ) -> Result<Option<TransactionStatus>, anyhow::Error> {
+        let request = requests::GetTransactionInfoByName {
+            program_id,
+            tx_name: name.to_string(),
+        };
+        let transaction = match self
+            .rpc_client
+            .send_typed(&request, Self::query_options())
+            .await
+        {
+            Ok(transaction) => transaction,
+            Err(e) if matches!(e.downcast_ref(), Some(BitvmxError::NotFound(..))) => {
+                return Ok(None)
+            }
+            Err(e) => return Err(anyhow::anyhow!("Failed to get transaction {name}: {e:?}")),
+        };
+
+        let request = requests::GetTransaction {
+            id: Uuid::new_v4(),
+            txid: transaction.compute_txid(),
+        };
+        match self
+            .rpc_client
+            .send_typed(&request, Self::query_options())
+            .await
+        {
+            Ok(tx_status) if matches!(tx_status.status, TransactionBlockchainStatus::Orphan) => {
+                Ok(None)
+            }
+            Ok(tx_status) => Ok(Some(tx_status)),
+            Err(e) if matches!(e.downcast_ref(), Some(BitvmxError::NotFound(..))) => Ok(None),
+            Err(e) => Err(anyhow::anyhow!(
+                "Failed to get status of transaction {name}: {e:?}"
+            )),
+        }
     }
 
     pub async fn set_variable(
@@ -245,16 +300,37 @@ impl BitvmxService {
         value: VariableTypes,
     ) -> Result<(), anyhow::Error> {
         self.rpc_client
-            .send_fire_and_forget(IncomingBitVMXApiMessages::SetVar(
+            .send_fire_and_forget(&requests::SetVar {
                 program_id,
-                key.to_string(),
+                key: key.to_string(),
                 value,
-            ))
+            })
             .await?;
 
         Ok(())
     }
 
+    /// Program variable as BitVMX has it, None when it is not set
+    pub async fn get_variable(
+        &self,
+        program_id: Uuid,
+        key: &str,
+    ) -> Result<Option<VariableTypes>, anyhow::Error> {
+        let request = requests::GetVar {
+            program_id,
+            key: key.to_string(),
+        };
+        match self
+            .rpc_client
+            .send_typed(&request, Self::query_options())
+            .await
+        {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if matches!(e.downcast_ref(), Some(BitvmxError::NotFound(..))) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
     pub async fn program_setup(
         &self,
         program_id: Uuid,
@@ -262,34 +338,23 @@ impl BitvmxService {
         participants: Vec<BitVMXP2PAddress>,
         leader_idx: u16,
     ) -> Result<Uuid, anyhow::Error> {
-        let response = self
-            .rpc_client
-            .send_request(IncomingBitVMXApiMessages::Setup(
-                program_id,
-                program_type.to_string(),
-                participants,
-                leader_idx,
-            ))
+        let request = requests::Setup {
+            program_id,
+            program_type: program_type.to_string(),
+            participants,
+            leader_idx,
+        };
+        self.rpc_client
+            .send_typed(&request, RequestOptions::timeout(SETUP_TIMEOUT))
             .await?;
-
-        if let OutgoingBitVMXApiMessages::SetupCompleted(uuid) = response {
-            if uuid != program_id {
-                return Err(anyhow::anyhow!(
-                    "Expected SetupCompleted response with program ID: {:?}, got: {:?}",
-                    program_id,
-                    uuid
-                ));
-            }
-        } else {
-            return Err(anyhow::anyhow!(
-                "Expected SetupCompleted response, got: {:?}",
-                response
-            ));
-        }
-
         Ok(program_id)
     }
 
+    /// Get the current block height
+    pub async fn get_block_height(&self) -> Result<u32, anyhow::Error> {
+        self.bitcoin_service.get_block_height().await
+    }
+
     pub fn protocol_cost(&self) -> u64 {
         bitvmx_client::program::protocols::dispute::protocol_cost()
     }
@@ -298,17 +363,10 @@ impl BitvmxService {
         &self,
         program_id: Uuid,
     ) -> Result<(String, TransactionStatus), anyhow::Error> {
-        let tx_name = dispute::START_CH.to_string();
         // Dispatch the start challenge transaction
-        let response = self
-            .rpc_client
-            .send_request(IncomingBitVMXApiMessages::DispatchTransactionName(
-                program_id,
-                tx_name.clone(),
-            ))
+        let (transaction_status, tx_name) = self
+            .send_transaction_by_name(program_id, dispute::START_CH)
             .await?;
-
-        let (transaction_status, _) = Self::transaction_response(response, Some(&tx_name))?;
         Ok((tx_name, transaction_status))
     }
 
@@ -337,40 +395,6 @@ impl BitvmxService {
         .await
     }
 
-    fn transaction_response(
-        response: OutgoingBitVMXApiMessages,
-        tx_name: Option<&str>,
-    ) -> Result<(TransactionStatus, Option<String>), anyhow::Error> {
-        match response {
-            OutgoingBitVMXApiMessages::Transaction(_uuid, tx_status, name) => {
-                if let Some(tx_name) = tx_name {
-                    match name {
-                        Some(name) => {
-                            if name != tx_name {
-                                return Err(anyhow::anyhow!(
-                                    "Expected Transaction response with name: {:?}, got: {:?}",
-                                    tx_name,
-                                    name
-                                ));
-                            }
-                            Ok((tx_status, Some(name)))
-                        }
-                        None => Err(anyhow::anyhow!(
-                            "Expected Transaction response with name: {:?}, got None",
-                            tx_name
-                        )),
-                    }
-                } else {
-                    Ok((tx_status, name))
-                }
-            }
-            _ => Err(anyhow::anyhow!(
-                "Expected Transaction response, got: {:?}",
-                response
-            )),
-        }
-    }
-
     /// Send the transaction by name
     pub async fn send_transaction_by_name(
         &self,
@@ -378,15 +402,14 @@ impl BitvmxService {
         tx_name: &str,
     ) -> Result<(TransactionStatus, String), anyhow::Error> {
         // Dispatch the transaction by name
-        let response = self
+        let request = requests::DispatchTransactionName {
+            program_id,
+            tx_name: tx_name.to_string(),
+        };
+        let transaction_status = self
             .rpc_client
-            .send_request(IncomingBitVMXApiMessages::DispatchTransactionName(
-                program_id,
-                tx_name.to_string(),
-            ))
+            .send_typed(&request, RequestOptions::timeout(DISPATCH_TIMEOUT))
             .await?;
-
-        let (transaction_status, _) = Self::transaction_response(response, Some(tx_name))?;
         Ok((transaction_status, tx_name.to_string()))
     }
 
@@ -398,74 +421,118 @@ impl BitvmxService {
     /// Get the funding address
     #[instrument(skip(self))]
     pub async fn get_funding_address(&self) -> Result<Address, anyhow::Error> {
-        let response = self
+        let request = requests::GetFundingAddress { id: Uuid::new_v4() };
+        let address = self
             .rpc_client
-            .send_request(IncomingBitVMXApiMessages::GetFundingAddress(Uuid::new_v4()))
+            .send_typed(&request, Self::query_options())
             .await?;
-        if let OutgoingBitVMXApiMessages::FundingAddress(_uuid, address) = response {
-            Ok(address.assume_checked())
-        } else {
-            Err(anyhow::anyhow!(
-                "Expected Funding Address response, got: {:?}",
-                response
-            ))
-        }
+        Ok(address.assume_checked())
     }
 
     /// Get the funding balance
     #[instrument(skip(self))]
     pub async fn get_funding_balance(&self) -> Result<u64, anyhow::Error> {
-        let response = self
-            .rpc_client
-            .send_request(IncomingBitVMXApiMessages::GetFundingBalance(Uuid::new_v4()))
-            .await?;
-
-        match response {
-            OutgoingBitVMXApiMessages::FundingBalance(_uuid, balance) => Ok(balance),
-            OutgoingBitVMXApiMessages::WalletNotReady(uuid) => Err(anyhow::anyhow!(
-                "Get balance: Wallet not ready correlation id: {:?}",
-                uuid
-            )),
-            OutgoingBitVMXApiMessages::WalletError(uuid, error) => Err(anyhow::anyhow!(
-                "Get balance: Wallet error correlation id: {:?}, error: {:?}",
-                uuid,
-                error
-            )),
-            _ => Err(anyhow::anyhow!(
-                "Get balance: Expected Funding Balance response, got: {:?}",
-                response
-            )),
-        }
+        let request = requests::GetFundingBalance { id: Uuid::new_v4() };
+        self.rpc_client
+            .send_typed(&request, Self::query_options())
+            .await
+            .map_err(|e| anyhow::anyhow!("Get balance: {e}"))
     }
 
     #[instrument(skip(self))]
     pub async fn generate_new_pub_key(&self) -> Result<(Uuid, PublicKey), anyhow::Error> {
-        let pub_key_id = Uuid::new_v4();
-        let response = self
+        let request = requests::GetPubKey {
+            id: Uuid::new_v4(),
+            new: true,
+        };
+        let pub_key = self
             .rpc_client
-            .send_request(IncomingBitVMXApiMessages::GetPubKey(pub_key_id, true))
+            .send_typed(&request, RequestOptions::timeout(QUERY_TIMEOUT))
             .await
             .map_err(|e| anyhow::anyhow!("Failed to get pub key: {e:?}"))?;
+        Ok((request.id, pub_key))
+    }
+
+    /// Get the pub key already generated in BitVMX for the given id
+    /// Returns None when BitVMX does not know the id
+    #[instrument(skip(self))]
+    pub async fn get_pub_key_by_id(
+        &self,
+        pub_key_id: Uuid,
+    ) -> Result<Option<PublicKey>, anyhow::Error> {
+        let request = requests::GetPubKey {
+            id: pub_key_id,
+            new: false,
+        };
+        match self
+            .rpc_client
+            .send_typed(&request, Self::query_options())
+            .await
+        {
+            Ok(pub_key) => Ok(Some(pub_key)),
+            Err(e) if matches!(e.downcast_ref(), Some(BitvmxError::NotFound(..))) => Ok(None),
+            Err(e) => Err(anyhow::anyhow!("Failed to get pub key: {e:?}")),
+        }
+    }
 
-        if let OutgoingBitVMXApiMessages::PubKey(_uuid, pub_key) = response {
-            Ok((pub_key_id, pub_key))
-        } else {
-            return Err(anyhow::anyhow!(
-                "Expected Operator PubKey response, got: {:?}",
-                response
-            ));
+    /// Get the status of the initial setup steps
+    pub async fn setup_status(&self) -> SetupStatus {
+        let info = self.bitvmx_store.get_info().await;
+        SetupStatus {
+            p2p_address: info.p2p_address.is_some(),
+            wallet_address: info.wallet_address.is_some(),
+            wallet_funded: info.wallet_funded,
+            operator_key: info.pub_key.is_some(),
+            funding_key: info.funding_key.is_some(),
+            funding_utxo: info.funding_utxo.is_some(),
+            completed: info.setup_completed,
         }
     }
 
     // ----- Start internal methods -----
 
-    /// Update P2P address
+    /// Check that a stored key is still known by BitVMX
+    async fn stored_key_exists(
+        &self,
+        id: Option<Uuid>,
+        key: Option<&String>,
+    ) -> Result<bool, anyhow::Error> {
+        let (Some(id), Some(key)) = (id, key) else {
+            return Ok(false);
+        };
+        match self.get_pub_key_by_id(id).await? {
+            Some(pub_key) if pub_key.to_string() == *key => Ok(true),
+            Some(pub_key) => {
+                warn!("Stored key {key} does not match BitVMX key {pub_key} for id {id}");
+                Ok(false)
+            }
+            None => {
+                warn!("Stored key {key} not found in BitVMX for id {id}");
+                Ok(false)
+            }
+        }
+    }
+
+    /// Update wallet address and fund it if needed
     #[instrument(skip(self))]
     async fn set_wallet_address(&self) -> Result<(), anyhow::Error> {
         let wallet_address: Address = self.get_funding_address().await?;
         self.bitvmx_store
             .set_wallet_address(wallet_address.clone())
             .await?;
+        trace!("Updated wallet address in store");
+
+        // Funds survive a restart, only mine when the wallet is missing them
+        if self.bitvmx_store.get_info().await.wallet_funded {
+            match self.get_funding_balance().await {
+                Ok(balance) if balance >= WALLET_MIN_BALANCE => {
+                    debug!("Wallet already funded with balance: {:?}", balance);
+                    return Ok(());
+                }
+                Ok(balance) => debug!("Wallet balance {balance} is too low, funding it again"),
+                Err(e) => debug!("Could not get the wallet balance, funding it again: {e:?}"),
+            }
+        }
 
         debug!("Adding funds for wallet address: {:?}", wallet_address);
 
@@ -478,27 +545,18 @@ impl BitvmxService {
         let balance = self.get_funding_balance().await?;
         info!("Funding balance: {:?}", balance);
 
-        if balance < 100_000_000 {
+        if balance < WALLET_MIN_BALANCE {
             return Err(anyhow::anyhow!("Funding balance is less than 1 BTC"));
         }
+        self.bitvmx_store.set_wallet_funded().await?;
 
-        trace!("Updated wallet address in store");
         Ok(())
     }
 
     pub async fn get_comm_info(&self) -> Result<BitVMXP2PAddress, anyhow::Error> {
-        let response = self
-            .rpc_client
-            .send_request(IncomingBitVMXApiMessages::GetCommInfo())
-            .await?;
-        if let OutgoingBitVMXApiMessages::CommInfo(comm_info) = response {
-            Ok(comm_info)
-        } else {
-            Err(anyhow::anyhow!(
-                "Expected Comm Info response, got: {:?}",
-                response
-            ))
-        }
+        self.rpc_client
+            .send_typed(&requests::GetCommInfo, Self::query_options())
+            .await
     }
 
     /// Update P2P address
@@ -517,34 +575,86 @@ impl BitvmxService {
         Ok(())
     }
 
-    /// Update pub key
+    /// Update pub key, reusing the stored one if BitVMX still has it
     #[instrument(skip(self))]
     async fn set_pub_key(&self) -> Result<(), anyhow::Error> {
+        let info = self.bitvmx_store.get_info().await;
+        if self
+            .stored_key_exists(info.pub_key_id, info.pub_key.as_ref())
+            .await?
+        {
+            debug!("Reusing operator pub key: {:?}", info.pub_key);
+            return Ok(());
+        }
+
         trace!("Create operator key from BitVMX");
-        let (_uuid, pub_key) = self.generate_new_pub_key().await?;
+        let (uuid, pub_key) = self.generate_new_pub_key().await?;
         info!(
             "Operator compressed {} public key: {:?}",
             pub_key.to_string(),
             pub_key
         );
-        self.bitvmx_store.set_pub_key(pub_key).await?;
+        self.bitvmx_store.set_pub_key(uuid, pub_key).await?;
 
         trace!("Updated pub key in store");
         Ok(())
     }
 
-    /// Update funding key
+    /// Update funding key, reusing the stored one if BitVMX still has it
     #[instrument(skip(self))]
     async fn set_funding_key(&self) -> Result<(), anyhow::Error> {
+        let info = self.bitvmx_store.get_info().await;
+        if self
+            .stored_key_exists(info.funding_key_id, info.funding_key.as_ref())
+            .await?
+        {
+            debug!("Reusing funding key: {:?}", info.funding_key);
+            return Ok(());
+        }
+
         trace!("Create funding key for speedups from BitVMX");
-        let (_uuid, funding_pubkey) = self.generate_new_pub_key().await?;
+        let (uuid, funding_pubkey) = self.generate_new_pub_key().await?;
         info!(
             "Funding  compressed {} public key: {:?}",
             funding_pubkey.to_string(),
             funding_pubkey
         );
-        self.bitvmx_store.set_funding_key(funding_pubkey).await?;
+        self.bitvmx_store
+            .set_funding_key(uuid, funding_pubkey)
+            .await?;
         trace!("Updated funding key in store");
+        Ok(())
+    }
+
+    /// Send funds to the funding key and register the UTXO in BitVMX, unless it was already done
+    #[instrument(skip(self))]
+    async fn set_funding_utxo(&self) -> Result<(), anyhow::Error> {
+        let info = self.bitvmx_store.get_info().await;
+        let funding_key = info
+            .funding_key
+            .ok_or(anyhow::anyhow!("Funding key not found"))?;
+        let funding_pubkey = PublicKey::from_str(&funding_key)?;
+
+        // The stored UTXO is only valid for the current funding key and while it is unspent
+        if let Some(funding_utxo) = info
+            .funding_utxo
+            .filter(|funding_utxo| funding_utxo.pub_key == funding_key)
+        {
+            let txid = Txid::from_str(&funding_utxo.txid)?;
+            if self
+                .bitcoin_service
+                .is_unspent(txid, funding_utxo.vout)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to check the funding utxo: {e:?}"))?
+            {
+                debug!("Reusing funding utxo: {:?}", funding_utxo);
+                // BitVMX may have lost it, e.g. its storage was reset
+                return self
+                    .register_funding_utxo(&funding_utxo, funding_pubkey)
+                    .await;
+            }
+            warn!("Stored funding utxo was spent, funding again: {funding_utxo:?}");
+        }
 
         // Send 1 BTC to the funding key
         let amount = 100_000_000; // 1 BTC
@@ -558,46 +668,123 @@ impl BitvmxService {
         // Wait for the transaction confirmation reponse to use the utxo
         let tx_status = self.wait_transaction_response(uuid.to_string()).await?;
 
+        let funding_utxo = FundingUtxo {
+            txid: tx_status.tx_id.to_string(),
+            vout: 0,
+            amount,
+            pub_key: funding_key,
+        };
+        self.register_funding_utxo(&funding_utxo, funding_pubkey)
+            .await?;
+        self.bitvmx_store.set_funding_utxo(funding_utxo).await?;
+
+        trace!("Updated funding utxo in store");
+        Ok(())
+    }
+
+    /// Register the funding UTXO in BitVMX
+    async fn register_funding_utxo(
+        &self,
+        funding_utxo: &FundingUtxo,
+        funding_pubkey: PublicKey,
+    ) -> Result<(), anyhow::Error> {
         self.rpc_client
-            .send_fire_and_forget(IncomingBitVMXApiMessages::SetFundingUtxo(
-                bitvmx_client::protocol_builder::types::Utxo {
-                    txid: tx_status.tx_id,
-                    vout: 0,
-                    amount,
+            .send_fire_and_forget(&requests::SetFundingUtxo {
+                utxo: bitvmx_client::protocol_builder::types::Utxo {
+                    txid: Txid::from_str(&funding_utxo.txid)?,
+                    vout: funding_utxo.vout,
+                    amount: funding_utxo.amount,
                     pub_key: funding_pubkey,
                 },
-            ))
+            })
             .await
             .map_err(|e| anyhow::anyhow!("Failed to set funding utxo: {e:?}"))?;
-
-        trace!("Updated funding key in store");
         Ok(())
     }
 
     /// Setup BitVMX
+    /// Every step checks what a previous run already did, so it is safe to call on each start
     #[instrument(skip(self))]
     pub async fn initial_setup(&self) -> Result<(), anyhow::Error> {
         debug!("Get BitVMX info and initial keys setup");
+        self.bitvmx_store.set_setup_completed(false).await?;
 
         self.set_p2p_address().await?;
 
         // Set wallet address
         self.set_wallet_address().await?;
 
-        // If keys do not exist, setup keys
-        if self.get_pub_key().await?.is_none() {
-            debug!("No keys found, creating them");
-            // Set operator pub key
-            self.set_pub_key().await?;
+        // Set operator pub key
+        self.set_pub_key().await?;
 
-            // Set funding key
-            self.set_funding_key().await?;
-        } else {
-            return Err(anyhow::anyhow!("Keys already exist!!! cannot setup"));
-        }
+        // Set funding key and its utxo
+        self.set_funding_key().await?;
+        self.set_funding_utxo().await?;
 
+        self.bitvmx_store.set_setup_completed(true).await?;
         Ok(())
     }
 
     // ----- End internal methods -----
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::fake_bitvmx::FAKE_PUB_KEY;
+    use crate::test_support::{FakeBitvmx, FakeChain};
+    use bitvmx_client::types::IncomingBitVMXApiMessages;
+    use tokio::sync::broadcast;
+
+    #[tokio::test]
+    async fn test_set_funding_utxo_reuses_it_while_unspent() {
+        let chain = FakeChain::new();
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let bitvmx = FakeBitvmx::new(&chain);
+        let (rpc_client, _listener_task) = RpcClient::with_transport(bitvmx.clone(), &shutdown_tx);
+        let service = BitvmxService::new(rpc_client, chain.clone(), Arc::new(BitvmxStore::new()));
+        service
+            .bitvmx_store
+            .set_funding_key(Uuid::new_v4(), PublicKey::from_str(FAKE_PUB_KEY).unwrap())
+            .await
+            .unwrap();
+        let registered_utxos = || {
+            bitvmx
+                .received()
+                .into_iter()
+                .filter_map(|message| match message {
+                    IncomingBitVMXApiMessages::SetFundingUtxo(utxo) => Some(utxo.txid),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let funds_sent = || {
+            bitvmx
+                .received()
+                .iter()
+                .filter(|message| matches!(message, IncomingBitVMXApiMessages::SendFunds(..)))
+                .count()
+        };
+
+        service.set_funding_utxo().await.unwrap();
+        let funding_txid = registered_utxos()[0];
+        assert_eq!(funds_sent(), 1);
+
+        // Unspent, registered again without sending funds
+        service.set_funding_utxo().await.unwrap();
+        assert_eq!(registered_utxos(), vec![funding_txid, funding_txid]);
+        assert_eq!(funds_sent(), 1);
+
+        // Spent, funded again
+        chain.spend(funding_txid, 0);
+        service.set_funding_utxo().await.unwrap();
+        assert_eq!(funds_sent(), 2);
+        let registered = registered_utxos();
+        assert_eq!(registered.len(), 3);
+        assert_ne!(registered[2], funding_txid);
+        let stored = service.bitvmx_store.get_info().await.funding_utxo.unwrap();
+        assert_eq!(stored.txid, registered[2].to_string());
+
+        let _ = shutdown_tx.send(());
+    }
+}
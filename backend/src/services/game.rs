@@ -0,0 +1,1980 @@
+use crate::config::GameConfig;
+use crate::games::dispute::{
+    participant_role, payout_tx_name, timeout_steps, timeout_tx_name, timeout_tx_names,
+};
+use crate::games::program::{InputOwner, InvalidProgramInput, ProgramDefinition};
+use crate::games::{Game, GamePhase, GameStatus, GameStep};
+use crate::models::{
+    DisputeParameters, DisputeParametersMismatch, GameOutcome, GameReason, GameRecord,
+    GameSetupRequest, InvalidDisputeParameters, P2PAddress, PlayerRole, Utxo,
+};
+use crate::services::{BitvmxService, OperatorRegistry, WorkerService};
+use crate::stores::{GameStore, TypedGameStore};
+use crate::utils;
+use anyhow::Context;
+use bitvmx_client::bitcoin::{OutPoint, PublicKey};
+use bitvmx_client::bitcoin_coordinator::{TransactionBlockchainStatus, TransactionStatus};
+use bitvmx_client::bitvmx_wallet::wallet::Destination;
+use bitvmx_client::program::participant::{CommsAddress as BitVMXP2PAddress, ParticipantRole};
+use bitvmx_client::program::protocols::dispute;
+use bitvmx_client::program::variables::VariableTypes;
+use bitvmx_client::protocol_builder::types::OutputType;
+use bitvmx_client::types::OutgoingBitVMXApiMessages;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{debug, error, info, instrument, trace, warn};
+use uuid::Uuid;
+
+/// How often a dispute wait checks if the game was decided by another job
+const GAME_STATUS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Name the payout of a won bet is recorded with
+const PAYOUT_TX_NAME: &str = "PAYOUT";
+
+/// Dispute transactions the reconciliation looks for on chain
+fn expected_dispute_txs(submit_input_index: u32) -> Vec<String> {
+    let mut tx_names = vec![dispute::START_CH.to_string()];
+    for (_, expected, _) in timeout_steps(submit_input_index) {
+        tx_names.push(expected);
+    }
+    tx_names.extend([
+        dispute::COMMITMENT.to_string(),
+        dispute::EXECUTE.to_string(),
+        dispute::CHALLENGE.to_string(),
+    ]);
+    tx_names.extend(timeout_tx_names(submit_input_index));
+    for role in [ParticipantRole::Prover, ParticipantRole::Verifier] {
+        tx_names.push(dispute::action_wins(&role, 1));
+    }
+    tx_names
+}
+
+/// Timeout transaction a player can dispatch once the block height reaches the deadline
+#[derive(Debug, Clone, PartialEq)]
+struct PendingTimeout {
+    tx_name: String,
+    deadline: u32,
+}
+
+/// The transaction is mined and was not orphaned
+fn is_confirmed(tx_status: &TransactionStatus) -> bool {
+    tx_status.confirmations > 0 && tx_status.status != TransactionBlockchainStatus::Orphan
+}
+
+/// Block height of a recorded transaction, None while it is not mined
+fn tx_height(tx_status: &serde_json::Value) -> Option<u32> {
+    tx_status["block_info"]["height"]
+        .as_u64()
+        .and_then(|height| u32::try_from(height).ok())
+}
+
+/// Find the latest dispute step where the other player has to answer `role`.
+/// The timeout transaction is named after the missing transaction.
+fn pending_timeout(
+    txs: &HashMap<String, serde_json::Value>,
+    role: &ParticipantRole,
+    timelock_blocks: u32,
+    submit_input_index: u32,
+) -> Option<PendingTimeout> {
+    timeout_steps(submit_input_index)
+        .into_iter()
+        .rev()
+        .find(|(seen, expected, _)| txs.contains_key(seen) && !txs.contains_key(expected))
+        .filter(|(_, _, expected_from)| expected_from != role)
+        .and_then(|(seen, expected, _)| {
+            let timeout_tx_name = timeout_tx_name(&expected);
+            if txs.contains_key(&timeout_tx_name) {
+                return None;
+            }
+            Some(PendingTimeout {
+                tx_name: timeout_tx_name,
+                deadline: tx_height(&txs[&seen])? + timelock_blocks,
+            })
+        })
+}
+
+/// The game is waiting for dispute transactions
+fn is_in_dispute(phase: &GamePhase) -> bool {
+    matches!(phase, GamePhase::StartGame | GamePhase::SubmitGameData)
+}
+
+/// The game can still move forward with the dispute transactions seen on chain
+fn needs_reconciliation(phase: &GamePhase) -> bool {
+    matches!(
+        phase,
+        GamePhase::StartGame
+            | GamePhase::SubmitGameData
+            | GamePhase::GameComplete { .. }
+            | GamePhase::TransferBetFunds
+    )
+}
+
+/// Outcome of the game for the player with `role` when `winner` won the dispute
+fn game_outcome(role: &PlayerRole, winner: &ParticipantRole) -> GameOutcome {
+    if *winner == participant_role(role) {
+        GameOutcome::Win
+    } else {
+        GameOutcome::Lose
+    }
+}
+
+/// What the reconciliation of a game with the chain changed or found
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reconciliation<S> {
+    /// Dispute transactions seen on chain that were not recorded
+    pub filled_txs: Vec<String>,
+    /// Status the game moved to with the transactions seen on chain
+    pub advanced_to: Option<S>,
+    /// Recorded state that contradicts the chain
+    pub contradictions: Vec<String>,
+}
+
+impl<S> Default for Reconciliation<S> {
+    fn default() -> Self {
+        Self {
+            filled_txs: vec![],
+            advanced_to: None,
+            contradictions: vec![],
+        }
+    }
+}
+
+/// Game flow shared by every game: setup, funding, challenge, dispute waits and payout.
+/// What is specific to a game is delegated to `G`.
+#[derive(Debug)]
+pub struct GameService<G: Game> {
+    game: G,
+    game_store: TypedGameStore<G::Status>,
+    operators: Arc<OperatorRegistry>,
+    game_config: GameConfig,
+    /// Parsed program definitions by allowed name
+    program_definitions: Mutex<HashMap<String, Arc<ProgramDefinition>>>,
+}
+
+impl<G: Game> GameService<G> {
+    /// New GameService
+    pub fn new(
+        game: G,
+        game_store: Arc<dyn GameStore>,
+        operators: Arc<OperatorRegistry>,
+        game_config: GameConfig,
+    ) -> Self {
+        Self {
+            game,
+            game_store: TypedGameStore::new(game_store),
+            operators,
+            game_config,
+            program_definitions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Game played by this service
+    pub fn game(&self) -> &G {
+        &self.game
+    }
+
+    /// How often the unfinished games are reconciled with the chain
+    pub fn reconcile_interval(&self) -> Duration {
+        Duration::from_secs(self.game_config.reconcile_interval_secs)
+    }
+
+    /// Status of a game of this type
+    fn status(game: &GameRecord) -> Result<G::Status, anyhow::Error> {
+        game.status_as()
+    }
+
+    /// BitVMX service of the operator the game runs on, it fails while the operator is unhealthy
+    fn bitvmx(&self, game: &GameRecord) -> Result<Arc<BitvmxService>, anyhow::Error> {
+        Ok(self
+            .operators
+            .get_healthy(&game.operator)?
+            .bitvmx_service
+            .clone())
+    }
+
+    /// Get the current game
+    pub async fn get_current_game(&self) -> Result<Option<GameRecord>, anyhow::Error> {
+        let game = self
+            .game_store
+            .get_current_game(G::GAME_TYPE)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to get current game: {e:?}"))?;
+
+        Ok(game)
+    }
+
+    /// Get the game, games of other types are not found
+    pub async fn get_game(&self, id: Uuid) -> Result<Option<GameRecord>, anyhow::Error> {
+        let game = self
+            .game_store
+            .get_game(id)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to get game: {e:?}"))?;
+
+        Ok(game.filter(|game| game.game_type == G::GAME_TYPE))
+    }
+
+    /// List all the games of this type
+    pub async fn list_games(&self) -> Result<Vec<GameRecord>, anyhow::Error> {
+        let games = self
+            .game_store
+            .list_games()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to list games: {e:?}"))?;
+
+        Ok(games
+            .into_iter()
+            .filter(|game| game.game_type == G::GAME_TYPE)
+            .collect())
+    }
+
+    /// Setup the participants on the `operator` BitVMX node, the default operator when none is given
+    pub async fn setup_participants(
+        &self,
+        aggregated_id: Uuid,
+        leader_idx: u16,
+        participants_addresses: Vec<P2PAddress>,
+        participants_keys: Vec<String>,
+        role: PlayerRole,
+        operator: Option<&str>,
+    ) -> Result<(Uuid, PublicKey), anyhow::Error> {
+        let operator = self.operators.select(operator)?;
+        let bitvmx_service = &operator.bitvmx_service;
+
+        let p2p_addresses: Vec<BitVMXP2PAddress> = participants_addresses
+            .iter()
+            .map(|p2p| p2p.clone().into())
+            .collect();
+
+        // Validate the participants keys
+        let public_keys = participants_keys
+            .iter()
+            .map(|key| {
+                if key.is_empty() {
+                    return Err(anyhow::anyhow!("Participants key cannot be empty"));
+                }
+                PublicKey::from_str(key).map_err(|_| anyhow::anyhow!("Invalid participants key"))
+            })
+            .collect::<Result<Vec<PublicKey>, anyhow::Error>>()?;
+
+        // Create the aggregated key
+        let aggregated_key = bitvmx_service
+            .create_agregated_key(aggregated_id, p2p_addresses, Some(public_keys), leader_idx)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to create aggregated key: {e:?}"))?;
+
+        debug!("Aggregated key created: {:?}", aggregated_key);
+
+        // Create the program id
+        let program_id = Uuid::new_v5(&Uuid::NAMESPACE_OID, aggregated_id.as_bytes());
+        debug!("🎉 Setting up game with program id: {:?}", program_id);
+
+        // Setup the game
+        self.game_store
+            .setup_participants(
+                program_id,
+                G::GAME_TYPE,
+                &operator.name,
+                aggregated_id,
+                participants_addresses,
+                participants_keys,
+                aggregated_key,
+                role,
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to setup game: {e:?}"))?;
+
+        Ok((program_id, aggregated_key))
+    }
+
+    /// Place the bet
+    pub async fn place_bet(
+        &self,
+        program_id: Uuid,
+        amount: u64,
+    ) -> Result<GameRecord, anyhow::Error> {
+        // Get the game
+        let game = self
+            .get_game(program_id)
+            .await?
+            .ok_or(anyhow::anyhow!("Game not found"))?;
+        let bitvmx_service = self.bitvmx(&game)?;
+
+        if game.role == PlayerRole::Player2 {
+            let game = self
+                .game_store
+                .apply_event(program_id, GameStep::PlaceBet.into())
+                .await
+                .context("Failed to update game state")?;
+            return Ok(game);
+        }
+
+        // Check the funding can be saved before sending any funds
+        Self::status(&game)?.transition(&game.role, GameStep::SaveFundingUtxos.into())?;
+
+        // Get the aggregated key
+        let aggregated_key = game.bitvmx_program_properties.aggregated_key;
+
+        // Get the protocol fees amount
+        let protocol_amount = bitvmx_service.protocol_cost();
+        // Preparer the utxo destination for the protocol fees
+        let protocol_destination = utils::protocol_destination(&aggregated_key, protocol_amount)?;
+
+        // Prepare the utxo destination for the bet
+        let bet_destination = utils::protocol_destination(&aggregated_key, amount)?;
+
+        // Send funds to cover protocol fees to the aggregated key
+        let (funding_uuid, funding_txid) = bitvmx_service
+            .send_funds(&Destination::Batch(vec![
+                protocol_destination,
+                bet_destination,
+            ]))
+            .await
+            .map_err(|e| anyhow::anyhow!(format!("Failed to send protocol funds: {e:?}")))?;
+        debug!(
+            "Sent {protocol_amount} satoshis to cover protocol fees and bet {} satoshis to the aggregated key txid: {:?} uuid: {:?}",
+            amount, funding_txid, funding_uuid
+        );
+
+        // Wait for the Transaction Status responses
+        debug!("Waiting for transaction status responses");
+        let funding_tx_status = bitvmx_service
+            .wait_transaction_response(funding_uuid.to_string())
+            .await
+            .map_err(|e| {
+                anyhow::anyhow!(format!(
+                    "Failed to wait for transaction status response: {e:?}"
+                ))
+            })?;
+
+        debug!(
+            "Received transaction status responses for correlation ids: {:?} and {:?}",
+            funding_uuid, funding_txid
+        );
+
+        if funding_tx_status.confirmations == 0 {
+            error!(
+                "Transaction {} not confirmed for correlation id: {:?}",
+                funding_txid, funding_uuid
+            );
+            return Err(anyhow::anyhow!("Transaction not confirmed"));
+        }
+        // Protcol cost transaction
+        self.game_store
+            .set_dispute_tx(
+                program_id,
+                dispute::EXTERNAL_START.to_string(),
+                funding_tx_status.clone(),
+            )
+            .await
+            .map_err(|e| {
+                anyhow::anyhow!(format!("Failed to set EXTERNAL_START dispute tx: {e:?}"))
+            })?;
+
+        // Player bet transaction
+        self.game_store
+            .set_dispute_tx(
+                program_id,
+                dispute::external_action(&ParticipantRole::Verifier, 1).to_string(), //EXTERNAL_ACTION
+                funding_tx_status,
+            )
+            .await
+            .map_err(|e| {
+                anyhow::anyhow!(format!("Failed to set EXTERNAL_START dispute tx: {e:?}"))
+            })?;
+
+        debug!("Protocol and bet transactions confirmed, marking funding UTXOs as mined");
+        let protocol_leaves = utils::protocol_scripts(&aggregated_key);
+
+        let protocol_output_type =
+            OutputType::taproot(protocol_amount, &aggregated_key, &protocol_leaves).map_err(
+                |e| {
+                    anyhow::anyhow!(format!(
+                        "Failed to obtain protocol output type from aggregated key: {e:?}"
+                    ))
+                },
+            )?;
+        let bet_output_type = OutputType::taproot(amount, &aggregated_key, &protocol_leaves)
+            .map_err(|e| {
+                anyhow::anyhow!(format!(
+                    "Failed to obtain protocol output type from aggregated key: {e:?}"
+                ))
+            })?;
+
+        let funding_protocol_utxo: Utxo = Utxo {
+            txid: funding_txid.to_string(),
+            vout: 0,
+            amount: protocol_amount,
+            output_type: serde_json::to_value(protocol_output_type).map_err(|e| {
+                anyhow::anyhow!(format!(
+                    "Failed to convert protocol output type to JSON: {e:?}"
+                ))
+            })?,
+        };
+        let funding_bet_utxo: Utxo = Utxo {
+            txid: funding_txid.to_string(),
+            vout: 1,
+            amount,
+            output_type: serde_json::to_value(bet_output_type).map_err(|e| {
+                anyhow::anyhow!(format!("Failed to convert bet output type to JSON: {e:?}"))
+            })?,
+        };
+
+        // Save the funding UTXOs of the game
+        self.game_store
+            .save_funding_utxos(
+                program_id,
+                funding_protocol_utxo.clone(),
+                funding_bet_utxo.clone(),
+            )
+            .await
+            .context("Failed to save my funding UTXO")?;
+
+        debug!("Saved my funding UTXOs of the game");
+
+        Ok(game)
+    }
+
+    /// Setup the funding UTXOs
+    pub async fn setup_funding_utxo(
+        &self,
+        program_id: Uuid,
+        funding_protocol_utxo: Utxo,
+        funding_bet_utxo: Utxo,
+    ) -> Result<(), anyhow::Error> {
+        let game = self
+            .get_game(program_id)
+            .await?
+            .ok_or(anyhow::anyhow!("Game not found"))?;
+        let bitvmx_service = self.bitvmx(&game)?;
+
+        // Save the funding UTXOs
+        self.game_store
+            .save_funding_utxos(
+                program_id,
+                funding_protocol_utxo.clone(),
+                funding_bet_utxo.clone(),
+            )
+            .await
+            .context("Failed to add funding UTXO")?;
+
+        // For now we use the same transaction for the protocol cost and the player bet with different vouts
+        if funding_protocol_utxo.txid != funding_bet_utxo.txid {
+            return Err(anyhow::anyhow!(
+                "Protocol and bet UTXOs should have the same transaction ID at this moment"
+            ));
+        }
+
+        // Get the funding transaction status
+        let funding_tx_status = bitvmx_service
+            .get_transaction(funding_protocol_utxo.txid)
+            .await
+            .map_err(|e| anyhow::anyhow!(format!("Failed to get transaction: {e:?}")))?;
+
+        // Protcol cost transaction
+        self.game_store
+            .set_dispute_tx(
+                program_id,
+                dispute::EXTERNAL_START.to_string(),
+                funding_tx_status.clone(),
+            )
+            .await
+            .map_err(|e| {
+                anyhow::anyhow!(format!("Failed to set EXTERNAL_START dispute tx: {e:?}"))
+            })?;
+
+        // Player bet transaction
+        self.game_store
+            .set_dispute_tx(
+                program_id,
+                dispute::external_action(&ParticipantRole::Verifier, 1).to_string(), //EXTERNAL_ACTION
+                funding_tx_status,
+            )
+            .await
+            .map_err(|e| {
+                anyhow::anyhow!(format!("Failed to set EXTERNAL_START dispute tx: {e:?}"))
+            })?;
+
+        Ok(())
+    }
+
+    /// Resolve the dispute parameters of a game from the request, the game and the game config.
+    /// Player 2 must send the parameters player 1 set up the game with, they must be the same.
+    pub fn dispute_parameters(
+        &self,
+        game: &GameRecord,
+        request: &GameSetupRequest<G::SetupData>,
+    ) -> Result<DisputeParameters, anyhow::Error> {
+        let program_definition = request
+            .program_definition
+            .clone()
+            .unwrap_or_else(|| self.game.program_definition());
+        let parameters = self.game_config.dispute_parameters(
+            request.timelock_blocks,
+            Some(program_definition),
+            request.leader_idx,
+        )?;
+
+        let participants = game.bitvmx_program_properties.participants_addresses.len();
+        if usize::from(parameters.leader_idx) >= participants {
+            return Err(InvalidDisputeParameters(format!(
+                "Leader index must be lower than the {participants} participants"
+            ))
+            .into());
+        }
+
+        let peer_parameters = match (&game.role, &request.peer_parameters) {
+            (PlayerRole::Player2, None) => {
+                return Err(InvalidDisputeParameters(
+                    "Player 2 must send the dispute parameters of player 1".to_string(),
+                )
+                .into())
+            }
+            (_, peer_parameters) => peer_parameters,
+        };
+        if let Some(peer_parameters) = peer_parameters {
+            if *peer_parameters != parameters {
+                return Err(DisputeParametersMismatch {
+                    local: parameters,
+                    peer: peer_parameters.clone(),
+                }
+                .into());
+            }
+        }
+
+        Ok(parameters)
+    }
+
+    /// Program definition file of the bitvmx-client, read and checked once per allowed name
+    fn program_definition(
+        &self,
+        program_definition: &str,
+    ) -> Result<Arc<ProgramDefinition>, anyhow::Error> {
+        // The allow-list is checked first so only allowed names are cached
+        let path = self
+            .game_config
+            .program_definition_path(program_definition)?;
+        if let Some(definition) = self
+            .program_definitions
+            .lock()
+            .unwrap()
+            .get(program_definition)
+        {
+            return Ok(definition.clone());
+        }
+
+        let definition = ProgramDefinition::load(&path)?;
+        let submit_input_index = self.game.submit_input_index();
+        if definition.inputs.get(submit_input_index as usize).is_none() {
+            return Err(anyhow::anyhow!(
+                "Program definition {program_definition} has no input {submit_input_index} for the answer"
+            ));
+        }
+        let definition = Arc::new(definition);
+        self.program_definitions
+            .lock()
+            .unwrap()
+            .insert(program_definition.to_string(), definition.clone());
+        Ok(definition)
+    }
+
+    /// Check BitVMX has the agreed dispute parameters, the variables are set without an answer
+    async fn check_dispute_variables(
+        &self,
+        bitvmx_service: &BitvmxService,
+        program_id: Uuid,
+        dispute_parameters: &DisputeParameters,
+    ) -> Result<(), anyhow::Error> {
+        let expected = [
+            (
+                "program_definition",
+                VariableTypes::String(dispute_parameters.program_definition.clone()),
+            ),
+            (
+                dispute::TIMELOCK_BLOCKS_KEY,
+                VariableTypes::Number(dispute_parameters.timelock_blocks),
+            ),
+        ];
+        for (key, expected) in expected {
+            let value = bitvmx_service.get_variable(program_id, key).await?;
+            if value.as_ref() != Some(&expected) {
+                return Err(anyhow::anyhow!(
+                    "BitVMX has {key} = {value:?} instead of {expected:?}"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Setup the game
+    #[instrument(name = "setup_game", skip(self, worker_service))]
+    pub async fn setup_game(
+        &self,
+        request: GameSetupRequest<G::SetupData>,
+        worker_service: Arc<WorkerService<G>>,
+    ) -> Result<DisputeParameters, anyhow::Error> {
+        let program_id = request.program_id;
+
+        // Get the game
+        let game = self
+            .get_game(program_id)
+            .await?
+            .ok_or(anyhow::anyhow!("Game not found"))?
+            .clone();
+        let bitvmx_service = self.bitvmx(&game)?;
+
+        // Check the game can be setup before sending anything to BitVMX
+        Self::status(&game)?.transition(&game.role, GameStep::SetupGame.into())?;
+
+        // Both players must agree on the dispute parameters before the program setup
+        let dispute_parameters = self.dispute_parameters(&game, &request)?;
+
+        // Check the inputs against the program definition before anything reaches BitVMX
+        let program = self.program_definition(&dispute_parameters.program_definition)?;
+        let owners = [
+            InputOwner::Const,
+            InputOwner::of(&participant_role(&game.role)),
+        ];
+        let inputs = self
+            .game
+            .setup_inputs(&game.role, &request.data)
+            .into_iter()
+            .map(|(index, values)| Ok((index, program.encode_input(index, &owners, &values)?)))
+            .collect::<Result<Vec<_>, InvalidProgramInput>>()?;
+
+        // Set all necesary program variables in BitVMX
+
+        // Set the program inputs of the game, e.g. the two numbers to sum
+        for (input_index, input) in inputs {
+            bitvmx_service
+                .set_program_input(program_id, input_index, input)
+                .await
+                .map_err(|e| anyhow::anyhow!(format!("Failed to set program input: {e:?}")))?;
+        }
+
+        // Set aggregated key
+        bitvmx_service
+            .set_variable(
+                program_id,
+                "aggregated",
+                VariableTypes::PubKey(game.bitvmx_program_properties.aggregated_key),
+            )
+            .await
+            .map_err(|e| {
+                anyhow::anyhow!(format!("Failed to set variable aggregated pubkey: {e:?}"))
+            })?;
+
+        // Set protocol cost utxo
+        let protocol_utxo = game
+            .bitvmx_program_properties
+            .funding_protocol_utxo
+            .ok_or(anyhow::anyhow!("Protocol UTXO not found"))?;
+
+        bitvmx_service
+            .set_variable(
+                program_id,
+                "utxo",
+                VariableTypes::Utxo(protocol_utxo.into()),
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!(format!("Failed to set variable protocol utxo: {e:?}")))?;
+
+        // Set bet utxo
+        let bet_utxo = game
+            .bitvmx_program_properties
+            .funding_bet_utxo
+            .ok_or(anyhow::anyhow!("Bet UTXO not found"))?;
+
+        bitvmx_service
+            .set_variable(
+                program_id,
+                "utxo_prover_win_action",
+                VariableTypes::Utxo(bet_utxo.clone().into()),
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!(format!("Failed to set variable bet utxo: {e:?}")))?;
+
+        bitvmx_service
+            .set_variable(
+                program_id,
+                "utxo_verifier_win_action",
+                VariableTypes::Utxo(bet_utxo.clone().into()),
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!(format!("Failed to set variable bet utxo: {e:?}")))?;
+
+        // Set program definition file path
+        bitvmx_service
+            .set_variable(
+                program_id,
+                "program_definition",
+                VariableTypes::String(dispute_parameters.program_definition.clone()),
+            )
+            .await
+            .map_err(|e| {
+                anyhow::anyhow!(format!("Failed to set variable program definition: {e:?}"))
+            })?;
+
+        // Set timelock blocks
+        bitvmx_service
+            .set_variable(
+                program_id,
+                dispute::TIMELOCK_BLOCKS_KEY,
+                VariableTypes::Number(dispute_parameters.timelock_blocks),
+            )
+            .await
+            .map_err(|e| {
+                anyhow::anyhow!(format!("Failed to set variable timelock blocks: {e:?}"))
+            })?;
+
+        self.check_dispute_variables(&bitvmx_service, program_id, &dispute_parameters)
+            .await?;
+
+        // Get the participants addresses
+        let participants_addresses: Vec<BitVMXP2PAddress> = game
+            .bitvmx_program_properties
+            .participants_addresses
+            .iter()
+            .map(|p2p| p2p.clone().into())
+            .collect();
+
+        // Setup program in BitVMX
+        bitvmx_service
+            .program_setup(
+                program_id,
+                bitvmx_client::types::PROGRAM_TYPE_DRP,
+                participants_addresses,
+                dispute_parameters.leader_idx,
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to set variable program setup: {e:?}"))?;
+
+        let data = serde_json::to_value(&request.data)
+            .map_err(|e| anyhow::anyhow!("Failed to serialize setup data: {e:?}"))?;
+
+        // Set game as started
+        self.game_store
+            .setup_game(program_id, data, dispute_parameters.clone())
+            .await
+            .context("Failed to save start game state")?;
+
+        // Both players follow the dispute to claim a timeout if the other one stops answering
+        worker_service
+            .handle_timeout_watch(program_id)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to enqueue job to watch timeouts: {e:?}"))?;
+
+        if game.role == PlayerRole::Player2 {
+            // Player 1 will send the challenge transaction to start the game.
+            // Player 2 will wait until see the first challenge transaction.
+            worker_service
+                .handle_start_game_tx(program_id)
+                .await
+                .map_err(|e| {
+                    anyhow::anyhow!("Failed to enqueue job to wait for start game: {e:?}")
+                })?;
+        }
+        Ok(dispute_parameters)
+    }
+
+    /// Start the game
+    /// Player 1 will send the challenge transaction to start the game.
+    #[instrument(name = "start_game", skip(self, worker_service))]
+    pub async fn start_game(
+        &self,
+        program_id: Uuid,
+        worker_service: Arc<WorkerService<G>>,
+    ) -> Result<(String, TransactionStatus), anyhow::Error> {
+        // Get the game
+        let game = self
+            .get_game(program_id)
+            .await?
+            .ok_or(anyhow::anyhow!("Game not found"))?;
+        let bitvmx_service = self.bitvmx(&game)?;
+
+        Self::status(&game)?.transition(&game.role, GameStep::StartGame.into())?;
+
+        if game.role != PlayerRole::Player1 {
+            return Err(anyhow::anyhow!(
+                "Invalid game role, only player 1 can start the game"
+            ));
+        }
+
+        // Player 1 send the challenge transaction to start the game.
+        let (challenge_tx_name, challenge_tx) = bitvmx_service
+            .start_challenge(program_id)
+            .await
+            .map_err(|e| anyhow::anyhow!(format!("Failed to start challenge: {e:?}")))?;
+
+        // Set the game as setup
+        self.game_store
+            .start_game(program_id, challenge_tx_name.clone(), &challenge_tx)
+            .await
+            .context("Failed to set game as started")?;
+
+        // Player 2 will submit the answer
+        // Player 1 will wait until see the game result.
+        worker_service
+            .handle_player2_wins_game_outcome_tx(program_id)
+            .await
+            .map_err(|e| {
+                anyhow::anyhow!("Failed to enqueue job to wait for player 2 to win the game: {e:?}")
+            })?;
+
+        Ok((challenge_tx_name, challenge_tx))
+    }
+
+    /// Wait for the other player to start the game
+    /// Player 2 will wait until see the first challenge transaction.
+    #[instrument(name = "wait_start_game_tx", skip(self))]
+    pub async fn wait_start_game_tx(&self, program_id: Uuid) -> Result<(), anyhow::Error> {
+        let game = self
+            .get_game(program_id)
+            .await?
+            .ok_or(anyhow::anyhow!("Game not found"))?;
+        if Self::status(&game)?.phase() != GamePhase::StartGame {
+            debug!("Game already started, e.g. by the reconciliation");
+            return Ok(());
+        }
+        let bitvmx_service = self.bitvmx(&game)?;
+
+        debug!("Waiting for other player to start the game");
+        let (challenge_tx_name, challenge_tx) = bitvmx_service
+            .wait_transaction_by_name_response(program_id, dispute::START_CH)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to wait for start game: {e:?}"))?;
+        debug!("Other player started the game");
+
+        // Set the game as setuped
+        self.game_store
+            .start_game(program_id, challenge_tx_name.clone(), &challenge_tx)
+            .await
+            .context("Failed to set game as started")?;
+
+        Ok(())
+    }
+
+    /// Submit the answer
+    /// Player 2 will send the answer, e.g. the sum, to answer the challenge.
+    /// The dispute outcome is watched in background.
+    #[instrument(name = "submit", skip(self, worker_service))]
+    pub async fn submit(
+        &self,
+        program_id: Uuid,
+        data: G::SubmitData,
+        worker_service: Arc<WorkerService<G>>,
+    ) -> Result<GameRecord, anyhow::Error> {
+        let game = self
+            .get_game(program_id)
+            .await?
+            .ok_or(anyhow::anyhow!("Game not found"))?;
+        let bitvmx_service = self.bitvmx(&game)?;
+        let input_index = self.game.submit_input_index();
+
+        // Check the answer against the program definition before storing it
+        let program_definition = game
+            .bitvmx_program_properties
+            .dispute_parameters
+            .as_ref()
+            .map_or(self.game.program_definition(), |parameters| {
+                parameters.program_definition.clone()
+            });
+        let input = self.program_definition(&program_definition)?.encode_input(
+            input_index,
+            &[InputOwner::of(&participant_role(&game.role))],
+            &self.game.submit_input(&data),
+        )?;
+
+        let data = serde_json::to_value(&data)
+            .map_err(|e| anyhow::anyhow!("Failed to serialize submitted data: {e:?}"))?;
+
+        // Store the submitted answer
+        self.game_store
+            .submit_game_data(program_id, data)
+            .await
+            .context("Failed to store submitted answer")?;
+
+        // Player 2 sets the input transaction with the answer in BitVMX
+        bitvmx_service
+            .set_program_input(program_id, input_index, input)
+            .await
+            .map_err(|e| anyhow::anyhow!(format!("Failed to set program input: {e:?}")))?;
+
+        // Send the input transaction to BitVMX
+        let (challenge_input_tx, challenge_input_tx_name) = bitvmx_service
+            .send_transaction_by_name(
+                program_id,
+                BitvmxService::dispute_input_tx_name(input_index).as_str(),
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!(format!("Failed to send challenge input: {e:?}")))?;
+        debug!(
+            "Challenge input transaction: {:?}",
+            challenge_input_tx.tx_id
+        );
+
+        // Set the challenge input transaction
+        self.game_store
+            .set_dispute_tx(program_id, challenge_input_tx_name, challenge_input_tx)
+            .await
+            .map_err(|e| anyhow::anyhow!(format!("Failed to set challenge tx: {e:?}")))?;
+
+        // Player 2 will wait until see the game result.
+        worker_service
+            .handle_submit_outcome_tx(program_id)
+            .await
+            .map_err(|e| {
+                anyhow::anyhow!("Failed to enqueue job to wait for the dispute outcome: {e:?}")
+            })?;
+
+        self.get_game(program_id)
+            .await?
+            .ok_or(anyhow::anyhow!("Game not found"))
+    }
+
+    /// Wait for the outcome of the dispute after player 2 sent the answer
+    #[instrument(name = "wait_submit_outcome_tx", skip(self))]
+    pub async fn wait_submit_outcome_tx(
+        &self,
+        program_id: Uuid,
+    ) -> Result<GameRecord, anyhow::Error> {
+        self.wait_dispute_outcome(program_id).await
+    }
+
+    /// Wait for the dispute transactions until a terminal one shows the winner, then complete the game
+    async fn wait_dispute_outcome(&self, program_id: Uuid) -> Result<GameRecord, anyhow::Error> {
+        let game = self
+            .get_game(program_id)
+            .await?
+            .ok_or(anyhow::anyhow!("Game not found"))?;
+        let bitvmx_service = self.bitvmx(&game)?;
+
+        let seen_txs: Vec<String> = game.bitvmx_program_properties.txs.keys().cloned().collect();
+        let (winner, reason) = match self.game.dispute_outcome(&seen_txs) {
+            // Decided before a restart, the game was not completed yet
+            Some(outcome) => outcome,
+            None => {
+                tokio::select! {
+                    outcome = self.wait_dispute_transactions(&bitvmx_service, program_id, seen_txs) => outcome?,
+                    game = self.wait_game_decided(program_id) => {
+                        debug!("Game decided while waiting for the dispute transactions");
+                        return game;
+                    }
+                }
+            }
+        };
+
+        let outcome = game_outcome(&game.role, &winner);
+        info!("{winner:?} won the game by {reason:?}, outcome: {outcome:?}");
+
+        self.game_store
+            .set_game_complete(program_id, outcome, reason)
+            .await
+            .context("Failed to set game complete")
+    }
+
+    /// Record the program transactions as they are seen, whatever dispute branch is played,
+    /// until one of them decides the winner
+    async fn wait_dispute_transactions(
+        &self,
+        bitvmx_service: &BitvmxService,
+        program_id: Uuid,
+        mut seen_txs: Vec<String>,
+    ) -> Result<(ParticipantRole, GameReason), anyhow::Error> {
+        debug!("Waiting for dispute transactions to be confirmed");
+        let mut subscription = bitvmx_service.subscribe_program(program_id).await;
+
+        while let Some(event) = subscription.recv().await {
+            let OutgoingBitVMXApiMessages::Transaction(_, tx_status, Some(tx_name)) = event else {
+                trace!("Program event ignored while waiting for the dispute: {event:?}");
+                continue;
+            };
+            if seen_txs.contains(&tx_name) {
+                continue;
+            }
+            debug!("Dispute transaction seen: {tx_name}");
+
+            self.game_store
+                .set_dispute_tx(program_id, tx_name.clone(), tx_status)
+                .await
+                .map_err(|e| anyhow::anyhow!(format!("Failed to set dispute tx: {e:?}")))?;
+            seen_txs.push(tx_name);
+
+            if let Some(outcome) = self.game.dispute_outcome(&seen_txs) {
+                return Ok(outcome);
+            }
+        }
+
+        Err(anyhow::anyhow!(
+            "Program subscription closed without a winner"
+        ))
+    }
+
+    /// Wait for the outcome of the dispute after player 1 started the game.
+    /// The answer of player 2 is recorded with the other dispute transactions, a timeout
+    /// dispatched when it is missing also ends with a winner action transaction.
+    #[instrument(name = "wait_player2_wins_game_outcome_tx", skip(self))]
+    pub async fn wait_player2_wins_game_outcome_tx(
+        &self,
+        program_id: Uuid,
+    ) -> Result<(), anyhow::Error> {
+        debug!("Waiting for player 2 to answer or time out");
+        self.wait_dispute_outcome(program_id).await?;
+        Ok(())
+    }
+
+    /// Resolves once the game is no longer in the dispute, e.g. completed by the timeout watch
+    async fn wait_game_decided(&self, program_id: Uuid) -> Result<GameRecord, anyhow::Error> {
+        loop {
+            sleep(GAME_STATUS_INTERVAL).await;
+            let game = self
+                .get_game(program_id)
+                .await?
+                .ok_or(anyhow::anyhow!("Game not found"))?;
+            if !is_in_dispute(&Self::status(&game)?.phase()) {
+                return Ok(game);
+            }
+        }
+    }
+
+    /// Follow the block height while the game is in dispute.
+    /// When the other player stops answering for the timelock blocks, dispatch the timeout
+    /// transaction. The game is completed by the dispute waiters once the winner action
+    /// transaction that follows it is seen.
+    #[instrument(name = "watch_timeout", skip(self))]
+    pub async fn watch_timeout(&self, program_id: Uuid) -> Result<(), anyhow::Error> {
+        let interval = Duration::from_secs(self.game_config.timeout_check_interval_secs);
+        loop {
+            let game = self
+                .get_game(program_id)
+                .await?
+                .ok_or(anyhow::anyhow!("Game not found"))?;
+            if !is_in_dispute(&Self::status(&game)?.phase()) {
+                debug!("Game is no longer in dispute, stop watching timeouts");
+                return Ok(());
+            }
+            let bitvmx_service = self.bitvmx(&game)?;
+
+            let timelock_blocks = game
+                .bitvmx_program_properties
+                .dispute_parameters
+                .as_ref()
+                .map_or(self.game_config.timelock_blocks, |parameters| {
+                    parameters.timelock_blocks
+                });
+            let pending_timeout = pending_timeout(
+                &game.bitvmx_program_properties.txs,
+                &participant_role(&game.role),
+                timelock_blocks,
+                self.game.submit_input_index(),
+            );
+
+            if let Some(pending_timeout) = pending_timeout {
+                let height = bitvmx_service.get_block_height().await?;
+                if height >= pending_timeout.deadline {
+                    info!(
+                        "Other player did not answer before block {}, dispatching {}",
+                        pending_timeout.deadline, pending_timeout.tx_name
+                    );
+                    match bitvmx_service
+                        .send_transaction_by_name(program_id, &pending_timeout.tx_name)
+                        .await
+                    {
+                        Ok((timeout_tx, timeout_tx_name)) => {
+                            self.game_store
+                                .set_dispute_tx(program_id, timeout_tx_name, timeout_tx)
+                                .await
+                                .context("Failed to set timeout tx")?;
+                        }
+                        // Try again on the next check
+                        Err(e) => warn!("Failed to dispatch {}: {e:?}", pending_timeout.tx_name),
+                    }
+                }
+            }
+
+            sleep(interval).await;
+        }
+    }
+
+    /// Reconcile the games that are not finished with the chain once, in case a waiter missed
+    /// a BitVMX message. The `reconcile_games` job runs it every `reconcile_interval_secs`
+    #[instrument(name = "reconcile_games", skip(self))]
+    pub async fn reconcile_games(&self) -> Result<(), anyhow::Error> {
+        for game in self.list_games().await? {
+            let needs_reconciliation =
+                Self::status(&game).is_ok_and(|status| needs_reconciliation(&status.phase()));
+            if !needs_reconciliation {
+                continue;
+            }
+            match self.reconcile_game(game.program_id).await {
+                Ok(reconciliation) if reconciliation != Reconciliation::default() => {
+                    info!("Game {} reconciled: {reconciliation:?}", game.program_id)
+                }
+                Ok(_) => trace!("Game {} matches the chain", game.program_id),
+                Err(e) => warn!("Failed to reconcile game {}: {e:?}", game.program_id),
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the expected dispute transactions of a game on chain.
+    /// The missing ones are recorded and the game moves forward when they decide its next status,
+    /// recorded transactions that differ from the chain are kept as contradictions of the game.
+    #[instrument(name = "reconcile_game", skip(self))]
+    pub async fn reconcile_game(
+        &self,
+        program_id: Uuid,
+    ) -> Result<Reconciliation<G::Status>, anyhow::Error> {
+        let game = self
+            .get_game(program_id)
+            .await?
+            .ok_or(anyhow::anyhow!("Game not found"))?;
+        let bitvmx_service = self.bitvmx(&game)?;
+        let mut reconciliation = Reconciliation::default();
+
+        for tx_name in expected_dispute_txs(self.game.submit_input_index()) {
+            let Some(tx_status) = bitvmx_service
+                .find_transaction_by_name(program_id, &tx_name)
+                .await?
+            else {
+                continue;
+            };
+            match game.bitvmx_program_properties.txs.get(&tx_name) {
+                None => {
+                    debug!("Dispute transaction {tx_name} found on chain");
+                    self.game_store
+                        .set_dispute_tx(program_id, tx_name.clone(), tx_status)
+                        .await
+                        .context("Failed to set dispute tx")?;
+                    reconciliation.filled_txs.push(tx_name);
+                }
+                Some(recorded) => {
+                    let txid = tx_status.tx_id.to_string();
+                    if recorded["tx_id"].as_str() != Some(txid.as_str()) {
+                        reconciliation.contradictions.push(format!(
+                            "{tx_name} is recorded as {} but the chain has {txid}",
+                            recorded["tx_id"]
+                        ));
+                    }
+                }
+            }
+        }
+
+        let mut game = self
+            .get_game(program_id)
+            .await?
+            .ok_or(anyhow::anyhow!("Game not found"))?;
+        let status = Self::status(&game)?;
+        let txs = &game.bitvmx_program_properties.txs;
+        let seen_txs: Vec<String> = txs.keys().cloned().collect();
+        let chain_outcome = self.game.dispute_outcome(&seen_txs);
+
+        if status.phase() == GamePhase::StartGame && txs.contains_key(dispute::START_CH) {
+            game = self
+                .game_store
+                .apply_event(program_id, GameStep::StartGame.into())
+                .await
+                .context("Failed to set game as started")?;
+        }
+        if let (GamePhase::SubmitGameData, Some((winner, reason))) =
+            (Self::status(&game)?.phase(), &chain_outcome)
+        {
+            game = self
+                .game_store
+                .set_game_complete(program_id, game_outcome(&game.role, winner), reason.clone())
+                .await
+                .context("Failed to set game complete")?;
+        }
+        if let GamePhase::GameComplete { outcome, reason } = Self::status(&game)?.phase() {
+            if let Some((winner, chain_reason)) = &chain_outcome {
+                let chain_game_outcome = game_outcome(&game.role, winner);
+                if (&chain_game_outcome, chain_reason) != (&outcome, &reason) {
+                    reconciliation.contradictions.push(format!(
+                        "Game is recorded as {outcome:?} by {reason:?} but the chain shows {chain_game_outcome:?} by {chain_reason:?}"
+                    ));
+                }
+            }
+        }
+        // The payout is dispatched by the `wait_payout` job, it is only flagged here
+        let action_tx_name = dispute::action_wins(&participant_role(&game.role), 1);
+        if Self::status(&game)?.phase() == GamePhase::TransferBetFunds
+            && game
+                .bitvmx_program_properties
+                .txs
+                .contains_key(&action_tx_name)
+            && game.payout_txid.is_none()
+        {
+            reconciliation.contradictions.push(format!(
+                "{action_tx_name} is on chain but the bet was not paid out"
+            ));
+        }
+        let reconciled_status = Self::status(&game)?;
+        if reconciled_status != status {
+            reconciliation.advanced_to = Some(reconciled_status);
+        }
+
+        if reconciliation.contradictions != game.contradictions {
+            for contradiction in &reconciliation.contradictions {
+                warn!("Game {program_id} contradicts the chain: {contradiction}");
+            }
+            self.game_store
+                .set_contradictions(program_id, reconciliation.contradictions.clone())
+                .await
+                .context("Failed to set contradictions")?;
+        }
+        Ok(reconciliation)
+    }
+
+    /// Claim the bet of a won game
+    /// The payout is watched in background, the game is finished once it is confirmed.
+    #[instrument(name = "claim_bet", skip(self, worker_service))]
+    pub async fn claim_bet(
+        &self,
+        program_id: Uuid,
+        worker_service: Arc<WorkerService<G>>,
+    ) -> Result<GameRecord, anyhow::Error> {
+        let game = self
+            .game_store
+            .apply_event(program_id, GameStep::ClaimBet.into())
+            .await
+            .context("Failed to claim bet")?;
+
+        worker_service
+            .handle_payout_tx(program_id)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to enqueue job to wait for payout: {e:?}"))?;
+
+        Ok(game)
+    }
+
+    /// Pay out a claimed bet: wait for the winner action transaction to be confirmed, dispatch the
+    /// payout transaction spending its output and finish the game once the payout is confirmed
+    #[instrument(name = "wait_payout_tx", skip(self))]
+    pub async fn wait_payout_tx(&self, program_id: Uuid) -> Result<GameRecord, anyhow::Error> {
+        let game = self
+            .get_game(program_id)
+            .await?
+            .ok_or(anyhow::anyhow!("Game not found"))?;
+        if Self::status(&game)?.phase() == GamePhase::Finished {
+            debug!("Bet already paid out, e.g. found by the reconciliation");
+            return Ok(game);
+        }
+        let bitvmx_service = self.bitvmx(&game)?;
+        let action_tx_name = dispute::action_wins(&participant_role(&game.role), 1);
+
+        // The prover action is recorded while waiting for the dispute transactions
+        let action_tx = match game.bitvmx_program_properties.txs.get(&action_tx_name) {
+            Some(action_tx) => serde_json::from_value(action_tx.clone())
+                .map_err(|e| anyhow::anyhow!("Failed to parse action transaction: {e:?}"))?,
+            None => match bitvmx_service
+                .send_transaction_by_name(program_id, &action_tx_name)
+                .await
+            {
+                Ok((action_tx, _)) => action_tx,
+                Err(e) => {
+                    // BitVMX may have dispatched it already, wait to see it on chain
+                    warn!("Failed to dispatch {action_tx_name}, waiting for it: {e:?}");
+                    let (_, action_tx) = bitvmx_service
+                        .wait_transaction_by_name_response(program_id, &action_tx_name)
+                        .await
+                        .map_err(|e| {
+                            anyhow::anyhow!("Failed to wait for {action_tx_name}: {e:?}")
+                        })?;
+                    action_tx
+                }
+            },
+        };
+        let action_tx = self
+            .wait_action_tx_confirmed(&bitvmx_service, program_id, &action_tx_name, action_tx)
+            .await?;
+        self.game_store
+            .set_dispute_tx(program_id, action_tx_name, action_tx.clone())
+            .await
+            .context("Failed to set action tx")?;
+
+        // A payout sent before a restart is only waited for. The payout transaction is fixed
+        // by the program, dispatching it again cannot pay twice
+        let payout_tx = match game.payout_txid {
+            Some(payout_txid) => {
+                debug!("Payout already sent in {payout_txid}, waiting for its confirmation");
+                self.wait_payout_confirmed(&bitvmx_service, payout_txid)
+                    .await?
+            }
+            None => self.send_payout(&bitvmx_service, &game, &action_tx).await?,
+        };
+        info!("Bet paid out in transaction: {:?}", payout_tx.tx_id);
+
+        self.game_store
+            .set_payout(program_id, PAYOUT_TX_NAME.to_string(), payout_tx)
+            .await
+            .context("Failed to set payout")
+    }
+
+    /// Wait until the winner action transaction is mined and not orphaned
+    async fn wait_action_tx_confirmed(
+        &self,
+        bitvmx_service: &BitvmxService,
+        program_id: Uuid,
+        action_tx_name: &str,
+        mut action_tx: TransactionStatus,
+    ) -> Result<TransactionStatus, anyhow::Error> {
+        while !is_confirmed(&action_tx) {
+            debug!("Waiting for {action_tx_name} to be confirmed");
+            sleep(GAME_STATUS_INTERVAL).await;
+            if let Some(tx_status) = bitvmx_service
+                .find_transaction_by_name(program_id, action_tx_name)
+                .await?
+            {
+                action_tx = tx_status;
+            }
+        }
+        Ok(action_tx)
+    }
+
+    /// Dispatch the payout transaction of the program, which spends the output of the winner
+    /// action transaction, and wait for it to be confirmed
+    async fn send_payout(
+        &self,
+        bitvmx_service: &BitvmxService,
+        game: &GameRecord,
+        action_tx: &TransactionStatus,
+    ) -> Result<TransactionStatus, anyhow::Error> {
+        let program_id = game.program_id;
+        let payout_tx_name = payout_tx_name(&participant_role(&game.role));
+        // The action transaction pays the bet to its first output
+        let action_outpoint = OutPoint::new(action_tx.tx_id, 0);
+
+        let payout_tx = match bitvmx_service
+            .find_transaction_by_name(program_id, &payout_tx_name)
+            .await?
+        {
+            Some(payout_tx) => payout_tx,
+            None => {
+                info!("Dispatching {payout_tx_name} to claim the action output {action_outpoint}");
+                match bitvmx_service
+                    .send_transaction_by_name(program_id, &payout_tx_name)
+                    .await
+                {
+                    Ok((payout_tx, _)) => payout_tx,
+                    Err(e) => {
+                        // BitVMX may have dispatched it already, wait to see it on chain
+                        warn!("Failed to dispatch {payout_tx_name}, waiting for it: {e:?}");
+                        let (_, payout_tx) = bitvmx_service
+                            .wait_transaction_by_name_response(program_id, &payout_tx_name)
+                            .await
+                            .map_err(|e| {
+                                anyhow::anyhow!("Failed to wait for {payout_tx_name}: {e:?}")
+                            })?;
+                        payout_tx
+                    }
+                }
+            }
+        };
+        let spends_action_output = payout_tx
+            .tx
+            .input
+            .iter()
+            .any(|input| input.previous_output == action_outpoint);
+        if !spends_action_output {
+            return Err(anyhow::anyhow!(
+                "{payout_tx_name} {} does not spend the action output {action_outpoint}",
+                payout_tx.tx_id
+            ));
+        }
+        self.game_store
+            .set_payout_sent(program_id, payout_tx.tx_id.to_string())
+            .await
+            .context("Failed to set payout sent")?;
+
+        if is_confirmed(&payout_tx) {
+            return Ok(payout_tx);
+        }
+        self.wait_payout_confirmed(bitvmx_service, payout_tx.tx_id.to_string())
+            .await
+    }
+
+    /// Wait until the payout transaction is mined and not orphaned
+    async fn wait_payout_confirmed(
+        &self,
+        bitvmx_service: &BitvmxService,
+        payout_txid: String,
+    ) -> Result<TransactionStatus, anyhow::Error> {
+        loop {
+            match bitvmx_service.get_transaction(payout_txid.clone()).await {
+                Ok(payout_tx) if is_confirmed(&payout_tx) => return Ok(payout_tx),
+                Ok(_) => debug!("Waiting for the payout {payout_txid} to be confirmed"),
+                Err(e) => debug!("Payout {payout_txid} not seen yet: {e:?}"),
+            }
+            sleep(GAME_STATUS_INTERVAL).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{AddNumbersGameStatus, AddNumbersSetup, AddNumbersSubmit};
+    use crate::services::UnknownOperator;
+    use crate::test_support::fake_bitvmx::FAKE_PUB_KEY;
+    use crate::test_support::{wait_until, FakeChain, FakePlayer};
+    use bitvmx_client::types::{IncomingBitVMXApiMessages, OutgoingBitVMXApiMessages};
+    use tokio::sync::broadcast;
+
+    fn mined_at(height: u32) -> serde_json::Value {
+        serde_json::json!({ "block_info": { "height": height } })
+    }
+
+    #[test]
+    fn test_pending_timeout() {
+        let mut txs = HashMap::new();
+        let verifier = ParticipantRole::Verifier;
+        let prover = ParticipantRole::Prover;
+
+        // Nothing to wait for before the challenge starts
+        assert_eq!(pending_timeout(&txs, &verifier, 5, 1), None);
+
+        // Player 2 never sends the input after START_CH
+        txs.insert(dispute::START_CH.to_string(), mined_at(100));
+        assert_eq!(
+            pending_timeout(&txs, &verifier, 5, 1),
+            Some(PendingTimeout {
+                tx_name: format!("{}_TO", dispute::input_tx_name(1)),
+                deadline: 105,
+            })
+        );
+        assert_eq!(pending_timeout(&txs, &prover, 5, 1), None);
+
+        // Player 1 disappears in the n-ary search
+        txs.insert(dispute::input_tx_name(1), mined_at(101));
+        txs.insert("NARY_PROVER_1".to_string(), mined_at(110));
+        assert_eq!(pending_timeout(&txs, &verifier, 5, 1), None);
+        assert_eq!(
+            pending_timeout(&txs, &prover, 5, 1),
+            Some(PendingTimeout {
+                tx_name: "NARY_VERIFIER_1_TO".to_string(),
+                deadline: 115,
+            })
+        );
+
+        // Already dispatched
+        txs.insert("NARY_VERIFIER_1_TO".to_string(), mined_at(116));
+        assert_eq!(pending_timeout(&txs, &prover, 5, 1), None);
+
+        // Not mined yet, the deadline is unknown
+        let mut txs = HashMap::new();
+        txs.insert(dispute::START_CH.to_string(), serde_json::Value::Null);
+        assert_eq!(pending_timeout(&txs, &verifier, 5, 1), None);
+    }
+
+    /// Both players join the game and fund it on the fake chain, returns the program id
+    async fn fund_game(player1: &FakePlayer, player2: &FakePlayer) -> Uuid {
+        let addresses = vec![player1.p2p_address(), player2.p2p_address()];
+        let keys = vec![FAKE_PUB_KEY.to_string(), FAKE_PUB_KEY.to_string()];
+        let aggregated_id = Uuid::new_v4();
+        let mut program_ids = Vec::new();
+        for (player, role) in [
+            (player1, PlayerRole::Player1),
+            (player2, PlayerRole::Player2),
+        ] {
+            let (program_id, _) = player
+                .add_numbers_service()
+                .setup_participants(
+                    aggregated_id,
+                    1,
+                    addresses.clone(),
+                    keys.clone(),
+                    role,
+                    None,
+                )
+                .await
+                .unwrap();
+            program_ids.push(program_id);
+        }
+        assert_eq!(program_ids[0], program_ids[1]);
+        let program_id = program_ids[0];
+
+        // Player 1 funds the protocol and the bet, player 2 gets its UTXOs
+        let service1 = &player1.add_numbers_service();
+        let service2 = &player2.add_numbers_service();
+        service1.place_bet(program_id, 10_000).await.unwrap();
+        service2.place_bet(program_id, 10_000).await.unwrap();
+        let funded = service1.get_game(program_id).await.unwrap().unwrap();
+        let properties = funded.bitvmx_program_properties;
+        service2
+            .setup_funding_utxo(
+                program_id,
+                properties.funding_protocol_utxo.unwrap(),
+                properties.funding_bet_utxo.unwrap(),
+            )
+            .await
+            .unwrap();
+
+        program_id
+    }
+
+    fn setup_request(program_id: Uuid) -> GameSetupRequest<AddNumbersSetup> {
+        GameSetupRequest {
+            program_id,
+            data: AddNumbersSetup {
+                number1: 2,
+                number2: 3,
+            },
+            timelock_blocks: None,
+            program_definition: None,
+            leader_idx: None,
+            peer_parameters: None,
+        }
+    }
+
+    /// Both players set up the game on the fake chain, returns the program id
+    async fn setup_game(player1: &FakePlayer, player2: &FakePlayer) -> Uuid {
+        let program_id = fund_game(player1, player2).await;
+        let service1 = &player1.add_numbers_service();
+        let service2 = &player2.add_numbers_service();
+        let request = setup_request(program_id);
+        let parameters = service1
+            .setup_game(request.clone(), player1.add_numbers_worker())
+            .await
+            .unwrap();
+        service2
+            .setup_game(
+                GameSetupRequest {
+                    peer_parameters: Some(parameters),
+                    ..request
+                },
+                player2.add_numbers_worker(),
+            )
+            .await
+            .unwrap();
+
+        program_id
+    }
+
+    async fn game_status(player: &FakePlayer, program_id: Uuid) -> AddNumbersGameStatus {
+        player
+            .add_numbers_service()
+            .get_game(program_id)
+            .await
+            .unwrap()
+            .unwrap()
+            .status_as()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_add_numbers_flow_on_fake_bitvmx() {
+        let chain = FakeChain::new();
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let player1 = FakePlayer::start(&chain, &shutdown_tx).unwrap();
+        let player2 = FakePlayer::start(&chain, &shutdown_tx).unwrap();
+
+        let program_id = setup_game(&player1, &player2).await;
+        for player in [&player1, &player2] {
+            assert_eq!(
+                player
+                    .bitvmx
+                    .variable(program_id, &dispute::program_input(0)),
+                Some(VariableTypes::Input(vec![0, 0, 0, 2, 0, 0, 0, 3]))
+            );
+            assert!(player.bitvmx.received().iter().any(|message| matches!(
+                message,
+                IncomingBitVMXApiMessages::Setup(id, ..) if *id == program_id
+            )));
+        }
+
+        // Player 2 sees the challenge sent by player 1
+        let start_ch = crate::rpc::tx_name_to_correlation_id(&program_id, dispute::START_CH);
+        wait_until("player 2 waits for the challenge", || {
+            player2.state.rpc_client.is_waiting_for(&start_ch)
+        })
+        .await;
+        player1
+            .add_numbers_service()
+            .start_game(program_id, player1.add_numbers_worker())
+            .await
+            .unwrap();
+        wait_until("player 2 sees the challenge", || async {
+            game_status(&player2, program_id).await == AddNumbersGameStatus::SubmitGameData
+        })
+        .await;
+
+        player2
+            .add_numbers_service()
+            .submit(
+                program_id,
+                AddNumbersSubmit { guess: 5 },
+                player2.add_numbers_worker(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            player2
+                .bitvmx
+                .variable(program_id, &dispute::program_input(1)),
+            Some(VariableTypes::Input(vec![0, 0, 0, 5]))
+        );
+
+        // BitVMX runs the dispute, the prover wins
+        for player in [&player1, &player2] {
+            wait_until("the players follow the dispute", || {
+                player.state.rpc_client.is_subscribed(program_id)
+            })
+            .await;
+        }
+        chain.mine(program_id, "NARY_PROVER_1");
+        chain.mine(
+            program_id,
+            &dispute::action_wins(&ParticipantRole::Prover, 1),
+        );
+
+        for (player, outcome) in [(&player1, GameOutcome::Lose), (&player2, GameOutcome::Win)] {
+            let expected = AddNumbersGameStatus::GameComplete {
+                outcome,
+                reason: GameReason::Challenge,
+            };
+            wait_until("the game is complete", || async {
+                game_status(player, program_id).await == expected
+            })
+            .await;
+        }
+
+        // The winner claims the bet
+        player2
+            .add_numbers_service()
+            .claim_bet(program_id, player2.add_numbers_worker())
+            .await
+            .unwrap();
+        wait_until("the bet is paid out", || async {
+            game_status(&player2, program_id).await == AddNumbersGameStatus::Finished
+        })
+        .await;
+        let game = player2
+            .add_numbers_service()
+            .get_game(program_id)
+            .await
+            .unwrap()
+            .unwrap();
+
+        // The payout transaction spends the output of the action transaction
+        let action_tx = chain
+            .named_transaction(
+                program_id,
+                &dispute::action_wins(&ParticipantRole::Prover, 1),
+            )
+            .unwrap();
+        let payout_txid = game.payout_txid.unwrap();
+        let payout_tx = chain.transaction(&payout_txid.parse().unwrap()).unwrap();
+        assert!(is_confirmed(&payout_tx));
+        assert_eq!(
+            payout_tx.tx.input[0].previous_output,
+            OutPoint::new(action_tx.tx_id, 0)
+        );
+        assert_eq!(
+            game.bitvmx_program_properties.txs[PAYOUT_TX_NAME]["tx_id"],
+            payout_txid
+        );
+        // Nothing is paid from the operator wallet
+        assert!(!player2
+            .bitvmx
+            .received()
+            .iter()
+            .any(|message| matches!(message, IncomingBitVMXApiMessages::SendFunds(..))));
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[tokio::test]
+    async fn test_timeout_when_player2_does_not_answer() {
+        let chain = FakeChain::new();
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let player1 = FakePlayer::start(&chain, &shutdown_tx).unwrap();
+        let player2 = FakePlayer::start(&chain, &shutdown_tx).unwrap();
+        let program_id = setup_game(&player1, &player2).await;
+        let service1 = &player1.add_numbers_service();
+
+        service1
+            .start_game(program_id, player1.add_numbers_worker())
+            .await
+            .unwrap();
+        wait_until("player 1 follows the dispute", || {
+            player1.state.rpc_client.is_subscribed(program_id)
+        })
+        .await;
+        let timeout_tx_name = timeout_tx_name(&dispute::input_tx_name(1));
+        let game = service1.get_game(program_id).await.unwrap().unwrap();
+        assert!(!game
+            .bitvmx_program_properties
+            .txs
+            .contains_key(&timeout_tx_name));
+
+        // Player 2 does not answer for the timelock blocks
+        chain.mine_empty_blocks(player1.state.config.game.timelock_blocks);
+        wait_until("player 1 dispatches the timeout", || async {
+            let game = service1.get_game(program_id).await.unwrap().unwrap();
+            game.bitvmx_program_properties
+                .txs
+                .contains_key(&timeout_tx_name)
+        })
+        .await;
+        assert!(chain
+            .named_transaction(program_id, &timeout_tx_name)
+            .is_some());
+        // The game is only decided by the winner action transaction
+        assert_eq!(
+            game_status(&player1, program_id).await,
+            AddNumbersGameStatus::SubmitGameData
+        );
+
+        chain.mine(
+            program_id,
+            &dispute::action_wins(&ParticipantRole::Verifier, 1),
+        );
+        let expected = AddNumbersGameStatus::GameComplete {
+            outcome: GameOutcome::Win,
+            reason: GameReason::Timeout,
+        };
+        wait_until("player 1 wins by timeout", || async {
+            game_status(&player1, program_id).await == expected
+        })
+        .await;
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_game_with_missed_transactions() {
+        let chain = FakeChain::new();
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let player1 = FakePlayer::start(&chain, &shutdown_tx).unwrap();
+        let player2 = FakePlayer::start(&chain, &shutdown_tx).unwrap();
+        let program_id = setup_game(&player1, &player2).await;
+        let service2 = &player2.add_numbers_service();
+
+        // Player 2 never hears about the challenge
+        chain.mine_unreported(program_id, dispute::START_CH);
+        let reconciliation = service2.reconcile_game(program_id).await.unwrap();
+        assert_eq!(
+            reconciliation.filled_txs,
+            vec![dispute::START_CH.to_string()]
+        );
+        assert_eq!(
+            reconciliation.advanced_to,
+            Some(AddNumbersGameStatus::SubmitGameData)
+        );
+
+        // Nor about the end of the dispute
+        let input_tx = dispute::input_tx_name(service2.game().submit_input_index());
+        let prover_wins = dispute::action_wins(&ParticipantRole::Prover, 1);
+        chain.mine_unreported(program_id, &input_tx);
+        chain.mine_unreported(program_id, &prover_wins);
+        let reconciliation = service2.reconcile_game(program_id).await.unwrap();
+        assert_eq!(
+            reconciliation.filled_txs,
+            vec![input_tx, prover_wins.clone()]
+        );
+        assert_eq!(
+            reconciliation.advanced_to,
+            Some(AddNumbersGameStatus::GameComplete {
+                outcome: GameOutcome::Win,
+                reason: GameReason::Challenge,
+            })
+        );
+        assert_eq!(
+            service2.reconcile_game(program_id).await.unwrap(),
+            Reconciliation::default()
+        );
+
+        // The chain now has another challenge transaction than the recorded one
+        chain.mine_unreported(program_id, dispute::START_CH);
+        let reconciliation = service2.reconcile_game(program_id).await.unwrap();
+        assert_eq!(reconciliation.contradictions.len(), 1);
+        let game = service2.get_game(program_id).await.unwrap().unwrap();
+        assert_eq!(game.contradictions, reconciliation.contradictions);
+
+        // The claimed bet is only paid by the payout job, the winner action transaction is flagged
+        service2
+            .game_store
+            .apply_event(program_id, GameStep::ClaimBet.into())
+            .await
+            .unwrap();
+        let reconciliation = service2.reconcile_game(program_id).await.unwrap();
+        assert_eq!(reconciliation.advanced_to, None);
+        assert_eq!(reconciliation.contradictions.len(), 2);
+        assert!(reconciliation.contradictions[1].starts_with(&prover_wins));
+        let game = service2.get_game(program_id).await.unwrap().unwrap();
+        assert_eq!(
+            game.status_as::<AddNumbersGameStatus>().unwrap(),
+            AddNumbersGameStatus::TransferBetFunds
+        );
+        assert_eq!(game.payout_txid, None);
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[tokio::test]
+    async fn test_program_definition_is_read_once() {
+        let chain = FakeChain::new();
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let player = FakePlayer::start(&chain, &shutdown_tx).unwrap();
+        let service = player.add_numbers_service();
+        let game_config = &player.state.config.game;
+
+        let definition = service
+            .program_definition(&game_config.program_definition)
+            .unwrap();
+        std::fs::remove_file(
+            std::path::Path::new(&game_config.bitvmx_client_path)
+                .join(&game_config.program_definition),
+        )
+        .unwrap();
+        let cached = service
+            .program_definition(&game_config.program_definition)
+            .unwrap();
+        assert!(Arc::ptr_eq(&definition, &cached));
+
+        let error = service.program_definition("other.yaml").unwrap_err();
+        assert!(error.downcast_ref::<InvalidDisputeParameters>().is_some());
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[tokio::test]
+    async fn test_setup_game_checks_the_dispute_parameters() {
+        let chain = FakeChain::new();
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let player1 = FakePlayer::start(&chain, &shutdown_tx).unwrap();
+        let player2 = FakePlayer::start(&chain, &shutdown_tx).unwrap();
+        let program_id = fund_game(&player1, &player2).await;
+        let service1 = &player1.add_numbers_service();
+        let service2 = &player2.add_numbers_service();
+        let set_up = |player: &FakePlayer| {
+            player.bitvmx.received().iter().any(|message| {
+                matches!(message, IncomingBitVMXApiMessages::Setup(id, ..) if *id == program_id)
+            })
+        };
+
+        // Only the allowed program definitions are read
+        let error = service1
+            .setup_game(
+                GameSetupRequest {
+                    program_definition: Some("../../../etc/passwd".to_string()),
+                    ..setup_request(program_id)
+                },
+                player1.add_numbers_worker(),
+            )
+            .await
+            .unwrap_err();
+        assert!(error.downcast_ref::<InvalidDisputeParameters>().is_some());
+
+        // BitVMX lost a variable set without an answer
+        player1.bitvmx.on(move |message| match message {
+            IncomingBitVMXApiMessages::GetVar(uuid, key) if key == "program_definition" => Some(
+                vec![OutgoingBitVMXApiMessages::NotFound(*uuid, key.clone())],
+            ),
+            _ => None,
+        });
+        let error = service1
+            .setup_game(setup_request(program_id), player1.add_numbers_worker())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("BitVMX has program_definition"));
+        assert!(!set_up(&player1));
+
+        // Player 2 must send the parameters of player 1
+        let error = service2
+            .setup_game(setup_request(program_id), player2.add_numbers_worker())
+            .await
+            .unwrap_err();
+        assert!(error.downcast_ref::<InvalidDisputeParameters>().is_some());
+        assert!(!set_up(&player2));
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[tokio::test]
+    async fn test_setup_participants_fails_when_bitvmx_has_no_aggregated_key() {
+        let chain = FakeChain::new();
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let player = FakePlayer::start(&chain, &shutdown_tx).unwrap();
+        player.bitvmx.on(|message| match message {
+            IncomingBitVMXApiMessages::SetupKey(uuid, ..) => {
+                Some(vec![OutgoingBitVMXApiMessages::AggregatedPubkeyNotReady(
+                    *uuid,
+                )])
+            }
+            _ => None,
+        });
+
+        let result = player
+            .add_numbers_service()
+            .setup_participants(
+                Uuid::new_v4(),
+                0,
+                vec![player.p2p_address()],
+                vec![FAKE_PUB_KEY.to_string()],
+                PlayerRole::Player1,
+                None,
+            )
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(
+            player
+                .add_numbers_service()
+                .get_current_game()
+                .await
+                .unwrap(),
+            None
+        );
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[tokio::test]
+    async fn test_setup_participants_on_an_operator() {
+        let chain = FakeChain::new();
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let player =
+            FakePlayer::start_with_operators(&chain, &shutdown_tx, &["operator_2"]).unwrap();
+        let operator_2 = &player.operators[0].1;
+        let service = &player.add_numbers_service();
+
+        let (program_id, _) = service
+            .setup_participants(
+                Uuid::new_v4(),
+                0,
+                vec![operator_2.comm_address().into()],
+                vec![FAKE_PUB_KEY.to_string()],
+                PlayerRole::Player1,
+                Some("operator_2"),
+            )
+            .await
+            .unwrap();
+
+        let game = service.get_game(program_id).await.unwrap().unwrap();
+        assert_eq!(game.operator, "operator_2");
+        let is_setup_key = |message: &IncomingBitVMXApiMessages| {
+            matches!(message, IncomingBitVMXApiMessages::SetupKey(..))
+        };
+        assert!(operator_2.received().iter().any(is_setup_key));
+        assert!(!player.bitvmx.received().iter().any(is_setup_key));
+
+        let error = service
+            .setup_participants(
+                Uuid::new_v4(),
+                0,
+                vec![player.p2p_address()],
+                vec![FAKE_PUB_KEY.to_string()],
+                PlayerRole::Player1,
+                Some("missing"),
+            )
+            .await
+            .unwrap_err();
+        assert!(error.is::<UnknownOperator>());
+
+        let _ = shutdown_tx.send(());
+    }
+}
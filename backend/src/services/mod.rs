@@ -1,9 +1,16 @@
-pub mod add_numbers;
 pub mod bitcoin;
 pub mod bitvmx;
+pub mod game;
+pub mod operators;
+pub mod registry;
 pub mod worker;
 
-pub use add_numbers::AddNumbersService;
-pub use bitcoin::BitcoinService;
+pub use bitcoin::{BitcoinService, BitcoindService};
 pub use bitvmx::BitvmxService;
-pub use worker::WorkerService;
+pub use game::GameService;
+pub use operators::{Operator, OperatorRegistry, UnhealthyOperator, UnknownOperator};
+pub use registry::{GameRegistry, RegisteredGame};
+pub use worker::{PendingWait, WorkerService};
+
+/// Service of the add numbers game
+pub type AddNumbersService = GameService<crate::games::AddNumbers>;
@@ -0,0 +1,301 @@
+use crate::config::DEFAULT_OPERATOR;
+use crate::rpc::rpc_client::{ListenerTask, RpcClient};
+use crate::services::BitvmxService;
+use std::sync::{Arc, Mutex};
+use tokio::task::{JoinHandle, JoinSet};
+use tracing::error;
+
+/// Error returned when no operator has the requested name
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownOperator(pub String);
+
+impl std::fmt::Display for UnknownOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unknown operator: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownOperator {}
+
+/// Error returned when the operator failed, e.g. its setup or its BitVMX listener
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnhealthyOperator {
+    pub name: String,
+    pub reason: String,
+}
+
+impl std::fmt::Display for UnhealthyOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Operator {} is unhealthy: {}", self.name, self.reason)
+    }
+}
+
+impl std::error::Error for UnhealthyOperator {}
+
+/// BitVMX node of an operator and the service talking to it
+#[derive(Debug)]
+pub struct Operator {
+    pub name: String,
+    pub rpc_client: Arc<RpcClient>,
+    pub bitvmx_service: Arc<BitvmxService>,
+    /// Why the operator stopped working, None while it is healthy
+    unhealthy: Mutex<Option<String>>,
+}
+
+impl Operator {
+    /// Healthy operator
+    pub fn new(
+        name: String,
+        rpc_client: Arc<RpcClient>,
+        bitvmx_service: Arc<BitvmxService>,
+    ) -> Self {
+        Self {
+            name,
+            rpc_client,
+            bitvmx_service,
+            unhealthy: Mutex::new(None),
+        }
+    }
+
+    /// Stop using the operator, the other operators keep serving
+    pub fn mark_unhealthy(&self, reason: String) {
+        error!("❌ Operator {} is unhealthy: {reason}", self.name);
+        *self.unhealthy.lock().unwrap() = Some(reason);
+    }
+
+    /// Why the operator stopped working, None while it is healthy
+    pub fn unhealthy_reason(&self) -> Option<String> {
+        self.unhealthy.lock().unwrap().clone()
+    }
+
+    /// Fail if the operator is unhealthy
+    pub fn check_healthy(&self) -> Result<(), UnhealthyOperator> {
+        match self.unhealthy_reason() {
+            Some(reason) => Err(UnhealthyOperator {
+                name: self.name.clone(),
+                reason,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Operators managed by the backend, games run on one of them
+#[derive(Debug)]
+pub struct OperatorRegistry {
+    /// The default operator first
+    operators: Vec<Arc<Operator>>,
+}
+
+impl OperatorRegistry {
+    /// Registry of the operators, one of them must be the default operator
+    pub fn new(operators: Vec<Operator>) -> Result<Self, anyhow::Error> {
+        let mut registry = Self {
+            operators: Vec::new(),
+        };
+        for operator in operators {
+            if registry.get(&operator.name).is_ok() {
+                return Err(anyhow::anyhow!(
+                    "Operator {} is registered more than once",
+                    operator.name
+                ));
+            }
+            registry.operators.push(Arc::new(operator));
+        }
+        let default_index = registry
+            .operators
+            .iter()
+            .position(|operator| operator.name == DEFAULT_OPERATOR)
+            .ok_or(anyhow::anyhow!("The default operator is missing"))?;
+        registry.operators.swap(0, default_index);
+        Ok(registry)
+    }
+
+    /// Operator of the `bitvmx` section of the config
+    pub fn default_operator(&self) -> &Arc<Operator> {
+        &self.operators[0]
+    }
+
+    /// Get an operator by name
+    pub fn get(&self, name: &str) -> Result<Arc<Operator>, UnknownOperator> {
+        self.operators
+            .iter()
+            .find(|operator| operator.name == name)
+            .cloned()
+            .ok_or(UnknownOperator(name.to_string()))
+    }
+
+    /// Operator picked by a route selector, the default operator when none is given
+    pub fn select(&self, name: Option<&str>) -> Result<Arc<Operator>, UnknownOperator> {
+        match name {
+            Some(name) => self.get(name),
+            None => Ok(self.default_operator().clone()),
+        }
+    }
+
+    /// Every operator, the default one first
+    pub fn list(&self) -> &[Arc<Operator>] {
+        &self.operators
+    }
+
+    /// Operator by name that is healthy
+    pub fn get_healthy(&self, name: &str) -> Result<Arc<Operator>, anyhow::Error> {
+        let operator = self.get(name)?;
+        operator.check_healthy()?;
+        Ok(operator)
+    }
+}
+
+/// Wait for a task of each operator, e.g. its setup. The operators whose task fails or
+/// panics are marked unhealthy and the others keep going. Returns how many failed.
+pub async fn join_operator_tasks(
+    operators: &OperatorRegistry,
+    tasks: Vec<(String, JoinHandle<Result<(), anyhow::Error>>)>,
+    task_name: &str,
+) -> usize {
+    let mut joined = JoinSet::new();
+    for (name, task) in tasks {
+        // Awaiting the handle turns a panic into an error of the operator
+        joined.spawn(async move { (name, task.await) });
+    }
+    let mut failed = 0;
+    while let Some(result) = joined.join_next().await {
+        let (name, reason) = match result {
+            Ok((_, Ok(Ok(())))) => continue,
+            Ok((name, Ok(Err(e)))) => (name, format!("{task_name} failed: {e}")),
+            Ok((name, Err(e))) => (name, format!("{task_name} panicked: {e}")),
+            Err(e) => {
+                error!("❌ Failed to join the {task_name} of an operator: {e}");
+                continue;
+            }
+        };
+        failed += 1;
+        match operators.get(&name) {
+            Ok(operator) => operator.mark_unhealthy(reason),
+            Err(e) => error!("❌ {e}, {reason}"),
+        }
+    }
+    failed
+}
+
+/// Run the listeners of every operator, an operator whose listener fails is marked unhealthy.
+/// It only fails when every listener failed.
+pub fn join_listeners(
+    operators: Arc<OperatorRegistry>,
+    listener_tasks: Vec<(String, ListenerTask)>,
+) -> ListenerTask {
+    tokio::spawn(async move {
+        let listeners = listener_tasks.len();
+        let failed = join_operator_tasks(&operators, listener_tasks, "BitVMX listener").await;
+        if failed > 0 && failed == listeners {
+            return Err(anyhow::anyhow!(
+                "The BitVMX listener of every operator failed"
+            ));
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::BitcoindService;
+    use crate::stores::BitvmxStore;
+    use crate::test_support::{FakeBitvmx, FakeChain};
+    use tokio::sync::broadcast;
+
+    fn operator(name: &str, shutdown_tx: &broadcast::Sender<()>) -> Operator {
+        let bitvmx = FakeBitvmx::new(&FakeChain::new());
+        let (rpc_client, _listener_task) = RpcClient::with_transport(bitvmx, shutdown_tx);
+        let bitcoin_service = Arc::new(BitcoindService::new(
+            crate::config::Config::default().bitcoin,
+        ));
+        let bitvmx_service = Arc::new(BitvmxService::new(
+            rpc_client.clone(),
+            bitcoin_service,
+            Arc::new(BitvmxStore::new()),
+        ));
+        Operator::new(name.to_string(), rpc_client, bitvmx_service)
+    }
+
+    #[tokio::test]
+    async fn test_select_operator() {
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let registry = OperatorRegistry::new(vec![
+            operator("operator_2", &shutdown_tx),
+            operator(DEFAULT_OPERATOR, &shutdown_tx),
+        ])
+        .unwrap();
+
+        assert_eq!(registry.default_operator().name, DEFAULT_OPERATOR);
+        assert_eq!(registry.select(None).unwrap().name, DEFAULT_OPERATOR);
+        assert_eq!(
+            registry.select(Some("operator_2")).unwrap().name,
+            "operator_2"
+        );
+        assert_eq!(
+            registry.select(Some("missing")).unwrap_err(),
+            UnknownOperator("missing".to_string())
+        );
+        let _ = shutdown_tx.send(());
+    }
+
+    #[tokio::test]
+    async fn test_registry_needs_unique_names_and_a_default_operator() {
+        let (shutdown_tx, _) = broadcast::channel(1);
+        assert!(OperatorRegistry::new(vec![operator("operator_2", &shutdown_tx)]).is_err());
+        assert!(OperatorRegistry::new(vec![
+            operator(DEFAULT_OPERATOR, &shutdown_tx),
+            operator(DEFAULT_OPERATOR, &shutdown_tx),
+        ])
+        .is_err());
+        let _ = shutdown_tx.send(());
+    }
+
+    #[tokio::test]
+    async fn test_failed_operator_is_unhealthy_and_the_others_keep_serving() {
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let registry = Arc::new(
+            OperatorRegistry::new(vec![
+                operator(DEFAULT_OPERATOR, &shutdown_tx),
+                operator("operator_2", &shutdown_tx),
+            ])
+            .unwrap(),
+        );
+
+        let tasks: Vec<(String, ListenerTask)> = vec![
+            (DEFAULT_OPERATOR.to_string(), tokio::spawn(async { Ok(()) })),
+            (
+                "operator_2".to_string(),
+                tokio::spawn(async { Err(anyhow::anyhow!("BitVMX is gone")) }),
+            ),
+        ];
+        assert_eq!(join_operator_tasks(&registry, tasks, "setup").await, 1);
+        assert!(registry.get_healthy(DEFAULT_OPERATOR).is_ok());
+        let error = registry.get_healthy("operator_2").unwrap_err();
+        let unhealthy = error.downcast_ref::<UnhealthyOperator>().unwrap();
+        assert_eq!(unhealthy.reason, "setup failed: BitVMX is gone");
+        // Still listed so its state can be checked
+        assert_eq!(
+            registry.select(Some("operator_2")).unwrap().name,
+            "operator_2"
+        );
+
+        // The listeners only fail when all of them failed
+        let listener = join_listeners(
+            registry.clone(),
+            vec![(
+                "operator_2".to_string(),
+                tokio::spawn(async { panic!("listener panicked") }),
+            )],
+        );
+        assert!(listener.await.unwrap().is_err());
+        assert!(registry
+            .get("operator_2")
+            .unwrap()
+            .unhealthy_reason()
+            .unwrap()
+            .contains("panicked"));
+        let _ = shutdown_tx.send(());
+    }
+}
@@ -0,0 +1,172 @@
+use crate::config::GameConfig;
+use crate::games::Game;
+use crate::jobs::JobWorker;
+use crate::routes;
+use crate::services::{GameService, OperatorRegistry, WorkerService};
+use crate::state::AppState;
+use crate::stores::GameStore;
+use async_trait::async_trait;
+use axum::Router;
+use std::any::Any;
+use std::fmt::Debug;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+/// Game type played by the backend, without its type so the games of every type are in one registry
+#[async_trait]
+pub trait RegisteredGame: Debug + Send + Sync + 'static {
+    /// Name of the game, e.g. "add-numbers"
+    fn game_type(&self) -> &'static str;
+
+    /// Routes of the game, nested under /api/games/{game_type}/
+    fn router(&self) -> Router<AppState>;
+
+    /// Re-enqueue the background waits of the persisted games after a restart
+    async fn resume_pending_jobs(&self) -> Result<usize, anyhow::Error>;
+
+    /// Reconcile the stored games with the chain every interval until the backend stops
+    async fn handle_reconciliation(&self, shutdown_rx: broadcast::Receiver<()>);
+
+    /// The worker service of the game, to get back its type
+    fn as_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync>;
+}
+
+#[async_trait]
+impl<G: Game> RegisteredGame for WorkerService<G> {
+    fn game_type(&self) -> &'static str {
+        G::GAME_TYPE
+    }
+
+    fn router(&self) -> Router<AppState> {
+        routes::games::router(self.game_service().clone(), Arc::new(self.clone()))
+    }
+
+    async fn resume_pending_jobs(&self) -> Result<usize, anyhow::Error> {
+        WorkerService::resume_pending_jobs(self).await
+    }
+
+    async fn handle_reconciliation(&self, shutdown_rx: broadcast::Receiver<()>) {
+        WorkerService::handle_reconciliation(self, shutdown_rx).await
+    }
+
+    fn as_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self
+    }
+}
+
+/// Games played by the backend, the game services share the stores, operators and job worker
+#[derive(Debug)]
+pub struct GameRegistry {
+    game_store: Arc<dyn GameStore>,
+    operators: Arc<OperatorRegistry>,
+    game_config: GameConfig,
+    job_worker: Arc<JobWorker>,
+    games: Vec<Arc<dyn RegisteredGame>>,
+}
+
+impl GameRegistry {
+    /// Registry without games
+    pub fn new(
+        game_store: Arc<dyn GameStore>,
+        operators: Arc<OperatorRegistry>,
+        game_config: GameConfig,
+        job_worker: Arc<JobWorker>,
+    ) -> Self {
+        Self {
+            game_store,
+            operators,
+            game_config,
+            job_worker,
+            games: Vec::new(),
+        }
+    }
+
+    /// Register a game, its routes are served and its persisted jobs are resumed
+    pub fn register<G: Game>(&mut self, game: G) -> Result<(), anyhow::Error> {
+        if self.get(G::GAME_TYPE).is_some() {
+            return Err(anyhow::anyhow!(
+                "Game {} is registered more than once",
+                G::GAME_TYPE
+            ));
+        }
+        let game_service = Arc::new(GameService::new(
+            game,
+            self.game_store.clone(),
+            self.operators.clone(),
+            self.game_config.clone(),
+        ));
+        let worker_service = Arc::new(WorkerService::new(self.job_worker.clone(), game_service));
+        self.games.push(worker_service);
+        Ok(())
+    }
+
+    /// Get a game by game type
+    pub fn get(&self, game_type: &str) -> Option<&Arc<dyn RegisteredGame>> {
+        self.games.iter().find(|game| game.game_type() == game_type)
+    }
+
+    /// Every registered game
+    pub fn all(&self) -> &[Arc<dyn RegisteredGame>] {
+        &self.games
+    }
+
+    /// Worker service of the game `G`, None when it is not registered
+    pub fn worker_service<G: Game>(&self) -> Option<Arc<WorkerService<G>>> {
+        self.get(G::GAME_TYPE)?
+            .clone()
+            .as_any()
+            .downcast::<WorkerService<G>>()
+            .ok()
+    }
+
+    /// Game service of the game `G`, None when it is not registered
+    pub fn game_service<G: Game>(&self) -> Option<Arc<GameService<G>>> {
+        Some(self.worker_service::<G>()?.game_service().clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::AddNumbers;
+    use crate::jobs::{GameJobFactory, JobFactory, WAIT_PAYOUT};
+    use crate::models::{BackoffPolicy, JobRecord};
+    use crate::stores::MemoryGameStore;
+    use crate::test_support::{FakeChain, FakePlayer};
+    use tokio::sync::broadcast;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_game_registry() {
+        let chain = FakeChain::new();
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let player = FakePlayer::start(&chain, &shutdown_tx).unwrap();
+        let games = &player.state.games;
+
+        assert!(games.get(AddNumbers::GAME_TYPE).is_some());
+        assert!(games.get("other").is_none());
+        let game_service = games.game_service::<AddNumbers>().unwrap();
+
+        // A game type is registered once
+        let mut registry = GameRegistry::new(
+            Arc::new(MemoryGameStore::new()),
+            player.state.operators.clone(),
+            player.state.config.game.clone(),
+            player.state.job_worker.clone(),
+        );
+        let game = game_service.game().clone();
+        registry.register(game.clone()).unwrap();
+        assert!(registry.register(game).is_err());
+
+        // Jobs are only rebuilt by the factory of their game
+        let factory = GameJobFactory { game_service };
+        let mut record = JobRecord::new(WAIT_PAYOUT, Uuid::new_v4(), BackoffPolicy::default(), 0);
+        assert!(factory.build(&record).is_some());
+        record.game_type = Some(AddNumbers::GAME_TYPE.to_string());
+        assert!(factory.build(&record).is_some());
+        record.game_type = Some("other".to_string());
+        assert!(factory.build(&record).is_none());
+
+        let _ = shutdown_tx.send(());
+    }
+}
@@ -1,39 +1,358 @@
 use uuid::Uuid;
 
 use crate::{
-    jobs::{JobWorker, WaitPlayer2WinsGameOutcomeJob, WaitStartGameJob},
-    services::AddNumbersService,
+    games::{AddNumbers, Game, GamePhase, GameStatus},
+    jobs::{
+        GameJobFactory, JobWorker, ReconcileGamesJob, WaitPayoutJob, WaitPlayer2WinsGameOutcomeJob,
+        WaitStartGameJob, WaitSubmitOutcomeJob, WatchTimeoutJob, WAIT_PAYOUT,
+        WAIT_PLAYER2_WINS_GAME_OUTCOME, WAIT_START_GAME, WAIT_SUBMIT_OUTCOME, WATCH_TIMEOUT,
+    },
+    models::{GameRecord, PlayerRole},
+    services::{BitvmxService, GameService},
 };
 use std::sync::Arc;
+use tokio::sync::broadcast;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{debug, info, warn};
 
-#[derive(Debug, Clone)]
-pub struct WorkerService {
+/// Background wait a game still needs after a restart
+#[derive(Debug, Clone, PartialEq)]
+pub enum PendingWait {
+    /// Player 2 waits for player 1 to dispatch the start challenge transaction
+    StartGame,
+    /// Player 1 waits for player 2 input and the dispute outcome
+    Player2WinsGameOutcome,
+    /// Player 2 already sent the answer and waits for the dispute outcome
+    SubmitOutcome,
+    /// The winner claimed the bet and waits for the payout transaction
+    Payout,
+    /// Both players follow the block height to claim a timeout during the dispute
+    Timeout,
+}
+
+impl PendingWait {
+    /// Work out which waits are still pending for a persisted game in `phase`,
+    /// player 2 answers the challenge in the `submit_input_index` program input
+    pub fn for_game(game: &GameRecord, phase: &GamePhase, submit_input_index: u32) -> Vec<Self> {
+        let timeout = matches!(phase, GamePhase::StartGame | GamePhase::SubmitGameData)
+            .then_some(Self::Timeout);
+
+        let pending_wait = match (phase, &game.role) {
+            (GamePhase::StartGame, PlayerRole::Player2) => Some(Self::StartGame),
+            (GamePhase::SubmitGameData, PlayerRole::Player1) => Some(Self::Player2WinsGameOutcome),
+            (GamePhase::SubmitGameData, PlayerRole::Player2) => {
+                // Only once the input transaction was sent, otherwise player 2 still has to submit the answer
+                let input_tx_name = BitvmxService::dispute_input_tx_name(submit_input_index);
+                game.bitvmx_program_properties
+                    .txs
+                    .contains_key(&input_tx_name)
+                    .then_some(Self::SubmitOutcome)
+            }
+            (GamePhase::TransferBetFunds, _) => Some(Self::Payout),
+            _ => None,
+        };
+
+        pending_wait.into_iter().chain(timeout).collect()
+    }
+
+    /// Kind of the job that waits for it
+    pub fn job_kind(&self) -> &'static str {
+        match self {
+            Self::StartGame => WAIT_START_GAME,
+            Self::Player2WinsGameOutcome => WAIT_PLAYER2_WINS_GAME_OUTCOME,
+            Self::SubmitOutcome => WAIT_SUBMIT_OUTCOME,
+            Self::Payout => WAIT_PAYOUT,
+            Self::Timeout => WATCH_TIMEOUT,
+        }
+    }
+}
+
+/// Enqueues the background jobs of the games played by a [`GameService`]
+#[derive(Debug)]
+pub struct WorkerService<G: Game = AddNumbers> {
     job_worker: Arc<JobWorker>,
-    add_numbers_service: Arc<AddNumbersService>,
+    game_service: Arc<GameService<G>>,
+}
+
+impl<G: Game> Clone for WorkerService<G> {
+    fn clone(&self) -> Self {
+        Self {
+            job_worker: self.job_worker.clone(),
+            game_service: self.game_service.clone(),
+        }
+    }
 }
 
-impl WorkerService {
-    pub fn new(job_worker: Arc<JobWorker>, add_numbers_service: Arc<AddNumbersService>) -> Self {
+impl<G: Game> WorkerService<G> {
+    /// New WorkerService, the persisted jobs of the game are resumed by the job worker
+    pub fn new(job_worker: Arc<JobWorker>, game_service: Arc<GameService<G>>) -> Self {
+        job_worker.register_factory(Arc::new(GameJobFactory {
+            game_service: game_service.clone(),
+        }));
         Self {
             job_worker: job_worker.clone(),
-            add_numbers_service: add_numbers_service.clone(),
+            game_service: game_service.clone(),
+        }
+    }
+
+    /// Game service whose jobs are enqueued
+    pub fn game_service(&self) -> &Arc<GameService<G>> {
+        &self.game_service
+    }
+
+    pub async fn handle_start_game_tx(&self, program_id: Uuid) -> Result<(), anyhow::Error> {
+        self.job_worker
+            .enqueue(WaitStartGameJob {
+                program_id,
+                game_service: self.game_service.clone(),
+            })
+            .await?;
+        Ok(())
+    }
+
+    pub async fn handle_player2_wins_game_outcome_tx(
+        &self,
+        program_id: Uuid,
+    ) -> Result<(), anyhow::Error> {
+        self.job_worker
+            .enqueue(WaitPlayer2WinsGameOutcomeJob {
+                program_id,
+                game_service: self.game_service.clone(),
+            })
+            .await?;
+        Ok(())
+    }
+
+    pub async fn handle_submit_outcome_tx(&self, program_id: Uuid) -> Result<(), anyhow::Error> {
+        self.job_worker
+            .enqueue(WaitSubmitOutcomeJob {
+                program_id,
+                game_service: self.game_service.clone(),
+            })
+            .await?;
+        Ok(())
+    }
+
+    pub async fn handle_payout_tx(&self, program_id: Uuid) -> Result<(), anyhow::Error> {
+        self.job_worker
+            .enqueue(WaitPayoutJob {
+                program_id,
+                game_service: self.game_service.clone(),
+            })
+            .await?;
+        Ok(())
+    }
+
+    pub async fn handle_timeout_watch(&self, program_id: Uuid) -> Result<(), anyhow::Error> {
+        self.job_worker
+            .enqueue(WatchTimeoutJob {
+                program_id,
+                game_service: self.game_service.clone(),
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Enqueue a `reconcile_games` job every `reconcile_interval_secs` until the backend stops,
+    /// the tick is skipped while the job of the previous one is still queued or running
+    pub async fn handle_reconciliation(&self, mut shutdown_rx: broadcast::Receiver<()>) {
+        let mut ticker = interval(self.game_service.reconcile_interval());
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = shutdown_rx.recv() => break,
+                _ = ticker.tick() => {}
+            }
+            let job = ReconcileGamesJob {
+                game_service: self.game_service.clone(),
+            };
+            if let Err(e) = self.job_worker.enqueue(job).await {
+                warn!(
+                    "Failed to enqueue the {} games reconciliation: {e:?}",
+                    G::GAME_TYPE
+                );
+            }
         }
+        debug!("{} games reconciliation stopped", G::GAME_TYPE);
     }
 
-    pub fn handle_start_game_tx(&self, program_id: Uuid) -> Result<(), anyhow::Error> {
-        self.job_worker.enqueue(WaitStartGameJob {
-            program_id,
-            add_numbers_service: self.add_numbers_service.clone(),
-        })
+    /// Re-enqueue the background waits of the persisted games after a restart,
+    /// unless the job worker already resumes their persisted job
+    /// Returns the number of jobs enqueued
+    pub async fn resume_pending_jobs(&self) -> Result<usize, anyhow::Error> {
+        let games = self.game_service.list_games().await?;
+        let submit_input_index = self.game_service.game().submit_input_index();
+        let mut resumed = 0;
+        for game in games {
+            let phase = match game.status_as::<G::Status>() {
+                Ok(status) => status.phase(),
+                Err(e) => {
+                    warn!("Cannot resume game {}: {e:?}", game.program_id);
+                    continue;
+                }
+            };
+            let pending_waits = PendingWait::for_game(&game, &phase, submit_input_index);
+            if pending_waits.is_empty() {
+                debug!("No pending wait for game {}", game.program_id);
+            }
+
+            for pending_wait in pending_waits {
+                if self
+                    .job_worker
+                    .is_pending(pending_wait.job_kind(), game.program_id)
+                    .await
+                {
+                    debug!(
+                        "{:?} wait for game {} is already queued",
+                        pending_wait, game.program_id
+                    );
+                    continue;
+                }
+                info!(
+                    "Resuming {:?} wait for game {}",
+                    pending_wait, game.program_id
+                );
+                self.resume(&pending_wait, game.program_id).await?;
+                resumed += 1;
+            }
+        }
+
+        Ok(resumed)
     }
 
-    pub fn handle_player2_wins_game_outcome_tx(
+    async fn resume(
         &self,
+        pending_wait: &PendingWait,
         program_id: Uuid,
     ) -> Result<(), anyhow::Error> {
-        self.job_worker.enqueue(WaitPlayer2WinsGameOutcomeJob {
-            program_id,
-            add_numbers_service: self.add_numbers_service.clone(),
-        })
+        match pending_wait {
+            PendingWait::StartGame => self.handle_start_game_tx(program_id).await,
+            PendingWait::Player2WinsGameOutcome => {
+                self.handle_player2_wins_game_outcome_tx(program_id).await
+            }
+            PendingWait::SubmitOutcome => self.handle_submit_outcome_tx(program_id).await,
+            PendingWait::Payout => self.handle_payout_tx(program_id).await,
+            PendingWait::Timeout => self.handle_timeout_watch(program_id).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{AddNumbersGameStatus, BitVMXProgramProperties, GameOutcome, GameReason};
+    use crate::test_support::{FakeChain, FakePlayer};
+    use bitvmx_client::bitcoin::PublicKey;
+    use std::collections::HashMap;
+    use std::str::FromStr;
+    use tokio::sync::broadcast;
+
+    fn game(status: AddNumbersGameStatus, role: PlayerRole) -> GameRecord {
+        GameRecord {
+            program_id: Uuid::new_v4(),
+            game_type: AddNumbers::GAME_TYPE.to_string(),
+            operator: crate::config::DEFAULT_OPERATOR.to_string(),
+            role,
+            status: serde_json::to_value(status).unwrap(),
+            created_at: 0,
+            updated_at: 0,
+            bitvmx_program_properties: BitVMXProgramProperties {
+                aggregated_key: PublicKey::from_str(
+                    "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+                )
+                .unwrap(),
+                aggregated_id: Uuid::new_v4(),
+                protocol_address: String::new(),
+                participants_addresses: vec![],
+                participants_keys: vec![],
+                funding_protocol_utxo: None,
+                funding_bet_utxo: None,
+                txs: HashMap::new(),
+                dispute_parameters: None,
+            },
+            payout_txid: None,
+            contradictions: vec![],
+            data: serde_json::Map::new(),
+        }
+    }
+
+    fn pending_waits(game: &GameRecord) -> Vec<PendingWait> {
+        let status: AddNumbersGameStatus = game.status_as().unwrap();
+        PendingWait::for_game(game, &status.phase(), 1)
+    }
+
+    #[test]
+    fn test_pending_wait_for_game() {
+        use AddNumbersGameStatus::*;
+        use PlayerRole::*;
+
+        assert_eq!(
+            pending_waits(&game(StartGame, Player2)),
+            vec![PendingWait::StartGame, PendingWait::Timeout]
+        );
+        assert_eq!(
+            pending_waits(&game(StartGame, Player1)),
+            vec![PendingWait::Timeout]
+        );
+        assert_eq!(
+            pending_waits(&game(SubmitGameData, Player1)),
+            vec![PendingWait::Player2WinsGameOutcome, PendingWait::Timeout]
+        );
+
+        // Player 2 has not sent the sum yet
+        let mut submit = game(SubmitGameData, Player2);
+        assert_eq!(pending_waits(&submit), vec![PendingWait::Timeout]);
+        submit.bitvmx_program_properties.txs.insert(
+            BitvmxService::dispute_input_tx_name(1),
+            serde_json::Value::Null,
+        );
+        assert_eq!(
+            pending_waits(&submit),
+            vec![PendingWait::SubmitOutcome, PendingWait::Timeout]
+        );
+
+        let complete = GameComplete {
+            outcome: GameOutcome::Win,
+            reason: GameReason::Challenge,
+        };
+        assert_eq!(
+            pending_waits(&game(TransferBetFunds, Player1)),
+            vec![PendingWait::Payout]
+        );
+
+        for status in [PlaceBet, SetupFunding, SetupGame, complete, Finished] {
+            assert!(pending_waits(&game(status.clone(), Player1)).is_empty());
+            assert!(pending_waits(&game(status, Player2)).is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_waiting_twice_for_the_same_game_enqueues_one_job() {
+        let chain = FakeChain::new();
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let player = FakePlayer::start(&chain, &shutdown_tx).unwrap();
+        let worker_service = &player.add_numbers_worker();
+        let program_id = Uuid::new_v4();
+
+        worker_service
+            .handle_start_game_tx(program_id)
+            .await
+            .unwrap();
+        worker_service
+            .handle_start_game_tx(program_id)
+            .await
+            .unwrap();
+        worker_service
+            .handle_timeout_watch(program_id)
+            .await
+            .unwrap();
+
+        let jobs = player.state.job_worker.list(None).await;
+        let kinds: Vec<_> = jobs.iter().map(|job| job.kind.as_str()).collect();
+        assert_eq!(jobs.len(), 2, "{kinds:?}");
+        assert!(kinds.contains(&WAIT_START_GAME));
+        assert!(kinds.contains(&WATCH_TIMEOUT));
+
+        let _ = shutdown_tx.send(());
     }
 }
@@ -0,0 +1,194 @@
+use crate::jobs::JobWorker;
+use crate::rpc::ListenerTask;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::Sender;
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+use tracing::{error, info, warn};
+
+/// Parts of the backend stopped by [`Shutdown::run`], in the order they are stopped
+#[derive(Debug)]
+pub struct Shutdown {
+    /// Stops the HTTP server, the job worker loop and the setup task
+    pub shutdown_tx: Sender<()>,
+    pub http_task: JoinHandle<Result<(), anyhow::Error>>,
+    pub job_worker: Arc<JobWorker>,
+    pub job_worker_task: JoinHandle<Result<(), anyhow::Error>>,
+    /// How long the HTTP requests and the running jobs have to finish
+    pub grace_period: Duration,
+    /// Database of the game, job and BitVMX stores, `None` when they are in memory
+    pub db: Option<sled::Db>,
+    /// Closes the BitVMX connections, kept open until the jobs are drained
+    pub rpc_shutdown_tx: Sender<()>,
+    pub rpc_listener_task: ListenerTask,
+}
+
+impl Shutdown {
+    /// Stop the HTTP server, drain the jobs, flush the stores and close the BitVMX connections.
+    /// The tasks that already finished are skipped
+    pub async fn run(self) -> Result<(), anyhow::Error> {
+        info!("Shutdown 1/4: stopping the HTTP server and the job worker");
+        let _ = self.shutdown_tx.send(());
+        join("axum_server", self.http_task, self.grace_period).await;
+        join("job_worker", self.job_worker_task, self.grace_period).await;
+
+        info!(
+            "Shutdown 2/4: waiting up to {} ms for the running jobs",
+            self.grace_period.as_millis()
+        );
+        let checkpointed = self.job_worker.drain(self.grace_period).await?;
+        info!(
+            "Shutdown 2/4: jobs drained, {} queued again for the next start",
+            checkpointed.len()
+        );
+
+        match &self.db {
+            Some(db) => {
+                info!("Shutdown 3/4: flushing the stores");
+                db.flush_async()
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Failed to flush the stores: {e:?}"))?;
+            }
+            None => info!("Shutdown 3/4: stores are in memory, nothing to flush"),
+        }
+
+        info!("Shutdown 4/4: closing the BitVMX connections");
+        let _ = self.rpc_shutdown_tx.send(());
+        join("rpc_listener", self.rpc_listener_task, self.grace_period).await;
+
+        info!("Shutdown complete");
+        Ok(())
+    }
+}
+
+/// Wait up to `grace_period` for a task that was told to stop
+async fn join(name: &str, mut task: JoinHandle<Result<(), anyhow::Error>>, grace_period: Duration) {
+    // Awaiting a task again after it finished panics
+    if task.is_finished() {
+        return;
+    }
+    match timeout(grace_period, &mut task).await {
+        Ok(Ok(Ok(()))) => info!("{name}: Stopped"),
+        Ok(Ok(Err(e))) => error!("❌ {name}: Error: {e}"),
+        Ok(Err(e)) => error!("💥 {name}: Panic: {e}"),
+        Err(_) => {
+            warn!("{name}: Still running after the grace period, aborting it");
+            task.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::JobsConfig;
+    use crate::jobs::Job;
+    use crate::models::JobStatus;
+    use crate::rpc::{requests, RequestOptions, RpcClient};
+    use crate::stores::MemoryJobStore;
+    use crate::test_support::{wait_until, FakeBitvmx, FakeChain};
+    use async_trait::async_trait;
+    use tokio::sync::broadcast;
+    use tokio::time::sleep;
+    use uuid::Uuid;
+
+    /// Job that talks to BitVMX once the shutdown started
+    #[derive(Debug)]
+    struct LateRpcJob {
+        rpc_client: Arc<RpcClient>,
+    }
+
+    #[async_trait]
+    impl Job for LateRpcJob {
+        fn kind(&self) -> &'static str {
+            "late_rpc"
+        }
+
+        fn program_id(&self) -> Uuid {
+            Uuid::nil()
+        }
+
+        async fn run(&self) -> Result<(), anyhow::Error> {
+            sleep(Duration::from_millis(200)).await;
+            self.rpc_client
+                .send_typed(&requests::GetCommInfo, RequestOptions::default())
+                .await?;
+            Ok(())
+        }
+    }
+
+    /// Job that never finishes
+    #[derive(Debug)]
+    struct StuckJob;
+
+    #[async_trait]
+    impl Job for StuckJob {
+        fn kind(&self) -> &'static str {
+            "stuck"
+        }
+
+        fn program_id(&self) -> Uuid {
+            Uuid::nil()
+        }
+
+        async fn run(&self) -> Result<(), anyhow::Error> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_jobs_before_closing_bitvmx() {
+        let chain = FakeChain::new();
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let (rpc_shutdown_tx, _) = broadcast::channel(1);
+        let (rpc_client, rpc_listener_task) =
+            RpcClient::with_transport(FakeBitvmx::new(&chain), &rpc_shutdown_tx);
+        let (job_worker, job_worker_task) = JobWorker::start(
+            Arc::new(MemoryJobStore::new()),
+            JobsConfig::default(),
+            &shutdown_tx,
+        );
+        let mut http_shutdown_rx = shutdown_tx.subscribe();
+        let http_task = tokio::spawn(async move {
+            let _ = http_shutdown_rx.recv().await;
+            Ok(())
+        });
+        let db = sled::Config::new().temporary(true).open().unwrap();
+
+        let late_rpc_id = job_worker
+            .enqueue(LateRpcJob {
+                rpc_client: rpc_client.clone(),
+            })
+            .await
+            .unwrap();
+        let stuck_id = job_worker.enqueue(StuckJob).await.unwrap();
+        wait_until("both jobs run", || async {
+            job_worker.list(Some(JobStatus::Running)).await.len() == 2
+        })
+        .await;
+
+        Shutdown {
+            shutdown_tx,
+            http_task,
+            job_worker: job_worker.clone(),
+            job_worker_task,
+            grace_period: Duration::from_secs(1),
+            db: Some(db),
+            rpc_shutdown_tx,
+            rpc_listener_task,
+        }
+        .run()
+        .await
+        .unwrap();
+
+        // BitVMX was still reachable while the jobs were drained
+        let late_rpc = job_worker.get(late_rpc_id).await.unwrap();
+        assert_eq!(late_rpc.status, JobStatus::Succeeded);
+        assert_eq!(late_rpc.last_error, None);
+        // The stuck job resumes on the next start
+        let stuck = job_worker.get(stuck_id).await.unwrap();
+        assert_eq!(stuck.status, JobStatus::Queued);
+        assert_eq!(stuck.attempts, 0);
+    }
+}
@@ -1,8 +1,11 @@
 use crate::config::Config;
+use crate::games;
 use crate::jobs::JobWorker;
 use crate::rpc::rpc_client::RpcClient;
-use crate::services::{bitvmx::BitvmxService, AddNumbersService};
-use crate::services::{BitcoinService, WorkerService};
+use crate::services::bitvmx::BitvmxService;
+use crate::services::operators::{Operator, OperatorRegistry};
+use crate::services::{BitcoinService, GameRegistry};
+use crate::stores;
 use std::sync::Arc;
 
 /// Shared application state that can be accessed by both Axum routes and BitVMX RPC
@@ -10,43 +13,63 @@ use std::sync::Arc;
 pub struct AppState {
     /// Configuration
     pub config: Arc<Config>,
-    /// Game services
-    pub add_numbers_service: Arc<AddNumbersService>,
-    /// BitVMX service
+    /// Games played by the backend by game type
+    pub games: Arc<GameRegistry>,
+    /// BitVMX service of the default operator
     pub bitvmx_service: Arc<BitvmxService>,
     /// Bitcoin service
-    pub bitcoin_service: Arc<BitcoinService>,
-    /// BitVMX RPC client
+    pub bitcoin_service: Arc<dyn BitcoinService>,
+    /// BitVMX RPC client of the default operator
     pub rpc_client: Arc<RpcClient>,
-    /// Worker service
-    pub worker_service: Arc<WorkerService>,
+    /// BitVMX nodes of every operator
+    pub operators: Arc<OperatorRegistry>,
+    /// Background jobs
+    pub job_worker: Arc<JobWorker>,
 }
 
 impl AppState {
-    /// Create a new application state
-    pub fn new(config: Config, rpc_client: Arc<RpcClient>, job_worker: Arc<JobWorker>) -> Self {
-        let bitcoin_service = Arc::new(BitcoinService::new(config.bitcoin.clone()));
-        let bitvmx_service = Arc::new(BitvmxService::new(
-            rpc_client.clone(),
-            bitcoin_service.clone(),
-        ));
+    /// Create a new application state with the RPC client of each operator by name,
+    /// the stores are persisted in `db` when the configured storage has one
+    pub fn new(
+        config: Config,
+        db: Option<&sled::Db>,
+        rpc_clients: Vec<(String, Arc<RpcClient>)>,
+        bitcoin_service: Arc<dyn BitcoinService>,
+        job_worker: Arc<JobWorker>,
+    ) -> Result<Self, anyhow::Error> {
+        let game_store = stores::new_game_store(db)?;
 
-        // Create AddNumbersService without WorkerService
-        let add_numbers_service = Arc::new(AddNumbersService::new(bitvmx_service.clone()));
+        let operators = rpc_clients
+            .into_iter()
+            .map(|(name, rpc_client)| {
+                let bitvmx_store = stores::new_bitvmx_store(db, &name)?;
+                let bitvmx_service = Arc::new(BitvmxService::new(
+                    rpc_client.clone(),
+                    bitcoin_service.clone(),
+                    bitvmx_store,
+                ));
+                Ok(Operator::new(name, rpc_client, bitvmx_service))
+            })
+            .collect::<Result<Vec<_>, anyhow::Error>>()?;
+        let operators = Arc::new(OperatorRegistry::new(operators)?);
+        let default_operator = operators.default_operator().clone();
 
-        // Create WorkerService with the AddNumbersService
-        let worker_service = Arc::new(WorkerService::new(
+        let mut games = GameRegistry::new(
+            game_store,
+            operators.clone(),
+            config.game.clone(),
             job_worker.clone(),
-            add_numbers_service.clone(),
-        ));
+        );
+        games::register_games(&mut games, &config.game)?;
 
-        Self {
+        Ok(Self {
             config: Arc::new(config.clone()),
-            add_numbers_service,
+            games: Arc::new(games),
             bitcoin_service,
-            bitvmx_service,
-            rpc_client,
-            worker_service,
-        }
+            bitvmx_service: default_operator.bitvmx_service.clone(),
+            rpc_client: default_operator.rpc_client.clone(),
+            operators,
+            job_worker,
+        })
     }
 }
@@ -1,19 +1,54 @@
+use crate::config::DEFAULT_OPERATOR;
 use crate::models::P2PAddress;
 use bitvmx_client::bitcoin::{Address, PublicKey};
-
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-#[derive(Debug, Clone)]
+use uuid::Uuid;
+
+/// Name of the sled tree and key where the BitVMX info is stored
+const BITVMX_TREE: &str = "bitvmx";
+const BITVMX_INFO_KEY: &str = "info";
+
+/// Tree of the BitVMX info of an operator, the default operator keeps the original tree
+fn bitvmx_tree(operator: &str) -> String {
+    if operator == DEFAULT_OPERATOR {
+        BITVMX_TREE.to_string()
+    } else {
+        format!("{BITVMX_TREE}_{operator}")
+    }
+}
+
+/// UTXO sent to the funding key and registered in BitVMX to pay for speedups
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FundingUtxo {
+    pub txid: String,
+    pub vout: u32,
+    pub amount: u64,
+    pub pub_key: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct BitvmxInfo {
     pub p2p_address: Option<P2PAddress>,
     pub pub_key: Option<String>,
+    /// Id used to generate the operator pub key in BitVMX
+    pub pub_key_id: Option<Uuid>,
     pub funding_key: Option<String>,
-    pub wallet_address: Option<Address>,
+    /// Id used to generate the funding key in BitVMX
+    pub funding_key_id: Option<Uuid>,
+    pub funding_utxo: Option<FundingUtxo>,
+    pub wallet_address: Option<String>,
+    pub wallet_funded: bool,
+    pub setup_completed: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct BitvmxStore {
     pub info: Arc<RwLock<BitvmxInfo>>,
+    /// Tree where the info is persisted, in memory only when not set
+    tree: Option<sled::Tree>,
 }
 
 impl Default for BitvmxStore {
@@ -25,15 +60,54 @@ impl Default for BitvmxStore {
 impl BitvmxStore {
     pub fn new() -> Self {
         Self {
-            info: Arc::new(RwLock::new(BitvmxInfo {
-                p2p_address: None,
-                pub_key: None,
-                funding_key: None,
-                wallet_address: None,
-            })),
+            info: Arc::new(RwLock::new(BitvmxInfo::default())),
+            tree: None,
         }
     }
 
+    /// Open the store of the operator persisted in the given database,
+    /// loading the info saved by a previous run
+    pub fn open(db: &sled::Db, operator: &str) -> Result<Self, anyhow::Error> {
+        let tree = db
+            .open_tree(bitvmx_tree(operator))
+            .map_err(|e| anyhow::anyhow!("Failed to open bitvmx tree: {e:?}"))?;
+        let info = match tree
+            .get(BITVMX_INFO_KEY)
+            .map_err(|e| anyhow::anyhow!("Failed to read bitvmx info: {e:?}"))?
+        {
+            Some(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| anyhow::anyhow!("Failed to deserialize bitvmx info: {e:?}"))?,
+            None => BitvmxInfo::default(),
+        };
+
+        Ok(Self {
+            info: Arc::new(RwLock::new(info)),
+            tree: Some(tree),
+        })
+    }
+
+    /// Apply a change to the info and persist it
+    async fn update(&self, update: impl FnOnce(&mut BitvmxInfo)) -> Result<(), anyhow::Error> {
+        let mut info = self.info.write().await;
+        update(&mut info);
+
+        if let Some(tree) = &self.tree {
+            let bytes = serde_json::to_vec(&*info)
+                .map_err(|e| anyhow::anyhow!("Failed to serialize bitvmx info: {e:?}"))?;
+            tree.insert(BITVMX_INFO_KEY, bytes)
+                .map_err(|e| anyhow::anyhow!("Failed to write bitvmx info: {e:?}"))?;
+            tree.flush_async()
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to flush bitvmx info: {e:?}"))?;
+        }
+        Ok(())
+    }
+
+    /// Get a copy of the whole info
+    pub async fn get_info(&self) -> BitvmxInfo {
+        self.info.read().await.clone()
+    }
+
     /// Get pub key
     pub async fn get_pub_key(&self) -> Result<Option<String>, anyhow::Error> {
         let bitvmx_info = self.info.read().await;
@@ -55,30 +129,139 @@ impl BitvmxStore {
     /// Get wallet address
     pub async fn get_wallet_address(&self) -> Result<Option<Address>, anyhow::Error> {
         let bitvmx_info = self.info.read().await;
-        Ok(bitvmx_info.wallet_address.clone())
+        bitvmx_info
+            .wallet_address
+            .as_deref()
+            .map(|address| {
+                Address::from_str(address)
+                    .map(|address| address.assume_checked())
+                    .map_err(|e| anyhow::anyhow!("Invalid stored wallet address: {e:?}"))
+            })
+            .transpose()
     }
 
-    /// Update P2P address
+    /// Update wallet address
     pub async fn set_wallet_address(&self, wallet_address: Address) -> Result<(), anyhow::Error> {
-        self.info.write().await.wallet_address = Some(wallet_address.clone());
-        Ok(())
+        self.update(|info| {
+            let wallet_address = wallet_address.to_string();
+            // A different wallet needs to be funded again
+            if info.wallet_address.as_ref() != Some(&wallet_address) {
+                info.wallet_funded = false;
+            }
+            info.wallet_address = Some(wallet_address);
+        })
+        .await
+    }
+
+    /// Mark the wallet as funded
+    pub async fn set_wallet_funded(&self) -> Result<(), anyhow::Error> {
+        self.update(|info| info.wallet_funded = true).await
     }
 
     /// Update P2P address
     pub async fn set_p2p_address(&self, p2p_address: P2PAddress) -> Result<(), anyhow::Error> {
-        self.info.write().await.p2p_address = Some(p2p_address);
-        Ok(())
+        self.update(|info| info.p2p_address = Some(p2p_address))
+            .await
     }
 
     /// Update pub key
-    pub async fn set_pub_key(&self, pub_key: PublicKey) -> Result<(), anyhow::Error> {
-        self.info.write().await.pub_key = Some(pub_key.to_string());
-        Ok(())
+    pub async fn set_pub_key(&self, id: Uuid, pub_key: PublicKey) -> Result<(), anyhow::Error> {
+        self.update(|info| {
+            info.pub_key_id = Some(id);
+            info.pub_key = Some(pub_key.to_string());
+        })
+        .await
     }
 
     /// Update funding key
-    pub async fn set_funding_key(&self, funding_key: PublicKey) -> Result<(), anyhow::Error> {
-        self.info.write().await.funding_key = Some(funding_key.to_string());
-        Ok(())
+    pub async fn set_funding_key(
+        &self,
+        id: Uuid,
+        funding_key: PublicKey,
+    ) -> Result<(), anyhow::Error> {
+        self.update(|info| {
+            info.funding_key_id = Some(id);
+            info.funding_key = Some(funding_key.to_string());
+        })
+        .await
+    }
+
+    /// Update the funding UTXO registered in BitVMX
+    pub async fn set_funding_utxo(&self, funding_utxo: FundingUtxo) -> Result<(), anyhow::Error> {
+        self.update(|info| info.funding_utxo = Some(funding_utxo))
+            .await
+    }
+
+    /// Mark the initial setup as completed
+    pub async fn set_setup_completed(&self, completed: bool) -> Result<(), anyhow::Error> {
+        self.update(|info| info.setup_completed = completed).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUB_KEY: &str = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+
+    #[tokio::test]
+    async fn test_bitvmx_info_survives_reopen() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let pub_key = PublicKey::from_str(PUB_KEY).unwrap();
+        let pub_key_id = Uuid::new_v4();
+        let address = Address::from_str("bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080")
+            .unwrap()
+            .assume_checked();
+
+        let store = BitvmxStore::open(&db, DEFAULT_OPERATOR).unwrap();
+        store.set_pub_key(pub_key_id, pub_key).await.unwrap();
+        store.set_wallet_address(address.clone()).await.unwrap();
+        store.set_wallet_funded().await.unwrap();
+        store.set_setup_completed(true).await.unwrap();
+        drop(store);
+
+        let store = BitvmxStore::open(&db, DEFAULT_OPERATOR).unwrap();
+        let info = store.get_info().await;
+        assert_eq!(info.pub_key_id, Some(pub_key_id));
+        assert_eq!(info.pub_key, Some(PUB_KEY.to_string()));
+        assert!(info.wallet_funded);
+        assert!(info.setup_completed);
+        assert_eq!(store.get_wallet_address().await.unwrap(), Some(address));
+        assert!(info.funding_key.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_each_operator_has_its_own_info() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let pub_key = PublicKey::from_str(PUB_KEY).unwrap();
+
+        let store = BitvmxStore::open(&db, "operator_2").unwrap();
+        store.set_pub_key(Uuid::new_v4(), pub_key).await.unwrap();
+
+        let default_store = BitvmxStore::open(&db, DEFAULT_OPERATOR).unwrap();
+        assert!(default_store.get_info().await.pub_key.is_none());
+        let store = BitvmxStore::open(&db, "operator_2").unwrap();
+        assert_eq!(store.get_info().await.pub_key, Some(PUB_KEY.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_new_wallet_address_needs_funding() {
+        let store = BitvmxStore::new();
+        let first = Address::from_str("bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080")
+            .unwrap()
+            .assume_checked();
+        let second = Address::from_str("bcrt1q6rhpng9evdsfnn833a4f4vej0asu6dk5srld6x")
+            .unwrap()
+            .assume_checked();
+
+        store.set_wallet_address(first.clone()).await.unwrap();
+        store.set_wallet_funded().await.unwrap();
+
+        // Same address keeps the funded flag
+        store.set_wallet_address(first).await.unwrap();
+        assert!(store.get_info().await.wallet_funded);
+
+        store.set_wallet_address(second).await.unwrap();
+        assert!(!store.get_info().await.wallet_funded);
     }
 }
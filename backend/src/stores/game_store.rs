@@ -0,0 +1,504 @@
+use crate::games::{GamePhase, GameStatus, GameStep};
+use crate::models::{
+    BitVMXProgramProperties, DisputeParameters, GameOutcome, GameReason, GameRecord, P2PAddress,
+    PlayerRole, Utxo,
+};
+use crate::utils;
+use async_trait::async_trait;
+use bitvmx_client::bitcoin::PublicKey;
+use bitvmx_client::bitcoin_coordinator::TransactionStatus;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+/// Mutation applied to a stored game inside [`GameStore::update_game`].
+/// If it returns an error the stored game is left untouched.
+pub type GameUpdate = Box<dyn FnOnce(&mut GameRecord) -> Result<(), anyhow::Error> + Send>;
+
+/// Current unix timestamp in seconds
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Fire `event` on the game, moving it to the next status of the transition table
+fn fire<S: GameStatus>(game: &mut GameRecord, event: S::Event) -> Result<(), anyhow::Error> {
+    let status: S = game.status_as()?;
+    game.set_status(&status.transition(&game.role, event)?)
+}
+
+/// Persistence for the games of every game type.
+///
+/// Implementations only provide the storage primitives, the game operations used by
+/// `GameService` are built on top of them by [`TypedGameStore`] so every backend validates
+/// games the same way.
+#[async_trait]
+pub trait GameStore: Send + Sync + Debug {
+    /// Insert a new game, fails if a game with the same program id already exists
+    async fn insert_game(&self, game: GameRecord) -> Result<(), anyhow::Error>;
+
+    /// Get a game by program id
+    async fn get_game(&self, id: Uuid) -> Result<Option<GameRecord>, anyhow::Error>;
+
+    /// List all the stored games
+    async fn list_games(&self) -> Result<Vec<GameRecord>, anyhow::Error>;
+
+    /// Atomically apply `update` to a stored game and return the updated game
+    async fn update_game(&self, id: Uuid, update: GameUpdate) -> Result<GameRecord, anyhow::Error>;
+
+    async fn set_dispute_tx(
+        &self,
+        program_id: Uuid,
+        dispute_tx_name: String,
+        dispute_tx: TransactionStatus,
+    ) -> Result<(), anyhow::Error> {
+        let dispute_tx_status = serde_json::to_value(dispute_tx)
+            .map_err(|e| anyhow::anyhow!("Failed to convert dispute transaction to JSON: {e:?}"))?;
+
+        self.update_game(
+            program_id,
+            Box::new(move |game| {
+                game.bitvmx_program_properties
+                    .txs
+                    .insert(dispute_tx_name, dispute_tx_status);
+                Ok(())
+            }),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Replace the contradictions between the game and the chain
+    async fn set_contradictions(
+        &self,
+        program_id: Uuid,
+        contradictions: Vec<String>,
+    ) -> Result<GameRecord, anyhow::Error> {
+        self.update_game(
+            program_id,
+            Box::new(move |game| {
+                game.contradictions = contradictions;
+                Ok(())
+            }),
+        )
+        .await
+    }
+}
+
+/// Games of one game type in a [`GameStore`], with the game operations that move them
+/// through the statuses `S` of the game type
+pub struct TypedGameStore<S> {
+    store: Arc<dyn GameStore>,
+    status: PhantomData<fn() -> S>,
+}
+
+impl<S> Debug for TypedGameStore<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("TypedGameStore").field(&self.store).finish()
+    }
+}
+
+impl<S> Deref for TypedGameStore<S> {
+    type Target = dyn GameStore;
+
+    fn deref(&self) -> &Self::Target {
+        self.store.as_ref()
+    }
+}
+
+impl<S: GameStatus> TypedGameStore<S> {
+    pub fn new(store: Arc<dyn GameStore>) -> Self {
+        Self {
+            store,
+            status: PhantomData,
+        }
+    }
+
+    /// Create the game of `game_type` on the `operator` BitVMX node once the aggregated key
+    /// of the participants is known
+    #[allow(clippy::too_many_arguments)]
+    pub async fn setup_participants(
+        &self,
+        program_id: Uuid,
+        game_type: &str,
+        operator: &str,
+        aggregated_id: Uuid,
+        participants_addresses: Vec<P2PAddress>,
+        participants_keys: Vec<String>,
+        aggregated_key: PublicKey,
+        role: PlayerRole,
+    ) -> Result<GameRecord, anyhow::Error> {
+        let now = now();
+        let protocol_address = utils::protocol_address(&aggregated_key)?.to_string();
+
+        let mut game = GameRecord {
+            program_id,
+            game_type: game_type.to_string(),
+            operator: operator.to_string(),
+            status: serde_json::Value::Null,
+            created_at: now,
+            updated_at: now,
+            role,
+            bitvmx_program_properties: BitVMXProgramProperties {
+                aggregated_key,
+                aggregated_id,
+                protocol_address,
+                participants_addresses,
+                participants_keys,
+                funding_protocol_utxo: None,
+                funding_bet_utxo: None,
+                txs: HashMap::new(),
+                dispute_parameters: None,
+            },
+            payout_txid: None,
+            contradictions: vec![],
+            data: serde_json::Map::new(),
+        };
+        game.set_status(&S::initial())?;
+
+        self.insert_game(game.clone()).await?;
+
+        Ok(game)
+    }
+
+    /// Get the game of `game_type` that is not finished yet
+    pub async fn get_current_game(
+        &self,
+        game_type: &str,
+    ) -> Result<Option<GameRecord>, anyhow::Error> {
+        let games = self.list_games().await?;
+        Ok(games.into_iter().find(|game| {
+            game.game_type == game_type
+                && game
+                    .status_as::<S>()
+                    .is_ok_and(|status| status.phase() != GamePhase::Finished)
+        }))
+    }
+
+    /// Save the funding utxos for the current participant
+    pub async fn save_funding_utxos(
+        &self,
+        program_id: Uuid,
+        funding_protocol_utxo: Utxo,
+        funding_bet_utxo: Utxo,
+    ) -> Result<(), anyhow::Error> {
+        self.update_game(
+            program_id,
+            Box::new(move |game| {
+                fire::<S>(game, GameStep::SaveFundingUtxos.into())?;
+
+                // Save the funding bet UTXO
+                game.bitvmx_program_properties.funding_bet_utxo = Some(funding_bet_utxo);
+                game.bitvmx_program_properties.funding_protocol_utxo = Some(funding_protocol_utxo);
+                Ok(())
+            }),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Move the game to the next status, fails with [`crate::models::InvalidTransition`] if the event is not allowed
+    pub async fn apply_event(
+        &self,
+        program_id: Uuid,
+        event: S::Event,
+    ) -> Result<GameRecord, anyhow::Error> {
+        self.update_game(program_id, Box::new(move |game| fire::<S>(game, event)))
+            .await
+    }
+
+    /// Save the setup data of the game, e.g. the numbers to sum, and its dispute parameters
+    pub async fn setup_game(
+        &self,
+        program_id: Uuid,
+        data: serde_json::Value,
+        dispute_parameters: DisputeParameters,
+    ) -> Result<(), anyhow::Error> {
+        self.update_game(
+            program_id,
+            Box::new(move |game| {
+                fire::<S>(game, GameStep::SetupGame.into())?;
+
+                game.set_data(&data)?;
+                game.bitvmx_program_properties.dispute_parameters = Some(dispute_parameters);
+                Ok(())
+            }),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn start_game(
+        &self,
+        program_id: Uuid,
+        challenge_tx_name: String,
+        challenge_tx: &TransactionStatus,
+    ) -> Result<(), anyhow::Error> {
+        let challenge_tx_status = serde_json::to_value(challenge_tx).map_err(|e| {
+            anyhow::anyhow!("Failed to convert challenge transaction to JSON: {e:?}")
+        })?;
+
+        self.update_game(
+            program_id,
+            Box::new(move |game| {
+                fire::<S>(game, GameStep::StartGame.into())?;
+
+                game.bitvmx_program_properties
+                    .txs
+                    .insert(challenge_tx_name, challenge_tx_status);
+                Ok(())
+            }),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Save the answer submitted by player 2, e.g. the sum
+    pub async fn submit_game_data(
+        &self,
+        id: Uuid,
+        data: serde_json::Value,
+    ) -> Result<GameRecord, anyhow::Error> {
+        self.update_game(
+            id,
+            Box::new(move |game| {
+                // Player 2 is the prover that will send the answer transaction to the program.
+                fire::<S>(game, GameStep::SubmitGameData.into())?;
+
+                game.set_data(&data)
+            }),
+        )
+        .await
+    }
+
+    pub async fn set_game_complete(
+        &self,
+        program_id: Uuid,
+        outcome: GameOutcome,
+        reason: GameReason,
+    ) -> Result<GameRecord, anyhow::Error> {
+        self.update_game(
+            program_id,
+            Box::new(move |game| {
+                fire::<S>(game, GameStep::CompleteGame { outcome, reason }.into())
+            }),
+        )
+        .await
+    }
+
+    /// Record the payout transaction of a claimed bet once it is sent, before it is confirmed
+    pub async fn set_payout_sent(
+        &self,
+        program_id: Uuid,
+        payout_txid: String,
+    ) -> Result<GameRecord, anyhow::Error> {
+        self.update_game(
+            program_id,
+            Box::new(move |game| {
+                if game.status_as::<S>()?.phase() != GamePhase::TransferBetFunds {
+                    return Err(anyhow::anyhow!(
+                        "Payout sent while the game is {}",
+                        game.status
+                    ));
+                }
+                game.payout_txid = Some(payout_txid);
+                Ok(())
+            }),
+        )
+        .await
+    }
+
+    /// Record the confirmed payout transaction of a claimed bet and finish the game
+    pub async fn set_payout(
+        &self,
+        program_id: Uuid,
+        payout_tx_name: String,
+        payout_tx: TransactionStatus,
+    ) -> Result<GameRecord, anyhow::Error> {
+        let payout_txid = payout_tx.tx_id.to_string();
+        let payout_tx_status = serde_json::to_value(payout_tx)
+            .map_err(|e| anyhow::anyhow!("Failed to convert payout transaction to JSON: {e:?}"))?;
+
+        self.update_game(
+            program_id,
+            Box::new(move |game| {
+                fire::<S>(game, GameStep::Finish.into())?;
+
+                game.bitvmx_program_properties
+                    .txs
+                    .insert(payout_tx_name, payout_tx_status);
+                game.payout_txid = Some(payout_txid);
+                Ok(())
+            }),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DEFAULT_OPERATOR;
+    use crate::models::{AddNumbersGameEvent, AddNumbersGameStatus, InvalidTransition};
+    use crate::stores::{MemoryGameStore, SledGameStore};
+    use serde_json::json;
+    use std::str::FromStr;
+
+    const AGGREGATED_KEY: &str =
+        "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+
+    fn utxo(vout: u32) -> Utxo {
+        Utxo {
+            txid: "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b".to_string(),
+            vout,
+            amount: 1_000,
+            output_type: json!({}),
+        }
+    }
+
+    fn dispute_parameters() -> DisputeParameters {
+        DisputeParameters {
+            timelock_blocks: 5,
+            program_definition: "./verifiers/add-test-with-const-pre.yaml".to_string(),
+            leader_idx: 1,
+        }
+    }
+
+    fn numbers() -> serde_json::Value {
+        json!({ "number1": 1, "number2": 2 })
+    }
+
+    type AddNumbersStore = TypedGameStore<AddNumbersGameStatus>;
+
+    fn status(game: &GameRecord) -> AddNumbersGameStatus {
+        game.status_as().unwrap()
+    }
+
+    async fn setup(store: &AddNumbersStore, role: PlayerRole) -> Uuid {
+        let program_id = Uuid::new_v4();
+        store
+            .setup_participants(
+                program_id,
+                "add-numbers",
+                DEFAULT_OPERATOR,
+                Uuid::new_v4(),
+                vec![],
+                vec![],
+                PublicKey::from_str(AGGREGATED_KEY).unwrap(),
+                role,
+            )
+            .await
+            .unwrap();
+        program_id
+    }
+
+    async fn check_game_lifecycle(store: Arc<dyn GameStore>) {
+        let store = &AddNumbersStore::new(store);
+        let program_id = setup(store, PlayerRole::Player1).await;
+
+        // Duplicated games are rejected
+        let game = store.get_game(program_id).await.unwrap().unwrap();
+        assert!(store.insert_game(game).await.is_err());
+
+        // A failed update leaves the stored game untouched
+        let error = store
+            .setup_game(program_id, numbers(), dispute_parameters())
+            .await
+            .unwrap_err();
+        assert!(error.downcast_ref::<InvalidTransition>().is_some());
+        let game = store.get_game(program_id).await.unwrap().unwrap();
+        assert_eq!(status(&game), AddNumbersGameStatus::PlaceBet);
+        assert!(game.data.is_empty());
+
+        store
+            .save_funding_utxos(program_id, utxo(0), utxo(1))
+            .await
+            .unwrap();
+        store
+            .setup_game(program_id, numbers(), dispute_parameters())
+            .await
+            .unwrap();
+
+        let game = store.get_game(program_id).await.unwrap().unwrap();
+        assert_eq!(status(&game), AddNumbersGameStatus::StartGame);
+        assert_eq!(game.data["number1"], json!(1));
+        assert_eq!(game.data["number2"], json!(2));
+        assert_eq!(
+            game.bitvmx_program_properties.dispute_parameters,
+            Some(dispute_parameters())
+        );
+        assert_eq!(
+            game.bitvmx_program_properties.funding_bet_utxo,
+            Some(utxo(1))
+        );
+
+        let current = store
+            .get_current_game("add-numbers")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(current.program_id, program_id);
+        assert!(store.get_current_game("other").await.unwrap().is_none());
+        assert!(store.get_game(Uuid::new_v4()).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_game_lifecycle() {
+        check_game_lifecycle(Arc::new(MemoryGameStore::new())).await;
+    }
+
+    #[tokio::test]
+    async fn test_sled_store_game_lifecycle() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        check_game_lifecycle(Arc::new(SledGameStore::new(&db).unwrap())).await;
+    }
+
+    /// sled releases the file lock from a background thread, retry until the previous db is gone
+    async fn reopen(path: &std::path::Path) -> sled::Db {
+        for _ in 0..50 {
+            if let Ok(db) = sled::open(path) {
+                return db;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        sled::open(path).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_sled_store_survives_reopen() {
+        let path = std::env::temp_dir().join(format!("game-store-{}", Uuid::new_v4()));
+
+        let program_id = {
+            let db = sled::open(&path).unwrap();
+            let store = AddNumbersStore::new(Arc::new(SledGameStore::new(&db).unwrap()));
+            let program_id = setup(&store, PlayerRole::Player2).await;
+            store
+                .apply_event(program_id, AddNumbersGameEvent::PlaceBet)
+                .await
+                .unwrap();
+            program_id
+        };
+
+        let db = reopen(&path).await;
+        let store = SledGameStore::new(&db).unwrap();
+        let games = store.list_games().await.unwrap();
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].program_id, program_id);
+        assert_eq!(games[0].role, PlayerRole::Player2);
+        assert_eq!(status(&games[0]), AddNumbersGameStatus::SetupFunding);
+
+        drop(store);
+        drop(db);
+        let _ = std::fs::remove_dir_all(&path);
+    }
+}
@@ -0,0 +1,272 @@
+use crate::models::{JobRecord, JobStatus};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+/// Mutation applied to a stored job inside [`JobStore::update`] and [`JobStore::restore`]
+pub type JobUpdate = Box<dyn FnOnce(&mut JobRecord) + Send>;
+
+/// Current unix timestamp in milliseconds
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+/// How long the succeeded and cancelled jobs are kept
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JobRetention {
+    /// Finished jobs not updated for longer are removed
+    pub ttl: Duration,
+    /// Finished jobs kept at most, the least recently updated are removed first
+    pub max_finished: usize,
+}
+
+impl JobRetention {
+    /// Finished jobs to remove at `now_ms`, failed jobs stay in the dead letter list
+    pub fn expired(&self, jobs: &[JobRecord], now_ms: u64) -> Vec<Uuid> {
+        let mut finished: Vec<_> = jobs
+            .iter()
+            .filter(|job| matches!(job.status, JobStatus::Succeeded | JobStatus::Cancelled))
+            .collect();
+        // Most recently updated first
+        finished.sort_by_key(|job| std::cmp::Reverse(job.updated_at_ms));
+        let ttl_ms = self.ttl.as_millis() as u64;
+        finished
+            .iter()
+            .enumerate()
+            .filter(|(index, job)| {
+                *index >= self.max_finished || job.updated_at_ms.saturating_add(ttl_ms) < now_ms
+            })
+            .map(|(_, job)| job.id)
+            .collect()
+    }
+}
+
+/// Persistence for the background jobs, queued jobs survive a restart when it is persisted.
+///
+/// Implementations only provide the storage primitives, the retention of the finished jobs
+/// is built on top of them so every backend prunes jobs the same way.
+#[async_trait]
+pub trait JobStore: Send + Sync + Debug {
+    /// Insert or replace a job
+    async fn save(&self, job: &JobRecord) -> Result<(), anyhow::Error>;
+
+    /// Save a new job unless a queued or running job has the same dedupe key,
+    /// returns the saved job or the pending one
+    async fn save_unless_pending(&self, job: &JobRecord) -> Result<JobRecord, anyhow::Error>;
+
+    /// Apply `update` to a job and persist it, returns the updated job
+    async fn update(&self, id: Uuid, update: JobUpdate) -> Result<JobRecord, anyhow::Error>;
+
+    /// Move a job that failed every attempt to the dead letter list
+    async fn dead_letter(&self, job: &JobRecord) -> Result<(), anyhow::Error>;
+
+    /// Move a job of the dead letter list back to the jobs, applying `update` to it.
+    /// A queued or running job with the same dedupe key is returned instead, the job stays in the dead letter list
+    async fn restore(&self, id: Uuid, update: JobUpdate) -> Result<JobRecord, anyhow::Error>;
+
+    /// Remove jobs, dead letters are not removed
+    async fn remove(&self, ids: &[Uuid]) -> Result<(), anyhow::Error>;
+
+    /// Get a job, dead letters are not included
+    async fn get(&self, id: Uuid) -> Option<JobRecord>;
+
+    /// Get a job of the dead letter list
+    async fn get_dead_letter(&self, id: Uuid) -> Option<JobRecord>;
+
+    /// Jobs that are not in the dead letter list, oldest first
+    async fn jobs(&self) -> Vec<JobRecord>;
+
+    /// Jobs that failed every attempt, oldest first
+    async fn dead_letters(&self) -> Vec<JobRecord>;
+
+    /// Remove the finished jobs past the retention, returns how many were removed
+    async fn prune_finished(
+        &self,
+        retention: &JobRetention,
+        now_ms: u64,
+    ) -> Result<usize, anyhow::Error> {
+        let expired = retention.expired(&self.jobs().await, now_ms);
+        if !expired.is_empty() {
+            self.remove(&expired).await?;
+        }
+        Ok(expired.len())
+    }
+}
+
+/// Jobs and dead letters indexed by id, the memory store and the cache of the sled store
+#[derive(Debug, Default)]
+pub(crate) struct JobRecords {
+    pub jobs: HashMap<Uuid, JobRecord>,
+    pub dead_letters: HashMap<Uuid, JobRecord>,
+}
+
+impl JobRecords {
+    /// Queued or running job with the dedupe key of `job`
+    pub fn pending_duplicate(&self, job: &JobRecord) -> Option<&JobRecord> {
+        let dedupe_key = job.dedupe_key.as_ref()?;
+        self.jobs.values().find(|pending| {
+            pending.status.is_pending() && pending.dedupe_key.as_ref() == Some(dedupe_key)
+        })
+    }
+
+    /// Copy of a job with `update` applied, not stored yet
+    pub fn updated(
+        records: &HashMap<Uuid, JobRecord>,
+        id: Uuid,
+        update: JobUpdate,
+        not_found: &str,
+    ) -> Result<JobRecord, anyhow::Error> {
+        let mut job = records
+            .get(&id)
+            .cloned()
+            .ok_or(anyhow::anyhow!("{not_found} {id} not found"))?;
+        update(&mut job);
+        job.updated_at_ms = now_ms();
+        Ok(job)
+    }
+
+    /// Records oldest first
+    pub fn sorted(records: &HashMap<Uuid, JobRecord>) -> Vec<JobRecord> {
+        let mut jobs: Vec<_> = records.values().cloned().collect();
+        jobs.sort_by_key(|job| job.created_at_ms);
+        jobs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::BackoffPolicy;
+    use crate::stores::{MemoryJobStore, SledJobStore};
+    use std::sync::Arc;
+
+    fn job(status: JobStatus, updated_at_ms: u64) -> JobRecord {
+        let mut job = JobRecord::new("wait_payout", Uuid::new_v4(), BackoffPolicy::default(), 0);
+        job.status = status;
+        job.updated_at_ms = updated_at_ms;
+        job
+    }
+
+    #[tokio::test]
+    async fn test_prune_finished_jobs() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let stores: Vec<Arc<dyn JobStore>> = vec![
+            Arc::new(MemoryJobStore::new()),
+            Arc::new(SledJobStore::open(&db).unwrap()),
+        ];
+        let mut retention = JobRetention {
+            ttl: Duration::from_secs(60),
+            max_finished: 10,
+        };
+        for store in stores {
+            let queued = job(JobStatus::Queued, 0);
+            let expired = job(JobStatus::Succeeded, 1_000);
+            let oldest = job(JobStatus::Cancelled, 100_000);
+            let kept = [
+                job(JobStatus::Succeeded, 110_000),
+                job(JobStatus::Cancelled, 120_000),
+            ];
+            for job in [&queued, &expired, &oldest, &kept[0], &kept[1]] {
+                store.save(job).await.unwrap();
+            }
+            let failed = job(JobStatus::Failed, 0);
+            store.save(&failed).await.unwrap();
+            store.dead_letter(&failed).await.unwrap();
+
+            // Past the TTL
+            assert_eq!(store.prune_finished(&retention, 130_000).await.unwrap(), 1);
+            assert!(store.get(expired.id).await.is_none());
+            // Over the max count
+            retention.max_finished = 2;
+            assert_eq!(store.prune_finished(&retention, 130_000).await.unwrap(), 1);
+            retention.max_finished = 10;
+            let mut ids: Vec<_> = store.jobs().await.iter().map(|job| job.id).collect();
+            ids.sort();
+            let mut expected = vec![queued.id, kept[0].id, kept[1].id];
+            expected.sort();
+            assert_eq!(ids, expected);
+            assert_eq!(store.dead_letters().await, vec![failed]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_jobs_survive_reopen() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let queued = JobRecord::new(
+            "wait_start_game",
+            Uuid::new_v4(),
+            BackoffPolicy::default(),
+            1,
+        );
+        let failed = JobRecord::new("wait_payout", Uuid::new_v4(), BackoffPolicy::default(), 2);
+
+        let store = SledJobStore::open(&db).unwrap();
+        store.save(&queued).await.unwrap();
+        store.save(&failed).await.unwrap();
+        let failed = store
+            .update(
+                failed.id,
+                Box::new(|job| {
+                    job.attempts = 5;
+                    job.status = JobStatus::Failed;
+                    job.last_error = Some("Payout not found".to_string());
+                }),
+            )
+            .await
+            .unwrap();
+        store.dead_letter(&failed).await.unwrap();
+        drop(store);
+
+        let store = SledJobStore::open(&db).unwrap();
+        assert_eq!(store.jobs().await, vec![queued.clone()]);
+        assert_eq!(store.get(queued.id).await, Some(queued));
+        assert_eq!(store.dead_letters().await, vec![failed.clone()]);
+        assert_eq!(store.get(failed.id).await, None);
+        assert_eq!(store.get_dead_letter(failed.id).await, Some(failed.clone()));
+
+        let restored = store
+            .restore(failed.id, Box::new(|job| job.status = JobStatus::Queued))
+            .await
+            .unwrap();
+        drop(store);
+
+        let store = SledJobStore::open(&db).unwrap();
+        assert_eq!(store.get(failed.id).await, Some(restored));
+        assert!(store.dead_letters().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_save_unless_pending() {
+        let store = MemoryJobStore::new();
+        let program_id = Uuid::new_v4();
+        let job = |now_ms| {
+            let mut job = JobRecord::new(
+                "wait_start_game",
+                program_id,
+                BackoffPolicy::default(),
+                now_ms,
+            );
+            job.dedupe_key = Some(format!("wait_start_game:{program_id}"));
+            job
+        };
+
+        let first = job(1);
+        assert_eq!(store.save_unless_pending(&first).await.unwrap(), first);
+        assert_eq!(store.save_unless_pending(&job(2)).await.unwrap(), first);
+        assert_eq!(store.jobs().await.len(), 1);
+
+        // Once the first one succeeded the same job can be queued again
+        store
+            .update(first.id, Box::new(|job| job.status = JobStatus::Succeeded))
+            .await
+            .unwrap();
+        let second = job(3);
+        assert_eq!(store.save_unless_pending(&second).await.unwrap(), second);
+        assert_eq!(store.jobs().await.len(), 2);
+    }
+}
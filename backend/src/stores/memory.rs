@@ -0,0 +1,147 @@
+use crate::models::{GameRecord, JobRecord};
+use crate::stores::game_store::{now, GameStore, GameUpdate};
+use crate::stores::job_store::{JobRecords, JobStore, JobUpdate};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// Game store that keeps every game in memory, games are lost on restart
+#[derive(Debug)]
+pub struct MemoryGameStore {
+    games: Arc<RwLock<HashMap<Uuid, GameRecord>>>,
+}
+
+impl Default for MemoryGameStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryGameStore {
+    pub fn new() -> Self {
+        Self {
+            games: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+}
+
+#[async_trait]
+impl GameStore for MemoryGameStore {
+    async fn insert_game(&self, game: GameRecord) -> Result<(), anyhow::Error> {
+        let mut hash_map = self.games.write().await;
+        if hash_map.contains_key(&game.program_id) {
+            return Err(anyhow::anyhow!("Game already exists"));
+        }
+        hash_map.insert(game.program_id, game);
+        Ok(())
+    }
+
+    async fn get_game(&self, id: Uuid) -> Result<Option<GameRecord>, anyhow::Error> {
+        let hash_map = self.games.read().await;
+        Ok(hash_map.get(&id).cloned())
+    }
+
+    async fn list_games(&self) -> Result<Vec<GameRecord>, anyhow::Error> {
+        let hash_map = self.games.read().await;
+        Ok(hash_map.values().cloned().collect())
+    }
+
+    async fn update_game(&self, id: Uuid, update: GameUpdate) -> Result<GameRecord, anyhow::Error> {
+        let mut hash_map = self.games.write().await;
+        let game = hash_map
+            .get_mut(&id)
+            .ok_or(anyhow::anyhow!("Game not found"))?;
+
+        // Work on a copy so a failed update leaves the stored game untouched
+        let mut updated = game.clone();
+        update(&mut updated)?;
+        updated.updated_at = now();
+        *game = updated.clone();
+
+        Ok(updated)
+    }
+}
+
+/// Job store that keeps every job in memory, jobs are lost on restart
+#[derive(Debug, Default)]
+pub struct MemoryJobStore {
+    records: RwLock<JobRecords>,
+}
+
+impl MemoryJobStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl JobStore for MemoryJobStore {
+    async fn save(&self, job: &JobRecord) -> Result<(), anyhow::Error> {
+        self.records.write().await.jobs.insert(job.id, job.clone());
+        Ok(())
+    }
+
+    async fn save_unless_pending(&self, job: &JobRecord) -> Result<JobRecord, anyhow::Error> {
+        let mut records = self.records.write().await;
+        if let Some(pending) = records.pending_duplicate(job) {
+            return Ok(pending.clone());
+        }
+        records.jobs.insert(job.id, job.clone());
+        Ok(job.clone())
+    }
+
+    async fn update(&self, id: Uuid, update: JobUpdate) -> Result<JobRecord, anyhow::Error> {
+        let mut records = self.records.write().await;
+        let job = JobRecords::updated(&records.jobs, id, update, "Job")?;
+        records.jobs.insert(id, job.clone());
+        Ok(job)
+    }
+
+    async fn dead_letter(&self, job: &JobRecord) -> Result<(), anyhow::Error> {
+        let mut records = self.records.write().await;
+        records.jobs.remove(&job.id);
+        records.dead_letters.insert(job.id, job.clone());
+        Ok(())
+    }
+
+    async fn restore(&self, id: Uuid, update: JobUpdate) -> Result<JobRecord, anyhow::Error> {
+        let mut records = self.records.write().await;
+        let pending = records
+            .dead_letters
+            .get(&id)
+            .and_then(|job| records.pending_duplicate(job));
+        if let Some(pending) = pending {
+            return Ok(pending.clone());
+        }
+        let job = JobRecords::updated(&records.dead_letters, id, update, "Dead letter job")?;
+        records.dead_letters.remove(&id);
+        records.jobs.insert(id, job.clone());
+        Ok(job)
+    }
+
+    async fn remove(&self, ids: &[Uuid]) -> Result<(), anyhow::Error> {
+        let mut records = self.records.write().await;
+        for id in ids {
+            records.jobs.remove(id);
+        }
+        Ok(())
+    }
+
+    async fn get(&self, id: Uuid) -> Option<JobRecord> {
+        self.records.read().await.jobs.get(&id).cloned()
+    }
+
+    async fn get_dead_letter(&self, id: Uuid) -> Option<JobRecord> {
+        self.records.read().await.dead_letters.get(&id).cloned()
+    }
+
+    async fn jobs(&self) -> Vec<JobRecord> {
+        JobRecords::sorted(&self.records.read().await.jobs)
+    }
+
+    async fn dead_letters(&self) -> Vec<JobRecord> {
+        JobRecords::sorted(&self.records.read().await.dead_letters)
+    }
+}
@@ -1,4 +1,53 @@
-pub mod add_numbers;
 pub mod bitvmx;
-pub use add_numbers::AddNumbersStore;
+pub mod game_store;
+pub mod job_store;
+pub mod memory;
+pub mod sled_store;
 pub use bitvmx::BitvmxStore;
+pub use game_store::{GameStore, TypedGameStore};
+pub use job_store::{JobRetention, JobStore};
+pub use memory::{MemoryGameStore, MemoryJobStore};
+pub use sled_store::{SledGameStore, SledJobStore};
+
+use crate::config::{StorageBackend, StorageConfig};
+use std::sync::Arc;
+
+/// Open the on-disk database when the configured backend needs one
+pub fn open_database(config: &StorageConfig) -> Result<Option<sled::Db>, anyhow::Error> {
+    match config.backend {
+        StorageBackend::Memory => Ok(None),
+        StorageBackend::Sled => {
+            let db = sled::open(&config.path).map_err(|e| {
+                anyhow::anyhow!("Failed to open database at {}: {e:?}", config.path)
+            })?;
+            Ok(Some(db))
+        }
+    }
+}
+
+/// Create the game store for the configured backend
+pub fn new_game_store(db: Option<&sled::Db>) -> Result<Arc<dyn GameStore>, anyhow::Error> {
+    match db {
+        Some(db) => Ok(Arc::new(SledGameStore::new(db)?)),
+        None => Ok(Arc::new(MemoryGameStore::new())),
+    }
+}
+
+/// Create the BitVMX store of an operator, persisted when a database is configured
+pub fn new_bitvmx_store(
+    db: Option<&sled::Db>,
+    operator: &str,
+) -> Result<Arc<BitvmxStore>, anyhow::Error> {
+    match db {
+        Some(db) => Ok(Arc::new(BitvmxStore::open(db, operator)?)),
+        None => Ok(Arc::new(BitvmxStore::new())),
+    }
+}
+
+/// Create the store of the background jobs, persisted when a database is configured
+pub fn new_job_store(db: Option<&sled::Db>) -> Result<Arc<dyn JobStore>, anyhow::Error> {
+    match db {
+        Some(db) => Ok(Arc::new(SledJobStore::open(db)?)),
+        None => Ok(Arc::new(MemoryJobStore::new())),
+    }
+}
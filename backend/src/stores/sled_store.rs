@@ -0,0 +1,255 @@
+use crate::models::{GameRecord, JobRecord};
+use crate::stores::game_store::{now, GameStore, GameUpdate};
+use crate::stores::job_store::{JobRecords, JobStore, JobUpdate};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use tokio::sync::{Mutex, RwLock};
+use uuid::Uuid;
+
+/// Name of the sled tree where the games of every type are stored, named after the first game
+const GAMES_TREE: &str = "add_numbers_games";
+/// Names of the sled trees of the jobs and of the jobs that failed every attempt
+const JOBS_TREE: &str = "jobs";
+const DEAD_LETTER_TREE: &str = "dead_letter_jobs";
+
+/// Game store backed by an embedded sled database, games survive a backend restart
+#[derive(Debug)]
+pub struct SledGameStore {
+    games: sled::Tree,
+    /// Serializes read-modify-write cycles so concurrent updates are not lost
+    write_lock: Mutex<()>,
+}
+
+impl SledGameStore {
+    pub fn new(db: &sled::Db) -> Result<Self, anyhow::Error> {
+        let games = db
+            .open_tree(GAMES_TREE)
+            .map_err(|e| anyhow::anyhow!("Failed to open games tree: {e:?}"))?;
+        Ok(Self {
+            games,
+            write_lock: Mutex::new(()),
+        })
+    }
+
+    fn decode(bytes: &[u8]) -> Result<GameRecord, anyhow::Error> {
+        serde_json::from_slice(bytes)
+            .map_err(|e| anyhow::anyhow!("Failed to deserialize stored game: {e:?}"))
+    }
+
+    async fn write(&self, game: &GameRecord) -> Result<(), anyhow::Error> {
+        let bytes = serde_json::to_vec(game)
+            .map_err(|e| anyhow::anyhow!("Failed to serialize game: {e:?}"))?;
+        self.games
+            .insert(game.program_id.as_bytes(), bytes)
+            .map_err(|e| anyhow::anyhow!("Failed to write game: {e:?}"))?;
+        // Make sure the game is on disk before acknowledging the change
+        self.games
+            .flush_async()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to flush games: {e:?}"))?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl GameStore for SledGameStore {
+    async fn insert_game(&self, game: GameRecord) -> Result<(), anyhow::Error> {
+        let _guard = self.write_lock.lock().await;
+        if self
+            .games
+            .contains_key(game.program_id.as_bytes())
+            .map_err(|e| anyhow::anyhow!("Failed to read game: {e:?}"))?
+        {
+            return Err(anyhow::anyhow!("Game already exists"));
+        }
+        self.write(&game).await
+    }
+
+    async fn get_game(&self, id: Uuid) -> Result<Option<GameRecord>, anyhow::Error> {
+        let bytes = self
+            .games
+            .get(id.as_bytes())
+            .map_err(|e| anyhow::anyhow!("Failed to read game: {e:?}"))?;
+        bytes.map(|bytes| Self::decode(&bytes)).transpose()
+    }
+
+    async fn list_games(&self) -> Result<Vec<GameRecord>, anyhow::Error> {
+        self.games
+            .iter()
+            .values()
+            .map(|bytes| {
+                let bytes = bytes.map_err(|e| anyhow::anyhow!("Failed to read game: {e:?}"))?;
+                Self::decode(&bytes)
+            })
+            .collect()
+    }
+
+    async fn update_game(&self, id: Uuid, update: GameUpdate) -> Result<GameRecord, anyhow::Error> {
+        let _guard = self.write_lock.lock().await;
+        let mut game = self
+            .get_game(id)
+            .await?
+            .ok_or(anyhow::anyhow!("Game not found"))?;
+
+        update(&mut game)?;
+        game.updated_at = now();
+        self.write(&game).await?;
+
+        Ok(game)
+    }
+}
+
+/// Job store backed by an embedded sled database, queued jobs survive a backend restart.
+/// The jobs are cached in memory, every change is written through before it is cached.
+#[derive(Debug)]
+pub struct SledJobStore {
+    /// Locked while writing so the cache and the trees do not diverge
+    records: RwLock<JobRecords>,
+    jobs: sled::Tree,
+    dead_letters: sled::Tree,
+}
+
+impl SledJobStore {
+    /// Open the store persisted in the given database, loading the jobs of a previous run
+    pub fn open(db: &sled::Db) -> Result<Self, anyhow::Error> {
+        let open_tree = |name: &str| {
+            db.open_tree(name)
+                .map_err(|e| anyhow::anyhow!("Failed to open {name} tree: {e:?}"))
+        };
+        let jobs = open_tree(JOBS_TREE)?;
+        let dead_letters = open_tree(DEAD_LETTER_TREE)?;
+
+        let records = JobRecords {
+            jobs: Self::load(&jobs)?,
+            dead_letters: Self::load(&dead_letters)?,
+        };
+        Ok(Self {
+            records: RwLock::new(records),
+            jobs,
+            dead_letters,
+        })
+    }
+
+    fn load(tree: &sled::Tree) -> Result<HashMap<Uuid, JobRecord>, anyhow::Error> {
+        tree.iter()
+            .values()
+            .map(|bytes| {
+                let bytes = bytes.map_err(|e| anyhow::anyhow!("Failed to read job: {e:?}"))?;
+                let job: JobRecord = serde_json::from_slice(&bytes)
+                    .map_err(|e| anyhow::anyhow!("Failed to deserialize stored job: {e:?}"))?;
+                Ok((job.id, job))
+            })
+            .collect()
+    }
+
+    fn insert(tree: &sled::Tree, job: &JobRecord) -> Result<(), anyhow::Error> {
+        let bytes = serde_json::to_vec(job)
+            .map_err(|e| anyhow::anyhow!("Failed to serialize job: {e:?}"))?;
+        tree.insert(job.id.as_bytes(), bytes)
+            .map_err(|e| anyhow::anyhow!("Failed to write job: {e:?}"))?;
+        Ok(())
+    }
+
+    fn delete(tree: &sled::Tree, id: &Uuid) -> Result<(), anyhow::Error> {
+        tree.remove(id.as_bytes())
+            .map_err(|e| anyhow::anyhow!("Failed to remove job: {e:?}"))?;
+        Ok(())
+    }
+
+    /// Make sure the changes are on disk before acknowledging them
+    async fn flush(tree: &sled::Tree) -> Result<(), anyhow::Error> {
+        tree.flush_async()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to flush jobs: {e:?}"))?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl JobStore for SledJobStore {
+    async fn save(&self, job: &JobRecord) -> Result<(), anyhow::Error> {
+        let mut records = self.records.write().await;
+        Self::insert(&self.jobs, job)?;
+        Self::flush(&self.jobs).await?;
+        records.jobs.insert(job.id, job.clone());
+        Ok(())
+    }
+
+    async fn save_unless_pending(&self, job: &JobRecord) -> Result<JobRecord, anyhow::Error> {
+        let mut records = self.records.write().await;
+        if let Some(pending) = records.pending_duplicate(job) {
+            return Ok(pending.clone());
+        }
+        Self::insert(&self.jobs, job)?;
+        Self::flush(&self.jobs).await?;
+        records.jobs.insert(job.id, job.clone());
+        Ok(job.clone())
+    }
+
+    async fn update(&self, id: Uuid, update: JobUpdate) -> Result<JobRecord, anyhow::Error> {
+        let mut records = self.records.write().await;
+        let job = JobRecords::updated(&records.jobs, id, update, "Job")?;
+        Self::insert(&self.jobs, &job)?;
+        Self::flush(&self.jobs).await?;
+        records.jobs.insert(id, job.clone());
+        Ok(job)
+    }
+
+    async fn dead_letter(&self, job: &JobRecord) -> Result<(), anyhow::Error> {
+        let mut records = self.records.write().await;
+        Self::insert(&self.dead_letters, job)?;
+        Self::flush(&self.dead_letters).await?;
+        Self::delete(&self.jobs, &job.id)?;
+        Self::flush(&self.jobs).await?;
+        records.jobs.remove(&job.id);
+        records.dead_letters.insert(job.id, job.clone());
+        Ok(())
+    }
+
+    async fn restore(&self, id: Uuid, update: JobUpdate) -> Result<JobRecord, anyhow::Error> {
+        let mut records = self.records.write().await;
+        let pending = records
+            .dead_letters
+            .get(&id)
+            .and_then(|job| records.pending_duplicate(job));
+        if let Some(pending) = pending {
+            return Ok(pending.clone());
+        }
+        let job = JobRecords::updated(&records.dead_letters, id, update, "Dead letter job")?;
+        Self::insert(&self.jobs, &job)?;
+        Self::flush(&self.jobs).await?;
+        Self::delete(&self.dead_letters, &id)?;
+        Self::flush(&self.dead_letters).await?;
+        records.dead_letters.remove(&id);
+        records.jobs.insert(id, job.clone());
+        Ok(job)
+    }
+
+    async fn remove(&self, ids: &[Uuid]) -> Result<(), anyhow::Error> {
+        let mut records = self.records.write().await;
+        for id in ids {
+            Self::delete(&self.jobs, id)?;
+        }
+        Self::flush(&self.jobs).await?;
+        for id in ids {
+            records.jobs.remove(id);
+        }
+        Ok(())
+    }
+
+    async fn get(&self, id: Uuid) -> Option<JobRecord> {
+        self.records.read().await.jobs.get(&id).cloned()
+    }
+
+    async fn get_dead_letter(&self, id: Uuid) -> Option<JobRecord> {
+        self.records.read().await.dead_letters.get(&id).cloned()
+    }
+
+    async fn jobs(&self) -> Vec<JobRecord> {
+        JobRecords::sorted(&self.records.read().await.jobs)
+    }
+
+    async fn dead_letters(&self) -> Vec<JobRecord> {
+        JobRecords::sorted(&self.records.read().await.dead_letters)
+    }
+}
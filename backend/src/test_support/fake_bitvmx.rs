@@ -0,0 +1,503 @@
+//! Scriptable BitVMX peer that answers the [`crate::rpc::RpcClient`] in process,
+//! so the game flows run without a broker, a bitvmx-client or bitcoind
+
+use crate::games::dispute::payout_tx_name;
+use crate::rpc::{BitvmxTransport, MemoryTransport, TransportMessage};
+use crate::services::BitcoinService;
+use async_trait::async_trait;
+use bitvmx_client::bitcoin::absolute::LockTime;
+use bitvmx_client::bitcoin::hashes::Hash;
+use bitvmx_client::bitcoin::transaction::Version;
+use bitvmx_client::bitcoin::{
+    Address, Amount, BlockHash, CompressedPublicKey, Network, OutPoint, PublicKey, ScriptBuf,
+    Transaction, TxIn, TxOut, Txid,
+};
+use bitvmx_client::bitcoin_coordinator::{
+    BlockInfo, TransactionBlockchainStatus, TransactionStatus,
+};
+use bitvmx_client::program::participant::{CommsAddress, ParticipantRole};
+use bitvmx_client::program::protocols::dispute;
+use bitvmx_client::program::variables::VariableTypes;
+use bitvmx_client::types::{IncomingBitVMXApiMessages, OutgoingBitVMXApiMessages};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// Time until a sent transaction is seen by the nodes of the chain
+pub const BLOCK_TIME: Duration = Duration::from_millis(50);
+
+/// How long a receive waits for a message before returning None
+const RECEIVE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Operator and funding key of every fake node
+pub const FAKE_PUB_KEY: &str = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+
+/// Aggregated key returned for every program
+pub const FAKE_AGGREGATED_KEY: &str =
+    "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5";
+
+/// Wallet balance of every fake node, 10 BTC
+pub const FAKE_BALANCE: u64 = 1_000_000_000;
+
+/// Scripted replies to a request, None falls back to the default replies
+type Handler =
+    Box<dyn Fn(&IncomingBitVMXApiMessages) -> Option<Vec<OutgoingBitVMXApiMessages>> + Send + Sync>;
+
+/// Chain shared by the fake BitVMX nodes of a test.
+/// Transactions sent by a node are seen by the other nodes after [`BLOCK_TIME`].
+/// Every transaction is mined in a new block, it is also the bitcoin node of the players.
+#[derive(Debug, Default)]
+pub struct FakeChain {
+    nodes: Mutex<Vec<mpsc::UnboundedSender<OutgoingBitVMXApiMessages>>>,
+    transactions: Mutex<HashMap<Txid, TransactionStatus>>,
+    /// Last transaction mined with each name of a program
+    named_transactions: Mutex<HashMap<(Uuid, String), Txid>>,
+    next_tx: AtomicU32,
+    height: AtomicU32,
+    spent_outputs: Mutex<HashSet<(Txid, u32)>>,
+}
+
+impl FakeChain {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Height of the last block
+    pub fn height(&self) -> u32 {
+        self.height.load(Ordering::SeqCst)
+    }
+
+    /// Mine empty blocks, returns the new height
+    pub fn mine_empty_blocks(&self, blocks: u32) -> u32 {
+        self.height.fetch_add(blocks, Ordering::SeqCst) + blocks
+    }
+
+    /// Add a transaction to the chain, confirmed in a new block
+    pub fn new_transaction(&self) -> TransactionStatus {
+        self.new_transaction_spending(vec![])
+    }
+
+    /// Add a transaction spending `outpoints` to the chain, confirmed in a new block
+    fn new_transaction_spending(&self, outpoints: Vec<OutPoint>) -> TransactionStatus {
+        let index = self.next_tx.fetch_add(1, Ordering::SeqCst);
+        let height = self.mine_empty_blocks(1);
+        let mut hash = [0; 32];
+        hash[..4].copy_from_slice(&height.to_le_bytes());
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::from_consensus(index),
+            input: outpoints
+                .iter()
+                .map(|outpoint| TxIn {
+                    previous_output: *outpoint,
+                    ..Default::default()
+                })
+                .collect(),
+            output: vec![TxOut {
+                value: Amount::from_sat(u64::from(index) + 1),
+                script_pubkey: ScriptBuf::new(),
+            }],
+        };
+        let tx_status = TransactionStatus {
+            tx_id: tx.compute_txid(),
+            tx,
+            block_info: Some(BlockInfo {
+                height,
+                hash: BlockHash::from_byte_array(hash),
+            }),
+            confirmations: 1,
+            status: TransactionBlockchainStatus::Confirmed,
+        };
+
+        self.transactions
+            .lock()
+            .unwrap()
+            .insert(tx_status.tx_id, tx_status.clone());
+        let mut spent_outputs = self.spent_outputs.lock().unwrap();
+        spent_outputs.extend(
+            outpoints
+                .iter()
+                .map(|outpoint| (outpoint.txid, outpoint.vout)),
+        );
+        tx_status
+    }
+
+    /// Spend an output of the chain, e.g. BitVMX used the funding UTXO for a speedup
+    pub fn spend(&self, txid: Txid, vout: u32) {
+        self.spent_outputs.lock().unwrap().insert((txid, vout));
+    }
+
+    /// Get a transaction of the chain
+    pub fn transaction(&self, txid: &Txid) -> Option<TransactionStatus> {
+        self.transactions.lock().unwrap().get(txid).cloned()
+    }
+
+    /// Get the last transaction of a program mined with the name
+    pub fn named_transaction(&self, program_id: Uuid, tx_name: &str) -> Option<TransactionStatus> {
+        let txid = *self
+            .named_transactions
+            .lock()
+            .unwrap()
+            .get(&(program_id, tx_name.to_string()))?;
+        self.transaction(&txid)
+    }
+
+    /// Mine a program transaction without any node reporting it,
+    /// e.g. when the message of BitVMX was lost
+    pub fn mine_unreported(&self, program_id: Uuid, tx_name: &str) -> TransactionStatus {
+        self.mine_unreported_spending(program_id, tx_name, vec![])
+    }
+
+    /// Mine a program transaction spending `outpoints` without any node reporting it
+    fn mine_unreported_spending(
+        &self,
+        program_id: Uuid,
+        tx_name: &str,
+        outpoints: Vec<OutPoint>,
+    ) -> TransactionStatus {
+        let tx_status = self.new_transaction_spending(outpoints);
+        self.named_transactions
+            .lock()
+            .unwrap()
+            .insert((program_id, tx_name.to_string()), tx_status.tx_id);
+        tx_status
+    }
+
+    /// Mine a program transaction, every node reports it right away,
+    /// e.g. the dispute transactions BitVMX dispatches by itself
+    pub fn mine(&self, program_id: Uuid, tx_name: &str) -> TransactionStatus {
+        let tx_status = self.mine_unreported(program_id, tx_name);
+        for node in self.nodes.lock().unwrap().iter() {
+            let _ = node.send(OutgoingBitVMXApiMessages::Transaction(
+                program_id,
+                tx_status.clone(),
+                Some(tx_name.to_string()),
+            ));
+        }
+        tx_status
+    }
+
+    /// Add a node, returns its index
+    fn connect(&self, node: mpsc::UnboundedSender<OutgoingBitVMXApiMessages>) -> usize {
+        let mut nodes = self.nodes.lock().unwrap();
+        nodes.push(node);
+        nodes.len() - 1
+    }
+
+    /// Send a message to every node but `from` once the block is mined
+    fn broadcast(&self, from: usize, message: OutgoingBitVMXApiMessages) {
+        let nodes: Vec<_> = self
+            .nodes
+            .lock()
+            .unwrap()
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != from)
+            .map(|(_, node)| node.clone())
+            .collect();
+        tokio::spawn(async move {
+            tokio::time::sleep(BLOCK_TIME).await;
+            for node in nodes {
+                let _ = node.send(message.clone());
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl BitcoinService for FakeChain {
+    async fn mine_blocks(&self, blocks: u64) -> Result<(), anyhow::Error> {
+        self.mine_empty_blocks(u32::try_from(blocks)?);
+        Ok(())
+    }
+
+    async fn mine_blocks_to_address(
+        &self,
+        blocks: u64,
+        _address: Address,
+    ) -> Result<(), anyhow::Error> {
+        self.mine_blocks(blocks + 100).await
+    }
+
+    async fn get_block_height(&self) -> Result<u32, anyhow::Error> {
+        Ok(self.height())
+    }
+
+    async fn is_unspent(&self, txid: Txid, vout: u32) -> Result<bool, anyhow::Error> {
+        let exists = self
+            .transaction(&txid)
+            .is_some_and(|tx_status| (vout as usize) < tx_status.tx.output.len());
+        Ok(exists && !self.spent_outputs.lock().unwrap().contains(&(txid, vout)))
+    }
+}
+
+/// Fake BitVMX node, used as the transport of an `RpcClient`.
+/// Requests get canned replies unless a handler added with [`FakeBitvmx::on`] answers them.
+pub struct FakeBitvmx {
+    chain: Arc<FakeChain>,
+    index: usize,
+    outgoing_tx: mpsc::UnboundedSender<OutgoingBitVMXApiMessages>,
+    outgoing_rx: tokio::sync::Mutex<mpsc::UnboundedReceiver<OutgoingBitVMXApiMessages>>,
+    handlers: Mutex<Vec<Handler>>,
+    received: Mutex<Vec<IncomingBitVMXApiMessages>>,
+    variables: Mutex<HashMap<(Uuid, String), VariableTypes>>,
+    next_uid: AtomicU64,
+}
+
+impl std::fmt::Debug for FakeBitvmx {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FakeBitvmx")
+            .field("index", &self.index)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FakeBitvmx {
+    /// New node on the chain
+    pub fn new(chain: &Arc<FakeChain>) -> Arc<Self> {
+        let (outgoing_tx, outgoing_rx) = mpsc::unbounded_channel();
+        let index = chain.connect(outgoing_tx.clone());
+
+        Arc::new(Self {
+            chain: chain.clone(),
+            index,
+            outgoing_tx,
+            outgoing_rx: tokio::sync::Mutex::new(outgoing_rx),
+            handlers: Mutex::new(Vec::new()),
+            received: Mutex::new(Vec::new()),
+            variables: Mutex::new(HashMap::new()),
+            next_uid: AtomicU64::new(0),
+        })
+    }
+
+    /// P2P address of the node
+    pub fn comm_address(&self) -> CommsAddress {
+        CommsAddress {
+            address: format!("127.0.0.1:{}", 61180 + self.index).parse().unwrap(),
+            pubkey_hash: format!("fake-peer-{}", self.index),
+        }
+    }
+
+    /// Script the replies of the requests, the latest handler answering a request wins
+    pub fn on<F>(&self, handler: F)
+    where
+        F: Fn(&IncomingBitVMXApiMessages) -> Option<Vec<OutgoingBitVMXApiMessages>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.handlers.lock().unwrap().push(Box::new(handler));
+    }
+
+    /// Send a message to the client as if BitVMX sent it
+    pub fn emit(&self, message: OutgoingBitVMXApiMessages) {
+        let _ = self.outgoing_tx.send(message);
+    }
+
+    /// Requests received from the client
+    pub fn received(&self) -> Vec<IncomingBitVMXApiMessages> {
+        self.received.lock().unwrap().clone()
+    }
+
+    /// Program variable set by the client
+    pub fn variable(&self, program_id: Uuid, key: &str) -> Option<VariableTypes> {
+        self.variables
+            .lock()
+            .unwrap()
+            .get(&(program_id, key.to_string()))
+            .cloned()
+    }
+
+    fn handle(&self, message: IncomingBitVMXApiMessages) {
+        self.received.lock().unwrap().push(message.clone());
+
+        let scripted = self
+            .handlers
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find_map(|handler| handler(&message));
+        let replies = match scripted {
+            Some(replies) => replies,
+            None => self.default_replies(message),
+        };
+
+        for reply in replies {
+            self.emit(reply);
+        }
+    }
+
+    /// Replies of a bitvmx-client with a funded wallet where every request succeeds
+    fn default_replies(
+        &self,
+        message: IncomingBitVMXApiMessages,
+    ) -> Vec<OutgoingBitVMXApiMessages> {
+        let pub_key = PublicKey::from_str(FAKE_PUB_KEY).unwrap();
+
+        match message {
+            IncomingBitVMXApiMessages::Ping() => vec![OutgoingBitVMXApiMessages::Pong()],
+            IncomingBitVMXApiMessages::GetCommInfo() => {
+                vec![OutgoingBitVMXApiMessages::CommInfo(self.comm_address())]
+            }
+            IncomingBitVMXApiMessages::GetPubKey(uuid, _new_key) => {
+                vec![OutgoingBitVMXApiMessages::PubKey(uuid, pub_key)]
+            }
+            IncomingBitVMXApiMessages::GetFundingAddress(uuid) => {
+                let address =
+                    Address::p2wpkh(&CompressedPublicKey(pub_key.inner), Network::Regtest);
+                vec![OutgoingBitVMXApiMessages::FundingAddress(
+                    uuid,
+                    address.into_unchecked(),
+                )]
+            }
+            IncomingBitVMXApiMessages::GetFundingBalance(uuid) => {
+                vec![OutgoingBitVMXApiMessages::FundingBalance(
+                    uuid,
+                    FAKE_BALANCE,
+                )]
+            }
+            IncomingBitVMXApiMessages::SetupKey(uuid, ..)
+            | IncomingBitVMXApiMessages::GetAggregatedPubkey(uuid) => {
+                vec![OutgoingBitVMXApiMessages::AggregatedPubkey(
+                    uuid,
+                    PublicKey::from_str(FAKE_AGGREGATED_KEY).unwrap(),
+                )]
+            }
+            IncomingBitVMXApiMessages::Setup(uuid, ..) => {
+                vec![OutgoingBitVMXApiMessages::SetupCompleted(uuid)]
+            }
+            IncomingBitVMXApiMessages::SetVar(uuid, key, value) => {
+                self.variables.lock().unwrap().insert((uuid, key), value);
+                vec![]
+            }
+            IncomingBitVMXApiMessages::GetVar(uuid, key) => match self.variable(uuid, &key) {
+                Some(value) => vec![OutgoingBitVMXApiMessages::Variable(uuid, key, value)],
+                None => vec![OutgoingBitVMXApiMessages::NotFound(uuid, key)],
+            },
+            IncomingBitVMXApiMessages::GetProtocolVisualization(uuid) => {
+                vec![OutgoingBitVMXApiMessages::ProtocolVisualization(
+                    uuid,
+                    "digraph {}".to_string(),
+                )]
+            }
+            IncomingBitVMXApiMessages::SendFunds(uuid, _destination, _fee_rate) => {
+                // The confirmation is only seen once the block is mined
+                let tx_status = self.chain.new_transaction();
+                let outgoing_tx = self.outgoing_tx.clone();
+                let confirmation =
+                    OutgoingBitVMXApiMessages::Transaction(uuid, tx_status.clone(), None);
+                tokio::spawn(async move {
+                    tokio::time::sleep(BLOCK_TIME).await;
+                    let _ = outgoing_tx.send(confirmation);
+                });
+                vec![OutgoingBitVMXApiMessages::FundsSent(uuid, tx_status.tx_id)]
+            }
+            IncomingBitVMXApiMessages::GetTransaction(uuid, txid) => {
+                match self.chain.transaction(&txid) {
+                    Some(tx_status) => {
+                        vec![OutgoingBitVMXApiMessages::Transaction(
+                            uuid, tx_status, None,
+                        )]
+                    }
+                    None => vec![OutgoingBitVMXApiMessages::NotFound(uuid, txid.to_string())],
+                }
+            }
+            IncomingBitVMXApiMessages::GetTransactionInfoByName(uuid, name) => {
+                match self.chain.named_transaction(uuid, &name) {
+                    Some(tx_status) => {
+                        vec![OutgoingBitVMXApiMessages::TransactionInfo(
+                            uuid,
+                            name,
+                            tx_status.tx,
+                        )]
+                    }
+                    None => vec![OutgoingBitVMXApiMessages::NotFound(uuid, name)],
+                }
+            }
+            IncomingBitVMXApiMessages::DispatchTransactionName(uuid, name) => {
+                let tx_status = self.dispatch_named(uuid, &name);
+                let message = OutgoingBitVMXApiMessages::Transaction(uuid, tx_status, Some(name));
+                self.chain.broadcast(self.index, message.clone());
+                vec![message]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+impl FakeBitvmx {
+    /// Mine a program transaction dispatched by name. The payout transactions spend the output
+    /// of their action transaction and are mined once, like the transactions signed at setup
+    fn dispatch_named(&self, program_id: Uuid, name: &str) -> TransactionStatus {
+        let action_tx = [ParticipantRole::Prover, ParticipantRole::Verifier]
+            .iter()
+            .find(|winner| payout_tx_name(winner) == name)
+            .and_then(|winner| {
+                self.chain
+                    .named_transaction(program_id, &dispute::action_wins(winner, 1))
+            });
+        match action_tx {
+            Some(action_tx) => self
+                .chain
+                .named_transaction(program_id, name)
+                .unwrap_or_else(|| {
+                    self.chain.mine_unreported_spending(
+                        program_id,
+                        name,
+                        vec![OutPoint::new(action_tx.tx_id, 0)],
+                    )
+                }),
+            None => self.chain.mine_unreported(program_id, name),
+        }
+    }
+
+    /// Answer the client at the other end of a memory transport, until it is closed
+    pub async fn serve(self: Arc<Self>, client: MemoryTransport) -> Result<(), anyhow::Error> {
+        loop {
+            tokio::select! {
+                request = client.receive() => {
+                    if let Some(request) = request? {
+                        self.send(request.msg).await?;
+                    }
+                }
+                reply = self.receive() => {
+                    if let Some(reply) = reply? {
+                        client.send(reply.msg).await?;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl BitvmxTransport for FakeBitvmx {
+    async fn send(&self, msg: String) -> Result<(), anyhow::Error> {
+        let message = serde_json::from_str(&msg)
+            .map_err(|e| anyhow::anyhow!("Fake BitVMX received an invalid message: {e:?}"))?;
+        self.handle(message);
+        Ok(())
+    }
+
+    async fn receive(&self) -> Result<Option<TransportMessage>, anyhow::Error> {
+        let mut outgoing_rx = self.outgoing_rx.lock().await;
+        let Ok(Some(message)) = tokio::time::timeout(RECEIVE_INTERVAL, outgoing_rx.recv()).await
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(TransportMessage {
+            uid: self.next_uid.fetch_add(1, Ordering::SeqCst),
+            msg: serde_json::to_string(&message)?,
+        }))
+    }
+
+    async fn ack(&self, _uid: u64) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+//! Helpers to run the backend against fake BitVMX nodes in tests
+
+pub mod fake_bitvmx;
+
+pub use fake_bitvmx::{FakeBitvmx, FakeChain};
+
+use crate::config::{Config, DEFAULT_OPERATOR};
+use crate::games::program::ADD_NUMBERS_YAML;
+use crate::games::AddNumbers;
+use crate::jobs::JobWorker;
+use crate::models::P2PAddress;
+use crate::rpc::RpcClient;
+use crate::services::{AddNumbersService, WorkerService};
+use crate::state::AppState;
+use crate::stores::MemoryJobStore;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::Sender;
+use uuid::Uuid;
+
+/// How long [`wait_until`] waits before failing the test
+const WAIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Backend of a player talking to its own fake BitVMX node, with in memory stores
+#[derive(Debug)]
+pub struct FakePlayer {
+    /// Node of the default operator
+    pub bitvmx: Arc<FakeBitvmx>,
+    /// Nodes of the other operators by name
+    pub operators: Vec<(String, Arc<FakeBitvmx>)>,
+    pub state: AppState,
+    /// bitvmx-client directory of the config, removed with the player
+    _bitvmx_client_dir: FakeBitvmxClientDir,
+}
+
+impl FakePlayer {
+    /// Start the backend of a player on the chain
+    pub fn start(chain: &Arc<FakeChain>, shutdown_tx: &Sender<()>) -> Result<Self, anyhow::Error> {
+        Self::start_with_operators(chain, shutdown_tx, &[])
+    }
+
+    /// Start the backend of a player with a node for the default operator and for each of `operators`
+    pub fn start_with_operators(
+        chain: &Arc<FakeChain>,
+        shutdown_tx: &Sender<()>,
+        operators: &[&str],
+    ) -> Result<Self, anyhow::Error> {
+        let bitvmx = FakeBitvmx::new(chain);
+        let operators: Vec<_> = operators
+            .iter()
+            .map(|name| (name.to_string(), FakeBitvmx::new(chain)))
+            .collect();
+        let rpc_clients = std::iter::once((DEFAULT_OPERATOR.to_string(), bitvmx.clone()))
+            .chain(operators.iter().cloned())
+            .map(|(name, node)| (name, RpcClient::with_transport(node, shutdown_tx).0))
+            .collect();
+        let bitvmx_client_dir = FakeBitvmxClientDir::new()?;
+        let mut config = Config::default();
+        config.game.bitvmx_client_path = bitvmx_client_dir.0.display().to_string();
+        config.game.timeout_check_interval_secs = 1;
+        let (job_worker, _worker_task) = JobWorker::start(
+            Arc::new(MemoryJobStore::new()),
+            config.jobs.clone(),
+            shutdown_tx,
+        );
+        let state = AppState::new(config, None, rpc_clients, chain.clone(), job_worker)?;
+
+        Ok(Self {
+            bitvmx,
+            operators,
+            state,
+            _bitvmx_client_dir: bitvmx_client_dir,
+        })
+    }
+
+    /// Service of the add numbers games of the player
+    pub fn add_numbers_service(&self) -> Arc<AddNumbersService> {
+        self.state
+            .games
+            .game_service::<AddNumbers>()
+            .expect("Add numbers game is registered")
+    }
+
+    /// Worker service of the add numbers games of the player
+    pub fn add_numbers_worker(&self) -> Arc<WorkerService<AddNumbers>> {
+        self.state
+            .games
+            .worker_service::<AddNumbers>()
+            .expect("Add numbers game is registered")
+    }
+
+    /// P2P address of the player BitVMX node
+    pub fn p2p_address(&self) -> P2PAddress {
+        self.bitvmx.comm_address().into()
+    }
+}
+
+/// Temporary bitvmx-client directory with the add numbers program definition of the default
+/// game config, removed on drop
+#[derive(Debug)]
+struct FakeBitvmxClientDir(PathBuf);
+
+impl FakeBitvmxClientDir {
+    fn new() -> Result<Self, anyhow::Error> {
+        let dir = Self(std::env::temp_dir().join(format!("fake-bitvmx-client-{}", Uuid::new_v4())));
+        let program_definition = dir.0.join(&Config::default().game.program_definition);
+        std::fs::create_dir_all(program_definition.parent().unwrap_or(&dir.0))
+            .and_then(|_| std::fs::write(&program_definition, ADD_NUMBERS_YAML))
+            .map_err(|e| anyhow::anyhow!("Failed to write the program definition: {e:?}"))?;
+        Ok(dir)
+    }
+}
+
+impl Drop for FakeBitvmxClientDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Poll the condition until it holds, panics after a while
+pub async fn wait_until<F, Fut>(what: &str, condition: F)
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let wait = async {
+        while !condition().await {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    };
+    if tokio::time::timeout(WAIT_TIMEOUT, wait).await.is_err() {
+        panic!("Timed out waiting until {what}");
+    }
+}
@@ -1,4 +1,5 @@
 use bitvmx_client::bitcoin::{secp256k1, Address, Network, PublicKey, ScriptBuf, XOnlyPublicKey};
+use bitvmx_client::bitvmx_wallet::wallet::Destination;
 use bitvmx_client::protocol_builder::scripts::{self, ProtocolScript};
 
 pub fn pub_key_to_p2tr(
@@ -15,3 +16,33 @@ pub fn pub_key_to_p2tr(
 pub fn pub_key_to_xonly(pubkey: &PublicKey) -> Result<XOnlyPublicKey, anyhow::Error> {
     Ok(pubkey.inner.into())
 }
+
+pub fn protocol_scripts(aggregated_key: &PublicKey) -> Vec<ProtocolScript> {
+    // Todo check if this tap leaves are correct
+    vec![
+        scripts::check_aggregated_signature(aggregated_key, scripts::SignMode::Aggregate),
+        scripts::check_aggregated_signature(aggregated_key, scripts::SignMode::Aggregate),
+    ]
+}
+
+pub fn protocol_destination(
+    aggregated_key: &PublicKey,
+    amount: u64,
+) -> Result<Destination, anyhow::Error> {
+    // Get the aggregated key and protocol information
+    let x_only_pubkey = pub_key_to_xonly(aggregated_key)
+        .map_err(|e| anyhow::anyhow!("Failed to convert aggregated key to x only pubkey: {e:?}"))?;
+    let tap_leaves = protocol_scripts(aggregated_key);
+    let destination = Destination::P2TR(x_only_pubkey, tap_leaves, amount);
+    Ok(destination)
+}
+
+pub fn protocol_address(aggregated_key: &PublicKey) -> Result<Address, anyhow::Error> {
+    // Todo check if this tap leaves are correct
+    let x_only_pubkey = pub_key_to_xonly(aggregated_key)
+        .map_err(|e| anyhow::anyhow!("Failed to convert aggregated key to x only pubkey: {e:?}"))?;
+    let tap_leaves = protocol_scripts(aggregated_key);
+    let p2tr_address = pub_key_to_p2tr(&x_only_pubkey, &tap_leaves)
+        .map_err(|e| anyhow::anyhow!("Failed to convert aggregated key to p2tr address: {e:?}"))?;
+    Ok(p2tr_address)
+}
@@ -1,4 +1,9 @@
-use crate::models::ErrorResponse;
+use crate::games::program::InvalidProgramInput;
+use crate::models::{
+    DisputeParametersMismatch, ErrorResponse, InvalidDisputeParameters, InvalidJobAction,
+    InvalidTransition, JobNotFound,
+};
+use crate::services::{UnhealthyOperator, UnknownOperator};
 use axum::Json;
 use http::StatusCode;
 
@@ -24,6 +29,69 @@ pub fn not_found(message: &str) -> (StatusCode, Json<ErrorResponse>) {
     )
 }
 
+/// Create a conflict error response
+pub fn conflict(message: &str) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::CONFLICT,
+        Json(ErrorResponse {
+            error: "CONFLICT".to_string(),
+            message: message.to_string(),
+        }),
+    )
+}
+
+/// Create an error response for a failed game operation.
+/// Illegal game transitions and disagreements with the other player are a conflict,
+/// invalid parameters or program inputs a bad request, unknown operators are not found,
+/// unhealthy operators unavailable and anything else an internal server error.
+pub fn game_error(message: &str, error: &anyhow::Error) -> (StatusCode, Json<ErrorResponse>) {
+    for cause in error.chain() {
+        if let Some(transition) = cause.downcast_ref::<InvalidTransition>() {
+            return conflict(&format!("{message}: {transition}"));
+        }
+        if let Some(mismatch) = cause.downcast_ref::<DisputeParametersMismatch>() {
+            return conflict(&format!("{message}: {mismatch}"));
+        }
+        if let Some(invalid) = cause.downcast_ref::<InvalidDisputeParameters>() {
+            return bad_request(&format!("{message}: {invalid}"));
+        }
+        if let Some(invalid) = cause.downcast_ref::<InvalidProgramInput>() {
+            return bad_request(&format!("{message}: {invalid}"));
+        }
+        if let Some(unknown) = cause.downcast_ref::<UnknownOperator>() {
+            return not_found(&format!("{message}: {unknown}"));
+        }
+        if let Some(unhealthy) = cause.downcast_ref::<UnhealthyOperator>() {
+            return service_unavailable(&format!("{message}: {unhealthy}"));
+        }
+    }
+    internal_server_error(&format!("{message}: {error:?}"))
+}
+
+/// Create an error response for a failed job operation.
+/// Unknown jobs are not found, actions refused in the job status a conflict
+/// and anything else an internal server error.
+pub fn job_error(message: &str, error: &anyhow::Error) -> (StatusCode, Json<ErrorResponse>) {
+    if let Some(not_found_job) = error.downcast_ref::<JobNotFound>() {
+        return not_found(&format!("{message}: {not_found_job}"));
+    }
+    if let Some(invalid) = error.downcast_ref::<InvalidJobAction>() {
+        return conflict(&format!("{message}: {invalid}"));
+    }
+    internal_server_error(&format!("{message}: {error:?}"))
+}
+
+/// Create a service unavailable error response
+pub fn service_unavailable(message: &str) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(ErrorResponse {
+            error: "SERVICE_UNAVAILABLE".to_string(),
+            message: message.to_string(),
+        }),
+    )
+}
+
 /// Create an internal server error response
 pub fn internal_server_error(message: &str) -> (StatusCode, Json<ErrorResponse>) {
     (
@@ -70,6 +138,77 @@ mod tests {
         assert_eq!(response.0.message, "Resource not found");
     }
 
+    #[test]
+    fn test_conflict() {
+        let (status, response) = conflict("Already done");
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(response.0.error, "CONFLICT");
+        assert_eq!(response.0.message, "Already done");
+    }
+
+    #[test]
+    fn test_game_error() {
+        use crate::games::GameStatus;
+        use crate::models::{AddNumbersGameEvent, AddNumbersGameStatus, PlayerRole};
+        use anyhow::Context;
+
+        let transition = AddNumbersGameStatus::Finished
+            .transition(&PlayerRole::Player1, AddNumbersGameEvent::Finish)
+            .unwrap_err();
+        let error = Err::<(), _>(transition)
+            .context("Failed to save")
+            .unwrap_err();
+        let (status, response) = game_error("Failed to finish game", &error);
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(response.0.error, "CONFLICT");
+
+        let error = anyhow::Error::new(InvalidDisputeParameters("Bad timelock".to_string()));
+        let (status, _) = game_error("Failed to setup game", &error);
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let error = anyhow::Error::new(UnknownOperator("operator_2".to_string()));
+        let (status, _) = game_error("Failed to create aggregated key", &error);
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let error = anyhow::Error::new(UnhealthyOperator {
+            name: "operator_2".to_string(),
+            reason: "setup failed".to_string(),
+        });
+        let (status, _) = game_error("Failed to place bet", &error);
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+        let (status, _) = game_error("Failed to finish game", &anyhow::anyhow!("Game not found"));
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn test_job_error() {
+        use crate::models::JobStatus;
+        use uuid::Uuid;
+
+        let error = anyhow::Error::new(JobNotFound(Uuid::nil()));
+        let (status, _) = job_error("Failed to cancel job", &error);
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let error = anyhow::Error::new(InvalidJobAction {
+            id: Uuid::nil(),
+            status: JobStatus::Succeeded,
+            action: "retry",
+        });
+        let (status, response) = job_error("Failed to retry job", &error);
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(
+            response.0.message,
+            format!(
+                "Failed to retry job: Cannot retry job {} in Succeeded status",
+                Uuid::nil()
+            )
+        );
+
+        let (status, _) = job_error("Failed to retry job", &anyhow::anyhow!("Store failed"));
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
     #[test]
     fn test_internal_server_error() {
         let (status, response) = internal_server_error("Internal error occurred");